@@ -0,0 +1,63 @@
+//! Compares building a TXT record with many entries by repeated `insert()` after
+//! `TxtRecord::new()` or `TxtRecord::with_capacity()` and with `TxtRecord::from_pairs()`, and
+//! encoding one into its wire format with `to_bytes()` and `TxtRecord::build_into()`.
+//!
+//! Run with `cargo bench --bench txt_record`.
+
+use std::time::{Duration, Instant};
+use zeroconf::prelude::*;
+use zeroconf::TxtRecord;
+
+const ENTRIES: usize = 200;
+const ITERATIONS: u32 = 1000;
+
+fn build(mut txt_record: TxtRecord, pairs: &[(&str, &str)]) -> TxtRecord {
+    for (key, value) in pairs {
+        txt_record.insert(key, value).unwrap();
+    }
+
+    txt_record
+}
+
+/// Keeps the optimizer from discarding `value`, like `std::hint::black_box()`, which needs a newer
+/// Rust than the crate supports.
+fn black_box<T>(value: T) -> T {
+    let result = unsafe { std::ptr::read_volatile(&value) };
+    std::mem::forget(value);
+    result
+}
+
+fn bench<T, F: FnMut() -> T>(name: &str, mut f: F) {
+    let start = Instant::now();
+
+    for _ in 0..ITERATIONS {
+        black_box(f());
+    }
+
+    let per_iteration: Duration = start.elapsed() / ITERATIONS;
+    println!("{:<32} {:>12?} per record", name, per_iteration);
+}
+
+fn main() {
+    let keys: Vec<String> = (0..ENTRIES).map(|i| format!("device{}", i)).collect();
+    let pairs: Vec<(&str, &str)> = keys.iter().map(|k| (k.as_str(), "online")).collect();
+
+    println!("building records of {} entries", ENTRIES);
+
+    bench("new + insert", || build(TxtRecord::new(), &pairs));
+    bench("with_capacity + insert", || {
+        build(TxtRecord::with_capacity(ENTRIES), &pairs)
+    });
+    bench("from_pairs", || TxtRecord::from_pairs(&pairs).unwrap());
+
+    println!("encoding records of {} entries", ENTRIES);
+
+    let txt_record = TxtRecord::from_pairs(&pairs).unwrap();
+    let mut buf = Vec::new();
+
+    bench("to_bytes", || txt_record.to_bytes());
+    bench("build_into (reused buffer)", || {
+        buf.clear();
+        TxtRecord::build_into(&mut buf, &pairs).unwrap()
+    });
+}
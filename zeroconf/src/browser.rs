@@ -1,23 +1,88 @@
 //! Trait definition for cross-platform browser
 
-use crate::{EventLoop, NetworkInterface, Result, ServiceType, TxtRecord};
+use crate::error::{Error, NameError};
+use crate::prelude::*;
+use crate::service;
+use crate::{
+    CancellationToken, ClientStateCallback, NetworkInterface, NetworkProtocol, Result, Scope,
+    ServiceType, TxtRecord,
+};
 use std::any::Any;
-use std::sync::Arc;
+use std::collections::{HashSet, VecDeque};
+#[cfg(feature = "diagnostics")]
+use std::fmt;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Maximum amount of time blocking helpers wait on the event loop per iteration
+pub(crate) const BLOCKING_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How often a browser with [`TMdnsBrowser::set_long_lived()`] enabled browses wide-area domains
+/// again where the backend cannot subscribe to updates
+///
+/// [`TMdnsBrowser::set_long_lived()`]: trait.TMdnsBrowser.html#tymethod.set_long_lived
+pub const LONG_LIVED_POLL_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Number of consecutive failures to resolve a service over IPv6 after which a browser with
+/// [`TMdnsBrowser::set_ipv6_fallback()`] enabled falls back to IPv4.
+///
+/// [`TMdnsBrowser::set_ipv6_fallback()`]: trait.TMdnsBrowser.html#tymethod.set_ipv6_fallback
+pub const IPV6_FALLBACK_THRESHOLD: u32 = 3;
 
 /// Interface for interacting with underlying mDNS implementation service browsing capabilities.
+///
+/// [`MdnsBrowser`] is the implementation for the current platform. Application code can be
+/// written against this trait instead so that another implementation, such as a mock in tests,
+/// can be substituted:
+///
+/// ```no_run
+/// use std::time::Duration;
+/// use zeroconf::prelude::*;
+/// use zeroconf::{MdnsBrowser, ServiceType};
+///
+/// fn find_printer<B: TMdnsBrowser>() -> Option<String> {
+///     let mut browser = B::new(ServiceType::new("ipp", "tcp").unwrap());
+///     let service = browser.wait_for("Office", Duration::from_secs(5)).ok()?;
+///     Some(service.host_name().clone())
+/// }
+///
+/// find_printer::<MdnsBrowser>();
+/// ```
+///
+/// [`MdnsBrowser`]: ../type.MdnsBrowser.html
 pub trait TMdnsBrowser {
+    /// The event loop returned when the browser is started, which borrows the browser.
+    type EventLoop<'a>: TEventLoop
+    where
+        Self: 'a;
+
     /// Creates a new `MdnsBrowser` that browses for the specified `kind` (e.g. `_http._tcp`)
     fn new(service_type: ServiceType) -> Self;
 
     /// Sets the network interface on which to browse for services on.
     ///
     /// Most applications will want to use the default value `NetworkInterface::Unspec` to browse
-    /// on all available interfaces.
+    /// on all available interfaces. On Linux, [`browse_services()`] returns an error if Avahi
+    /// cannot represent the index of the interface.
+    ///
+    /// [`browse_services()`]: #tymethod.browse_services
     fn set_network_interface(&mut self, interface: NetworkInterface);
 
     /// Returns the network interface on which to browse for services on.
     fn network_interface(&self) -> NetworkInterface;
 
+    /// Sets the network protocol over which to browse for services. Defaults to
+    /// `NetworkProtocol::Unspec`, which browses over both IPv4 and IPv6.
+    ///
+    /// Bonjour does not support restricting the protocol of a browser, so on macOS this only
+    /// restricts the family of the addresses looked up for resolved services.
+    fn set_network_protocol(&mut self, protocol: NetworkProtocol);
+
+    /// Returns the network protocol over which to browse for services.
+    fn network_protocol(&self) -> NetworkProtocol;
+
     /// Sets the [`ServiceDiscoveredCallback`] that is invoked when the browser has discovered and
     /// resolved a service.
     ///
@@ -27,6 +92,435 @@ pub trait TMdnsBrowser {
         service_discovered_callback: Box<ServiceDiscoveredCallback>,
     );
 
+    /// Sets the [`ServiceBrowsedCallback`] that is invoked when a service appears on or
+    /// disappears from the network, before any resolution takes place.
+    ///
+    /// [`ServiceBrowsedCallback`]: ../type.ServiceBrowsedCallback.html
+    fn set_service_browsed_callback(
+        &mut self,
+        service_browsed_callback: Box<ServiceBrowsedCallback>,
+    );
+
+    /// Sets the [`ClientStateCallback`] that is invoked when the state of the browser's
+    /// connection to the mDNS daemon changes. Takes effect the next time the browser is started.
+    ///
+    /// The callback is invoked for the connection the browser creates itself when it is first
+    /// started, and for one shared through a [`Zeroconf`] facade with the states it goes through
+    /// once the browser has been started. On Linux, the initial state of a connection of its own
+    /// is reported while the connection is being created, before [`browse_services()`] returns.
+    ///
+    /// [`ClientStateCallback`]: ../type.ClientStateCallback.html
+    /// [`Zeroconf`]: ../type.Zeroconf.html
+    /// [`browse_services()`]: #tymethod.browse_services
+    fn set_client_state_callback(&mut self, client_state_callback: Box<ClientStateCallback>);
+
+    /// Sets whether newly browsed services should be resolved automatically. Defaults to `true`.
+    ///
+    /// Resolving a service is considerably more expensive than browsing for it. When set to
+    /// `false`, the browser only reports [`BrowseEvent`]s and individual services can be resolved
+    /// on demand with [`resolve()`].
+    ///
+    /// [`BrowseEvent`]: enum.BrowseEvent.html
+    /// [`resolve()`]: #tymethod.resolve
+    fn set_resolve_services(&mut self, resolve_services: bool);
+
+    /// Returns whether newly browsed services are resolved automatically.
+    fn resolve_services(&self) -> bool;
+
+    /// Sets whether the TXT record of a service should be retrieved when it is resolved. Defaults
+    /// to `true`.
+    ///
+    /// When set to `false`, [`ServiceDiscovery::txt()`] is always `None`. On Linux, Avahi skips
+    /// the TXT lookup entirely. On macOS, Bonjour always returns the TXT record with the
+    /// resolution, so it is merely discarded.
+    ///
+    /// [`ServiceDiscovery::txt()`]: struct.ServiceDiscovery.html#method.txt
+    fn set_resolve_txt(&mut self, resolve_txt: bool);
+
+    /// Returns whether the TXT record of a service is retrieved when it is resolved.
+    fn resolve_txt(&self) -> bool;
+
+    /// Sets whether every TXT record of a service should be retrieved when it is resolved rather
+    /// than only the first. Defaults to `false`.
+    ///
+    /// Publishing more than one TXT record for a service is uncommon but legal. When set to
+    /// `true`, all of them are available through [`ServiceDiscovery::txt_records()`]. Has no effect
+    /// if [`set_resolve_txt()`] is set to `false`.
+    ///
+    /// On Linux, the TXT records are looked up separately once the service has been resolved,
+    /// which delays each discovery until the daemon has answered from its cache or the network,
+    /// usually around a second. On macOS, Bonjour only ever returns the first TXT record when
+    /// resolving a service, so this has no effect.
+    ///
+    /// [`ServiceDiscovery::txt_records()`]: struct.ServiceDiscovery.html#method.txt_records
+    /// [`set_resolve_txt()`]: #tymethod.set_resolve_txt
+    fn set_resolve_all_txt_records(&mut self, resolve_all_txt_records: bool);
+
+    /// Returns whether every TXT record of a service is retrieved when it is resolved.
+    fn resolve_all_txt_records(&self) -> bool;
+
+    /// Sets whether every address of a service's host should be retrieved when it is resolved
+    /// rather than only the first. Defaults to `false`.
+    ///
+    /// When set to `true`, [`ServiceDiscovery::addresses()`] contains every IPv4 and IPv6
+    /// address the host is known by on the interface the service was resolved on. Otherwise it
+    /// only contains the address returned by [`ServiceDiscovery::address()`].
+    ///
+    /// On Linux, the addresses are looked up separately once the service has been resolved,
+    /// which delays each discovery until the daemon has answered from its cache or the network,
+    /// usually around a second. On macOS, each discovery is delayed until Bonjour has no more
+    /// addresses immediately available.
+    ///
+    /// [`ServiceDiscovery::addresses()`]: struct.ServiceDiscovery.html#method.addresses
+    /// [`ServiceDiscovery::address()`]: struct.ServiceDiscovery.html#method.address
+    fn set_resolve_all_addresses(&mut self, resolve_all_addresses: bool);
+
+    /// Returns whether every address of a service's host is retrieved when it is resolved.
+    fn resolve_all_addresses(&self) -> bool;
+
+    /// Sets whether the resolutions of a service should be coalesced into a single
+    /// [`ServiceDiscovery`]. Defaults to `true`.
+    ///
+    /// A service is browsed once per interface and protocol it is advertised on, so a dual-stack
+    /// host is usually resolved once over IPv4 and once over IPv6, each resolution reporting a
+    /// different address. When enabled, the [`ServiceDiscoveredCallback`] is invoked once per
+    /// logical service, as identified by [`ServiceDiscovery::service_key()`], with the addresses
+    /// of every resolution in [`ServiceDiscovery::addresses()`]. The service is not resolved
+    /// again until it has been removed from every interface and protocol. When disabled, the
+    /// callback is invoked once per resolution.
+    ///
+    /// On Linux, the discovery is reported once every resolution of the service that was pending
+    /// has finished. On macOS, the service is only resolved the first time it is browsed and each
+    /// discovery is delayed until Bonjour has no more addresses immediately available.
+    ///
+    /// [`ServiceDiscovery`]: struct.ServiceDiscovery.html
+    /// [`ServiceDiscoveredCallback`]: type.ServiceDiscoveredCallback.html
+    /// [`ServiceDiscovery::service_key()`]: struct.ServiceDiscovery.html#method.service_key
+    /// [`ServiceDiscovery::addresses()`]: struct.ServiceDiscovery.html#method.addresses
+    fn set_coalesce_addresses(&mut self, coalesce_addresses: bool);
+
+    /// Returns whether the resolutions of a service are coalesced into a single
+    /// [`ServiceDiscovery`].
+    ///
+    /// [`ServiceDiscovery`]: struct.ServiceDiscovery.html
+    fn coalesce_addresses(&self) -> bool;
+
+    /// Sets whether the priority and weight of a service's SRV record should be retrieved when it
+    /// is resolved. Defaults to `false`, in which case [`ServiceDiscovery::priority()`] and
+    /// [`ServiceDiscovery::weight()`] are `0`.
+    ///
+    /// Neither Avahi nor Bonjour report these when resolving a service, so the SRV record is
+    /// looked up separately once the service has been resolved. The record was just retrieved by
+    /// the resolver, so this is usually answered from the daemon's cache.
+    ///
+    /// [`ServiceDiscovery::priority()`]: struct.ServiceDiscovery.html#method.priority
+    /// [`ServiceDiscovery::weight()`]: struct.ServiceDiscovery.html#method.weight
+    fn set_resolve_srv_priority(&mut self, resolve_srv_priority: bool);
+
+    /// Returns whether the priority and weight of a service's SRV record are retrieved when it is
+    /// resolved.
+    fn resolve_srv_priority(&self) -> bool;
+
+    /// Sets whether the browser should stop using IPv6 once resolving services over it keeps
+    /// failing. Defaults to `false`.
+    ///
+    /// On some hosts IPv6 is available but multicast DNS over it is not, such as behind a
+    /// firewall or without a multicast route, so each resolution over IPv6 only fails once the
+    /// daemon gives up on it. When enabled, after [`IPV6_FALLBACK_THRESHOLD`] consecutive
+    /// failures to resolve a service over IPv6, a warning is logged once and the browser only
+    /// resolves over IPv4 for as long as it exists. A successful resolution over IPv6 resets the
+    /// count.
+    ///
+    /// This is a heuristic: services that are only reachable over IPv6 are not resolved once the
+    /// browser has fallen back, in which case it should be left disabled.
+    ///
+    /// On macOS, Bonjour does not report which protocol a resolution failed over, so this has no
+    /// effect.
+    ///
+    /// [`IPV6_FALLBACK_THRESHOLD`]: constant.IPV6_FALLBACK_THRESHOLD.html
+    fn set_ipv6_fallback(&mut self, ipv6_fallback: bool);
+
+    /// Returns whether the browser stops using IPv6 once resolving services over it keeps
+    /// failing.
+    fn ipv6_fallback(&self) -> bool;
+
+    /// Sets whether a browsed service is resolved on the interface and protocol it was browsed
+    /// on, as reported by [`ServiceBrowse::interface()`] and [`ServiceBrowse::protocol()`].
+    /// Defaults to `true`.
+    ///
+    /// When disabled, services are resolved on `NetworkInterface::Unspec` and, unless the IPv6
+    /// fallback is active, `NetworkProtocol::Unspec`, leaving the daemon to pick an interface. On
+    /// a multi-homed host it may pick a different one than the service was seen on, where the
+    /// resolution fails or yields an address that is not reachable from the interface the service
+    /// is actually on. Pinning the resolver to the browse interface avoids both, so this should
+    /// only be disabled to let the daemon answer from whichever interface responds first.
+    ///
+    /// This applies to services resolved automatically as well as with [`resolve()`].
+    ///
+    /// [`ServiceBrowse::interface()`]: struct.ServiceBrowse.html#method.interface
+    /// [`ServiceBrowse::protocol()`]: struct.ServiceBrowse.html#method.protocol
+    /// [`resolve()`]: #tymethod.resolve
+    fn set_resolve_on_browse_interface(&mut self, resolve_on_browse_interface: bool);
+
+    /// Returns whether browsed services are resolved on the interface they were browsed on.
+    fn resolve_on_browse_interface(&self) -> bool;
+
+    /// Sets whether the browser should restart when the machine's network changes. Defaults to
+    /// `false`.
+    ///
+    /// Joining or leaving a network, such as a Wi-Fi network on a laptop, can leave previously
+    /// discovered services unreachable without them being reported as removed. When enabled, the
+    /// browser watches for addresses being added to or removed from the machine's interfaces.
+    /// Once they change, pending resolutions are abandoned, [`BrowseEvent::NetworkChanged`] is
+    /// reported to the [`ServiceBrowsedCallback`] so that previously discovered services can be
+    /// forgotten, and browsing starts over, reporting every service that is still present again.
+    ///
+    /// On Linux, changes are noticed through a netlink socket subscribed to the kernel's address
+    /// notifications and watched on the browser's event loop. On macOS, this is not supported:
+    /// `SCNetworkReachability` requires a `CFRunLoop`, which the event loop does not run, so
+    /// [`browse_services()`] returns [`Error::Unsupported`] if this is enabled.
+    ///
+    /// [`BrowseEvent::NetworkChanged`]: enum.BrowseEvent.html#variant.NetworkChanged
+    /// [`ServiceBrowsedCallback`]: type.ServiceBrowsedCallback.html
+    /// [`browse_services()`]: #tymethod.browse_services
+    /// [`Error::Unsupported`]: ../error/enum.Error.html#variant.Unsupported
+    fn set_restart_on_network_change(&mut self, restart_on_network_change: bool);
+
+    /// Returns whether the browser restarts when the machine's network changes.
+    fn restart_on_network_change(&self) -> bool;
+
+    /// Sets whether the browser keeps receiving updates for services in wide-area domains,
+    /// rather than only looking them up once. Defaults to `false`. Takes effect the next time the
+    /// browser is started, and only with a [`Scope`] other than `Scope::LinkLocal`, as multicast
+    /// browsing is always continuous.
+    ///
+    /// Updates are pushed to the browser only if the unicast DNS server of the domain supports
+    /// long-lived queries (LLQ) or DNS Push Notifications. Otherwise the domain is browsed again
+    /// every [`LONG_LIVED_POLL_INTERVAL`]:
+    ///
+    /// * On macOS, Bonjour subscribes to wide-area browses on its own, using LLQ or DNS Push
+    ///   where the server supports it and polling otherwise, so this setting changes nothing.
+    /// * On Linux, Avahi supports neither, so the browser polls: it starts over every
+    ///   [`LONG_LIVED_POLL_INTERVAL`] and compares the services it finds with those found before.
+    ///   Only services that were not found before are reported as new, and services that are no
+    ///   longer found are reported as removed once the poll is done, or once the next poll starts
+    ///   if the daemon does not report it done.
+    ///
+    /// [`Scope`]: enum.Scope.html
+    /// [`LONG_LIVED_POLL_INTERVAL`]: constant.LONG_LIVED_POLL_INTERVAL.html
+    fn set_long_lived(&mut self, long_lived: bool);
+
+    /// Returns whether the browser keeps receiving updates for services in wide-area domains.
+    fn long_lived(&self) -> bool;
+
+    /// Sets the domains to browse for services in, such as `local` and the browse domains of an
+    /// enterprise network. Takes effect the next time the browser is started. Defaults to no
+    /// domains, in which case the domain is chosen by the browser's [`Scope`].
+    ///
+    /// Every domain is browsed at once and each [`ServiceDiscovery::domain()`] and
+    /// [`ServiceBrowse::domain()`] is the domain it was found in. A leading dot, as in `.local`,
+    /// is ignored. On Linux, [`BrowseEvent::AllForNow`] is reported once every domain has been
+    /// browsed.
+    ///
+    /// Each domain costs a separate browse operation on the daemon, which sends its own queries
+    /// and holds its own results. For domains other than `local`, those are unicast DNS queries
+    /// whose answers are only found if the scope allows wide-area lookups, so such domains should
+    /// be combined with `Scope::WideArea` or `Scope::Both`.
+    ///
+    /// On macOS, browsing more than one domain requires a connection shared between the
+    /// browse operations, which the browser creates unless it already shares one through a
+    /// [`Zeroconf`] facade.
+    ///
+    /// [`Scope`]: enum.Scope.html
+    /// [`ServiceDiscovery::domain()`]: struct.ServiceDiscovery.html#method.domain
+    /// [`ServiceBrowse::domain()`]: struct.ServiceBrowse.html#method.domain
+    /// [`BrowseEvent::AllForNow`]: enum.BrowseEvent.html#variant.AllForNow
+    /// [`Zeroconf`]: type.Zeroconf.html
+    fn set_domains(&mut self, domains: Vec<String>);
+
+    /// Returns the domains to browse for services in, or an empty slice if the domain is chosen
+    /// by the browser's [`Scope`].
+    ///
+    /// [`Scope`]: enum.Scope.html
+    fn domains(&self) -> &[String];
+
+    /// Sets the [`Scope`] on which to browse for services. Defaults to `Scope::LinkLocal`. Takes
+    /// effect the next time the browser is started.
+    ///
+    /// [`Scope`]: enum.Scope.html
+    fn set_scope(&mut self, scope: Scope);
+
+    /// Returns the [`Scope`] on which to browse for services.
+    ///
+    /// [`Scope`]: enum.Scope.html
+    fn scope(&self) -> Scope;
+
+    /// Sets the [`DiscoveryMode`] trading off discovery speed against network and power usage.
+    /// Defaults to `DiscoveryMode::Balanced`. Takes effect the next time the browser is started.
+    ///
+    /// [`DiscoveryMode`]: enum.DiscoveryMode.html
+    fn set_discovery_mode(&mut self, mode: DiscoveryMode);
+
+    /// Returns the [`DiscoveryMode`] of this browser.
+    ///
+    /// [`DiscoveryMode`]: enum.DiscoveryMode.html
+    fn discovery_mode(&self) -> DiscoveryMode;
+
+    /// Sets what to do with services whose instance name is not valid UTF-8, as advertised by
+    /// some misbehaving devices. Defaults to `OnInvalidUtf8::Lossy`. See [`OnInvalidUtf8`].
+    ///
+    /// [`OnInvalidUtf8`]: enum.OnInvalidUtf8.html
+    fn set_on_invalid_utf8(&mut self, on_invalid_utf8: OnInvalidUtf8);
+
+    /// Returns what is done with services whose instance name is not valid UTF-8.
+    fn on_invalid_utf8(&self) -> OnInvalidUtf8;
+
+    /// Sets how many browsed services may be resolved at once. Defaults to
+    /// [`DEFAULT_MAX_CONCURRENT_RESOLVES`], and `0` is treated as `1`.
+    ///
+    /// On a busy network, each resolver holds resources in the daemon and, with
+    /// [`set_resolve_all_addresses()`] or [`set_resolve_srv_priority()`], further lookups of its
+    /// own. Services browsed while the limit is reached are queued and resolved in the order they
+    /// were browsed as earlier resolutions finish; queued services that are removed before their
+    /// turn are not resolved. Services resolved explicitly with [`resolve()`] are not limited.
+    ///
+    /// On macOS, Bonjour resolves browsed services one at a time, so this is stored but has no
+    /// effect.
+    ///
+    /// [`DEFAULT_MAX_CONCURRENT_RESOLVES`]: ../constant.DEFAULT_MAX_CONCURRENT_RESOLVES.html
+    /// [`set_resolve_all_addresses()`]: #tymethod.set_resolve_all_addresses
+    /// [`set_resolve_srv_priority()`]: #tymethod.set_resolve_srv_priority
+    /// [`resolve()`]: #tymethod.resolve
+    fn set_max_concurrent_resolves(&mut self, max_concurrent_resolves: usize);
+
+    /// Returns how many browsed services may be resolved at once.
+    fn max_concurrent_resolves(&self) -> usize;
+
+    /// Limits the service discovered callback to at most `per_second` invocations a second, to
+    /// protect consumers that cannot keep up with a busy network. Defaults to `0`, which is
+    /// unlimited. Takes effect the next time the browser is started.
+    ///
+    /// While limited, discoveries are queued and delivered one at a time each `1 / per_second`
+    /// seconds while the event loop is polled, so each may be delayed by up to that long. Only
+    /// the latest queued discovery of each service is kept, as returned by
+    /// [`ServiceDiscovery::service_key()`], replacing earlier ones in their place in the queue, and
+    /// likewise only the latest error. Browse events are not limited, and queued discoveries are
+    /// dropped when the browser is cancelled.
+    ///
+    /// [`ServiceDiscovery::service_key()`]: struct.ServiceDiscovery.html#method.service_key
+    fn set_callback_rate_limit(&mut self, per_second: u32);
+
+    /// Returns how many times a second the service discovered callback may be invoked, or `0`
+    /// if unlimited.
+    fn callback_rate_limit(&self) -> u32;
+
+    /// Stops reporting the service with the specified key, as returned by
+    /// [`ServiceDiscovery::service_key()`] or [`ServiceBrowse::service_key()`], until it is
+    /// passed to [`unignore()`].
+    ///
+    /// Discoveries of the service, as well as [`BrowseEvent::New`] and [`BrowseEvent::Removed`]
+    /// events for it, are dropped before the callbacks are invoked, so a service removed while it
+    /// is ignored is not reported as removed either. The service is still browsed and resolved.
+    /// The key is compared case-insensitively and ignoring a trailing dot in the domain.
+    ///
+    /// [`ServiceDiscovery::service_key()`]: struct.ServiceDiscovery.html#method.service_key
+    /// [`ServiceBrowse::service_key()`]: struct.ServiceBrowse.html#method.service_key
+    /// [`unignore()`]: #tymethod.unignore
+    /// [`BrowseEvent::New`]: enum.BrowseEvent.html#variant.New
+    /// [`BrowseEvent::Removed`]: enum.BrowseEvent.html#variant.Removed
+    fn ignore(&mut self, service_key: (String, String, String));
+
+    /// Resumes reporting a service previously passed to [`ignore()`].
+    ///
+    /// [`ignore()`]: #tymethod.ignore
+    fn unignore(&mut self, service_key: &(String, String, String));
+
+    /// Returns `true` if the service with the specified key is ignored.
+    fn is_ignored(&self, service_key: &(String, String, String)) -> bool;
+
+    /// Only reports services whose TXT record has an entry for `key` with exactly `value`, such
+    /// as `model=X`. The key is compared case-insensitively, as TXT keys are per RFC 6763, and the
+    /// value byte for byte. Requiring a key again replaces its value, and services must match
+    /// every required entry. With [`set_resolve_all_txt_records()`], an entry in any of the
+    /// service's TXT records matches.
+    ///
+    /// Services are still browsed and resolved, and the ones that do not match are dropped before
+    /// the [`ServiceDiscoveredCallback`] is invoked. Comparing requires the TXT record, so it is
+    /// resolved and reported even if [`set_resolve_txt()`] was set to `false`, in which case a
+    /// warning is logged.
+    ///
+    /// [`set_resolve_all_txt_records()`]: #tymethod.set_resolve_all_txt_records
+    /// [`ServiceDiscoveredCallback`]: type.ServiceDiscoveredCallback.html
+    /// [`set_resolve_txt()`]: #tymethod.set_resolve_txt
+    fn require_txt(&mut self, key: &str, value: &str);
+
+    /// Returns the TXT entries that services must have to be reported, as set with
+    /// [`require_txt()`].
+    ///
+    /// [`require_txt()`]: #tymethod.require_txt
+    fn required_txt(&self) -> &[(String, String)];
+
+    /// Resolves the specified browsed service. The result is delivered to the
+    /// [`ServiceDiscoveredCallback`].
+    ///
+    /// The browser must have been started with [`browse_services()`] first. Returns an
+    /// `EventLoop` which replaces the one returned by [`browse_services()`] and continues to
+    /// drive both the browser and the resolution. Returns [`Error::ServiceTypeMismatch`] if
+    /// `service` is not of the type the browser browses for, which would never be resolved.
+    ///
+    /// [`ServiceDiscoveredCallback`]: ../type.ServiceDiscoveredCallback.html
+    /// [`browse_services()`]: #tymethod.browse_services
+    /// [`Error::ServiceTypeMismatch`]: ../error/enum.Error.html#variant.ServiceTypeMismatch
+    fn resolve(&mut self, service: &ServiceBrowse) -> Result<Self::EventLoop<'_>>;
+
+    /// Resolves the specified browsed service and returns the result. Returns
+    /// [`Error::Timeout`] if the service is not resolved within `timeout`, in which case resolving
+    /// it is stopped and the underlying resolver is freed.
+    ///
+    /// The browser must have been started with [`browse_services()`] first. This replaces the
+    /// browser's [`ServiceDiscoveredCallback`].
+    ///
+    /// [`Error::Timeout`]: ../error/enum.Error.html#variant.Timeout
+    /// [`browse_services()`]: #tymethod.browse_services
+    /// [`ServiceDiscoveredCallback`]: ../type.ServiceDiscoveredCallback.html
+    fn resolve_timeout(
+        &mut self,
+        service: &ServiceBrowse,
+        timeout: Duration,
+    ) -> Result<ServiceDiscovery>;
+
+    /// Sets the [`CancellationToken`] used to cancel browsing while the `EventLoop` is in use.
+    ///
+    /// Once the token has been cancelled, no further callbacks are invoked by this browser and no
+    /// new resolutions are started, even if the `EventLoop` continues to be polled. The
+    /// underlying browser and resolvers are freed by [`cancel()`] or when the browser is dropped.
+    ///
+    /// [`CancellationToken`]: ../cancellation/struct.CancellationToken.html
+    /// [`cancel()`]: #tymethod.cancel
+    fn set_cancellation_token(&mut self, token: CancellationToken);
+
+    /// Stops browsing and frees the underlying browser and all in-flight resolvers. No callbacks
+    /// are invoked after this returns. Any [`CancellationToken`] previously set is discarded.
+    ///
+    /// The browser can be started again with [`browse_services()`].
+    ///
+    /// [`CancellationToken`]: ../cancellation/struct.CancellationToken.html
+    /// [`browse_services()`]: #tymethod.browse_services
+    fn cancel(&mut self);
+
+    /// Stops browsing and frees the underlying browser and all in-flight resolvers as
+    /// [`cancel()`] does, but returns the first error reported while freeing them instead of
+    /// only logging it, as dropping the browser does. Everything is freed even if freeing one of
+    /// them fails.
+    ///
+    /// On Linux, freeing an Avahi object asks the daemon over D-Bus to remove it, which can fail.
+    /// On macOS, deallocating a Bonjour reference cannot fail, so this always returns `Ok`.
+    ///
+    /// [`cancel()`]: #tymethod.cancel
+    fn close(self) -> Result<()>
+    where
+        Self: Sized;
+
     /// Sets the optional user context to pass through to the callback. This is useful if you need
     /// to share state between pre and post-callback. The context type must implement `Any`.
     fn set_context(&mut self, context: Box<dyn Any>);
@@ -35,30 +529,2257 @@ pub trait TMdnsBrowser {
     fn context(&self) -> Option<&dyn Any>;
 
     /// Starts the browser. Returns an `EventLoop` which can be called to keep the browser alive.
-    fn browse_services(&mut self) -> Result<EventLoop>;
+    ///
+    /// Returns an error if services are resolved, as they are by default, but no
+    /// [`ServiceDiscoveredCallback`] has been set, as the resolved services would otherwise go
+    /// unreported. A browser that does not resolve services may be started without callbacks,
+    /// such as to resolve services on demand with [`resolve_timeout()`].
+    ///
+    /// [`ServiceDiscoveredCallback`]: ../type.ServiceDiscoveredCallback.html
+    /// [`resolve_timeout()`]: #tymethod.resolve_timeout
+    fn browse_services(&mut self) -> Result<Self::EventLoop<'_>>;
+
+    /// Sets the multicast group and port to send queries to and listen on, which defaults to the
+    /// standard mDNS endpoint of `224.0.0.251:5353` and `[ff02::fb]:5353`.
+    ///
+    /// Neither Avahi on Linux nor Bonjour on macOS allow their clients to override this, as the
+    /// daemon owns the multicast socket and shares it between all clients. On both platforms,
+    /// only the standard endpoints are accepted, which leaves the browser unchanged, and
+    /// [`Error::Unsupported`] is returned for any other endpoint.
+    ///
+    /// [`Error::Unsupported`]: ../error/enum.Error.html#variant.Unsupported
+    fn set_multicast_endpoint(&mut self, endpoint: SocketAddr) -> Result<()> {
+        if is_standard_multicast_endpoint(endpoint) {
+            Ok(())
+        } else {
+            Err(Error::Unsupported(format!(
+                "browsing on the multicast endpoint {}",
+                endpoint
+            )))
+        }
+    }
+
+    /// Browses and resolves services until one with the specified instance `name` is discovered,
+    /// and returns it. Returns [`Error::Timeout`] if no such service is discovered within
+    /// `timeout`.
+    ///
+    /// This replaces the browser's [`ServiceDiscoveredCallback`]. The browser is stopped with
+    /// [`cancel()`] before returning, whether or not the service was found.
+    ///
+    /// [`Error::Timeout`]: ../error/enum.Error.html#variant.Timeout
+    /// [`ServiceDiscoveredCallback`]: ../type.ServiceDiscoveredCallback.html
+    /// [`cancel()`]: #tymethod.cancel
+    fn wait_for(&mut self, name: &str, timeout: Duration) -> Result<ServiceDiscovery> {
+        let found: Arc<Mutex<Option<ServiceDiscovery>>> = Arc::default();
+        let found_callback = found.clone();
+        let name = name.to_string();
+
+        self.set_service_discovered_callback(Box::new(move |result, _| match result {
+            Ok(service) if *service.name() == name => {
+                *found_callback.lock().unwrap() = Some(service);
+            }
+            Ok(_) => {}
+            Err(e) => warn!("error while waiting for service: {}", e),
+        }));
+
+        let start = Instant::now();
+
+        let result = self.browse_services().and_then(|event_loop| loop {
+            if let Some(service) = found.lock().unwrap().take() {
+                return Ok(service);
+            }
+
+            let elapsed = start.elapsed();
+
+            if elapsed >= timeout {
+                return Err(Error::Timeout);
+            }
+
+            event_loop.poll((timeout - elapsed).min(BLOCKING_POLL_INTERVAL))?;
+        });
+
+        self.cancel();
+
+        result
+    }
+
+    /// Browses and resolves services until the first one is discovered, and returns it as soon as
+    /// it has been resolved. Returns [`Error::Timeout`] if no service is discovered within
+    /// `timeout`.
+    ///
+    /// This is meant for connecting to whichever instance of the browser's service type answers
+    /// first. This replaces the browser's [`ServiceDiscoveredCallback`]. The browser is stopped
+    /// with [`cancel()`] before returning, whether or not a service was found.
+    ///
+    /// [`Error::Timeout`]: ../error/enum.Error.html#variant.Timeout
+    /// [`ServiceDiscoveredCallback`]: ../type.ServiceDiscoveredCallback.html
+    /// [`cancel()`]: #tymethod.cancel
+    fn find_first(&mut self, timeout: Duration) -> Result<ServiceDiscovery> {
+        let found: Arc<Mutex<Option<ServiceDiscovery>>> = Arc::default();
+        let found_callback = found.clone();
+
+        self.set_service_discovered_callback(Box::new(move |result, _| match result {
+            Ok(service) => {
+                found_callback.lock().unwrap().get_or_insert(service);
+            }
+            Err(e) => warn!("error while finding service: {}", e),
+        }));
+
+        let start = Instant::now();
+
+        let result = self.browse_services().and_then(|event_loop| loop {
+            if let Some(service) = found.lock().unwrap().take() {
+                return Ok(service);
+            }
+
+            let elapsed = start.elapsed();
+
+            if elapsed >= timeout {
+                return Err(Error::Timeout);
+            }
+
+            event_loop.poll((timeout - elapsed).min(BLOCKING_POLL_INTERVAL))?;
+        });
+
+        self.cancel();
+
+        result
+    }
+
+    /// Browses and resolves services for the full `timeout` and returns every service discovered
+    /// in that time, in the order they were resolved. A service advertised on more than one
+    /// interface or address family may be reported once for each.
+    ///
+    /// This replaces the browser's [`ServiceDiscoveredCallback`]. The browser is stopped with
+    /// [`cancel()`] before returning.
+    ///
+    /// [`ServiceDiscoveredCallback`]: ../type.ServiceDiscoveredCallback.html
+    /// [`cancel()`]: #tymethod.cancel
+    fn collect(&mut self, timeout: Duration) -> Result<Vec<ServiceDiscovery>> {
+        collect_until(self, timeout, |_| false)
+    }
+
+    /// Browses for the full `timeout` without resolving anything, and returns every service
+    /// instance that is still advertised when it elapses, in the order they appeared.
+    ///
+    /// Only the PTR records are looked up, so this is much cheaper than [`collect()`] for finding
+    /// out which instances exist: no SRV, TXT or address queries are sent. An instance advertised
+    /// on several interfaces is listed once for each, but only once across address families.
+    ///
+    /// This replaces the browser's [`ServiceBrowsedCallback`] and disables automatic resolution
+    /// while browsing, restoring it afterwards. The browser is stopped with [`cancel()`] before
+    /// returning.
+    ///
+    /// [`collect()`]: #method.collect
+    /// [`ServiceBrowsedCallback`]: ../type.ServiceBrowsedCallback.html
+    /// [`cancel()`]: #tymethod.cancel
+    fn list_instances(&mut self, timeout: Duration) -> Result<Vec<ServiceBrowse>> {
+        let instances: Arc<Mutex<Vec<ServiceBrowse>>> = Arc::default();
+        let instances_callback = instances.clone();
+
+        self.set_service_browsed_callback(Box::new(move |result, _| match result {
+            Ok(event) => record_instance(&mut instances_callback.lock().unwrap(), event),
+            Err(e) => warn!("error while listing instances: {}", e),
+        }));
+
+        let resolve_services = self.resolve_services();
+        self.set_resolve_services(false);
+
+        let start = Instant::now();
+
+        let result = self.browse_services().and_then(|event_loop| loop {
+            let elapsed = start.elapsed();
+
+            if elapsed >= timeout {
+                return Ok(());
+            }
+
+            event_loop.poll((timeout - elapsed).min(BLOCKING_POLL_INTERVAL))?;
+        });
+
+        self.cancel();
+        self.set_resolve_services(resolve_services);
+
+        result.map(|()| instances.lock().unwrap().drain(..).collect())
+    }
+
+    /// Like [`collect()`], but sorts the discovered services by the key returned by `key_fn`.
+    ///
+    /// Services with equal keys are ordered by name, domain, address and port, so the result does
+    /// not depend on the order the services were resolved in.
+    ///
+    /// [`collect()`]: #method.collect
+    fn discover_sorted<K, F>(
+        &mut self,
+        timeout: Duration,
+        key_fn: F,
+    ) -> Result<Vec<ServiceDiscovery>>
+    where
+        K: Ord,
+        F: FnMut(&ServiceDiscovery) -> K,
+    {
+        let mut services = self.collect(timeout)?;
+        sort_discoveries(&mut services, key_fn);
+        Ok(services)
+    }
 }
 
-/// Callback invoked from [`MdnsBrowser`] once a service has been discovered and resolved.
+/// How aggressively a [`MdnsBrowser`] queries the network.
 ///
-/// # Arguments
-/// * `discovered_service` - The service that was disovered
-/// * `context` - The optional user context passed through
+/// How often queries are sent is largely controlled by the daemon on both platforms, so these
+/// modes only adjust the few lookup flags each backend exposes:
+///
+/// * `Fast` - sets `AVAHI_LOOKUP_USE_MULTICAST` on the Avahi browser and resolvers, and
+///   `kDNSServiceFlagsForceMulticast` on the Bonjour browser.
+/// * `Balanced` - sets no flags on either platform.
+/// * `PowerSaving` - sets `kDNSServiceFlagsBackgroundTrafficClass` on the Bonjour browser. Avahi
+///   has no equivalent, so this behaves like `Balanced` on Linux.
+///
+/// Forcing multicast sends queries on the local link immediately rather than also consulting
+/// wide-area (unicast) DNS-SD. The background traffic class lets Bonjour defer queries to save
+/// power, at the cost of slower discovery.
 ///
 /// [`MdnsBrowser`]: type.MdnsBrowser.html
-pub type ServiceDiscoveredCallback = dyn Fn(Result<ServiceDiscovery>, Option<Arc<dyn Any>>);
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DiscoveryMode {
+    /// Discover services as quickly as possible
+    Fast,
+    /// Leave the query behavior to the daemon
+    #[default]
+    Balanced,
+    /// Query less aggressively to save power
+    PowerSaving,
+}
 
-/// Represents a service that has been discovered by a [`MdnsBrowser`].
+/// What a [`MdnsBrowser`] does with a service whose instance name is not valid UTF-8, set with
+/// [`TMdnsBrowser::set_on_invalid_utf8()`].
+///
+/// * `Skip` - the service is neither reported nor resolved.
+/// * `Lossy` - invalid sequences in the name are replaced with `U+FFFD` as by
+///   `String::from_utf8_lossy()`. The name as advertised is available from
+///   [`ServiceBrowse::raw_name_bytes()`] and [`ServiceDiscovery::raw_name_bytes()`], and is used
+///   to resolve the service.
+/// * `Error` - an [`Error::InvalidName`] is reported to the [`ServiceBrowsedCallback`] if one is
+///   set, or the [`ServiceDiscoveredCallback`] otherwise, and the service is not resolved.
 ///
 /// [`MdnsBrowser`]: type.MdnsBrowser.html
-#[derive(
-    Debug, Getters, Builder, BuilderDelegate, Serialize, Deserialize, Clone, PartialEq, Eq,
-)]
-pub struct ServiceDiscovery {
-    name: String,
-    service_type: ServiceType,
-    domain: String,
-    host_name: String,
-    address: String,
-    port: u16,
-    txt: Option<TxtRecord>,
+/// [`TMdnsBrowser::set_on_invalid_utf8()`]: trait.TMdnsBrowser.html#tymethod.set_on_invalid_utf8
+/// [`ServiceBrowse::raw_name_bytes()`]: struct.ServiceBrowse.html#method.raw_name_bytes
+/// [`ServiceDiscovery::raw_name_bytes()`]: struct.ServiceDiscovery.html#method.raw_name_bytes
+/// [`Error::InvalidName`]: ../error/enum.Error.html#variant.InvalidName
+/// [`ServiceBrowsedCallback`]: type.ServiceBrowsedCallback.html
+/// [`ServiceDiscoveredCallback`]: type.ServiceDiscoveredCallback.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OnInvalidUtf8 {
+    /// Ignore the service
+    Skip,
+    /// Replace invalid sequences in the name
+    #[default]
+    Lossy,
+    /// Report an error instead of the service
+    Error,
+}
+
+/// Decodes the browsed instance name `raw` according to `policy`, returning `None` if the service
+/// is to be skipped.
+pub(crate) fn decode_instance_name(raw: &[u8], policy: OnInvalidUtf8) -> Result<Option<String>> {
+    match std::str::from_utf8(raw) {
+        Ok(name) => Ok(Some(name.to_string())),
+        Err(_) => match policy {
+            OnInvalidUtf8::Skip => {
+                debug!("skipping service with invalid UTF-8 name: {:?}", raw);
+                Ok(None)
+            }
+            OnInvalidUtf8::Lossy => Ok(Some(String::from_utf8_lossy(raw).into_owned())),
+            OnInvalidUtf8::Error => Err(NameError::InvalidUtf8.into()),
+        },
+    }
+}
+
+/// Returns `raw` if it is not valid UTF-8, for keeping the advertised instance name of a service
+/// whose name was decoded lossily.
+pub(crate) fn invalid_utf8_name(raw: &[u8]) -> Option<Vec<u8>> {
+    std::str::from_utf8(raw).is_err().then(|| raw.to_vec())
+}
+
+/// Default number of browsed services a browser resolves at once, see
+/// [`TMdnsBrowser::set_max_concurrent_resolves()`].
+///
+/// [`TMdnsBrowser::set_max_concurrent_resolves()`]: trait.TMdnsBrowser.html#tymethod.set_max_concurrent_resolves
+pub const DEFAULT_MAX_CONCURRENT_RESOLVES: usize = 32;
+
+/// Services waiting to be resolved for [`TMdnsBrowser::set_max_concurrent_resolves()`].
+///
+/// The number of resolutions in flight is passed in rather than counted, so that it cannot drift
+/// from the resolvers the backend actually holds.
+///
+/// [`TMdnsBrowser::set_max_concurrent_resolves()`]: trait.TMdnsBrowser.html#tymethod.set_max_concurrent_resolves
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(target_vendor = "apple", allow(dead_code))]
+pub(crate) struct ResolveQueue<T> {
+    max_active: usize,
+    queued: VecDeque<T>,
+}
+
+#[cfg_attr(target_vendor = "apple", allow(dead_code))]
+impl<T> ResolveQueue<T> {
+    pub(crate) fn set_max_active(&mut self, max_active: usize) {
+        self.max_active = max_active.max(1);
+    }
+
+    pub(crate) fn max_active(&self) -> usize {
+        self.max_active
+    }
+
+    /// Returns `resolve` if it can be started now that `active` resolutions are in flight, and
+    /// queues it behind the services already waiting otherwise.
+    pub(crate) fn push(&mut self, active: usize, resolve: T) -> Option<T> {
+        if self.queued.is_empty() && active < self.max_active {
+            Some(resolve)
+        } else {
+            self.queued.push_back(resolve);
+            None
+        }
+    }
+
+    /// Returns the next queued service if it can be started now that `active` resolutions are in
+    /// flight.
+    pub(crate) fn pop(&mut self, active: usize) -> Option<T> {
+        if active < self.max_active {
+            self.queued.pop_front()
+        } else {
+            None
+        }
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &T> {
+        self.queued.iter()
+    }
+
+    pub(crate) fn retain<F: FnMut(&T) -> bool>(&mut self, f: F) {
+        self.queued.retain(f);
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.queued.clear();
+    }
+}
+
+impl<T> Default for ResolveQueue<T> {
+    fn default() -> Self {
+        Self {
+            max_active: DEFAULT_MAX_CONCURRENT_RESOLVES,
+            queued: VecDeque::new(),
+        }
+    }
+}
+
+/// The TXT entries that services must have to be reported for [`TMdnsBrowser::require_txt()`].
+///
+/// [`TMdnsBrowser::require_txt()`]: trait.TMdnsBrowser.html#tymethod.require_txt
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub(crate) struct TxtRequirements {
+    entries: Vec<(String, String)>,
+}
+
+impl TxtRequirements {
+    pub(crate) fn insert(&mut self, key: &str, value: &str) {
+        self.entries.retain(|(k, _)| !k.eq_ignore_ascii_case(key));
+        self.entries.push((key.to_string(), value.to_string()));
+    }
+
+    pub(crate) fn entries(&self) -> &[(String, String)] {
+        &self.entries
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns `true` if `result` is the discovery of a service without every required entry.
+    pub(crate) fn suppresses_discovery(&self, result: &Result<ServiceDiscovery>) -> bool {
+        let discovery = match result {
+            Ok(discovery) if !self.is_empty() => discovery,
+            _ => return false,
+        };
+
+        let txt_records = discovery.txt.iter().chain(&discovery.txt_records);
+
+        !self.entries.iter().all(|(key, value)| {
+            txt_records.clone().any(|txt| {
+                txt.iter_bytes()
+                    .any(|(k, v)| k.eq_ignore_ascii_case(key) && v == value.as_bytes())
+            })
+        })
+    }
+
+    /// Logs a warning if entries are required while the browser was told not to resolve TXT
+    /// records.
+    pub(crate) fn warn_if_txt_disabled(&self, resolve_txt: bool) {
+        if !resolve_txt && !self.is_empty() {
+            warn!("TXT records are resolved anyway, as the browser requires TXT entries");
+        }
+    }
+}
+
+/// Counts consecutive failures to resolve services over IPv6 for
+/// [`TMdnsBrowser::set_ipv6_fallback()`].
+///
+/// [`TMdnsBrowser::set_ipv6_fallback()`]: trait.TMdnsBrowser.html#tymethod.set_ipv6_fallback
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(target_vendor = "apple", allow(dead_code))]
+pub(crate) struct Ipv6Fallback {
+    enabled: bool,
+    failures: u32,
+    active: bool,
+}
+
+#[cfg_attr(target_vendor = "apple", allow(dead_code))]
+impl Ipv6Fallback {
+    pub(crate) fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+
+        if !enabled {
+            self.active = false;
+        }
+    }
+
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Returns `true` if only IPv4 should be used from now on.
+    pub(crate) fn is_active(&self) -> bool {
+        self.active
+    }
+
+    /// Records a failure to resolve a service over IPv6, logging a warning the first time the
+    /// fallback takes effect.
+    pub(crate) fn record_failure(&mut self) {
+        self.failures += 1;
+
+        if self.enabled && !self.active && self.failures >= IPV6_FALLBACK_THRESHOLD {
+            warn!(
+                "resolving services over IPv6 failed {} times in a row, only using IPv4 from now on",
+                self.failures
+            );
+            self.active = true;
+        }
+    }
+
+    /// Records a successful resolution of a service over IPv6.
+    pub(crate) fn record_success(&mut self) {
+        self.failures = 0;
+    }
+}
+
+/// The services that a browser has been told to ignore with [`TMdnsBrowser::ignore()`].
+///
+/// [`TMdnsBrowser::ignore()`]: trait.TMdnsBrowser.html#tymethod.ignore
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub(crate) struct IgnoredServices {
+    keys: HashSet<(String, String, String)>,
+}
+
+impl IgnoredServices {
+    pub(crate) fn insert(&mut self, service_key: (String, String, String)) {
+        self.keys.insert(normalize_service_key(service_key));
+    }
+
+    pub(crate) fn remove(&mut self, service_key: &(String, String, String)) {
+        self.keys
+            .remove(&normalize_service_key(service_key.clone()));
+    }
+
+    pub(crate) fn contains(&self, service_key: &(String, String, String)) -> bool {
+        !self.keys.is_empty()
+            && self
+                .keys
+                .contains(&normalize_service_key(service_key.clone()))
+    }
+
+    /// Returns `true` if `result` is the discovery of an ignored service.
+    pub(crate) fn suppresses_discovery(&self, result: &Result<ServiceDiscovery>) -> bool {
+        match result {
+            Ok(discovery) => self.contains(&discovery.service_key()),
+            Err(_) => false,
+        }
+    }
+
+    /// Returns `true` if `result` is a browse event for an ignored service.
+    pub(crate) fn suppresses_event(&self, result: &Result<BrowseEvent>) -> bool {
+        match result {
+            Ok(BrowseEvent::New(service)) | Ok(BrowseEvent::Removed(service)) => {
+                self.contains(&service.service_key())
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Discoveries waiting to be delivered for [`TMdnsBrowser::set_callback_rate_limit()`].
+///
+/// The queue does not keep time itself; the backend pops one discovery each [`interval()`] from a
+/// timer on the event loop.
+///
+/// [`TMdnsBrowser::set_callback_rate_limit()`]: trait.TMdnsBrowser.html#tymethod.set_callback_rate_limit
+/// [`interval()`]: #method.interval
+#[derive(Debug, Default)]
+pub(crate) struct CallbackThrottle {
+    per_second: u32,
+    // discoveries are keyed by their service, and errors by `None`
+    pending: VecDeque<(Option<ServiceKey>, Result<ServiceDiscovery>)>,
+}
+
+type ServiceKey = (String, String, String);
+
+impl CallbackThrottle {
+    pub(crate) fn set_per_second(&mut self, per_second: u32) {
+        self.per_second = per_second;
+    }
+
+    pub(crate) fn per_second(&self) -> u32 {
+        self.per_second
+    }
+
+    /// Returns how long to wait between deliveries, or `None` if unlimited.
+    pub(crate) fn interval(&self) -> Option<Duration> {
+        match self.per_second {
+            0 => None,
+            per_second => Some(Duration::from_secs(1) / per_second),
+        }
+    }
+
+    /// Queues `result`, replacing a queued result for the same service, or a queued error.
+    pub(crate) fn push(&mut self, result: Result<ServiceDiscovery>) {
+        let key = result.as_ref().ok().map(ServiceDiscovery::service_key);
+
+        match self.pending.iter_mut().find(|(k, _)| *k == key) {
+            Some(pending) => pending.1 = result,
+            None => self.pending.push_back((key, result)),
+        }
+    }
+
+    /// Returns the result that has been queued the longest.
+    pub(crate) fn pop(&mut self) -> Option<Result<ServiceDiscovery>> {
+        self.pending.pop_front().map(|(_, result)| result)
+    }
+
+    #[cfg(test)]
+    pub(crate) fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.pending.clear();
+    }
+}
+
+pub(crate) fn service_key(
+    name: &str,
+    service_type: &ServiceType,
+    domain: &str,
+) -> (String, String, String) {
+    normalize_service_key((
+        name.to_string(),
+        format!("_{}._{}", service_type.name(), service_type.protocol()),
+        domain.to_string(),
+    ))
+}
+
+fn normalize_service_key(
+    (name, kind, domain): (String, String, String),
+) -> (String, String, String) {
+    (
+        name.to_ascii_lowercase(),
+        kind.to_ascii_lowercase(),
+        domain.trim_end_matches('.').to_ascii_lowercase(),
+    )
+}
+
+/// Removes the leading dot of each domain, as in `.local`, along with any duplicates and empty
+/// domains, keeping the order in which they were given.
+pub(crate) fn normalize_browse_domains(domains: Vec<String>) -> Vec<String> {
+    let mut normalized: Vec<String> = Vec::with_capacity(domains.len());
+
+    for domain in domains {
+        let domain = domain.trim_start_matches('.');
+
+        let duplicate = normalized.iter().any(|d| {
+            d.trim_end_matches('.')
+                .eq_ignore_ascii_case(domain.trim_end_matches('.'))
+        });
+
+        if !domain.is_empty() && !duplicate {
+            normalized.push(domain.to_string());
+        }
+    }
+
+    normalized
+}
+
+/// Returns the priority and weight at the start of the RDATA of an SRV record.
+pub(crate) fn srv_priority_weight(rdata: &[u8]) -> Result<(u16, u16)> {
+    if rdata.len() < 4 {
+        return Err(format!("SRV record of {} bytes is too short", rdata.len()).into());
+    }
+
+    Ok((
+        u16::from_be_bytes([rdata[0], rdata[1]]),
+        u16::from_be_bytes([rdata[2], rdata[3]]),
+    ))
+}
+
+/// Picks the instance a client should connect to from `instances` of the same service type, per
+/// the server selection rules of RFC 2782, or returns `None` if `instances` is empty.
+///
+/// Only the instances with the lowest SRV priority are considered. Among those, one is picked at
+/// random with a probability proportional to its SRV weight, where instances with a weight of `0`
+/// are picked only rarely, or with equal probability if all weights are `0`.
+///
+/// The priority and weight are only known if the instances were resolved by a browser configured
+/// with [`set_resolve_srv_priority()`]. Otherwise all of them are `0` and an instance is picked
+/// uniformly at random. As RFC 2782 suggests, a client that fails to connect to the selected
+/// instance should remove it from `instances` and select again.
+///
+/// [`set_resolve_srv_priority()`]: trait.TMdnsBrowser.html#tymethod.set_resolve_srv_priority
+pub fn select_instance(instances: &[ServiceDiscovery]) -> Option<&ServiceDiscovery> {
+    select_instance_with(instances, |bound| {
+        (crate::service::random_u64() % (u64::from(bound) + 1)) as u32
+    })
+}
+
+/// Implements [`select_instance()`], where `random(bound)` returns a number between `0` and
+/// `bound` inclusive.
+///
+/// [`select_instance()`]: fn.select_instance.html
+fn select_instance_with<F>(
+    instances: &[ServiceDiscovery],
+    mut random: F,
+) -> Option<&ServiceDiscovery>
+where
+    F: FnMut(u32) -> u32,
+{
+    let priority = instances.iter().map(|i| i.priority).min()?;
+    let tier = instances.iter().filter(|i| i.priority == priority);
+    let total_weight: u32 = tier.clone().map(|i| u32::from(i.weight)).sum();
+
+    if total_weight == 0 {
+        let count = tier.clone().count() as u32;
+        return tier.clone().nth(random(count - 1) as usize);
+    }
+
+    // zero weight instances are ordered first, so that they are only selected if the random
+    // number is exactly 0
+    let mut ordered: Vec<_> = tier.collect();
+    ordered.sort_by_key(|i| i.weight != 0);
+
+    let target = random(total_weight);
+    let mut running_weight = 0;
+
+    ordered.into_iter().find(|i| {
+        running_weight += u32::from(i.weight);
+        running_weight >= target
+    })
+}
+
+/// Port of the standard mDNS multicast endpoints
+const MDNS_PORT: u16 = 5353;
+
+/// Returns `true` if `endpoint` is the standard IPv4 or IPv6 mDNS multicast endpoint.
+fn is_standard_multicast_endpoint(endpoint: SocketAddr) -> bool {
+    let group: IpAddr = match endpoint {
+        SocketAddr::V4(_) => Ipv4Addr::new(224, 0, 0, 251).into(),
+        SocketAddr::V6(_) => Ipv6Addr::new(0xff02, 0, 0, 0, 0, 0, 0, 0xfb).into(),
+    };
+
+    endpoint.ip() == group && endpoint.port() == MDNS_PORT
+}
+
+/// Browses and resolves services with `browser` until `timeout` elapses or a discovered service
+/// satisfies `stop`, and returns every service discovered until then.
+pub(crate) fn collect_until<B, F>(
+    browser: &mut B,
+    timeout: Duration,
+    stop: F,
+) -> Result<Vec<ServiceDiscovery>>
+where
+    B: TMdnsBrowser + ?Sized,
+    F: Fn(&ServiceDiscovery) -> bool,
+{
+    let found: Arc<Mutex<Vec<ServiceDiscovery>>> = Arc::default();
+    let found_callback = found.clone();
+
+    browser.set_service_discovered_callback(Box::new(move |result, _| match result {
+        Ok(service) => found_callback.lock().unwrap().push(service),
+        Err(e) => warn!("error while collecting services: {}", e),
+    }));
+
+    let start = Instant::now();
+
+    let result = browser.browse_services().and_then(|event_loop| loop {
+        if found.lock().unwrap().iter().any(&stop) {
+            return Ok(());
+        }
+
+        let elapsed = start.elapsed();
+
+        if elapsed >= timeout {
+            return Ok(());
+        }
+
+        event_loop.poll((timeout - elapsed).min(BLOCKING_POLL_INTERVAL))?;
+    });
+
+    browser.cancel();
+
+    result.map(|()| found.lock().unwrap().drain(..).collect())
+}
+
+/// Updates the instances listed by [`TMdnsBrowser::list_instances()`] with a browse event,
+/// treating instances with the same key on the same interface as one.
+///
+/// [`TMdnsBrowser::list_instances()`]: trait.TMdnsBrowser.html#method.list_instances
+fn record_instance(instances: &mut Vec<ServiceBrowse>, event: BrowseEvent) {
+    let same = |a: &ServiceBrowse, b: &ServiceBrowse| {
+        a.service_key() == b.service_key() && a.interface() == b.interface()
+    };
+
+    match event {
+        BrowseEvent::New(service) => {
+            if !instances.iter().any(|i| same(i, &service)) {
+                instances.push(service);
+            }
+        }
+        BrowseEvent::Removed(service) => instances.retain(|i| !same(i, &service)),
+        BrowseEvent::NetworkChanged => instances.clear(),
+        BrowseEvent::AllForNow => {}
+    }
+}
+
+/// Browses and resolves services of `service_type` and returns whether any instance advertises
+/// `port`, returning `false` if none does within `timeout`. Returns as soon as one is found.
+///
+/// This is meant for peers sharing a well-known service type to avoid picking a port another
+/// instance already uses. The answer is inherently racy: an instance that registers right after
+/// this returns, or that has not answered yet, is not seen, so two peers checking at the same time
+/// may both pick the same port. Callers that need a guarantee should still handle the port being
+/// taken when binding or registering.
+pub fn is_port_advertised(service_type: ServiceType, port: u16, timeout: Duration) -> Result<bool> {
+    let mut browser = crate::MdnsBrowser::new(service_type);
+    let services = collect_until(&mut browser, timeout, |s| *s.port() == port)?;
+
+    Ok(services.iter().any(|s| *s.port() == port))
+}
+
+/// Browses and resolves services of `service_type`, such as `_http._tcp`, for the full `timeout`,
+/// writing each to `out` as `avahi-browse -rt` prints it, and returns them.
+///
+/// This is meant for quick scripts and debugging, as a starting point that mirrors the standard
+/// tool. Like `avahi-browse -r`, a service is reported once for each interface and address family
+/// it is resolved on. Unlike [`TMdnsBrowser::collect()`], every TXT record, every address and the
+/// SRV priority and weight of each service are resolved, which takes additional lookups:
+///
+/// ```text
+/// = IPv4 My Service                     _http._tcp           local
+///    hostname = [host.local]
+///    address = [192.168.1.2]
+///    port = [8080]
+///    txt = ["path=/"]
+/// ```
+///
+/// Pass standard output to print the services like the tool does:
+///
+/// ```no_run
+/// use std::io;
+/// use std::time::Duration;
+///
+/// zeroconf::browse_and_dump("_http._tcp", Duration::from_secs(3), &mut io::stdout()).unwrap();
+/// ```
+///
+/// Returns an error if the services could not be written to `out`.
+///
+/// [`TMdnsBrowser::collect()`]: trait.TMdnsBrowser.html#method.collect
+pub fn browse_and_dump(
+    service_type: &str,
+    timeout: Duration,
+    out: &mut impl io::Write,
+) -> Result<Vec<ServiceDiscovery>> {
+    let mut browser = crate::MdnsBrowser::new(service_type.parse()?);
+
+    browser.set_resolve_all_txt_records(true);
+    browser.set_resolve_all_addresses(true);
+    browser.set_resolve_srv_priority(true);
+
+    let services = browser.collect(timeout)?;
+
+    for service in &services {
+        writeln!(out, "{}", dump_entry(service))
+            .map_err(|e| Error::from(format!("could not write service: {}", e)))?;
+    }
+
+    Ok(services)
+}
+
+/// Formats `service` as `avahi-browse -r` prints a resolved service.
+fn dump_entry(service: &ServiceDiscovery) -> String {
+    let family = match service.address.parse::<IpAddr>() {
+        Ok(IpAddr::V6(_)) => "IPv6",
+        _ => "IPv4",
+    };
+
+    let kind = format!(
+        "_{}._{}",
+        service.service_type.name(),
+        service.service_type.protocol()
+    );
+
+    let txt_records = match (&service.txt_records[..], &service.txt) {
+        ([], Some(txt)) => vec![txt],
+        (txt_records, _) => txt_records.iter().collect(),
+    };
+
+    let txt = txt_records
+        .into_iter()
+        .flat_map(quote_txt_entries)
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    format!(
+        "= {} {:<30} {:<20} {}\n   hostname = [{}]\n   address = [{}]\n   port = [{}]\n   txt = [{}]",
+        family,
+        service.name,
+        kind,
+        service.domain.trim_end_matches('.'),
+        service.host_name.trim_end_matches('.'),
+        service.address,
+        service.port,
+        txt
+    )
+}
+
+/// Returns [`Error::ServiceTypeMismatch`] if `service` is not of the type `browsed`, ignoring
+/// sub-types, as a browser can only resolve the services it browses for.
+///
+/// [`Error::ServiceTypeMismatch`]: ../error/enum.Error.html#variant.ServiceTypeMismatch
+pub(crate) fn check_resolved_type(browsed: &ServiceType, service: &ServiceBrowse) -> Result<()> {
+    let resolved = service.service_type();
+
+    if resolved.name() == browsed.name() && resolved.protocol() == browsed.protocol() {
+        Ok(())
+    } else {
+        Err(Error::ServiceTypeMismatch {
+            browsed: format!("_{}._{}", browsed.name(), browsed.protocol()),
+            resolved: format!("_{}._{}", resolved.name(), resolved.protocol()),
+        })
+    }
+}
+
+/// Returns the entries of `txt` as quoted, escaped `key=value` character strings.
+fn quote_txt_entries(txt: &TxtRecord) -> Vec<String> {
+    txt.iter_bytes()
+        .map(|(key, value)| {
+            let mut entry = key.into_bytes();
+            entry.push(b'=');
+            entry.extend_from_slice(value);
+            format!("\"{}\"", escape_character_string(&entry))
+        })
+        .collect()
+}
+
+/// Escapes `bytes` for use within a quoted character string in presentation format.
+fn escape_character_string(bytes: &[u8]) -> String {
+    let mut escaped = String::with_capacity(bytes.len());
+
+    for &b in bytes {
+        match b {
+            b'"' | b'\\' => {
+                escaped.push('\\');
+                escaped.push(b as char);
+            }
+            b' '..=b'~' => escaped.push(b as char),
+            b => escaped.push_str(&format!("\\{:03}", b)),
+        }
+    }
+
+    escaped
+}
+
+/// Sorts `services` by `key_fn`, breaking ties by name, domain, address and port.
+pub(crate) fn sort_discoveries<K, F>(services: &mut [ServiceDiscovery], mut key_fn: F)
+where
+    K: Ord,
+    F: FnMut(&ServiceDiscovery) -> K,
+{
+    services.sort_by(|a, b| {
+        key_fn(a).cmp(&key_fn(b)).then_with(|| {
+            (a.name(), a.domain(), a.address(), a.port()).cmp(&(
+                b.name(),
+                b.domain(),
+                b.address(),
+                b.port(),
+            ))
+        })
+    });
+}
+
+/// Callback invoked from [`MdnsBrowser`] once a service has been discovered and resolved.
+///
+/// # Arguments
+/// * `discovered_service` - The service that was disovered
+/// * `context` - The optional user context passed through
+///
+/// [`MdnsBrowser`]: type.MdnsBrowser.html
+pub type ServiceDiscoveredCallback = dyn Fn(Result<ServiceDiscovery>, Option<Arc<dyn Any>>);
+
+/// Callback invoked from [`MdnsBrowser`] once a service has been added to or removed from the
+/// network, before it is resolved.
+///
+/// # Arguments
+/// * `event` - The browse event
+/// * `context` - The optional user context passed through
+///
+/// [`MdnsBrowser`]: type.MdnsBrowser.html
+pub type ServiceBrowsedCallback = dyn Fn(Result<BrowseEvent>, Option<Arc<dyn Any>>);
+
+/// Event reported by a [`MdnsBrowser`] to the [`ServiceBrowsedCallback`].
+///
+/// [`MdnsBrowser`]: type.MdnsBrowser.html
+/// [`ServiceBrowsedCallback`]: type.ServiceBrowsedCallback.html
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub enum BrowseEvent {
+    /// A new service has appeared on the network
+    New(ServiceBrowse),
+    /// A previously browsed service has been removed from the network
+    Removed(ServiceBrowse),
+    /// The services already known to the daemon have all been reported. Emitted once each time
+    /// the browser is started.
+    ///
+    /// This is a good point to stop showing a "scanning" state, but it is not the end of
+    /// browsing: services that appear later, or answer the browser's queries late, are still
+    /// reported afterwards.
+    ///
+    /// On Linux, this is emitted when Avahi reports `AVAHI_BROWSER_ALL_FOR_NOW`. On macOS, this
+    /// is emitted after the first browse result without `kDNSServiceFlagsMoreComing`. Bonjour does
+    /// not report anything until a service has been browsed, so on macOS the event is not emitted
+    /// while no services are found.
+    AllForNow,
+    /// The machine's network has changed and the browser is starting over. Services reported
+    /// before may no longer be reachable; those that are will be reported as new again.
+    ///
+    /// Only emitted if the browser has been configured to restart on network changes with
+    /// [`TMdnsBrowser::set_restart_on_network_change()`].
+    ///
+    /// [`TMdnsBrowser::set_restart_on_network_change()`]: trait.TMdnsBrowser.html#tymethod.set_restart_on_network_change
+    NetworkChanged,
+}
+
+/// Represents a service that has been browsed by a [`MdnsBrowser`] but not yet resolved.
+///
+/// [`MdnsBrowser`]: type.MdnsBrowser.html
+#[derive(
+    Debug, Getters, Builder, BuilderDelegate, Serialize, Deserialize, Clone, PartialEq, Eq,
+)]
+#[builder(build_fn(private, name = "try_build"))]
+pub struct ServiceBrowse {
+    name: String,
+    service_type: ServiceType,
+    domain: String,
+    interface: NetworkInterface,
+    #[builder(default = "NetworkProtocol::Unspec")]
+    protocol: NetworkProtocol,
+    #[builder(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[getter(skip)]
+    raw_name: Option<Vec<u8>>,
+}
+
+builder_build_fn!(ServiceBrowseBuilder, ServiceBrowse);
+
+impl ServiceBrowse {
+    /// Returns the instance name as advertised. This differs from `name()` only if the name is
+    /// not valid UTF-8 and was decoded with [`OnInvalidUtf8::Lossy`].
+    ///
+    /// [`OnInvalidUtf8::Lossy`]: enum.OnInvalidUtf8.html#variant.Lossy
+    pub fn raw_name_bytes(&self) -> &[u8] {
+        self.raw_name.as_deref().unwrap_or(self.name.as_bytes())
+    }
+
+    /// Returns the same key as [`ServiceDiscovery::service_key()`] does once the service has
+    /// been resolved.
+    ///
+    /// [`ServiceDiscovery::service_key()`]: struct.ServiceDiscovery.html#method.service_key
+    pub fn service_key(&self) -> (String, String, String) {
+        service_key(&self.name, &self.service_type, &self.domain)
+    }
+}
+
+/// Represents a service that has been discovered by a [`MdnsBrowser`].
+///
+/// `address()` is the address the service was resolved to. `addresses()` describes that address
+/// and, if the browser was configured with [`set_resolve_all_addresses()`], every other address
+/// of the host, along with where each was received from.
+///
+/// A service usually has a single TXT record, but it is legal to publish more than one. `txt()`
+/// returns the first TXT record for convenience and `txt_records()` returns all of them, in the
+/// order they were received. Unless the browser was configured with
+/// [`set_resolve_all_txt_records()`], only the first TXT record is retrieved and `txt_records()`
+/// contains at most the one record returned by `txt()`.
+///
+/// `priority()` and `weight()` are those of the service's SRV record, as used for server
+/// selection per RFC 2782, if the browser was configured with [`set_resolve_srv_priority()`].
+/// Otherwise both are `0`.
+///
+/// With the `diagnostics` feature, `timing()` describes when the service was browsed and
+/// resolved. It is `None` for a discovery that was deserialized.
+///
+/// `is_our_own()` is `true` if the service was registered by an [`MdnsService`] of this process,
+/// so that a process that both publishes and browses a service type can tell its own services
+/// apart from those of its peers. On Linux, Avahi also reports services registered through the
+/// same connection to the daemon with `AVAHI_LOOKUP_RESULT_OUR_OWN`, which is honored as well.
+///
+/// [`MdnsBrowser`]: type.MdnsBrowser.html
+/// [`MdnsService`]: type.MdnsService.html
+/// [`set_resolve_all_addresses()`]: trait.TMdnsBrowser.html#tymethod.set_resolve_all_addresses
+/// [`set_resolve_all_txt_records()`]: trait.TMdnsBrowser.html#tymethod.set_resolve_all_txt_records
+/// [`set_resolve_srv_priority()`]: trait.TMdnsBrowser.html#tymethod.set_resolve_srv_priority
+#[derive(
+    Debug, Getters, Builder, BuilderDelegate, Serialize, Deserialize, Clone, PartialEq, Eq,
+)]
+#[builder(build_fn(private, name = "try_build"))]
+pub struct ServiceDiscovery {
+    name: String,
+    service_type: ServiceType,
+    domain: String,
+    host_name: String,
+    address: String,
+    port: u16,
+    txt: Option<TxtRecord>,
+    #[builder(default)]
+    #[serde(default)]
+    txt_records: Vec<TxtRecord>,
+    #[builder(default)]
+    #[serde(default)]
+    addresses: Vec<ServiceAddress>,
+    #[builder(default)]
+    #[serde(default)]
+    priority: u16,
+    #[builder(default)]
+    #[serde(default)]
+    weight: u16,
+    #[builder(default)]
+    #[serde(default)]
+    #[getter(skip)]
+    our_own: bool,
+    #[builder(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[getter(skip)]
+    raw_name: Option<Vec<u8>>,
+    #[cfg(feature = "diagnostics")]
+    #[builder(default)]
+    #[serde(skip)]
+    timing: Option<ResolveTiming>,
+}
+
+/// When each phase of discovering a service took place, for diagnosing where the latency of a
+/// discovery is spent. Only available with the `diagnostics` feature.
+///
+/// * `browsed_at` - when the browser reported the service. For a service resolved explicitly
+///   with [`TMdnsBrowser::resolve()`], this is when resolving was started.
+/// * `resolve_started_at` - when resolving the service was started
+/// * `resolved_at` - when the service was reported as resolved, including the time spent
+///   looking up any additional records and waiting for other resolutions to be coalesced
+///
+/// [`TMdnsBrowser::resolve()`]: trait.TMdnsBrowser.html#tymethod.resolve
+#[cfg(feature = "diagnostics")]
+#[derive(Debug, Getters, new, Clone, Copy, PartialEq, Eq)]
+pub struct ResolveTiming {
+    browsed_at: Instant,
+    resolve_started_at: Instant,
+    resolved_at: Instant,
+}
+
+/// What was attempted when resolving a service failed, for diagnosing why it resolved to no
+/// address. Reported with [`Error::ResolveFailed`], only with the `diagnostics` feature.
+///
+/// * `interface` - the interface the service was resolved on, or `Unspec` for any
+/// * `protocol` - the protocol the service's records were looked up over
+/// * `address_protocol` - the family of addresses the host was resolved to, or `Unspec` for any.
+///   A host that only has addresses of the other family, such as an IPv6-only host resolved
+///   over IPv4, resolves to no address.
+/// * `host_name` - the target host of the service's SRV record, if it was found, in which case
+///   the service's host was not reachable or had no A or AAAA record. On Linux, Avahi resolves
+///   the SRV record and addresses together and does not report which one was missing, so this
+///   is always `None`.
+/// * `reason` - the error reported by the daemon, such as a timeout
+///
+/// [`Error::ResolveFailed`]: ../error/enum.Error.html#variant.ResolveFailed
+#[cfg(feature = "diagnostics")]
+#[derive(Debug, Getters, new, Clone, PartialEq, Eq)]
+pub struct ResolveDiagnostics {
+    interface: NetworkInterface,
+    protocol: NetworkProtocol,
+    address_protocol: NetworkProtocol,
+    host_name: Option<String>,
+    reason: String,
+}
+
+#[cfg(feature = "diagnostics")]
+impl ResolveDiagnostics {
+    /// Returns `true` if the service's host was found but did not resolve to an address.
+    pub fn found_host_without_address(&self) -> bool {
+        self.host_name.is_some()
+    }
+}
+
+#[cfg(feature = "diagnostics")]
+impl fmt::Display for ResolveDiagnostics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} (interface: {:?}, protocol: {:?}, address protocol: {:?}",
+            self.reason, self.interface, self.protocol, self.address_protocol
+        )?;
+
+        match &self.host_name {
+            Some(host_name) => write!(f, ", host `{}` has no address)", host_name),
+            None => write!(f, ")"),
+        }
+    }
+}
+
+/// Returns the error reported when resolving a service failed. With the `diagnostics` feature,
+/// what was attempted is attached with [`Error::with_diagnostics()`].
+///
+/// [`Error::with_diagnostics()`]: ../error/enum.Error.html#method.with_diagnostics
+pub(crate) fn resolve_failed(message: String) -> Error {
+    Error::ResolveFailed {
+        message,
+        #[cfg(feature = "diagnostics")]
+        diagnostics: None,
+    }
+}
+
+builder_build_fn!(ServiceDiscoveryBuilder, ServiceDiscovery);
+
+impl ServiceDiscovery {
+    /// Returns `true` if `other` refers to the same logical service, which is the case if both
+    /// have the same [`service_key()`].
+    ///
+    /// The same service is usually discovered once per interface and protocol it is advertised
+    /// on, which is ignored along with the address, host and TXT record of the discoveries.
+    ///
+    /// [`service_key()`]: #method.service_key
+    pub fn same_service(&self, other: &ServiceDiscovery) -> bool {
+        self.service_key() == other.service_key()
+    }
+
+    /// Returns the instance name as advertised. This differs from `name()` only if the name is
+    /// not valid UTF-8 and was decoded with [`OnInvalidUtf8::Lossy`].
+    ///
+    /// [`OnInvalidUtf8::Lossy`]: enum.OnInvalidUtf8.html#variant.Lossy
+    pub fn raw_name_bytes(&self) -> &[u8] {
+        self.raw_name.as_deref().unwrap_or(self.name.as_bytes())
+    }
+
+    /// Returns the instance name, service type (e.g. `_http._tcp`) and domain (without a trailing
+    /// dot) of the service, all lowercased so that they compare case-insensitively, as DNS names
+    /// do per RFC 6763. Suitable as a key for a `HashMap` or `HashSet` of discovered services.
+    pub fn service_key(&self) -> (String, String, String) {
+        service_key(&self.name, &self.service_type, &self.domain)
+    }
+
+    /// Returns `true` if the service was registered by this process.
+    pub fn is_our_own(&self) -> bool {
+        self.our_own
+    }
+
+    /// Returns the target host name of the service's SRV record, such as `device-1234.local`,
+    /// without a trailing dot.
+    ///
+    /// This is the name the service's addresses were resolved from, as opposed to the addresses
+    /// themselves, which are returned by [`ip_addresses()`]. Clients that connect by host name
+    /// rather than by address, such as to send it as the server name in a TLS handshake, should
+    /// use it.
+    ///
+    /// [`ip_addresses()`]: #method.ip_addresses
+    pub fn target_host(&self) -> &str {
+        self.host_name.trim_end_matches('.')
+    }
+
+    /// Returns every distinct address that [`target_host()`] was resolved to, in the order they
+    /// were received, starting with `address()`.
+    ///
+    /// [`target_host()`]: #method.target_host
+    pub fn ip_addresses(&self) -> Vec<IpAddr> {
+        let mut ip_addresses: Vec<IpAddr> = self.address.parse().into_iter().collect();
+
+        for address in &self.addresses {
+            if !ip_addresses.contains(address.address()) {
+                ip_addresses.push(*address.address());
+            }
+        }
+
+        ip_addresses
+    }
+
+    /// Returns the records of the service in DNS presentation format, as in a zone file or the
+    /// output of `dig`: the PTR record of the service type, the SRV and TXT records of the
+    /// instance and an A or AAAA record for each distinct address of the host, one per line.
+    ///
+    /// ```text
+    /// _http._tcp.local. IN PTR My\032Service._http._tcp.local.
+    /// My\032Service._http._tcp.local. IN SRV 0 0 8080 host.local.
+    /// My\032Service._http._tcp.local. IN TXT "path=/"
+    /// host.local. IN A 192.168.1.2
+    /// ```
+    ///
+    /// TTLs are not reported by either platform and are left out. The instance name is escaped as
+    /// in [`ServiceRegistration::full_name()`], and TXT entries are quoted with quotes,
+    /// backslashes and non-printable bytes escaped. A service without a TXT record is shown
+    /// with an empty one, as published by both platforms.
+    ///
+    /// [`ServiceRegistration::full_name()`]: struct.ServiceRegistration.html#method.full_name
+    pub fn to_presentation(&self) -> String {
+        let domain = self.domain.trim_end_matches('.');
+        let kind = format!(
+            "_{}._{}",
+            self.service_type.name(),
+            self.service_type.protocol()
+        );
+        let full_name = format!("{}.{}.{}.", service::escape_label(&self.name), kind, domain);
+        let host_name = format!("{}.", self.host_name.trim_end_matches('.'));
+
+        let mut lines = vec![
+            format!("{}.{}. IN PTR {}", kind, domain, full_name),
+            format!(
+                "{} IN SRV {} {} {} {}",
+                full_name, self.priority, self.weight, self.port, host_name
+            ),
+        ];
+
+        let txt_records = match (&self.txt_records[..], &self.txt) {
+            ([], Some(txt)) => vec![txt],
+            (txt_records, _) => txt_records.iter().collect(),
+        };
+
+        if txt_records.is_empty() {
+            lines.push(format!("{} IN TXT \"\"", full_name));
+        }
+
+        for txt in txt_records {
+            let entries = quote_txt_entries(txt);
+            let entries = if entries.is_empty() {
+                "\"\"".to_string()
+            } else {
+                entries.join(" ")
+            };
+
+            lines.push(format!("{} IN TXT {}", full_name, entries));
+        }
+
+        let mut addresses: Vec<IpAddr> = Vec::new();
+
+        for address in self.addresses.iter().map(|a| a.address) {
+            if !addresses.contains(&address) {
+                addresses.push(address);
+            }
+        }
+
+        if addresses.is_empty() {
+            addresses.extend(self.address.parse::<IpAddr>());
+        }
+
+        for address in addresses {
+            let kind = if address.is_ipv4() { "A" } else { "AAAA" };
+            lines.push(format!("{} IN {} {}", host_name, kind, address));
+        }
+
+        lines.join("\n")
+    }
+
+    /// Returns the [`AddressScope`] of `address`, such as one of the [`addresses()`] of the
+    /// service, for deciding how to reach it. Link-local addresses in particular can only be
+    /// connected to along with the interface they were received on.
+    ///
+    /// [`AddressScope`]: enum.AddressScope.html
+    /// [`addresses()`]: #method.addresses
+    pub fn address_scope(address: &IpAddr) -> AddressScope {
+        match address {
+            IpAddr::V4(address) => ipv4_scope(address),
+            IpAddr::V6(address) => ipv6_scope(address),
+        }
+    }
+
+    /// Adds the addresses of `other`, another resolution of the same service, that this
+    /// discovery does not already have.
+    pub(crate) fn merge_addresses(&mut self, other: ServiceDiscovery) {
+        for address in other.addresses {
+            if !self.addresses.contains(&address) {
+                self.addresses.push(address);
+            }
+        }
+    }
+}
+
+/// An address that a [`ServiceDiscovery`]'s host was resolved to, along with the interface it was
+/// received on and the protocol it was received over.
+///
+/// On Linux, an IPv6 address may be received over IPv4 and vice versa, in which case the same
+/// address may be reported once for each protocol. On macOS, the protocol is always the family of
+/// the address.
+///
+/// [`ServiceDiscovery`]: struct.ServiceDiscovery.html
+#[derive(
+    Debug, Getters, Builder, BuilderDelegate, Serialize, Deserialize, Clone, PartialEq, Eq,
+)]
+#[builder(build_fn(private, name = "try_build"))]
+pub struct ServiceAddress {
+    address: IpAddr,
+    interface: NetworkInterface,
+    protocol: NetworkProtocol,
+}
+
+builder_build_fn!(ServiceAddressBuilder, ServiceAddress);
+
+/// The scope of an address, as returned by [`ServiceDiscovery::address_scope()`].
+///
+/// [`ServiceDiscovery::address_scope()`]: struct.ServiceDiscovery.html#method.address_scope
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AddressScope {
+    /// Only reachable on the local machine: `127.0.0.0/8`, `::1` and interface-local multicast
+    /// addresses
+    Loopback,
+    /// Only reachable on the link it was received on, which must be given as the scope of an
+    /// IPv6 address: `169.254.0.0/16`, `fe80::/10` and link-local multicast addresses
+    LinkLocal,
+    /// Reachable within a site or organization: IPv6 unique local addresses (`fc00::/7`) and the
+    /// private IPv4 networks of RFC 1918
+    UniqueLocal,
+    /// Any other address
+    Global,
+}
+
+fn ipv4_scope(address: &Ipv4Addr) -> AddressScope {
+    if address.is_loopback() {
+        AddressScope::Loopback
+    } else if address.is_link_local() {
+        AddressScope::LinkLocal
+    } else if address.is_private() {
+        AddressScope::UniqueLocal
+    } else {
+        AddressScope::Global
+    }
+}
+
+fn ipv6_scope(address: &Ipv6Addr) -> AddressScope {
+    if let Some(address) = address.to_ipv4_mapped() {
+        return ipv4_scope(&address);
+    }
+
+    let segments = address.segments();
+
+    if address.is_loopback() {
+        AddressScope::Loopback
+    } else if segments[0] & 0xffc0 == 0xfe80 {
+        AddressScope::LinkLocal
+    } else if segments[0] & 0xfe00 == 0xfc00 {
+        AddressScope::UniqueLocal
+    } else if address.is_multicast() {
+        // the scope of a multicast address is in the low bits of its second byte
+        match segments[0] & 0x000f {
+            0x1 => AddressScope::Loopback,
+            0x2 => AddressScope::LinkLocal,
+            _ => AddressScope::Global,
+        }
+    } else {
+        AddressScope::Global
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::InterfaceIndex;
+
+    fn discovery(name: &str, address: &str) -> ServiceDiscovery {
+        ServiceDiscovery::builder()
+            .name(name.to_string())
+            .service_type(ServiceType::new("http", "tcp").unwrap())
+            .domain("local".to_string())
+            .host_name("host.local".to_string())
+            .address(address.to_string())
+            .port(8080)
+            .txt(None)
+            .build()
+            .unwrap()
+    }
+
+    fn scope(address: &str) -> AddressScope {
+        ServiceDiscovery::address_scope(&address.parse().unwrap())
+    }
+
+    #[test]
+    fn address_scope_classifies_ipv6_addresses() {
+        assert_eq!(scope("::1"), AddressScope::Loopback);
+        assert_eq!(scope("fe80::1"), AddressScope::LinkLocal);
+        assert_eq!(scope("febf::1"), AddressScope::LinkLocal);
+        assert_eq!(scope("fd12:3456::1"), AddressScope::UniqueLocal);
+        assert_eq!(scope("fc00::1"), AddressScope::UniqueLocal);
+        assert_eq!(scope("2001:db8::1"), AddressScope::Global);
+        assert_eq!(scope("fec0::1"), AddressScope::Global);
+        assert_eq!(scope("ff01::1"), AddressScope::Loopback);
+        assert_eq!(scope("ff02::fb"), AddressScope::LinkLocal);
+        assert_eq!(scope("ff05::2"), AddressScope::Global);
+    }
+
+    #[test]
+    fn address_scope_classifies_ipv4_addresses() {
+        assert_eq!(scope("127.0.0.1"), AddressScope::Loopback);
+        assert_eq!(scope("169.254.10.1"), AddressScope::LinkLocal);
+        assert_eq!(scope("192.168.1.2"), AddressScope::UniqueLocal);
+        assert_eq!(scope("10.0.0.1"), AddressScope::UniqueLocal);
+        assert_eq!(scope("8.8.8.8"), AddressScope::Global);
+        assert_eq!(scope("::ffff:169.254.10.1"), AddressScope::LinkLocal);
+    }
+
+    #[test]
+    fn resolve_queue_respects_cap_under_flood() {
+        let mut queue = ResolveQueue::default();
+        queue.set_max_active(8);
+
+        let mut active: Vec<usize> = Vec::new();
+        let mut started = Vec::new();
+
+        for i in 0..1000 {
+            if let Some(resolve) = queue.push(active.len(), i) {
+                active.push(resolve);
+                started.push(resolve);
+            }
+
+            assert!(active.len() <= 8);
+
+            // finish a resolution every third discovery
+            if i % 3 == 0 {
+                active.remove(0);
+
+                while let Some(resolve) = queue.pop(active.len()) {
+                    active.push(resolve);
+                    started.push(resolve);
+                }
+            }
+
+            assert!(active.len() <= 8);
+        }
+
+        while !active.is_empty() {
+            active.remove(0);
+
+            while let Some(resolve) = queue.pop(active.len()) {
+                active.push(resolve);
+                started.push(resolve);
+            }
+
+            assert!(active.len() <= 8);
+        }
+
+        assert_eq!(started, (0..1000).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn resolve_queue_treats_zero_as_one() {
+        let mut queue = ResolveQueue::default();
+        queue.set_max_active(0);
+
+        assert_eq!(queue.max_active(), 1);
+        assert_eq!(queue.push(0, "a"), Some("a"));
+        assert_eq!(queue.push(1, "b"), None);
+        assert_eq!(queue.pop(1), None);
+        assert_eq!(queue.pop(0), Some("b"));
+    }
+
+    #[test]
+    fn standard_multicast_endpoints_are_recognized() {
+        assert!(is_standard_multicast_endpoint(
+            "224.0.0.251:5353".parse().unwrap()
+        ));
+        assert!(is_standard_multicast_endpoint(
+            "[ff02::fb]:5353".parse().unwrap()
+        ));
+        assert!(!is_standard_multicast_endpoint(
+            "224.0.0.251:5354".parse().unwrap()
+        ));
+        assert!(!is_standard_multicast_endpoint(
+            "239.255.0.1:5353".parse().unwrap()
+        ));
+    }
+
+    #[test]
+    fn sort_discoveries_sorts_by_key() {
+        let mut services = vec![discovery("b", "10.0.0.1"), discovery("a", "10.0.0.2")];
+
+        sort_discoveries(&mut services, |s| s.name().clone());
+
+        assert_eq!(services[0].name(), "a");
+        assert_eq!(services[1].name(), "b");
+    }
+
+    #[test]
+    fn sort_discoveries_is_independent_of_arrival_order() {
+        let services = vec![
+            discovery("b", "10.0.0.2"),
+            discovery("a", "10.0.0.3"),
+            discovery("b", "10.0.0.1"),
+            discovery("a", "10.0.0.1"),
+        ];
+
+        let mut reversed = services.iter().rev().cloned().collect::<Vec<_>>();
+        let mut services = services;
+
+        // every service has the same key, so the order comes entirely from the tie-break
+        sort_discoveries(&mut services, |_| ());
+        sort_discoveries(&mut reversed, |_| ());
+
+        assert_eq!(services, reversed);
+        assert_eq!(services[0], discovery("a", "10.0.0.1"));
+        assert_eq!(services[3], discovery("b", "10.0.0.2"));
+    }
+
+    fn browse_builder_without(field: &str) -> ServiceBrowseBuilder {
+        let mut builder = ServiceBrowse::builder();
+
+        if field != "name" {
+            builder.name("My Service".to_string());
+        }
+        if field != "service_type" {
+            builder.service_type(ServiceType::new("http", "tcp").unwrap());
+        }
+        if field != "domain" {
+            builder.domain("local".to_string());
+        }
+        if field != "interface" {
+            builder.interface(NetworkInterface::Unspec);
+        }
+
+        builder
+    }
+
+    fn discovery_builder_without(field: &str) -> ServiceDiscoveryBuilder {
+        let mut builder = ServiceDiscovery::builder();
+
+        if field != "name" {
+            builder.name("My Service".to_string());
+        }
+        if field != "service_type" {
+            builder.service_type(ServiceType::new("http", "tcp").unwrap());
+        }
+        if field != "domain" {
+            builder.domain("local".to_string());
+        }
+        if field != "host_name" {
+            builder.host_name("host.local".to_string());
+        }
+        if field != "address" {
+            builder.address("192.168.1.2".to_string());
+        }
+        if field != "port" {
+            builder.port(8080);
+        }
+        if field != "txt" {
+            builder.txt(None);
+        }
+
+        builder
+    }
+
+    #[test]
+    fn service_browse_builder_names_missing_field() {
+        assert!(browse_builder_without("").build().is_ok());
+
+        for field in &["name", "service_type", "domain", "interface"] {
+            assert_eq!(
+                browse_builder_without(field).build(),
+                Err(Error::MissingField(field.to_string()))
+            );
+        }
+    }
+
+    #[test]
+    fn service_discovery_builder_names_missing_field() {
+        assert!(discovery_builder_without("").build().is_ok());
+
+        let fields = &[
+            "name",
+            "service_type",
+            "domain",
+            "host_name",
+            "address",
+            "port",
+            "txt",
+        ];
+
+        for field in fields {
+            assert_eq!(
+                discovery_builder_without(field).build(),
+                Err(Error::MissingField(field.to_string()))
+            );
+        }
+    }
+
+    #[test]
+    fn service_address_builder_names_missing_field() {
+        assert_eq!(
+            ServiceAddress::builder()
+                .address(IpAddr::V4(std::net::Ipv4Addr::LOCALHOST))
+                .interface(NetworkInterface::Unspec)
+                .build(),
+            Err(Error::MissingField("protocol".to_string()))
+        );
+    }
+
+    #[test]
+    fn same_service_ignores_case_and_address() {
+        let mut other = discovery_builder_without("");
+        other
+            .name("MY SERVICE".to_string())
+            .service_type(ServiceType::new("HTTP", "tcp").unwrap())
+            .domain("Local.".to_string())
+            .address("10.0.0.1".to_string());
+
+        assert!(discovery("My Service", "192.168.1.2").same_service(&other.build().unwrap()));
+    }
+
+    #[test]
+    fn target_host_is_separate_from_addresses() {
+        let address = |ip: &str| {
+            ServiceAddress::builder()
+                .address(ip.parse().unwrap())
+                .interface(NetworkInterface::Unspec)
+                .protocol(NetworkProtocol::Unspec)
+                .build()
+                .unwrap()
+        };
+
+        let mut builder = discovery_builder_without("");
+        builder
+            .host_name("device-1234.local.".to_string())
+            .address("192.168.1.2".to_string())
+            .addresses(vec![
+                address("192.168.1.2"),
+                address("fe80::1"),
+                address("fe80::1"),
+            ]);
+
+        let discovery = builder.build().unwrap();
+
+        assert_eq!(discovery.target_host(), "device-1234.local");
+        assert_eq!(
+            discovery.ip_addresses(),
+            vec![
+                "192.168.1.2".parse::<IpAddr>().unwrap(),
+                "fe80::1".parse().unwrap()
+            ]
+        );
+    }
+
+    #[test]
+    fn same_service_differs_by_name() {
+        assert!(!discovery("a", "10.0.0.1").same_service(&discovery("b", "10.0.0.1")));
+    }
+
+    #[test]
+    fn service_key_is_normalized() {
+        assert_eq!(
+            discovery("My Service", "10.0.0.1").service_key(),
+            (
+                "my service".to_string(),
+                "_http._tcp".to_string(),
+                "local".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn merge_addresses_adds_missing_addresses() {
+        let address = |address: &str, protocol| {
+            ServiceAddress::builder()
+                .address(address.parse().unwrap())
+                .interface(NetworkInterface::AtIndex(InterfaceIndex(2)))
+                .protocol(protocol)
+                .build()
+                .unwrap()
+        };
+
+        let v4 = address("192.0.2.1", NetworkProtocol::Ipv4);
+        let v6 = address("2001:db8::1", NetworkProtocol::Ipv6);
+
+        let mut first = discovery_builder_without("");
+        first
+            .address("192.0.2.1".to_string())
+            .addresses(vec![v4.clone()]);
+        let mut first = first.build().unwrap();
+
+        let mut second = discovery_builder_without("");
+        second
+            .address("2001:db8::1".to_string())
+            .addresses(vec![v6.clone(), v4.clone()]);
+
+        first.merge_addresses(second.build().unwrap());
+
+        assert_eq!(first.address(), "192.0.2.1");
+        assert_eq!(first.addresses(), &vec![v4, v6]);
+    }
+
+    #[test]
+    fn to_presentation_lists_records_of_resolved_service() {
+        let address = |address: &str, interface| {
+            ServiceAddress::builder()
+                .address(address.parse().unwrap())
+                .interface(NetworkInterface::AtIndex(InterfaceIndex(interface)))
+                .protocol(NetworkProtocol::Ipv4)
+                .build()
+                .unwrap()
+        };
+
+        let mut txt = TxtRecord::new();
+        txt.insert("path", "/a \"b\"").unwrap();
+        txt.insert("txtvers", "1").unwrap();
+
+        let mut service = discovery_builder_without("");
+        service
+            .name("My Service".to_string())
+            .domain("local.".to_string())
+            .address("192.168.1.2".to_string())
+            .txt(Some(txt))
+            .addresses(vec![
+                address("192.168.1.2", 2),
+                address("192.168.1.2", 3),
+                address("fe80::1", 2),
+            ])
+            .priority(10)
+            .weight(5);
+
+        assert_eq!(
+            service.build().unwrap().to_presentation(),
+            "_http._tcp.local. IN PTR My\\032Service._http._tcp.local.\n\
+             My\\032Service._http._tcp.local. IN SRV 10 5 8080 host.local.\n\
+             My\\032Service._http._tcp.local. IN TXT \"path=/a \\\"b\\\"\" \"txtvers=1\"\n\
+             host.local. IN A 192.168.1.2\n\
+             host.local. IN AAAA fe80::1"
+        );
+    }
+
+    #[test]
+    fn to_presentation_shows_empty_txt_record() {
+        assert_eq!(
+            discovery("a", "10.0.0.1").to_presentation(),
+            "_http._tcp.local. IN PTR a._http._tcp.local.\n\
+             a._http._tcp.local. IN SRV 0 0 8080 host.local.\n\
+             a._http._tcp.local. IN TXT \"\"\n\
+             host.local. IN A 10.0.0.1"
+        );
+    }
+
+    #[test]
+    fn dump_entry_matches_avahi_browse() {
+        let mut service = discovery("My Service", "192.168.1.2");
+        service.txt = Some(TxtRecord::from_pairs(&[("path", "/")]).unwrap());
+
+        assert_eq!(
+            dump_entry(&service),
+            [
+                "= IPv4 My Service                     _http._tcp           local",
+                "   hostname = [host.local]",
+                "   address = [192.168.1.2]",
+                "   port = [8080]",
+                "   txt = [\"path=/\"]",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn dump_entry_shows_family_and_empty_txt() {
+        let service = discovery("My Service", "fe80::1");
+        let dump = dump_entry(&service);
+
+        assert!(dump.starts_with("= IPv6 My Service"));
+        assert!(dump.ends_with("txt = []"));
+    }
+
+    const INVALID_NAME: &[u8] = b"Printer \xff";
+
+    #[test]
+    fn decode_instance_name_keeps_valid_names() {
+        for policy in [
+            OnInvalidUtf8::Skip,
+            OnInvalidUtf8::Lossy,
+            OnInvalidUtf8::Error,
+        ] {
+            assert_eq!(
+                decode_instance_name(b"Printer", policy),
+                Ok(Some("Printer".to_string()))
+            );
+        }
+    }
+
+    #[test]
+    fn decode_instance_name_applies_policy_to_invalid_names() {
+        assert_eq!(
+            decode_instance_name(INVALID_NAME, OnInvalidUtf8::Skip),
+            Ok(None)
+        );
+        assert_eq!(
+            decode_instance_name(INVALID_NAME, OnInvalidUtf8::Lossy),
+            Ok(Some("Printer \u{fffd}".to_string()))
+        );
+        assert_eq!(
+            decode_instance_name(INVALID_NAME, OnInvalidUtf8::Error),
+            Err(Error::InvalidName(NameError::InvalidUtf8))
+        );
+        assert_eq!(OnInvalidUtf8::default(), OnInvalidUtf8::Lossy);
+    }
+
+    #[test]
+    fn raw_name_bytes_returns_advertised_name() {
+        let mut service = browse("Printer");
+        assert_eq!(service.raw_name_bytes(), b"Printer");
+
+        service.name = String::from_utf8_lossy(INVALID_NAME).into_owned();
+        service.raw_name = invalid_utf8_name(INVALID_NAME);
+        assert_eq!(service.raw_name_bytes(), INVALID_NAME);
+
+        let mut discovery = discovery("Printer", "192.168.1.10");
+        assert_eq!(discovery.raw_name_bytes(), b"Printer");
+
+        discovery.raw_name = invalid_utf8_name(INVALID_NAME);
+        assert_eq!(discovery.raw_name_bytes(), INVALID_NAME);
+        assert_eq!(invalid_utf8_name(b"Printer"), None);
+    }
+
+    #[test]
+    fn escape_character_string_escapes_special_bytes() {
+        assert_eq!(
+            escape_character_string(b"a\"b\\c\x01\xff"),
+            "a\\\"b\\\\c\\001\\255"
+        );
+    }
+
+    fn browse(name: &str) -> ServiceBrowse {
+        ServiceBrowse::builder()
+            .name(name.to_string())
+            .service_type(ServiceType::new("http", "tcp").unwrap())
+            .domain("local.".to_string())
+            .interface(NetworkInterface::Unspec)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn check_resolved_type_ignores_sub_types() {
+        let browsed = ServiceType::with_sub_types("http", "tcp", vec!["printer"]).unwrap();
+        assert_eq!(check_resolved_type(&browsed, &browse("printer")), Ok(()));
+    }
+
+    #[test]
+    fn check_resolved_type_rejects_other_type() {
+        let browsed = ServiceType::new("ipp", "tcp").unwrap();
+
+        assert_eq!(
+            check_resolved_type(&browsed, &browse("printer")),
+            Err(Error::ServiceTypeMismatch {
+                browsed: "_ipp._tcp".to_string(),
+                resolved: "_http._tcp".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn service_browse_key_matches_discovery_key() {
+        assert_eq!(
+            browse("My Service").service_key(),
+            discovery("My Service", "10.0.0.1").service_key()
+        );
+    }
+
+    fn discovery_with_txt(entries: &[(&str, &str)]) -> ServiceDiscovery {
+        let mut txt = TxtRecord::new();
+
+        for (key, value) in entries {
+            txt.insert(key, value).unwrap();
+        }
+
+        let mut discovery = discovery_builder_without("");
+        discovery.txt(Some(txt));
+        discovery.build().unwrap()
+    }
+
+    #[test]
+    fn txt_requirements_match_key_case_insensitively() {
+        let mut required = TxtRequirements::default();
+        required.insert("model", "X");
+
+        assert!(!required.suppresses_discovery(&Ok(discovery_with_txt(&[("Model", "X")]))));
+        assert!(required.suppresses_discovery(&Ok(discovery_with_txt(&[("model", "x")]))));
+        assert!(required.suppresses_discovery(&Ok(discovery_with_txt(&[]))));
+        assert!(required.suppresses_discovery(&Ok(discovery("a", "10.0.0.1"))));
+        assert!(!required.suppresses_discovery(&Err(Error::Timeout)));
+    }
+
+    #[test]
+    fn txt_requirements_require_every_entry() {
+        let mut required = TxtRequirements::default();
+        required.insert("model", "X");
+        required.insert("rev", "2");
+        required.insert("MODEL", "Y");
+
+        assert_eq!(
+            required.entries(),
+            &[
+                ("rev".to_string(), "2".to_string()),
+                ("MODEL".to_string(), "Y".to_string())
+            ]
+        );
+        assert!(!required
+            .suppresses_discovery(&Ok(discovery_with_txt(&[("model", "Y"), ("rev", "2")]))));
+        assert!(required.suppresses_discovery(&Ok(discovery_with_txt(&[("model", "Y")]))));
+    }
+
+    #[test]
+    fn ignored_services_suppress_discoveries_and_events() {
+        let mut ignored = IgnoredServices::default();
+        ignored.insert((
+            "MY SERVICE".to_string(),
+            "_HTTP._tcp".to_string(),
+            "local.".to_string(),
+        ));
+
+        assert!(ignored.suppresses_discovery(&Ok(discovery("My Service", "10.0.0.1"))));
+        assert!(!ignored.suppresses_discovery(&Ok(discovery("Other", "10.0.0.1"))));
+        assert!(!ignored.suppresses_discovery(&Err(Error::Timeout)));
+
+        assert!(ignored.suppresses_event(&Ok(BrowseEvent::New(browse("My Service")))));
+        assert!(ignored.suppresses_event(&Ok(BrowseEvent::Removed(browse("My Service")))));
+        assert!(!ignored.suppresses_event(&Ok(BrowseEvent::New(browse("Other")))));
+        assert!(!ignored.suppresses_event(&Ok(BrowseEvent::AllForNow)));
+    }
+
+    #[test]
+    fn ignored_services_can_be_removed() {
+        let mut ignored = IgnoredServices::default();
+        let key = discovery("My Service", "10.0.0.1").service_key();
+
+        ignored.insert(key.clone());
+        ignored.remove(&key);
+
+        assert!(!ignored.contains(&key));
+    }
+
+    #[test]
+    fn callback_throttle_is_unlimited_by_default() {
+        let mut throttle = CallbackThrottle::default();
+
+        assert_eq!(throttle.per_second(), 0);
+        assert_eq!(throttle.interval(), None);
+
+        throttle.set_per_second(4);
+
+        assert_eq!(throttle.interval(), Some(Duration::from_millis(250)));
+    }
+
+    #[test]
+    fn callback_throttle_keeps_latest_per_service() {
+        let mut throttle = CallbackThrottle::default();
+
+        throttle.push(Ok(discovery("a", "10.0.0.1")));
+        throttle.push(Err(Error::Timeout));
+        throttle.push(Ok(discovery("b", "10.0.0.1")));
+        throttle.push(Ok(discovery("A", "10.0.0.2")));
+        throttle.push(Err("could not resolve".into()));
+
+        assert_eq!(throttle.len(), 3);
+        assert_eq!(throttle.pop(), Some(Ok(discovery("A", "10.0.0.2"))));
+        assert_eq!(throttle.pop(), Some(Err("could not resolve".into())));
+        assert_eq!(throttle.pop(), Some(Ok(discovery("b", "10.0.0.1"))));
+        assert_eq!(throttle.pop(), None);
+    }
+
+    #[test]
+    fn callback_throttle_bounds_rate_under_flood() {
+        use crate::event_loop::LoopState;
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        let loop_state = Rc::<LoopState>::default();
+        let throttle = Rc::new(RefCell::new(CallbackThrottle::default()));
+        let delivered = Rc::new(RefCell::new(Vec::new()));
+        let start = Instant::now();
+
+        throttle.borrow_mut().set_per_second(10);
+
+        let _timer = loop_state.register_timer(throttle.borrow().interval().unwrap(), {
+            let throttle = Rc::clone(&throttle);
+            let delivered = Rc::clone(&delivered);
+            move || {
+                if let Some(result) = throttle.borrow_mut().pop() {
+                    delivered.borrow_mut().push(result);
+                }
+            }
+        });
+
+        for round in 0..20 {
+            for service in 0..50 {
+                throttle.borrow_mut().push(Ok(discovery(
+                    &format!("service {}", service),
+                    &format!("10.0.0.{}", round),
+                )));
+            }
+        }
+
+        assert_eq!(throttle.borrow().len(), 50);
+
+        for millis in 0..=1000 {
+            loop_state.run_due_timers(start + Duration::from_millis(millis));
+        }
+
+        let delivered = delivered.borrow();
+
+        assert!(
+            (9..=10).contains(&delivered.len()),
+            "{} delivered",
+            delivered.len()
+        );
+        assert_eq!(throttle.borrow().len(), 50 - delivered.len());
+        assert!(delivered
+            .iter()
+            .all(|d| *d.as_ref().unwrap().address() == "10.0.0.19"));
+    }
+
+    #[test]
+    fn normalize_browse_domains_trims_and_dedups() {
+        let domains = vec![
+            ".local".to_string(),
+            "example.com".to_string(),
+            "LOCAL.".to_string(),
+            ".".to_string(),
+        ];
+
+        assert_eq!(
+            normalize_browse_domains(domains),
+            vec!["local".to_string(), "example.com".to_string()]
+        );
+    }
+
+    fn weighted(name: &str, priority: u16, weight: u16) -> ServiceDiscovery {
+        let mut discovery = discovery(name, "192.168.1.10");
+        discovery.priority = priority;
+        discovery.weight = weight;
+        discovery
+    }
+
+    /// Returns how often each instance is selected when `random` takes every value between 0 and
+    /// the bound it is called with.
+    fn selection_counts(instances: &[ServiceDiscovery]) -> Vec<usize> {
+        let mut counts = vec![0; instances.len()];
+        let bound = {
+            let mut bound = 0;
+            select_instance_with(instances, |b| {
+                bound = b;
+                0
+            });
+            bound
+        };
+
+        for value in 0..=bound {
+            let selected = select_instance_with(instances, |_| value).unwrap();
+            let index = instances.iter().position(|i| i == selected).unwrap();
+            counts[index] += 1;
+        }
+
+        counts
+    }
+
+    #[test]
+    fn select_instance_returns_none_without_instances() {
+        assert_eq!(select_instance(&[]), None);
+    }
+
+    #[test]
+    fn select_instance_prefers_lowest_priority() {
+        let instances = [
+            weighted("backup", 20, 100),
+            weighted("primary", 10, 1),
+            weighted("other backup", 20, 100),
+        ];
+
+        for _ in 0..10 {
+            assert_eq!(select_instance(&instances).unwrap().name(), "primary");
+        }
+        assert_eq!(selection_counts(&instances), vec![0, 2, 0]);
+    }
+
+    #[test]
+    fn select_instance_is_proportional_to_weight() {
+        let instances = [
+            weighted("a", 0, 10),
+            weighted("b", 0, 30),
+            weighted("c", 0, 60),
+        ];
+
+        // the range 0..=100 is split 0..=10, 11..=40 and 41..=100
+        assert_eq!(selection_counts(&instances), vec![11, 30, 60]);
+    }
+
+    #[test]
+    fn select_instance_rarely_selects_zero_weight() {
+        let instances = [weighted("heavy", 0, 10), weighted("zero", 0, 0)];
+
+        assert_eq!(selection_counts(&instances), vec![10, 1]);
+        assert_eq!(
+            select_instance_with(&instances, |_| 0).unwrap().name(),
+            "zero"
+        );
+    }
+
+    #[test]
+    fn select_instance_is_uniform_without_weights() {
+        let instances = [
+            weighted("a", 0, 0),
+            weighted("b", 0, 0),
+            weighted("c", 5, 0),
+        ];
+
+        assert_eq!(selection_counts(&instances), vec![1, 1, 0]);
+        assert!(select_instance(&instances).unwrap().priority == 0);
+    }
+
+    #[test]
+    fn srv_priority_weight_reads_rdata() {
+        assert_eq!(
+            srv_priority_weight(&[0, 10, 0x01, 0x2c, 0x1f, 0x90, 0]),
+            Ok((10, 300))
+        );
+    }
+
+    #[test]
+    fn srv_priority_weight_rejects_short_rdata() {
+        assert_eq!(
+            srv_priority_weight(&[0, 10, 0]),
+            Err("SRV record of 3 bytes is too short".into())
+        );
+    }
+
+    #[test]
+    fn ipv6_fallback_activates_after_threshold() {
+        let mut fallback = Ipv6Fallback::default();
+        fallback.set_enabled(true);
+
+        for _ in 1..IPV6_FALLBACK_THRESHOLD {
+            fallback.record_failure();
+        }
+
+        assert!(!fallback.is_active());
+
+        fallback.record_failure();
+        assert!(fallback.is_active());
+    }
+
+    #[test]
+    fn ipv6_fallback_resets_on_success() {
+        let mut fallback = Ipv6Fallback::default();
+        fallback.set_enabled(true);
+
+        for _ in 1..IPV6_FALLBACK_THRESHOLD {
+            fallback.record_failure();
+        }
+
+        fallback.record_success();
+        fallback.record_failure();
+
+        assert!(!fallback.is_active());
+    }
+
+    #[test]
+    fn ipv6_fallback_is_inactive_when_disabled() {
+        let mut fallback = Ipv6Fallback::default();
+
+        for _ in 0..IPV6_FALLBACK_THRESHOLD {
+            fallback.record_failure();
+        }
+
+        assert!(!fallback.is_active());
+    }
+
+    #[test]
+    fn record_instance_lists_each_instance_once() {
+        let mut instances = vec![];
+        let mut ipv6 = browse("Printer");
+        ipv6.protocol = NetworkProtocol::Ipv6;
+        let mut other_interface = browse("Printer");
+        other_interface.interface = NetworkInterface::AtIndex(InterfaceIndex(2));
+
+        record_instance(&mut instances, BrowseEvent::New(browse("Printer")));
+        record_instance(&mut instances, BrowseEvent::New(ipv6));
+        record_instance(&mut instances, BrowseEvent::New(browse("Scanner")));
+        record_instance(&mut instances, BrowseEvent::New(other_interface.clone()));
+        record_instance(&mut instances, BrowseEvent::AllForNow);
+
+        assert_eq!(
+            instances,
+            vec![browse("Printer"), browse("Scanner"), other_interface]
+        );
+    }
+
+    #[test]
+    fn record_instance_forgets_removed_instances() {
+        let mut instances = vec![browse("Printer"), browse("Scanner")];
+
+        record_instance(&mut instances, BrowseEvent::Removed(browse("printer")));
+        assert_eq!(instances, vec![browse("Scanner")]);
+
+        record_instance(&mut instances, BrowseEvent::NetworkChanged);
+        assert!(instances.is_empty());
+    }
+
+    #[test]
+    fn resolve_failed_reports_message() {
+        let error = resolve_failed("failed to resolve service `Printer`".to_string());
+
+        assert!(matches!(
+            &error,
+            Error::ResolveFailed { message, .. } if message == "failed to resolve service `Printer`"
+        ));
+        assert_eq!(error.to_string(), "failed to resolve service `Printer`");
+    }
+
+    #[cfg(feature = "diagnostics")]
+    #[test]
+    fn resolve_failed_reports_diagnostics() {
+        let diagnostics = ResolveDiagnostics::new(
+            NetworkInterface::AtIndex(crate::InterfaceIndex(2)),
+            NetworkProtocol::Ipv4,
+            NetworkProtocol::Ipv4,
+            Some("printer.local.".to_string()),
+            "Timeout reached".to_string(),
+        );
+
+        assert!(diagnostics.found_host_without_address());
+
+        let error = resolve_failed("failed to resolve service `Printer`".to_string())
+            .with_diagnostics(diagnostics.clone());
+
+        assert_eq!(
+            error.to_string(),
+            "failed to resolve service `Printer`: Timeout reached (interface: \
+             AtIndex(InterfaceIndex(2)), protocol: Ipv4, address protocol: Ipv4, host \
+             `printer.local.` has no address)"
+        );
+        assert_eq!(
+            error,
+            Error::ResolveFailed {
+                message: "failed to resolve service `Printer`".to_string(),
+                diagnostics: Some(Box::new(diagnostics)),
+            }
+        );
+    }
 }
@@ -0,0 +1,75 @@
+//! State of the connection to the mDNS daemon
+
+use std::any::Any;
+use std::sync::Arc;
+
+/// The state of the connection between a [`MdnsService`] or [`MdnsBrowser`] and the mDNS daemon,
+/// as reported to the [`ClientStateCallback`].
+///
+/// On Linux, these are the states of the underlying `AvahiClient`. On macOS, Bonjour has no
+/// equivalent, so `Running` is reported once the service has been registered with or the browser
+/// has been started on the daemon and `Failure` once it reports that it is no longer running.
+/// The other states are never reported on macOS.
+///
+/// [`MdnsService`]: type.MdnsService.html
+/// [`MdnsBrowser`]: type.MdnsBrowser.html
+/// [`ClientStateCallback`]: type.ClientStateCallback.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClientState {
+    /// Waiting for the daemon to become available
+    Connecting,
+    /// Connected, but the daemon is still registering the host's own records
+    Registering,
+    /// Connected and the daemon is ready
+    Running,
+    /// The host name of the daemon collided with another host on the network
+    Collision,
+    /// The connection to the daemon failed and the client can no longer be used
+    Failure,
+}
+
+bitflags! {
+    /// Flags that the connection of a [`MdnsService`] to the mDNS daemon is created with, set
+    /// with [`TMdnsService::set_client_flags()`].
+    ///
+    /// On Linux, these are the `AvahiClientFlags` of the underlying `AvahiClient`, which Avahi
+    /// fixes when the client is created. On macOS, Bonjour has no equivalent and no flags are
+    /// supported.
+    ///
+    /// [`MdnsService`]: type.MdnsService.html
+    /// [`TMdnsService::set_client_flags()`]: trait.TMdnsService.html#tymethod.set_client_flags
+    #[derive(Default)]
+    pub struct ClientFlags: u32 {
+        /// Ignores the user's configuration of the client, such as the extra browse domains set
+        /// with the `AVAHI_BROWSE_DOMAINS` environment variable
+        const IGNORE_USER_CONFIG = 1 << 0;
+        /// Does not fail if the daemon is not running, neither when the connection is created
+        /// nor when the daemon stops later. The connection is [`ClientState::Connecting`] until
+        /// the daemon is available, and the service is published once it is.
+        ///
+        /// [`ClientState::Connecting`]: enum.ClientState.html#variant.Connecting
+        const NO_FAIL = 1 << 1;
+    }
+}
+
+/// Callback invoked from [`MdnsService`] and [`MdnsBrowser`] when the state of their connection
+/// to the mDNS daemon changes.
+///
+/// # Arguments
+/// * `state` - The new state of the connection
+/// * `context` - The optional user context passed through
+///
+/// [`MdnsService`]: type.MdnsService.html
+/// [`MdnsBrowser`]: type.MdnsBrowser.html
+pub type ClientStateCallback = dyn Fn(ClientState, Option<Arc<dyn Any>>);
+
+/// Callback invoked from [`MdnsService`] when the host name it is published under changes, such
+/// as after the daemon renamed the host because of a collision (`host.local` becoming
+/// `host-2.local`).
+///
+/// # Arguments
+/// * `host_name` - The new fully qualified host name
+/// * `context` - The optional user context passed through
+///
+/// [`MdnsService`]: type.MdnsService.html
+pub type HostNameChangedCallback = dyn Fn(&str, Option<Arc<dyn Any>>);
@@ -0,0 +1,57 @@
+//! Typed access to the user context passed through to callbacks
+
+use std::any::Any;
+use std::sync::Arc;
+
+/// Returns the user context passed to a callback as a `&T`, or `None` if no context was set or it
+/// is not a `T`.
+///
+/// The context set with `set_context()` on a [`MdnsBrowser`], [`MdnsService`] or any other
+/// browser is owned by it, passed to each of its callbacks and dropped along with it. This saves
+/// callbacks from downcasting it by hand:
+///
+/// ```no_run
+/// use std::sync::Arc;
+/// use zeroconf::prelude::*;
+/// use zeroconf::{MdnsBrowser, ServiceType};
+///
+/// #[derive(Debug)]
+/// struct AppState {
+///     name: String,
+/// }
+///
+/// let mut browser = MdnsBrowser::new(ServiceType::new("http", "tcp").unwrap());
+///
+/// browser.set_context(Box::new(Arc::new(AppState {
+///     name: "app".to_string(),
+/// })));
+///
+/// browser.set_service_discovered_callback(Box::new(|result, context| {
+///     let state = zeroconf::downcast_context::<Arc<AppState>>(&context).unwrap();
+///     println!("{}: {:?}", state.name, result);
+/// }));
+/// ```
+///
+/// [`MdnsBrowser`]: type.MdnsBrowser.html
+/// [`MdnsService`]: type.MdnsService.html
+pub fn downcast_context<T: Any>(context: &Option<Arc<dyn Any>>) -> Option<&T> {
+    context.as_deref().and_then(|c| c.downcast_ref::<T>())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn downcast_context_returns_context_of_type() {
+        let context: Option<Arc<dyn Any>> = Some(Arc::new(42u32));
+        assert_eq!(downcast_context::<u32>(&context), Some(&42));
+    }
+
+    #[test]
+    fn downcast_context_returns_none_for_other_type() {
+        let context: Option<Arc<dyn Any>> = Some(Arc::new(42u32));
+        assert_eq!(downcast_context::<String>(&context), None);
+        assert_eq!(downcast_context::<u32>(&None), None);
+    }
+}
@@ -0,0 +1,168 @@
+//! Trait definition for cross-platform domain browser
+
+use crate::browser::BLOCKING_POLL_INTERVAL;
+use crate::event_loop::TEventLoop;
+use crate::{DomainBrowser, NetworkInterface, Result};
+use std::any::Any;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// How long [`configured_browse_domains()`] collects the domains reported by the daemon for.
+///
+/// [`configured_browse_domains()`]: fn.configured_browse_domains.html
+const CONFIGURED_DOMAINS_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// The kind of domains enumerated by a [`DomainBrowser`].
+///
+/// Services are browsed for in browse domains but published into registration domains, which
+/// need not be the same. Both usually include `local`.
+///
+/// [`DomainBrowser`]: ../type.DomainBrowser.html
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum DomainBrowserKind {
+    /// Domains recommended for browsing for services
+    #[default]
+    Browse,
+    /// Domains recommended for registering services
+    Register,
+}
+
+/// Interface for interacting with underlying mDNS implementation domain enumeration
+/// capabilities.
+///
+/// A domain browser reports the DNS-SD domains the daemon recommends for browsing or registering,
+/// which can be passed to [`TMdnsBrowser::set_domains()`] or [`TMdnsService::set_domain()`].
+///
+/// [`TMdnsBrowser::set_domains()`]: ../browser/trait.TMdnsBrowser.html#tymethod.set_domains
+/// [`TMdnsService::set_domain()`]: ../service/trait.TMdnsService.html#tymethod.set_domain
+pub trait TDomainBrowser {
+    /// The event loop returned when the browser is started, which borrows the browser.
+    type EventLoop<'a>: TEventLoop
+    where
+        Self: 'a;
+
+    /// Creates a new `DomainBrowser`
+    fn new() -> Self;
+
+    /// Sets the kind of domains to enumerate. Defaults to `DomainBrowserKind::Browse`.
+    fn set_kind(&mut self, kind: DomainBrowserKind);
+
+    /// Returns the kind of domains to enumerate.
+    fn kind(&self) -> DomainBrowserKind;
+
+    /// Sets the network interface on which to enumerate domains on.
+    ///
+    /// Most applications will want to use the default value `NetworkInterface::Unspec` to
+    /// enumerate on all available interfaces.
+    fn set_network_interface(&mut self, interface: NetworkInterface);
+
+    /// Returns the network interface on which to enumerate domains on.
+    fn network_interface(&self) -> NetworkInterface;
+
+    /// Sets the [`DomainDiscoveredCallback`] that is invoked when the browser has discovered a
+    /// domain. Each domain is reported at most once, without a trailing dot.
+    ///
+    /// [`DomainDiscoveredCallback`]: ../type.DomainDiscoveredCallback.html
+    fn set_domain_discovered_callback(
+        &mut self,
+        domain_discovered_callback: Box<DomainDiscoveredCallback>,
+    );
+
+    /// Sets the optional user context to pass through to the callback. This is useful if you need
+    /// to share state between pre and post-callback. The context type must implement `Any`.
+    fn set_context(&mut self, context: Box<dyn Any>);
+
+    /// Returns the optional user context to pass through to the callback.
+    fn context(&self) -> Option<&dyn Any>;
+
+    /// Starts the browser. Returns an `EventLoop` which can be called to keep the browser alive.
+    fn browse_domains(&mut self) -> Result<Self::EventLoop<'_>>;
+}
+
+/// Callback invoked from [`DomainBrowser`] once a domain has been discovered.
+///
+/// # Arguments
+/// * `domain` - The domain that was discovered
+/// * `context` - The optional user context passed through
+///
+/// [`DomainBrowser`]: type.DomainBrowser.html
+pub type DomainDiscoveredCallback = dyn Fn(Result<String>, Option<Arc<dyn Any>>);
+
+/// Returns the domains the daemon is configured to browse, starting with `local`, such as to
+/// default a UI to the domains set by an administrator instead of only `local`.
+///
+/// The domains are enumerated with a [`DomainBrowser`] of kind [`DomainBrowserKind::Browse`],
+/// collecting what the daemon reports within about a second, as neither daemon signals when it
+/// has reported them all. Each domain is returned once, without a trailing dot.
+///
+/// # Platform differences
+///
+/// * On Linux, Avahi reports the `browse-domains` of `avahi-daemon.conf` along with the domains
+///   announced by the network's unicast DNS server, but never `local`, which is added here.
+/// * On macOS, `DNSServiceEnumerateDomains()` reports `local` along with the domains configured
+///   in the system's network settings and those announced by its DNS servers.
+///
+/// [`DomainBrowser`]: ../type.DomainBrowser.html
+/// [`DomainBrowserKind::Browse`]: enum.DomainBrowserKind.html#variant.Browse
+pub fn configured_browse_domains() -> Result<Vec<String>> {
+    let found: Arc<Mutex<Vec<String>>> = Arc::default();
+    let found_callback = found.clone();
+
+    let mut browser = DomainBrowser::new();
+    browser.set_kind(DomainBrowserKind::Browse);
+
+    browser.set_domain_discovered_callback(Box::new(move |result, _| match result {
+        Ok(domain) => found_callback.lock().unwrap().push(domain),
+        Err(e) => warn!("error while enumerating browse domains: {}", e),
+    }));
+
+    let event_loop = browser.browse_domains()?;
+    let start = Instant::now();
+
+    while start.elapsed() < CONFIGURED_DOMAINS_TIMEOUT {
+        let remaining = CONFIGURED_DOMAINS_TIMEOUT - start.elapsed();
+        event_loop.poll(remaining.min(BLOCKING_POLL_INTERVAL))?;
+    }
+
+    let found = found.lock().unwrap().drain(..).collect();
+    Ok(with_local_first(found))
+}
+
+/// Returns `domains` with `local` first and without duplicates, keeping their order otherwise.
+fn with_local_first(domains: Vec<String>) -> Vec<String> {
+    let mut result = vec!["local".to_string()];
+
+    for domain in domains {
+        if !result.contains(&domain) {
+            result.push(domain);
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn with_local_first_adds_local() {
+        assert_eq!(
+            with_local_first(vec!["example.com".to_string()]),
+            vec!["local".to_string(), "example.com".to_string()]
+        );
+    }
+
+    #[test]
+    fn with_local_first_moves_local_and_removes_duplicates() {
+        let domains = ["example.com", "local", "example.org", "example.com"]
+            .iter()
+            .map(|d| d.to_string())
+            .collect();
+
+        assert_eq!(
+            with_local_first(domains),
+            vec!["local", "example.com", "example.org"]
+        );
+    }
+}
@@ -1,18 +1,218 @@
 //! Utilities regarding error handling
 
+#[cfg(feature = "diagnostics")]
+use crate::browser::ResolveDiagnostics;
 use std::fmt;
 
 /// For when something goes wrong when interfacing with mDNS implementations
-#[derive(new, Debug, Clone, PartialEq, Eq)]
-pub struct Error {
-    description: String,
+///
+/// New variants may be added without a major release, so matches on `Error` need a wildcard arm.
+///
+/// # Migrating from the `Error` struct
+///
+/// Up to 0.12, `Error` was a struct holding only a description. [`Error::new()`] and the
+/// conversions from `&str` and `String` still build an [`Error::Generic`] with the description,
+/// and `Display` still prints it, so code that read the description should call `to_string()`.
+/// The deprecated `std::error::Error::description()` never returned it. Code that looked for
+/// particular failures in the description can match on the variants instead, such as
+/// [`Error::Timeout`].
+///
+/// [`Error::new()`]: #method.new
+/// [`Error::Generic`]: #variant.Generic
+/// [`Error::Timeout`]: #variant.Timeout
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// A general error with a description of what went wrong
+    Generic(String),
+    /// An operation was attempted while the library was already operating on the same object,
+    /// such as from within a callback that the object is currently dispatching.
+    Reentrant,
+    /// An operation did not complete within the allotted time
+    Timeout,
+    /// A builder was built without setting the named required field
+    MissingField(String),
+    /// A TXT record parsed in strict mode contained the listed keys more than once
+    DuplicateKeys(Vec<String>),
+    /// A service instance name or service type is not valid
+    InvalidName(NameError),
+    /// A [`ServiceConfig`] has the listed fields that are not valid, along with why
+    ///
+    /// [`ServiceConfig`]: ../struct.ServiceConfig.html
+    InvalidConfig(Vec<(String, Error)>),
+    /// The described feature is not supported by the mDNS implementation of this platform
+    Unsupported(String),
+    /// A browser was asked to resolve a service of another type than the one it browses for
+    ServiceTypeMismatch {
+        /// The type the browser browses for, such as `_http._tcp`
+        browsed: String,
+        /// The type of the service to resolve, such as `_ipp._tcp`
+        resolved: String,
+    },
+    /// A callback panicked while it was invoked from within `poll()` or a similar call. The panic
+    /// was caught so that it did not unwind into the mDNS implementation, and is reported by the
+    /// next call to `poll()`.
+    CallbackPanicked {
+        /// Which callback panicked, such as `service discovered callback`
+        callback: String,
+        /// The message the callback panicked with
+        message: String,
+    },
+    /// Resolving a service failed. With the `diagnostics` feature, what was attempted is attached
+    /// as well, so matches on this variant need a `..` pattern.
+    #[non_exhaustive]
+    ResolveFailed {
+        /// Which service could not be resolved
+        message: String,
+        /// What was attempted when resolving the service, if the platform reported it. Only
+        /// available with the `diagnostics` feature.
+        #[cfg(feature = "diagnostics")]
+        diagnostics: Option<Box<ResolveDiagnostics>>,
+    },
+}
+
+/// Describes why a name passed to [`validate_instance_name()`] or [`validate_service_type()`] is
+/// not valid.
+///
+/// [`validate_instance_name()`]: ../fn.validate_instance_name.html
+/// [`validate_service_type()`]: ../fn.validate_service_type.html
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NameError {
+    /// The name is empty
+    Empty,
+    /// The name is longer than the maximum number of bytes
+    TooLong {
+        /// The length of the name in bytes
+        len: usize,
+        /// The maximum length in bytes
+        max: usize,
+    },
+    /// The name contains a character that is not allowed
+    IllegalCharacter(char),
+    /// The name of a service type does not contain a letter
+    MissingLetter,
+    /// The name of a service type starts or ends with a hyphen or contains two in a row
+    MisplacedHyphen,
+    /// The service type is not of the form `_name._tcp` or `_name._udp`
+    InvalidFormat,
+    /// A browsed instance name is not valid UTF-8
+    InvalidUtf8,
+}
+
+impl fmt::Display for NameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NameError::Empty => write!(f, "name is empty"),
+            NameError::TooLong { len, max } => {
+                write!(f, "name is {} bytes long, exceeding {} bytes", len, max)
+            }
+            NameError::IllegalCharacter(c) => write!(f, "name contains illegal character {:?}", c),
+            NameError::MissingLetter => write!(f, "name does not contain a letter"),
+            NameError::MisplacedHyphen => {
+                write!(
+                    f,
+                    "name starts or ends with a hyphen or contains two in a row"
+                )
+            }
+            NameError::InvalidFormat => {
+                write!(
+                    f,
+                    "service type is not of the form `_name._tcp` or `_name._udp`"
+                )
+            }
+            NameError::InvalidUtf8 => write!(f, "name is not valid UTF-8"),
+        }
+    }
+}
+
+impl Error {
+    /// Creates a new generic `Error` with the specified description
+    pub fn new(description: String) -> Self {
+        Error::Generic(description)
+    }
+
+    /// Converts the error of a generated builder into an `Error`, naming the missing field if
+    /// that is what the error describes.
+    pub(crate) fn from_builder(message: String) -> Self {
+        let field = message
+            .strip_suffix("` must be initialized")
+            .and_then(|m| m.strip_prefix('`'));
+
+        match field {
+            Some(field) => Error::MissingField(field.to_string()),
+            None => Error::new(message),
+        }
+    }
+
+    /// Attaches what was attempted to an [`Error::ResolveFailed`], leaving other errors as they
+    /// are.
+    ///
+    /// [`Error::ResolveFailed`]: #variant.ResolveFailed
+    #[cfg(feature = "diagnostics")]
+    pub(crate) fn with_diagnostics(mut self, diagnostics: ResolveDiagnostics) -> Self {
+        if let Error::ResolveFailed {
+            diagnostics: attached,
+            ..
+        } = &mut self
+        {
+            *attached = Some(Box::new(diagnostics));
+        }
+
+        self
+    }
 }
 
 impl std::error::Error for Error {}
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.description)
+        match self {
+            Error::Generic(description) => write!(f, "{}", description),
+            Error::Reentrant => write!(f, "operation is not permitted while the object is in use"),
+            Error::Timeout => write!(f, "operation timed out"),
+            Error::MissingField(field) => write!(f, "required field `{}` was not set", field),
+            Error::DuplicateKeys(keys) => {
+                write!(f, "TXT record has duplicate keys: {}", keys.join(", "))
+            }
+            Error::InvalidName(e) => write!(f, "invalid name: {}", e),
+            Error::InvalidConfig(errors) => {
+                let errors = errors
+                    .iter()
+                    .map(|(field, e)| format!("`{}`: {}", field, e))
+                    .collect::<Vec<_>>();
+
+                write!(f, "invalid service config: {}", errors.join("; "))
+            }
+            Error::Unsupported(feature) => write!(f, "not supported: {}", feature),
+            Error::ServiceTypeMismatch { browsed, resolved } => write!(
+                f,
+                "cannot resolve a service of type {} with a browser for {}",
+                resolved, browsed
+            ),
+            Error::CallbackPanicked { callback, message } => {
+                write!(f, "{} panicked: {}", callback, message)
+            }
+            Error::ResolveFailed {
+                message,
+                #[cfg(feature = "diagnostics")]
+                diagnostics,
+            } => {
+                write!(f, "{}", message)?;
+
+                #[cfg(feature = "diagnostics")]
+                if let Some(diagnostics) = diagnostics {
+                    write!(f, ": {}", diagnostics)?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
+
+impl From<NameError> for Error {
+    fn from(e: NameError) -> Self {
+        Error::InvalidName(e)
     }
 }
 
@@ -27,3 +227,45 @@ impl From<String> for Error {
         Error::new(s)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_builder_names_missing_field() {
+        assert_eq!(
+            Error::from_builder("`service_type` must be initialized".to_string()),
+            Error::MissingField("service_type".to_string())
+        );
+    }
+
+    #[test]
+    fn from_builder_keeps_other_errors() {
+        assert_eq!(
+            Error::from_builder("invalid value".to_string()),
+            Error::Generic("invalid value".to_string())
+        );
+    }
+
+    #[test]
+    fn service_type_mismatch_display() {
+        let error = Error::ServiceTypeMismatch {
+            browsed: "_http._tcp".to_string(),
+            resolved: "_ipp._tcp".to_string(),
+        };
+
+        assert_eq!(
+            error.to_string(),
+            "cannot resolve a service of type _ipp._tcp with a browser for _http._tcp"
+        );
+    }
+
+    #[test]
+    fn missing_field_display() {
+        assert_eq!(
+            Error::MissingField("name".to_string()).to_string(),
+            "required field `name` was not set"
+        );
+    }
+}
@@ -1,11 +1,693 @@
 //! Trait definition for cross-platform event loop
 
-use crate::Result;
-use std::time::Duration;
+use crate::{ffi, Result};
+use std::cell::{Cell, RefCell};
+use std::fmt;
+use std::mem;
+use std::rc::{Rc, Weak};
+use std::thread;
+use std::time::{Duration, Instant};
 
 /// A handle on the underlying implementation to poll the event loop. Typically, `poll()`
 /// is called in a loop to keep a `MdnsService` or `MdnsBrowser` running.
 pub trait TEventLoop {
     /// Polls for new events.
+    ///
+    /// While the event loop is suspended with [`suspend()`], events are handled according to its
+    /// [`SuspendPolicy`] instead.
+    ///
+    /// This returns before `timeout` has elapsed when the TXT provider of a service, set with
+    /// [`TMdnsService::set_txt_provider()`], is due to be called, and calls it.
+    ///
+    /// A panic in a callback is caught rather than unwinding into the mDNS implementation, and is
+    /// returned as [`Error::CallbackPanicked`] once the events at hand have been handled. The
+    /// event loop stays usable afterwards. A panic in the [`ServiceRegisteredCallback`] also
+    /// moves the service to [`RegistrationState::Failure`].
+    ///
+    /// [`suspend()`]: #tymethod.suspend
+    /// [`SuspendPolicy`]: enum.SuspendPolicy.html
+    /// [`TMdnsService::set_txt_provider()`]: trait.TMdnsService.html#tymethod.set_txt_provider
+    /// [`Error::CallbackPanicked`]: ../error/enum.Error.html#variant.CallbackPanicked
+    /// [`ServiceRegisteredCallback`]: ../type.ServiceRegisteredCallback.html
+    /// [`RegistrationState::Failure`]: ../service/enum.RegistrationState.html#variant.Failure
     fn poll(&self, timeout: Duration) -> Result<()>;
+
+    /// Suspends invoking callbacks until [`resume()`] is called, such as during a CPU-intensive
+    /// phase in which they cannot be serviced. The connection to the daemon and everything
+    /// registered or browsed through it stay intact.
+    ///
+    /// `poll()` may still be called as usual while suspended; what happens to the events that
+    /// arrive in the meantime depends on `policy`. Suspending an already suspended event loop
+    /// replaces its policy.
+    ///
+    /// [`resume()`]: #tymethod.resume
+    fn suspend(&self, policy: SuspendPolicy);
+
+    /// Resumes invoking callbacks after [`suspend()`]. Events queued while suspended are
+    /// processed by the following calls to `poll()`.
+    ///
+    /// [`suspend()`]: #tymethod.suspend
+    fn resume(&self);
+
+    /// Returns the policy the event loop is suspended with, or `None` if it is not suspended.
+    fn suspension(&self) -> Option<SuspendPolicy>;
+
+    /// Returns the number of service resolvers currently alive under this event loop.
+    ///
+    /// A resolver is created for each discovered service and freed once it has reported its
+    /// result, so a count that keeps growing indicates that resolvers are being leaked. On macOS,
+    /// resolution completes before the browser callback returns, so this is always `0`.
+    fn active_resolvers(&self) -> usize;
+
+    /// Returns the number of browsers, including service type browsers, currently alive under
+    /// this event loop.
+    fn active_browsers(&self) -> usize;
+
+    /// Cancels every operation outstanding under this event loop: browsers stop browsing, along
+    /// with their resolvers and record lookups, and services are withdrawn from the network.
+    ///
+    /// Operations are torn down in the reverse order they were started, each the same way as
+    /// cancelling or dropping it would, so that nothing is freed before what depends on it.
+    /// Afterwards [`active_browsers()`] and [`active_resolvers()`] return `0` and the services,
+    /// browsers and the client they share are ready to be dropped. Any of them may also be started
+    /// again, which is not undone by an earlier call.
+    ///
+    /// [`active_browsers()`]: #tymethod.active_browsers
+    /// [`active_resolvers()`]: #tymethod.active_resolvers
+    fn cancel_all(&self);
+}
+
+/// What happens to the events that arrive while an event loop is suspended with
+/// [`TEventLoop::suspend()`].
+///
+/// The library does not keep a backlog of its own, so there is no bound to configure: either the
+/// events are left with the daemon or they are processed without invoking callbacks.
+///
+/// [`TEventLoop::suspend()`]: trait.TEventLoop.html#tymethod.suspend
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SuspendPolicy {
+    /// `poll()` does not read from the connection to the daemon and only waits for its timeout,
+    /// so events queue up on the connection and are processed in order once resumed. The queue is
+    /// bounded by the daemon: if the event loop stays suspended for long on a busy network, the
+    /// daemon may discard events or close the connection.
+    #[default]
+    Queue,
+    /// `poll()` keeps processing events, so registrations progress and resolvers are freed as
+    /// usual, but callbacks are not invoked and the events they would have reported are
+    /// discarded. Nothing queues up, however long the event loop stays suspended.
+    Discard,
+}
+
+/// Withdraws everything registered under `event_loop` and keeps polling it for `timeout`, so that
+/// peers are told the services are gone before the process exits.
+///
+/// When a service is withdrawn, the daemon sends goodbye packets announcing its records with a
+/// TTL of `0`, so that peers drop them at once instead of keeping them cached for the rest of
+/// their TTL. A tool that publishes a service and exits right after may not give the daemon the
+/// chance to: the withdrawal is only sent to the daemon when the service is dropped, which
+/// exiting with `std::process::exit()` or being killed by a signal skips, and the daemon sends
+/// the goodbyes over the following moments.
+///
+/// This withdraws every service and stops every browser with [`TEventLoop::cancel_all()`],
+/// which hands the withdrawals to the daemon before returning on both platforms, then polls the
+/// event loop for the rest of `timeout` to process what the daemon sends back and to give it
+/// time to send the goodbyes. Neither Avahi nor Bonjour reports when the goodbyes have been sent,
+/// so this always waits for the full `timeout`; a few hundred milliseconds is usually enough.
+///
+/// Call it from the thread that polls the event loop once a signal handler, such as one
+/// installed with the `ctrlc` crate, asks it to stop:
+///
+/// ```no_run
+/// use std::sync::atomic::{AtomicBool, Ordering};
+/// use std::time::Duration;
+/// use zeroconf::prelude::*;
+/// use zeroconf::{MdnsService, ServiceType};
+///
+/// // set from the signal handler, e.g. `ctrlc::set_handler(|| STOP.store(true, Ordering::SeqCst))`
+/// static STOP: AtomicBool = AtomicBool::new(false);
+///
+/// let mut service = MdnsService::new(ServiceType::new("http", "tcp").unwrap(), 8080);
+/// service.set_registered_callback(Box::new(|_, _| {}));
+///
+/// let event_loop = service.register().unwrap();
+///
+/// while !STOP.load(Ordering::SeqCst) {
+///     event_loop.poll(Duration::from_millis(100)).unwrap();
+/// }
+///
+/// zeroconf::flush_and_exit_prep(&event_loop, Duration::from_millis(500)).unwrap();
+/// std::process::exit(0);
+/// ```
+///
+/// [`TEventLoop::cancel_all()`]: trait.TEventLoop.html#tymethod.cancel_all
+pub fn flush_and_exit_prep<L>(event_loop: &L, timeout: Duration) -> Result<()>
+where
+    L: TEventLoop + ?Sized,
+{
+    let start = Instant::now();
+
+    event_loop.cancel_all();
+
+    while let Some(remaining) = timeout.checked_sub(start.elapsed()) {
+        if remaining.is_zero() {
+            break;
+        }
+
+        event_loop.poll(remaining.min(FLUSH_POLL_INTERVAL))?;
+    }
+
+    Ok(())
+}
+
+/// Longest time [`flush_and_exit_prep()`] polls for at once.
+///
+/// [`flush_and_exit_prep()`]: fn.flush_and_exit_prep.html
+const FLUSH_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Polls with `poll` as the event loop suspended with `suspension`, if any, does.
+pub(crate) fn poll_suspended<F>(
+    suspension: Option<SuspendPolicy>,
+    timeout: Duration,
+    poll: F,
+) -> Result<()>
+where
+    F: FnOnce(Duration) -> Result<()>,
+{
+    match suspension {
+        None => poll(timeout),
+        Some(SuspendPolicy::Queue) => {
+            thread::sleep(timeout);
+            Ok(())
+        }
+        Some(SuspendPolicy::Discard) => ffi::discarding_callbacks(|| poll(timeout)),
+    }
+}
+
+/// Polls with `poll` like [`poll_suspended()`], but no longer than until the next timer
+/// registered in `loop_state` is due, and runs the timers that are due afterwards. Timers are not run
+/// while suspended, and are run once resumed instead.
+///
+/// Returns [`Error::CallbackPanicked`] if a callback invoked meanwhile panicked.
+///
+/// [`Error::CallbackPanicked`]: ../error/enum.Error.html#variant.CallbackPanicked
+pub(crate) fn poll_with_timers<F>(
+    loop_state: &LoopState,
+    suspension: Option<SuspendPolicy>,
+    timeout: Duration,
+    poll: F,
+) -> Result<()>
+where
+    F: FnOnce(Duration) -> Result<()>,
+{
+    let timeout = match (suspension, loop_state.next_timer_due(Instant::now())) {
+        (None, Some(due)) => timeout.min(due),
+        _ => timeout,
+    };
+
+    poll_suspended(suspension, timeout, poll)?;
+
+    if suspension.is_none() {
+        loop_state.run_due_timers(Instant::now());
+    }
+
+    match ffi::take_callback_panic() {
+        Some(error) => Err(error),
+        None => Ok(()),
+    }
+}
+
+/// The state shared by everything started under an event loop: the counts of the resolvers and
+/// browsers that are alive, the teardowns run by [`TEventLoop::cancel_all()`] and the timers run
+/// while it is polled.
+///
+/// The state is only used from the thread that polls the event loop, so it is shared with `Rc`.
+#[derive(Debug, Default)]
+pub(crate) struct LoopState {
+    resolvers: Cell<usize>,
+    browsers: Cell<usize>,
+    teardowns: Teardowns,
+    timers: Timers,
+}
+
+impl LoopState {
+    /// Registers `teardown` to be run by [`cancel_all()`] for as long as the returned guard is
+    /// alive. Dropping the guard unregisters it without running it.
+    ///
+    /// [`cancel_all()`]: #method.cancel_all
+    pub(crate) fn register_teardown<F>(self: &Rc<Self>, teardown: F) -> TeardownGuard
+    where
+        F: FnOnce() + 'static,
+    {
+        let id = self.teardowns.next_id.get();
+        self.teardowns.next_id.set(id + 1);
+
+        self.teardowns
+            .entries
+            .borrow_mut()
+            .push((id, Box::new(teardown)));
+
+        TeardownGuard {
+            loop_state: Rc::downgrade(self),
+            id,
+        }
+    }
+
+    /// Runs every registered teardown, most recently registered first.
+    pub(crate) fn cancel_all(&self) {
+        // teardowns may drop guards of their own, so they are taken out before being run
+        let entries = mem::take(&mut *self.teardowns.entries.borrow_mut());
+
+        for (_, teardown) in entries.into_iter().rev() {
+            teardown();
+        }
+    }
+
+    /// Registers `tick` to be run every `interval` while an event loop sharing this state is
+    /// polled, starting `interval` from now, for as long as the returned guard is alive.
+    pub(crate) fn register_timer<F>(self: &Rc<Self>, interval: Duration, tick: F) -> TimerGuard
+    where
+        F: Fn() + 'static,
+    {
+        let id = self.timers.next_id.get();
+        self.timers.next_id.set(id + 1);
+
+        self.timers.entries.borrow_mut().push(Timer {
+            id,
+            interval,
+            due: Cell::new(Instant::now() + interval),
+            tick: Rc::new(tick),
+        });
+
+        TimerGuard {
+            loop_state: Rc::downgrade(self),
+            id,
+        }
+    }
+
+    /// Returns how long it is from `now` until the next registered timer is due, which is zero
+    /// if one is overdue, or `None` if no timer is registered.
+    pub(crate) fn next_timer_due(&self, now: Instant) -> Option<Duration> {
+        self.timers
+            .entries
+            .borrow()
+            .iter()
+            .map(|timer| timer.due.get().saturating_duration_since(now))
+            .min()
+    }
+
+    /// Runs every timer that is due at `now` once, and schedules it to run again an interval
+    /// later.
+    pub(crate) fn run_due_timers(&self, now: Instant) {
+        // ticks may register timers or drop guards of their own, so they are run outside the
+        // borrow
+        let due = self
+            .timers
+            .entries
+            .borrow()
+            .iter()
+            .filter(|timer| timer.due.get() <= now)
+            .map(|timer| {
+                timer.due.set(now + timer.interval);
+                Rc::clone(&timer.tick)
+            })
+            .collect::<Vec<_>>();
+
+        for tick in due {
+            tick();
+        }
+    }
+
+    pub(crate) fn resolvers(&self) -> usize {
+        self.resolvers.get()
+    }
+
+    pub(crate) fn browsers(&self) -> usize {
+        self.browsers.get()
+    }
+
+    fn count(&self, kind: ActiveKind) -> &Cell<usize> {
+        match kind {
+            ActiveKind::Resolver => &self.resolvers,
+            ActiveKind::Browser => &self.browsers,
+        }
+    }
+}
+
+/// The kind of object counted by an [`ActiveGuard`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ActiveKind {
+    #[cfg_attr(target_vendor = "apple", allow(dead_code))]
+    Resolver,
+    Browser,
+}
+
+/// Counts an object as active in [`LoopState`] for as long as the guard is alive.
+#[derive(Debug)]
+pub(crate) struct ActiveGuard {
+    loop_state: Rc<LoopState>,
+    kind: ActiveKind,
+}
+
+impl ActiveGuard {
+    pub(crate) fn new(loop_state: &Rc<LoopState>, kind: ActiveKind) -> Self {
+        let count = loop_state.count(kind);
+        count.set(count.get() + 1);
+
+        Self {
+            loop_state: Rc::clone(loop_state),
+            kind,
+        }
+    }
+}
+
+impl Drop for ActiveGuard {
+    fn drop(&mut self) {
+        let count = self.loop_state.count(self.kind);
+        count.set(count.get() - 1);
+    }
+}
+
+type Teardown = Box<dyn FnOnce()>;
+
+#[derive(Default)]
+struct Teardowns {
+    next_id: Cell<u64>,
+    entries: RefCell<Vec<(u64, Teardown)>>,
+}
+
+impl fmt::Debug for Teardowns {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Teardowns")
+            .field("len", &self.entries.borrow().len())
+            .finish()
+    }
+}
+
+/// Keeps a teardown registered with [`LoopState::register_teardown()`] for as long as the
+/// guard is alive.
+#[derive(Debug)]
+pub(crate) struct TeardownGuard {
+    loop_state: Weak<LoopState>,
+    id: u64,
+}
+
+impl Drop for TeardownGuard {
+    fn drop(&mut self) {
+        if let Some(loop_state) = self.loop_state.upgrade() {
+            let removed = {
+                let mut entries = loop_state.teardowns.entries.borrow_mut();
+                let index = entries.iter().position(|(id, _)| *id == self.id);
+                index.map(|i| entries.remove(i))
+            };
+
+            // dropped outside the borrow, as the teardown may own guards of its own
+            drop(removed);
+        }
+    }
+}
+
+struct Timer {
+    id: u64,
+    interval: Duration,
+    due: Cell<Instant>,
+    tick: Rc<dyn Fn()>,
+}
+
+#[derive(Default)]
+struct Timers {
+    next_id: Cell<u64>,
+    entries: RefCell<Vec<Timer>>,
+}
+
+impl fmt::Debug for Timers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Timers")
+            .field("len", &self.entries.borrow().len())
+            .finish()
+    }
+}
+
+/// Keeps a timer registered with [`LoopState::register_timer()`] for as long as the guard is
+/// alive.
+#[derive(Debug)]
+pub(crate) struct TimerGuard {
+    loop_state: Weak<LoopState>,
+    id: u64,
+}
+
+impl Drop for TimerGuard {
+    fn drop(&mut self) {
+        if let Some(loop_state) = self.loop_state.upgrade() {
+            let removed = {
+                let mut entries = loop_state.timers.entries.borrow_mut();
+                let index = entries.iter().position(|timer| timer.id == self.id);
+                index.map(|i| entries.remove(i))
+            };
+
+            drop(removed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Records the calls made to it, polling by sleeping for the timeout.
+    #[derive(Default)]
+    struct RecordingEventLoop {
+        calls: RefCell<Vec<&'static str>>,
+    }
+
+    impl TEventLoop for RecordingEventLoop {
+        fn poll(&self, timeout: Duration) -> Result<()> {
+            self.calls.borrow_mut().push("poll");
+            thread::sleep(timeout);
+            Ok(())
+        }
+
+        fn suspend(&self, _policy: SuspendPolicy) {}
+
+        fn resume(&self) {}
+
+        fn suspension(&self) -> Option<SuspendPolicy> {
+            None
+        }
+
+        fn active_resolvers(&self) -> usize {
+            0
+        }
+
+        fn active_browsers(&self) -> usize {
+            0
+        }
+
+        fn cancel_all(&self) {
+            self.calls.borrow_mut().push("cancel_all");
+        }
+    }
+
+    #[test]
+    fn flush_and_exit_prep_cancels_then_polls_for_timeout() {
+        let event_loop = RecordingEventLoop::default();
+        let start = Instant::now();
+
+        flush_and_exit_prep(&event_loop, Duration::from_millis(120)).unwrap();
+
+        assert!(start.elapsed() >= Duration::from_millis(120));
+
+        let calls = event_loop.calls.borrow();
+        assert_eq!(calls[0], "cancel_all");
+        assert!(calls.len() >= 3);
+        assert!(calls[1..].iter().all(|c| *c == "poll"));
+    }
+
+    #[test]
+    fn flush_and_exit_prep_without_timeout_only_cancels() {
+        let event_loop = RecordingEventLoop::default();
+
+        flush_and_exit_prep(&event_loop, Duration::ZERO).unwrap();
+
+        assert_eq!(*event_loop.calls.borrow(), vec!["cancel_all"]);
+    }
+
+    #[test]
+    fn poll_suspended_with_queue_does_not_poll() {
+        let mut polled = false;
+
+        let result = poll_suspended(Some(SuspendPolicy::Queue), Duration::ZERO, |_| {
+            polled = true;
+            Ok(())
+        });
+
+        assert_eq!(result, Ok(()));
+        assert!(!polled);
+    }
+
+    #[test]
+    fn poll_suspended_with_discard_discards_callbacks() {
+        let mut invoked = false;
+
+        poll_suspended(Some(SuspendPolicy::Discard), Duration::ZERO, |_| {
+            assert!(ffi::catch_callback_panic("callback", || invoked = true));
+            Ok(())
+        })
+        .unwrap();
+
+        assert!(!invoked);
+
+        poll_suspended(None, Duration::ZERO, |_| {
+            ffi::catch_callback_panic("callback", || invoked = true);
+            Ok(())
+        })
+        .unwrap();
+
+        assert!(invoked);
+    }
+
+    #[test]
+    fn active_guard_counts_while_alive() {
+        let loop_state = Rc::<LoopState>::default();
+
+        let resolver = ActiveGuard::new(&loop_state, ActiveKind::Resolver);
+        let browser = ActiveGuard::new(&loop_state, ActiveKind::Browser);
+        let other_browser = ActiveGuard::new(&loop_state, ActiveKind::Browser);
+
+        assert_eq!(loop_state.resolvers(), 1);
+        assert_eq!(loop_state.browsers(), 2);
+
+        drop(resolver);
+        drop(other_browser);
+
+        assert_eq!(loop_state.resolvers(), 0);
+        assert_eq!(loop_state.browsers(), 1);
+
+        drop(browser);
+
+        assert_eq!(loop_state.browsers(), 0);
+    }
+
+    #[test]
+    fn cancel_all_runs_teardowns_in_reverse_order() {
+        let loop_state = Rc::<LoopState>::default();
+        let order = Rc::new(RefCell::new(Vec::new()));
+
+        let guards = (0..3)
+            .map(|i| {
+                let order = Rc::clone(&order);
+                loop_state.register_teardown(move || order.borrow_mut().push(i))
+            })
+            .collect::<Vec<_>>();
+
+        loop_state.cancel_all();
+
+        assert_eq!(*order.borrow(), vec![2, 1, 0]);
+
+        drop(guards);
+        loop_state.cancel_all();
+
+        assert_eq!(*order.borrow(), vec![2, 1, 0]);
+    }
+
+    #[test]
+    fn dropped_teardown_guard_is_not_run() {
+        let loop_state = Rc::<LoopState>::default();
+        let run = Rc::new(Cell::new(false));
+
+        let active = ActiveGuard::new(&loop_state, ActiveKind::Browser);
+        let guard = loop_state.register_teardown({
+            let run = Rc::clone(&run);
+            move || {
+                drop(active);
+                run.set(true);
+            }
+        });
+
+        assert_eq!(loop_state.browsers(), 1);
+
+        drop(guard);
+
+        assert_eq!(loop_state.browsers(), 0);
+
+        loop_state.cancel_all();
+
+        assert!(!run.get());
+    }
+
+    #[test]
+    fn run_due_timers_runs_only_due_timers() {
+        let loop_state = Rc::<LoopState>::default();
+        let ticks = Rc::new(Cell::new(0));
+
+        let guard = loop_state.register_timer(Duration::from_secs(10), {
+            let ticks = Rc::clone(&ticks);
+            move || ticks.set(ticks.get() + 1)
+        });
+
+        let start = Instant::now();
+
+        loop_state.run_due_timers(start);
+        assert_eq!(ticks.get(), 0);
+
+        let due = start + Duration::from_secs(10);
+
+        loop_state.run_due_timers(due);
+        assert_eq!(ticks.get(), 1);
+
+        loop_state.run_due_timers(due);
+        assert_eq!(ticks.get(), 1);
+
+        drop(guard);
+        loop_state.run_due_timers(due + Duration::from_secs(60));
+
+        assert_eq!(ticks.get(), 1);
+    }
+
+    #[test]
+    fn next_timer_due_returns_earliest_timer() {
+        let loop_state = Rc::<LoopState>::default();
+
+        assert_eq!(loop_state.next_timer_due(Instant::now()), None);
+
+        let _slow = loop_state.register_timer(Duration::from_secs(60), || {});
+        let _fast = loop_state.register_timer(Duration::from_secs(5), || {});
+
+        let now = Instant::now();
+        let due = loop_state.next_timer_due(now).unwrap();
+
+        assert!(due <= Duration::from_secs(5));
+        assert!(due > Duration::from_secs(4));
+        assert_eq!(
+            loop_state.next_timer_due(now + Duration::from_secs(6)),
+            Some(Duration::ZERO)
+        );
+    }
+
+    #[test]
+    fn poll_with_timers_shortens_timeout_and_runs_timers() {
+        let loop_state = Rc::<LoopState>::default();
+        let ticked = Rc::new(Cell::new(false));
+
+        let _guard = loop_state.register_timer(Duration::ZERO, {
+            let ticked = Rc::clone(&ticked);
+            move || ticked.set(true)
+        });
+
+        poll_with_timers(
+            &loop_state,
+            Some(SuspendPolicy::Queue),
+            Duration::ZERO,
+            |_| Ok(()),
+        )
+        .unwrap();
+        assert!(!ticked.get());
+
+        poll_with_timers(&loop_state, None, Duration::from_secs(60), |timeout| {
+            assert_eq!(timeout, Duration::ZERO);
+            Ok(())
+        })
+        .unwrap();
+
+        assert!(ticked.get());
+    }
 }
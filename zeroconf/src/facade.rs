@@ -0,0 +1,37 @@
+//! Trait definition for a cross-platform facade that shares one mDNS connection between
+//! services and browsers
+
+use crate::{EventLoop, MdnsBrowser, MdnsService, Result, ServiceType};
+
+/// Interface for sharing a single connection to the underlying mDNS implementation between
+/// any number of services and browsers.
+///
+/// Services and browsers created through a `Zeroconf` are registered and started as usual with
+/// [`TMdnsService::register()`] and [`TMdnsBrowser::browse_services()`], but rather than each
+/// opening their own connection, they all use the one owned by the `Zeroconf`, which is driven by
+/// the single `EventLoop` returned by [`event_loop()`].
+///
+/// Changes in the state of the shared connection, such as the daemon restarting, are forwarded to
+/// every service and browser that has been registered or started, as with their own connection:
+/// their client state callbacks are invoked, services re-publish once the daemon is running again
+/// and report [`RegistrationState::Failure`] when the connection fails.
+///
+/// [`TMdnsService::register()`]: ../service/trait.TMdnsService.html#tymethod.register
+/// [`TMdnsBrowser::browse_services()`]: ../browser/trait.TMdnsBrowser.html#tymethod.browse_services
+/// [`event_loop()`]: #tymethod.event_loop
+/// [`RegistrationState::Failure`]: ../service/enum.RegistrationState.html#variant.Failure
+pub trait TZeroconf: Sized {
+    /// Creates a new `Zeroconf`, opening the shared connection to the mDNS implementation.
+    fn new() -> Result<Self>;
+
+    /// Creates a new `MdnsService` with the specified `ServiceType` and port that registers using
+    /// the shared connection.
+    fn new_service(&self, service_type: ServiceType, port: u16) -> MdnsService;
+
+    /// Creates a new `MdnsBrowser` with the specified `ServiceType` that browses using the shared
+    /// connection.
+    fn new_browser(&self, service_type: ServiceType) -> MdnsBrowser;
+
+    /// Returns an `EventLoop` that drives every service and browser created by this `Zeroconf`.
+    fn event_loop(&self) -> EventLoop;
+}
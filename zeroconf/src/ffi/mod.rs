@@ -1,6 +1,10 @@
 //! Utilities related to FFI bindings
 
+use crate::error::Error;
 use libc::c_void;
+use std::any::Any;
+use std::cell::{Cell, RefCell};
+use std::panic::{self, AssertUnwindSafe};
 use std::ptr;
 
 pub(crate) mod c_str;
@@ -63,6 +67,78 @@ impl<T> UnwrapMutOrNull<T> for Option<*mut T> {
     }
 }
 
+thread_local! {
+    // callbacks are invoked on the thread that polls, from within `poll()`
+    static DISCARDING_CALLBACKS: Cell<bool> = const { Cell::new(false) };
+    // the first panic caught since the last call to `take_callback_panic()`
+    static CAUGHT_PANIC: RefCell<Option<Error>> = const { RefCell::new(None) };
+}
+
+/// Calls `f`, during which [`catch_callback_panic()`] discards the callbacks it is passed
+/// instead of invoking them.
+pub(crate) fn discarding_callbacks<T, F: FnOnce() -> T>(f: F) -> T {
+    let _restore = RestoreDiscarding(DISCARDING_CALLBACKS.with(|d| d.replace(true)));
+    f()
+}
+
+/// Restores whether callbacks are discarded when dropped, including if `f` unwinds.
+struct RestoreDiscarding(bool);
+
+impl Drop for RestoreDiscarding {
+    fn drop(&mut self) {
+        DISCARDING_CALLBACKS.with(|d| d.set(self.0));
+    }
+}
+
+/// Invokes the specified user callback, catching any panic so that it does not unwind across the
+/// FFI boundary of the `extern "C"` trampoline it is called from. A caught panic is logged as an
+/// error, kept for [`take_callback_panic()`] and `false` is returned.
+///
+/// Within [`discarding_callbacks()`], the callback is dropped without being invoked.
+pub(crate) fn catch_callback_panic<F: FnOnce()>(callback_name: &str, f: F) -> bool {
+    if DISCARDING_CALLBACKS.with(Cell::get) {
+        debug!("Discarding {} while suspended", callback_name);
+        return true;
+    }
+
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(()) => true,
+        Err(payload) => {
+            let message = panic_message(payload.as_ref());
+            error!("{} panicked: {}", callback_name, message);
+
+            CAUGHT_PANIC.with(|caught| {
+                caught
+                    .borrow_mut()
+                    .get_or_insert_with(|| Error::CallbackPanicked {
+                        callback: callback_name.to_string(),
+                        message: message.to_string(),
+                    });
+            });
+
+            false
+        }
+    }
+}
+
+/// Returns the first panic caught by [`catch_callback_panic()`] on this thread since the last
+/// call, as an [`Error::CallbackPanicked`], so that the event loop can report it from `poll()`.
+///
+/// [`Error::CallbackPanicked`]: ../error/enum.Error.html#variant.CallbackPanicked
+pub(crate) fn take_callback_panic() -> Option<Error> {
+    CAUGHT_PANIC.with(|caught| caught.borrow_mut().take())
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message
+    } else {
+        "unknown panic"
+    }
+}
+
 #[cfg(target_vendor = "apple")]
 pub(crate) mod macos {
     use crate::Result;
@@ -99,4 +175,100 @@ pub(crate) mod macos {
             Ok(result as u32)
         }
     }
+
+    /// Returns `true` if the peer of the connected `sock_fd` has closed the connection, which is
+    /// checked by peeking at the socket without blocking or consuming any pending data.
+    ///
+    /// # Safety
+    /// This function is unsafe because it directly interfaces with C-library system calls.
+    pub unsafe fn is_closed(sock_fd: i32) -> bool {
+        if sock_fd < 0 {
+            return true;
+        }
+
+        let mut buf = [0u8; 1];
+
+        let result = libc::recv(
+            sock_fd,
+            buf.as_mut_ptr() as *mut libc::c_void,
+            buf.len(),
+            libc::MSG_PEEK | libc::MSG_DONTWAIT,
+        );
+
+        match result {
+            0 => true,
+            n if n > 0 => false,
+            _ => {
+                let errno = std::io::Error::last_os_error().raw_os_error();
+                errno != Some(libc::EAGAIN) && errno != Some(libc::EWOULDBLOCK)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn catch_callback_panic_returns_true_if_callback_completes() {
+        let mut called = false;
+        assert!(catch_callback_panic("callback", || called = true));
+        assert!(called);
+    }
+
+    #[test]
+    fn catch_callback_panic_catches_str_panic() {
+        assert!(!catch_callback_panic("callback", || panic!("uh oh")));
+    }
+
+    #[test]
+    fn catch_callback_panic_catches_string_panic() {
+        assert!(!catch_callback_panic("callback", || panic!("uh oh {}", 42)));
+    }
+
+    #[test]
+    fn take_callback_panic_returns_first_caught_panic() {
+        assert_eq!(take_callback_panic(), None);
+
+        catch_callback_panic("first callback", || panic!("uh oh"));
+        catch_callback_panic("second callback", || panic!("oh no"));
+
+        assert_eq!(
+            take_callback_panic(),
+            Some(Error::CallbackPanicked {
+                callback: "first callback".to_string(),
+                message: "uh oh".to_string(),
+            })
+        );
+        assert_eq!(take_callback_panic(), None);
+    }
+
+    #[test]
+    fn discarding_callbacks_is_undone_if_f_panics() {
+        let result = panic::catch_unwind(|| discarding_callbacks(|| panic!("uh oh")));
+
+        assert!(result.is_err());
+        assert!(!DISCARDING_CALLBACKS.with(Cell::get));
+    }
+
+    #[cfg(target_vendor = "apple")]
+    #[test]
+    fn is_closed_detects_closed_peer() {
+        use std::io::{Read, Write};
+        use std::os::unix::io::AsRawFd;
+        use std::os::unix::net::UnixStream;
+
+        let (mut ours, mut theirs) = UnixStream::pair().unwrap();
+        assert!(!unsafe { macos::is_closed(ours.as_raw_fd()) });
+
+        // pending data is left in place and keeps the connection open until read
+        theirs.write_all(b"x").unwrap();
+        drop(theirs);
+        assert!(!unsafe { macos::is_closed(ours.as_raw_fd()) });
+
+        ours.read_exact(&mut [0u8; 1]).unwrap();
+        assert!(unsafe { macos::is_closed(ours.as_raw_fd()) });
+        assert!(unsafe { macos::is_closed(-1) });
+    }
 }
@@ -1,8 +1,321 @@
+use libc::c_int;
+use std::fmt;
+use std::net::IpAddr;
+use std::ptr;
+
+/// The index of a network interface, as assigned by the operating system and returned by
+/// `if_nametoindex()`.
+///
+/// This wraps the raw index so that it cannot be mixed up with a port or any other integer. The
+/// operating system numbers interfaces from `1`, which leaves [`InterfaceIndex::ANY`] (`0`) to
+/// stand for no particular interface; it converts to [`NetworkInterface::Unspec`] and to the
+/// "any interface" sentinel of each platform, which is `AVAHI_IF_UNSPEC` (`-1`) for Avahi and `0`
+/// for Bonjour.
+///
+/// [`InterfaceIndex::ANY`]: #associatedconstant.ANY
+/// [`NetworkInterface::Unspec`]: enum.NetworkInterface.html#variant.Unspec
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize,
+)]
+pub struct InterfaceIndex(pub u32);
+
+impl InterfaceIndex {
+    /// No particular interface
+    pub const ANY: InterfaceIndex = InterfaceIndex(0);
+
+    /// Returns `true` if this is [`InterfaceIndex::ANY`].
+    ///
+    /// [`InterfaceIndex::ANY`]: #associatedconstant.ANY
+    pub fn is_any(self) -> bool {
+        self == Self::ANY
+    }
+}
+
+impl fmt::Display for InterfaceIndex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl From<InterfaceIndex> for NetworkInterface {
+    /// Converts [`InterfaceIndex::ANY`] to `NetworkInterface::Unspec` and any other index to
+    /// `NetworkInterface::AtIndex`.
+    ///
+    /// [`InterfaceIndex::ANY`]: struct.InterfaceIndex.html#associatedconstant.ANY
+    fn from(index: InterfaceIndex) -> Self {
+        if index.is_any() {
+            NetworkInterface::Unspec
+        } else {
+            NetworkInterface::AtIndex(index)
+        }
+    }
+}
+
+impl From<NetworkInterface> for InterfaceIndex {
+    /// Converts `NetworkInterface::Unspec` to [`InterfaceIndex::ANY`].
+    ///
+    /// [`InterfaceIndex::ANY`]: struct.InterfaceIndex.html#associatedconstant.ANY
+    fn from(interface: NetworkInterface) -> Self {
+        match interface {
+            NetworkInterface::Unspec => InterfaceIndex::ANY,
+            NetworkInterface::AtIndex(index) => index,
+        }
+    }
+}
+
 /// Represents a network interface for mDNS services
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum NetworkInterface {
     /// No interface specified, bind to all available interfaces
     Unspec,
-    /// An interface at a specified index
-    AtIndex(u32),
+    /// An interface at a specified index. `AtIndex(InterfaceIndex::ANY)` is treated like
+    /// `Unspec`.
+    AtIndex(InterfaceIndex),
+}
+
+impl NetworkInterface {
+    /// Returns `true` if this interface has an address of the same family as `address`. For
+    /// `Unspec`, any interface is considered.
+    pub(crate) fn supports_address(&self, address: &IpAddr) -> bool {
+        let family = match address {
+            IpAddr::V4(_) => libc::AF_INET,
+            IpAddr::V6(_) => libc::AF_INET6,
+        };
+
+        let mut addrs: *mut libc::ifaddrs = ptr::null_mut();
+
+        if unsafe { libc::getifaddrs(&mut addrs) } != 0 {
+            return false;
+        }
+
+        let mut supported = false;
+        let mut current = addrs;
+
+        while !current.is_null() && !supported {
+            let ifaddr = unsafe { &*current };
+
+            let is_family = !ifaddr.ifa_addr.is_null()
+                && c_int::from(unsafe { (*ifaddr.ifa_addr).sa_family }) == family;
+
+            supported = is_family
+                && match self {
+                    NetworkInterface::Unspec => true,
+                    NetworkInterface::AtIndex(index) if index.is_any() => true,
+                    NetworkInterface::AtIndex(index) => {
+                        let ifa_index = unsafe { libc::if_nametoindex(ifaddr.ifa_name) };
+                        ifa_index == index.0
+                    }
+                };
+
+            current = ifaddr.ifa_next;
+        }
+
+        unsafe { libc::freeifaddrs(addrs) };
+
+        supported
+    }
+
+    /// Returns the current addresses of this interface. For `Unspec`, the addresses of all
+    /// interfaces are returned.
+    pub(crate) fn addresses(&self) -> Vec<IpAddr> {
+        let mut addrs: *mut libc::ifaddrs = ptr::null_mut();
+
+        if unsafe { libc::getifaddrs(&mut addrs) } != 0 {
+            return Vec::new();
+        }
+
+        let mut addresses = Vec::new();
+        let mut current = addrs;
+
+        while !current.is_null() {
+            let ifaddr = unsafe { &*current };
+            current = ifaddr.ifa_next;
+
+            if ifaddr.ifa_addr.is_null() {
+                continue;
+            }
+
+            if let NetworkInterface::AtIndex(index) = self {
+                if !index.is_any() && unsafe { libc::if_nametoindex(ifaddr.ifa_name) } != index.0 {
+                    continue;
+                }
+            }
+
+            let address = match c_int::from(unsafe { (*ifaddr.ifa_addr).sa_family }) {
+                libc::AF_INET => {
+                    let addr = unsafe { &*(ifaddr.ifa_addr as *const libc::sockaddr_in) };
+                    IpAddr::from(u32::from_be(addr.sin_addr.s_addr).to_be_bytes())
+                }
+                libc::AF_INET6 => {
+                    let addr = unsafe { &*(ifaddr.ifa_addr as *const libc::sockaddr_in6) };
+                    IpAddr::from(addr.sin6_addr.s6_addr)
+                }
+                _ => continue,
+            };
+
+            addresses.push(address);
+        }
+
+        unsafe { libc::freeifaddrs(addrs) };
+
+        addresses
+    }
+}
+
+/// Returns the indices of the interfaces that are up, are not loopback interfaces and have an
+/// address of `protocol`, in ascending order.
+#[cfg_attr(target_vendor = "apple", allow(dead_code))]
+pub(crate) fn active_interfaces(protocol: NetworkProtocol) -> Vec<InterfaceIndex> {
+    let mut addrs: *mut libc::ifaddrs = ptr::null_mut();
+
+    if unsafe { libc::getifaddrs(&mut addrs) } != 0 {
+        return Vec::new();
+    }
+
+    let mut interfaces = Vec::new();
+    let mut current = addrs;
+
+    while !current.is_null() {
+        let ifaddr = unsafe { &*current };
+        current = ifaddr.ifa_next;
+
+        let flags = ifaddr.ifa_flags as c_int;
+
+        if ifaddr.ifa_addr.is_null() || flags & libc::IFF_UP == 0 || flags & libc::IFF_LOOPBACK != 0
+        {
+            continue;
+        }
+
+        let has_protocol = matches!(
+            (
+                c_int::from(unsafe { (*ifaddr.ifa_addr).sa_family }),
+                protocol
+            ),
+            (
+                libc::AF_INET,
+                NetworkProtocol::Ipv4 | NetworkProtocol::Unspec
+            ) | (
+                libc::AF_INET6,
+                NetworkProtocol::Ipv6 | NetworkProtocol::Unspec
+            )
+        );
+
+        if has_protocol {
+            interfaces.push(InterfaceIndex(unsafe {
+                libc::if_nametoindex(ifaddr.ifa_name)
+            }));
+        }
+    }
+
+    unsafe { libc::freeifaddrs(addrs) };
+
+    interfaces.sort();
+    interfaces.dedup();
+    interfaces.retain(|i| !i.is_any());
+    interfaces
+}
+
+/// Represents a network protocol (address family) for mDNS services
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NetworkProtocol {
+    /// No protocol specified, use both IPv4 and IPv6
+    Unspec,
+    /// IPv4 only
+    Ipv4,
+    /// IPv6 only
+    Ipv6,
+}
+
+/// The scope on which a service is advertised or browsed for.
+///
+/// * `LinkLocal` - only multicast DNS on the local link, ignoring any wide-area configuration of
+///   the daemon. Browsers query the `local` domain and force multicast lookups.
+/// * `WideArea` - only unicast (wide-area) DNS-SD in the daemon's default domains. This finds
+///   nothing unless the daemon has been configured for wide-area DNS-SD.
+/// * `Both` - whatever the daemon is configured for, usually both of the above.
+///
+/// Avahi can only publish services on the local link, so registering a [`MdnsService`] with
+/// `WideArea` fails on Linux and `Both` behaves like `LinkLocal`. Bonjour has no way of excluding
+/// the local link when registering, so on macOS a service registered with `WideArea` or `Both`
+/// is published in its default registration domains, which include `local`.
+///
+/// A domain set explicitly with [`TMdnsService::set_domain()`] takes precedence over the domain
+/// chosen by the scope.
+///
+/// [`MdnsService`]: type.MdnsService.html
+/// [`TMdnsService::set_domain()`]: trait.TMdnsService.html#tymethod.set_domain
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Scope {
+    /// Only the local link
+    #[default]
+    LinkLocal,
+    /// Only wide-area DNS-SD
+    WideArea,
+    /// Both the local link and wide-area DNS-SD
+    Both,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn active_interfaces_skips_loopback() {
+        let interfaces = active_interfaces(NetworkProtocol::Unspec);
+
+        // the loopback interface is `lo` on Linux and `lo0` on macOS
+        for name in &["lo", "lo0"] {
+            let name = c_string!(*name);
+            let loopback = InterfaceIndex(unsafe { libc::if_nametoindex(name.as_ptr()) });
+            assert!(!interfaces.contains(&loopback));
+        }
+
+        assert!(!interfaces.contains(&InterfaceIndex::ANY));
+        assert!(interfaces.windows(2).all(|w| w[0] < w[1]));
+
+        for protocol in [NetworkProtocol::Ipv4, NetworkProtocol::Ipv6] {
+            assert!(active_interfaces(protocol)
+                .iter()
+                .all(|i| interfaces.contains(i)));
+        }
+    }
+
+    #[test]
+    fn interface_index_any_converts_to_unspec() {
+        assert!(InterfaceIndex::ANY.is_any());
+        assert_eq!(
+            NetworkInterface::from(InterfaceIndex::ANY),
+            NetworkInterface::Unspec
+        );
+        assert_eq!(
+            InterfaceIndex::from(NetworkInterface::Unspec),
+            InterfaceIndex::ANY
+        );
+    }
+
+    #[test]
+    fn interface_index_converts_to_at_index() {
+        let index = InterfaceIndex(3);
+
+        assert!(!index.is_any());
+        assert_eq!(
+            NetworkInterface::from(index),
+            NetworkInterface::AtIndex(index)
+        );
+        assert_eq!(
+            InterfaceIndex::from(NetworkInterface::AtIndex(index)),
+            index
+        );
+        assert_eq!(index.to_string(), "3");
+    }
+
+    #[test]
+    fn at_index_any_supports_addresses_like_unspec() {
+        let address = IpAddr::from([127, 0, 0, 1]);
+
+        assert_eq!(
+            NetworkInterface::AtIndex(InterfaceIndex::ANY).supports_address(&address),
+            NetworkInterface::Unspec.supports_address(&address)
+        );
+    }
 }
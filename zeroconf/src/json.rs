@@ -0,0 +1,84 @@
+//! Machine-readable output of browse events as JSON lines
+
+use crate::BrowseEvent;
+use std::io::Write;
+
+/// Returns a function that writes each [`BrowseEvent`] passed to it to `writer` as compact JSON
+/// on a line of its own, flushing after each line.
+///
+/// This is the output format for tools built on the crate that pipe discoveries into other
+/// programs such as `jq`, one event per line:
+///
+/// ```no_run
+/// use std::io;
+/// use zeroconf::prelude::*;
+/// use zeroconf::{MdnsBrowser, ServiceType};
+///
+/// let mut browser = MdnsBrowser::new(ServiceType::new("http", "tcp").unwrap());
+/// let write_event = std::cell::RefCell::new(zeroconf::json_event_writer(io::stdout()));
+///
+/// browser.set_service_browsed_callback(Box::new(move |event, _| {
+///     if let Ok(event) = event {
+///         (write_event.borrow_mut())(&event);
+///     }
+/// }));
+/// ```
+///
+/// An event that cannot be written is logged and skipped, so that a closed pipe does not stop
+/// browsing.
+///
+/// [`BrowseEvent`]: enum.BrowseEvent.html
+pub fn json_event_writer<W: Write>(mut writer: W) -> impl FnMut(&BrowseEvent) {
+    move |event| {
+        let result = serde_json::to_string(event)
+            .map_err(|e| e.to_string())
+            .and_then(|line| {
+                writeln!(writer, "{}", line)
+                    .and_then(|_| writer.flush())
+                    .map_err(|e| e.to_string())
+            });
+
+        if let Err(e) = result {
+            warn!("could not write browse event: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+    use crate::{NetworkInterface, ServiceBrowse, ServiceType};
+
+    #[test]
+    fn json_event_writer_writes_one_event_per_line() {
+        let service = ServiceBrowse::builder()
+            .name("Printer".to_string())
+            .service_type(ServiceType::new("ipp", "tcp").unwrap())
+            .domain("local".to_string())
+            .interface(NetworkInterface::Unspec)
+            .build()
+            .unwrap();
+
+        let events = vec![
+            BrowseEvent::New(service.clone()),
+            BrowseEvent::AllForNow,
+            BrowseEvent::Removed(service),
+        ];
+
+        let mut output = Vec::new();
+
+        {
+            let mut write_event = json_event_writer(&mut output);
+            events.iter().for_each(&mut write_event);
+        }
+
+        let parsed = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str::<BrowseEvent>(line).unwrap())
+            .collect::<Vec<_>>();
+
+        assert_eq!(parsed, events);
+    }
+}
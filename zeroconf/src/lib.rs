@@ -10,6 +10,15 @@
 //!
 //! # Examples
 //!
+//! ## Publish a TCP service
+//!
+//! The quickest way to get started is [`publish_tcp()`], which publishes a service with sensible
+//! defaults until the returned guard is dropped:
+//!
+//! ```no_run
+//! let _guard = zeroconf::publish_tcp("My Web Server", "http", 8080).unwrap();
+//! ```
+//!
 //! ## Register a service
 //!
 //! When registering a service, you may optionally pass a "context" to pass state through the
@@ -187,6 +196,9 @@ extern crate derive_builder;
 extern crate zeroconf_macros;
 #[cfg(target_os = "linux")]
 extern crate avahi_sys;
+#[cfg(unix)]
+#[macro_use]
+extern crate bitflags;
 #[cfg(target_vendor = "apple")]
 extern crate bonjour_sys;
 #[macro_use]
@@ -210,10 +222,23 @@ mod service_type;
 mod tests;
 
 pub mod browser;
+pub mod cancellation;
+pub mod client_state;
+pub mod context;
+pub mod domain_browser;
 pub mod error;
 pub mod event_loop;
+pub mod facade;
+#[cfg(feature = "json")]
+pub mod json;
+pub mod monitor;
 pub mod prelude;
+#[cfg(feature = "recording")]
+pub mod recording;
+pub mod run_loop;
 pub mod service;
+pub mod service_config;
+pub mod service_type_browser;
 pub mod txt_record;
 
 #[cfg(target_os = "linux")]
@@ -221,10 +246,32 @@ pub mod linux;
 #[cfg(target_vendor = "apple")]
 pub mod macos;
 
-pub use browser::{ServiceDiscoveredCallback, ServiceDiscovery};
+pub use browser::{
+    browse_and_dump, is_port_advertised, select_instance, AddressScope, BrowseEvent, DiscoveryMode,
+    OnInvalidUtf8, ServiceAddress, ServiceBrowse, ServiceBrowsedCallback,
+    ServiceDiscoveredCallback, ServiceDiscovery, DEFAULT_MAX_CONCURRENT_RESOLVES,
+    LONG_LIVED_POLL_INTERVAL,
+};
+#[cfg(feature = "diagnostics")]
+pub use browser::{ResolveDiagnostics, ResolveTiming};
+pub use cancellation::CancellationToken;
+pub use client_state::{ClientFlags, ClientState, ClientStateCallback, HostNameChangedCallback};
+pub use context::downcast_context;
+pub use domain_browser::{configured_browse_domains, DomainBrowserKind, DomainDiscoveredCallback};
+pub use event_loop::{flush_and_exit_prep, SuspendPolicy};
 pub use interface::*;
-pub use service::{ServiceRegisteredCallback, ServiceRegistration};
+#[cfg(feature = "json")]
+pub use json::json_event_writer;
+pub use monitor::{MdnsMonitor, PresenceEvent};
+pub use run_loop::{RunHandle, RunOptions};
+pub use service::{
+    publish_tcp, validate_instance_name, RegistrationState, ServiceGuard,
+    ServiceRegisteredCallback, ServiceRegistration, TxtProvider, INSTANCE_NAME_MAX_LEN,
+    MIN_TXT_PROVIDER_INTERVAL, UNIQUE_NAME_SUFFIX_LEN,
+};
+pub use service_config::ServiceConfig;
 pub use service_type::*;
+pub use service_type_browser::ServiceTypeDiscoveredCallback;
 
 /// Type alias for the platform-specific mDNS browser implementation
 #[cfg(target_os = "linux")]
@@ -233,6 +280,20 @@ pub type MdnsBrowser = linux::browser::AvahiMdnsBrowser;
 #[cfg(target_vendor = "apple")]
 pub type MdnsBrowser = macos::browser::BonjourMdnsBrowser;
 
+/// Type alias for the platform-specific mDNS service type browser implementation
+#[cfg(target_os = "linux")]
+pub type ServiceTypeBrowser = linux::type_browser::AvahiMdnsServiceTypeBrowser;
+/// Type alias for the platform-specific mDNS service type browser implementation
+#[cfg(target_vendor = "apple")]
+pub type ServiceTypeBrowser = macos::type_browser::BonjourMdnsServiceTypeBrowser;
+
+/// Type alias for the platform-specific mDNS domain browser implementation
+#[cfg(target_os = "linux")]
+pub type DomainBrowser = linux::domain_browser::AvahiMdnsDomainBrowser;
+/// Type alias for the platform-specific mDNS domain browser implementation
+#[cfg(target_vendor = "apple")]
+pub type DomainBrowser = macos::domain_browser::BonjourMdnsDomainBrowser;
+
 /// Type alias for the platform-specific mDNS service implementation
 #[cfg(target_os = "linux")]
 pub type MdnsService = linux::service::AvahiMdnsService;
@@ -240,6 +301,22 @@ pub type MdnsService = linux::service::AvahiMdnsService;
 #[cfg(target_vendor = "apple")]
 pub type MdnsService = macos::service::BonjourMdnsService;
 
+/// Type alias for the platform-specific handle used to update a registered mDNS service
+#[cfg(target_os = "linux")]
+pub type ServiceHandle = linux::service::AvahiServiceHandle;
+/// Type alias for the platform-specific handle used to update a registered mDNS service
+#[cfg(target_vendor = "apple")]
+pub type ServiceHandle = macos::service::BonjourServiceHandle;
+
+/// Type alias for the platform-specific facade that shares one mDNS connection between services
+/// and browsers
+#[cfg(target_os = "linux")]
+pub type Zeroconf = linux::facade::AvahiZeroconf;
+/// Type alias for the platform-specific facade that shares one mDNS connection between services
+/// and browsers
+#[cfg(target_vendor = "apple")]
+pub type Zeroconf = macos::facade::BonjourZeroconf;
+
 /// Type alias for the platform-specific structure responsible for polling the mDNS event loop
 #[cfg(target_os = "linux")]
 pub type EventLoop<'a> = linux::event_loop::AvahiEventLoop<'a>;
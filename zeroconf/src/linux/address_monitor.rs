@@ -0,0 +1,168 @@
+//! Netlink socket for noticing changes to the addresses of the machine's interfaces
+
+use crate::Result;
+use libc::{c_int, c_void};
+use std::io;
+use std::mem;
+
+/// A non-blocking `NETLINK_ROUTE` socket subscribed to the kernel's IPv4 and IPv6 address
+/// notifications.
+///
+/// The socket's file descriptor can be watched for readability on an event loop, after which
+/// `AddressMonitor::drain()` reads all pending notifications. The socket is closed on
+/// `trait Drop`.
+#[derive(Debug)]
+pub struct AddressMonitor {
+    fd: c_int,
+}
+
+impl AddressMonitor {
+    /// Opens and binds the netlink socket; returning `Err(String)` if unsuccessful.
+    pub fn new() -> Result<Self> {
+        let fd = unsafe {
+            libc::socket(
+                libc::AF_NETLINK,
+                libc::SOCK_RAW | libc::SOCK_NONBLOCK | libc::SOCK_CLOEXEC,
+                libc::NETLINK_ROUTE,
+            )
+        };
+
+        if fd < 0 {
+            return Err(format!(
+                "could not open netlink socket: {}",
+                io::Error::last_os_error()
+            )
+            .into());
+        }
+
+        let monitor = Self { fd };
+
+        let mut addr: libc::sockaddr_nl = unsafe { mem::zeroed() };
+        addr.nl_family = libc::AF_NETLINK as u16;
+        addr.nl_groups = (libc::RTMGRP_IPV4_IFADDR | libc::RTMGRP_IPV6_IFADDR) as u32;
+
+        let err = unsafe {
+            libc::bind(
+                monitor.fd,
+                &addr as *const libc::sockaddr_nl as *const libc::sockaddr,
+                mem::size_of::<libc::sockaddr_nl>() as libc::socklen_t,
+            )
+        };
+
+        if err < 0 {
+            return Err(format!(
+                "could not bind netlink socket: {}",
+                io::Error::last_os_error()
+            )
+            .into());
+        }
+
+        Ok(monitor)
+    }
+
+    /// Returns the file descriptor of the underlying socket.
+    pub fn fd(&self) -> c_int {
+        self.fd
+    }
+
+    /// Reads all pending notifications without blocking, returning `true` if an address was
+    /// added or removed.
+    pub fn drain(&self) -> bool {
+        let mut buf = [0u8; 8192];
+        let mut changed = false;
+
+        loop {
+            let len = unsafe {
+                libc::recv(
+                    self.fd,
+                    buf.as_mut_ptr() as *mut c_void,
+                    buf.len(),
+                    libc::MSG_DONTWAIT,
+                )
+            };
+
+            if len <= 0 {
+                break;
+            }
+
+            changed |= has_address_change(&buf[..len as usize]);
+        }
+
+        changed
+    }
+}
+
+impl Drop for AddressMonitor {
+    fn drop(&mut self) {
+        unsafe { libc::close(self.fd) };
+    }
+}
+
+/// Returns `true` if the netlink messages in `buf` include an `RTM_NEWADDR` or `RTM_DELADDR`.
+fn has_address_change(mut buf: &[u8]) -> bool {
+    let header_len = mem::size_of::<libc::nlmsghdr>();
+
+    while buf.len() >= header_len {
+        let header = unsafe { (buf.as_ptr() as *const libc::nlmsghdr).read_unaligned() };
+        let msg_len = header.nlmsg_len as usize;
+
+        if msg_len < header_len || msg_len > buf.len() {
+            break;
+        }
+
+        if header.nlmsg_type == libc::RTM_NEWADDR || header.nlmsg_type == libc::RTM_DELADDR {
+            return true;
+        }
+
+        // messages are aligned to 4 bytes
+        let aligned_len = (msg_len + 3) & !3;
+        buf = &buf[aligned_len.min(buf.len())..];
+    }
+
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(kind: u16, payload_len: usize) -> Vec<u8> {
+        let header = libc::nlmsghdr {
+            nlmsg_len: (mem::size_of::<libc::nlmsghdr>() + payload_len) as u32,
+            nlmsg_type: kind,
+            nlmsg_flags: 0,
+            nlmsg_seq: 0,
+            nlmsg_pid: 0,
+        };
+
+        let mut buf = unsafe {
+            std::slice::from_raw_parts(
+                &header as *const libc::nlmsghdr as *const u8,
+                mem::size_of::<libc::nlmsghdr>(),
+            )
+        }
+        .to_vec();
+
+        buf.resize((buf.len() + payload_len + 3) & !3, 0);
+        buf
+    }
+
+    #[test]
+    fn has_address_change_finds_address_message() {
+        let mut buf = message(libc::RTM_NEWLINK, 5);
+        buf.extend(message(libc::RTM_DELADDR, 8));
+        assert!(has_address_change(&buf));
+    }
+
+    #[test]
+    fn has_address_change_ignores_other_messages() {
+        let buf = message(libc::RTM_NEWLINK, 5);
+        assert!(!has_address_change(&buf));
+    }
+
+    #[test]
+    fn has_address_change_ignores_truncated_message() {
+        let buf = message(libc::RTM_NEWADDR, 8);
+        assert!(!has_address_change(&buf[..buf.len() - 4]));
+    }
+}
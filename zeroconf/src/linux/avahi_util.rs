@@ -1,10 +1,21 @@
 //! Utilities related to Avahi
 
-use avahi_sys::{avahi_address_snprint, avahi_strerror, AvahiAddress};
-use libc::c_char;
-use std::ffi::CStr;
-
-use crate::{NetworkInterface, Result, ServiceType};
+use super::flags::LookupFlags;
+use avahi_sys::{
+    avahi_address_snprint, avahi_service_name_join, avahi_strerror, AvahiAddress,
+    AvahiAddress__bindgen_ty_1, AvahiClientFlags, AvahiClientState, AvahiDomainBrowserType,
+    AvahiIPv4Address, AvahiIPv6Address, AvahiIfIndex, AvahiProtocol,
+};
+use libc::{c_char, c_void};
+use std::convert::TryFrom;
+use std::ffi::{CStr, CString};
+use std::net::IpAddr;
+
+use crate::error::Error;
+use crate::{
+    ClientFlags, ClientState, DiscoveryMode, DomainBrowserKind, InterfaceIndex, NetworkInterface,
+    NetworkProtocol, Result, Scope, ServiceType,
+};
 
 /// Converts the specified `*const AvahiAddress` to a `String`.
 ///
@@ -29,6 +40,62 @@ pub unsafe fn avahi_address_to_string(addr: *const AvahiAddress) -> String {
         .to_string()
 }
 
+/// Converts the specified `IpAddr` to an `AvahiAddress`.
+pub fn avahi_address(address: &IpAddr) -> AvahiAddress {
+    match address {
+        IpAddr::V4(v4) => AvahiAddress {
+            proto: avahi_sys::AVAHI_PROTO_INET,
+            data: AvahiAddress__bindgen_ty_1 {
+                ipv4: AvahiIPv4Address {
+                    // stored in network byte order
+                    address: u32::from_ne_bytes(v4.octets()),
+                },
+            },
+        },
+        IpAddr::V6(v6) => AvahiAddress {
+            proto: avahi_sys::AVAHI_PROTO_INET6,
+            data: AvahiAddress__bindgen_ty_1 {
+                ipv6: AvahiIPv6Address {
+                    address: v6.octets(),
+                },
+            },
+        },
+    }
+}
+
+/// Converts the specified `*const AvahiAddress` to an `IpAddr`.
+///
+/// # Safety
+/// This function is unsafe because of raw pointer dereference.
+pub unsafe fn ip_address(addr: *const AvahiAddress) -> IpAddr {
+    assert_not_null!(addr);
+
+    let addr = &*addr;
+
+    if addr.proto == avahi_sys::AVAHI_PROTO_INET6 {
+        IpAddr::from(addr.data.ipv6.address)
+    } else {
+        // stored in network byte order
+        IpAddr::from(addr.data.ipv4.address.to_ne_bytes())
+    }
+}
+
+/// Parses the `rdata` of an `A` or `AAAA` record as returned by an `AvahiRecordBrowser`.
+///
+/// # Safety
+/// This function is unsafe because it provides no guarantees that `rdata` points to at least
+/// `size` readable bytes.
+pub unsafe fn address_from_rdata(kind: u16, rdata: *const c_void, size: usize) -> Result<IpAddr> {
+    let data = std::slice::from_raw_parts(rdata as *const u8, size);
+
+    match u32::from(kind) {
+        avahi_sys::AVAHI_DNS_TYPE_A => <[u8; 4]>::try_from(data).map(IpAddr::from).ok(),
+        avahi_sys::AVAHI_DNS_TYPE_AAAA => <[u8; 16]>::try_from(data).map(IpAddr::from).ok(),
+        _ => None,
+    }
+    .ok_or_else(|| format!("invalid address record (type: {}, size: {})", kind, size).into())
+}
+
 /// Returns the `&str` message associated with the specified error code.
 pub fn get_error<'a>(code: i32) -> &'a str {
     unsafe {
@@ -38,21 +105,248 @@ pub fn get_error<'a>(code: i32) -> &'a str {
     }
 }
 
-/// Converts the specified [`NetworkInterface`] to the Avahi expected value.
+/// Names of the Avahi error constants, indexed by the negated error code.
+const ERROR_NAMES: [&str; 54] = [
+    "AVAHI_OK",
+    "AVAHI_ERR_FAILURE",
+    "AVAHI_ERR_BAD_STATE",
+    "AVAHI_ERR_INVALID_HOST_NAME",
+    "AVAHI_ERR_INVALID_DOMAIN_NAME",
+    "AVAHI_ERR_NO_NETWORK",
+    "AVAHI_ERR_INVALID_TTL",
+    "AVAHI_ERR_IS_PATTERN",
+    "AVAHI_ERR_COLLISION",
+    "AVAHI_ERR_INVALID_RECORD",
+    "AVAHI_ERR_INVALID_SERVICE_NAME",
+    "AVAHI_ERR_INVALID_SERVICE_TYPE",
+    "AVAHI_ERR_INVALID_PORT",
+    "AVAHI_ERR_INVALID_KEY",
+    "AVAHI_ERR_INVALID_ADDRESS",
+    "AVAHI_ERR_TIMEOUT",
+    "AVAHI_ERR_TOO_MANY_CLIENTS",
+    "AVAHI_ERR_TOO_MANY_OBJECTS",
+    "AVAHI_ERR_TOO_MANY_ENTRIES",
+    "AVAHI_ERR_OS",
+    "AVAHI_ERR_ACCESS_DENIED",
+    "AVAHI_ERR_INVALID_OPERATION",
+    "AVAHI_ERR_DBUS_ERROR",
+    "AVAHI_ERR_DISCONNECTED",
+    "AVAHI_ERR_NO_MEMORY",
+    "AVAHI_ERR_INVALID_OBJECT",
+    "AVAHI_ERR_NO_DAEMON",
+    "AVAHI_ERR_INVALID_INTERFACE",
+    "AVAHI_ERR_INVALID_PROTOCOL",
+    "AVAHI_ERR_INVALID_FLAGS",
+    "AVAHI_ERR_NOT_FOUND",
+    "AVAHI_ERR_INVALID_CONFIG",
+    "AVAHI_ERR_VERSION_MISMATCH",
+    "AVAHI_ERR_INVALID_SERVICE_SUBTYPE",
+    "AVAHI_ERR_INVALID_PACKET",
+    "AVAHI_ERR_INVALID_DNS_ERROR",
+    "AVAHI_ERR_DNS_FORMERR",
+    "AVAHI_ERR_DNS_SERVFAIL",
+    "AVAHI_ERR_DNS_NXDOMAIN",
+    "AVAHI_ERR_DNS_NOTIMP",
+    "AVAHI_ERR_DNS_REFUSED",
+    "AVAHI_ERR_DNS_YXDOMAIN",
+    "AVAHI_ERR_DNS_YXRRSET",
+    "AVAHI_ERR_DNS_NXRRSET",
+    "AVAHI_ERR_DNS_NOTAUTH",
+    "AVAHI_ERR_DNS_NOTZONE",
+    "AVAHI_ERR_INVALID_RDATA",
+    "AVAHI_ERR_INVALID_DNS_CLASS",
+    "AVAHI_ERR_INVALID_DNS_TYPE",
+    "AVAHI_ERR_NOT_SUPPORTED",
+    "AVAHI_ERR_NOT_PERMITTED",
+    "AVAHI_ERR_INVALID_ARGUMENT",
+    "AVAHI_ERR_IS_EMPTY",
+    "AVAHI_ERR_NO_CHANGE",
+];
+
+/// Returns the name of the Avahi constant for the specified error code, as declared in
+/// [`avahi-common/error.h`] (e.g. `"AVAHI_ERR_COLLISION"`), or `"UNKNOWN"` if there is none.
+///
+/// [`avahi-common/error.h`]: https://avahi.org/doxygen/html/error_8h.html
+pub fn error_name(code: i32) -> &'static str {
+    code.checked_neg()
+        .and_then(|index| usize::try_from(index).ok())
+        .and_then(|index| ERROR_NAMES.get(index))
+        .copied()
+        .unwrap_or("UNKNOWN")
+}
+
+/// Converts the specified [`NetworkInterface`] to the Avahi expected value, returning an error if
+/// its index does not fit in an `AvahiIfIndex`.
 ///
 /// [`NetworkInterface`]: ../../enum.NetworkInterface.html
-pub fn interface_index(interface: NetworkInterface) -> i32 {
-    match interface {
-        NetworkInterface::Unspec => avahi_sys::AVAHI_IF_UNSPEC,
-        NetworkInterface::AtIndex(i) => i as i32,
-    }
+pub fn interface_index(interface: NetworkInterface) -> Result<AvahiIfIndex> {
+    AvahiIfIndex::try_from(interface)
 }
 
 /// Converts the specified Avahi interface index to a [`NetworkInterface`].
-pub fn interface_from_index(index: i32) -> NetworkInterface {
-    match index {
-        avahi_sys::AVAHI_IF_UNSPEC => NetworkInterface::Unspec,
-        _ => NetworkInterface::AtIndex(index as u32),
+///
+/// [`NetworkInterface`]: ../../enum.NetworkInterface.html
+pub fn interface_from_index(index: AvahiIfIndex) -> NetworkInterface {
+    index.into()
+}
+
+/// Converts the specified [`NetworkProtocol`] to the Avahi expected value.
+///
+/// [`NetworkProtocol`]: ../../enum.NetworkProtocol.html
+pub fn protocol(protocol: NetworkProtocol) -> AvahiProtocol {
+    protocol.into()
+}
+
+/// Converts the specified Avahi protocol to a [`NetworkProtocol`].
+///
+/// [`NetworkProtocol`]: ../../enum.NetworkProtocol.html
+pub fn protocol_from_raw(protocol: AvahiProtocol) -> NetworkProtocol {
+    protocol.into()
+}
+
+/// Converts the specified [`DomainBrowserKind`] to the Avahi domain browser type.
+///
+/// [`DomainBrowserKind`]: ../../domain_browser/enum.DomainBrowserKind.html
+pub fn domain_browser_type(kind: DomainBrowserKind) -> AvahiDomainBrowserType {
+    match kind {
+        DomainBrowserKind::Browse => avahi_sys::AvahiDomainBrowserType_AVAHI_DOMAIN_BROWSER_BROWSE,
+        DomainBrowserKind::Register => {
+            avahi_sys::AvahiDomainBrowserType_AVAHI_DOMAIN_BROWSER_REGISTER
+        }
+    }
+}
+
+/// Converts the specified [`ClientFlags`] to the Avahi expected value.
+///
+/// [`ClientFlags`]: ../../struct.ClientFlags.html
+pub fn client_flags(flags: ClientFlags) -> AvahiClientFlags {
+    let mut raw = AvahiClientFlags(0);
+
+    if flags.contains(ClientFlags::IGNORE_USER_CONFIG) {
+        raw = raw | AvahiClientFlags::AVAHI_CLIENT_IGNORE_USER_CONFIG;
+    }
+
+    if flags.contains(ClientFlags::NO_FAIL) {
+        raw = raw | AvahiClientFlags::AVAHI_CLIENT_NO_FAIL;
+    }
+
+    raw
+}
+
+/// Converts the specified Avahi client flags to [`ClientFlags`], ignoring unknown flags.
+///
+/// [`ClientFlags`]: ../../struct.ClientFlags.html
+pub fn client_flags_from_raw(raw: AvahiClientFlags) -> ClientFlags {
+    let mut flags = ClientFlags::empty();
+
+    if (raw & AvahiClientFlags::AVAHI_CLIENT_IGNORE_USER_CONFIG).0 != 0 {
+        flags |= ClientFlags::IGNORE_USER_CONFIG;
+    }
+
+    if (raw & AvahiClientFlags::AVAHI_CLIENT_NO_FAIL).0 != 0 {
+        flags |= ClientFlags::NO_FAIL;
+    }
+
+    flags
+}
+
+/// Converts the specified Avahi client state to a [`ClientState`].
+///
+/// [`ClientState`]: ../../enum.ClientState.html
+pub fn client_state_from_raw(state: AvahiClientState) -> ClientState {
+    match state {
+        avahi_sys::AvahiClientState_AVAHI_CLIENT_S_REGISTERING => ClientState::Registering,
+        avahi_sys::AvahiClientState_AVAHI_CLIENT_S_RUNNING => ClientState::Running,
+        avahi_sys::AvahiClientState_AVAHI_CLIENT_S_COLLISION => ClientState::Collision,
+        avahi_sys::AvahiClientState_AVAHI_CLIENT_CONNECTING => ClientState::Connecting,
+        _ => ClientState::Failure,
+    }
+}
+
+impl TryFrom<InterfaceIndex> for AvahiIfIndex {
+    type Error = Error;
+
+    /// Converts [`InterfaceIndex::ANY`] to `AVAHI_IF_UNSPEC` (`-1`) and any other index as is.
+    ///
+    /// Returns an error if the interface index does not fit in an `AvahiIfIndex`. A plain cast
+    /// would wrap such an index around to a negative value (e.g. `u32::MAX` would become
+    /// `AVAHI_IF_UNSPEC`).
+    ///
+    /// [`InterfaceIndex::ANY`]: ../../struct.InterfaceIndex.html#associatedconstant.ANY
+    fn try_from(index: InterfaceIndex) -> Result<Self> {
+        if index.is_any() {
+            return Ok(avahi_sys::AVAHI_IF_UNSPEC);
+        }
+
+        AvahiIfIndex::try_from(index.0)
+            .map_err(|_| format!("interface index out of range for Avahi: {}", index).into())
+    }
+}
+
+impl From<AvahiIfIndex> for InterfaceIndex {
+    /// Converts from `AVAHI_IF_UNSPEC` or an interface index. Avahi never reports negative
+    /// indices other than `AVAHI_IF_UNSPEC`, so any negative value is treated as
+    /// [`InterfaceIndex::ANY`] rather than being cast to a large positive index.
+    ///
+    /// [`InterfaceIndex::ANY`]: ../../struct.InterfaceIndex.html#associatedconstant.ANY
+    fn from(index: AvahiIfIndex) -> Self {
+        u32::try_from(index)
+            .map(InterfaceIndex)
+            .unwrap_or(InterfaceIndex::ANY)
+    }
+}
+
+impl TryFrom<NetworkInterface> for AvahiIfIndex {
+    type Error = Error;
+
+    /// Converts to `AVAHI_IF_UNSPEC` (`-1`) or the interface index, returning an error if the
+    /// interface index does not fit in an `AvahiIfIndex`.
+    fn try_from(interface: NetworkInterface) -> Result<Self> {
+        AvahiIfIndex::try_from(InterfaceIndex::from(interface))
+    }
+}
+
+impl From<AvahiIfIndex> for NetworkInterface {
+    /// Converts from `AVAHI_IF_UNSPEC` or an interface index, treating any negative value as
+    /// unspecified.
+    fn from(index: AvahiIfIndex) -> Self {
+        InterfaceIndex::from(index).into()
+    }
+}
+
+impl From<NetworkProtocol> for AvahiProtocol {
+    fn from(protocol: NetworkProtocol) -> Self {
+        match protocol {
+            NetworkProtocol::Unspec => avahi_sys::AVAHI_PROTO_UNSPEC,
+            NetworkProtocol::Ipv4 => avahi_sys::AVAHI_PROTO_INET,
+            NetworkProtocol::Ipv6 => avahi_sys::AVAHI_PROTO_INET6,
+        }
+    }
+}
+
+impl From<AvahiProtocol> for NetworkProtocol {
+    /// Converts from an Avahi protocol. Any unknown value, including `AVAHI_PROTO_UNSPEC`, is
+    /// treated as unspecified.
+    fn from(protocol: AvahiProtocol) -> Self {
+        match protocol {
+            avahi_sys::AVAHI_PROTO_INET => NetworkProtocol::Ipv4,
+            avahi_sys::AVAHI_PROTO_INET6 => NetworkProtocol::Ipv6,
+            _ => NetworkProtocol::Unspec,
+        }
+    }
+}
+
+/// Returns the `LookupFlags` used by browsers and resolvers for the specified
+/// [`DiscoveryMode`] and [`Scope`]. A scope other than `Scope::Both` takes precedence over the
+/// mode, as Avahi rejects lookups that force both multicast and wide-area.
+///
+/// [`DiscoveryMode`]: ../../enum.DiscoveryMode.html
+/// [`Scope`]: ../../enum.Scope.html
+pub fn lookup_flags(mode: DiscoveryMode, scope: Scope) -> LookupFlags {
+    match (scope, mode) {
+        (Scope::LinkLocal, _) | (Scope::Both, DiscoveryMode::Fast) => LookupFlags::USE_MULTICAST,
+        (Scope::WideArea, _) => LookupFlags::USE_WIDE_AREA,
+        (Scope::Both, DiscoveryMode::Balanced | DiscoveryMode::PowerSaving) => LookupFlags::empty(),
     }
 }
 
@@ -61,12 +355,60 @@ pub fn sys_exec<F: FnOnce() -> i32>(func: F, message: &str) -> Result<()> {
     let err = func();
 
     if err < 0 {
-        Err(format!("{}: `{}`", message, get_error(err)).into())
+        Err(format!("{}: `{}` ({})", message, get_error(err), error_name(err)).into())
     } else {
         Ok(())
     }
 }
 
+/// Joins the specified service `name`, `kind` and `domain` into the escaped, fully qualified
+/// domain name (e.g. `My\032Service._http._tcp.local`) under which the service's records are
+/// published.
+pub fn service_name_join(name: &str, kind: &str, domain: &str) -> Result<CString> {
+    let name = c_string!(name);
+    let kind = c_string!(kind);
+    let domain = c_string!(domain);
+    let mut buf = vec![0u8; avahi_sys::AVAHI_DOMAIN_NAME_MAX as usize];
+
+    sys_exec(
+        || unsafe {
+            avahi_service_name_join(
+                buf.as_mut_ptr() as *mut c_char,
+                buf.len(),
+                name.as_ptr(),
+                kind.as_ptr(),
+                domain.as_ptr(),
+            )
+        },
+        "could not join service name",
+    )?;
+
+    let len = buf.iter().position(|b| *b == 0).unwrap_or(buf.len());
+    buf.truncate(len);
+
+    Ok(CString::new(buf).expect("service name contained a null byte"))
+}
+
+/// Encodes the specified labels as an uncompressed domain name in DNS wire format, as used in the
+/// `rdata` of raw records. Empty labels are skipped, so a dotted name may be split on `'.'`
+/// whether or not it has a trailing dot.
+pub fn encode_name<'a>(labels: impl IntoIterator<Item = &'a str>) -> Result<Vec<u8>> {
+    let mut name = Vec::new();
+
+    for label in labels.into_iter().filter(|l| !l.is_empty()) {
+        if label.len() > 63 {
+            return Err(format!("label `{}` is longer than 63 bytes", label).into());
+        }
+
+        name.push(label.len() as u8);
+        name.extend_from_slice(label.as_bytes());
+    }
+
+    name.push(0);
+
+    Ok(name)
+}
+
 /// Formats the specified `ServiceType` as a `String` for use with Avahi
 pub fn format_service_type(service_type: &ServiceType) -> String {
     format!("_{}._{}", service_type.name(), service_type.protocol())
@@ -101,10 +443,55 @@ pub fn format_sub_type(sub_type: &str, kind: &str) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use avahi_sys::{
-        AvahiAddress__bindgen_ty_1, AvahiIPv4Address, AvahiIPv6Address, AVAHI_PROTO_INET,
-        AVAHI_PROTO_INET6,
-    };
+    use avahi_sys::{AVAHI_PROTO_INET, AVAHI_PROTO_INET6};
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    #[test]
+    fn client_flags_convert_to_and_from_raw() {
+        let flags = ClientFlags::IGNORE_USER_CONFIG | ClientFlags::NO_FAIL;
+        let raw = client_flags(flags);
+
+        assert_eq!(
+            raw.0,
+            (AvahiClientFlags::AVAHI_CLIENT_IGNORE_USER_CONFIG
+                | AvahiClientFlags::AVAHI_CLIENT_NO_FAIL)
+                .0
+        );
+        assert_eq!(client_flags_from_raw(raw), flags);
+        assert_eq!(client_flags(ClientFlags::empty()).0, 0);
+        assert_eq!(
+            client_flags_from_raw(AvahiClientFlags::AVAHI_CLIENT_NO_FAIL),
+            ClientFlags::NO_FAIL
+        );
+    }
+
+    #[test]
+    fn client_state_from_raw_maps_states() {
+        assert_eq!(
+            client_state_from_raw(avahi_sys::AvahiClientState_AVAHI_CLIENT_S_RUNNING),
+            ClientState::Running
+        );
+        assert_eq!(
+            client_state_from_raw(avahi_sys::AvahiClientState_AVAHI_CLIENT_CONNECTING),
+            ClientState::Connecting
+        );
+        assert_eq!(
+            client_state_from_raw(avahi_sys::AvahiClientState_AVAHI_CLIENT_FAILURE),
+            ClientState::Failure
+        );
+    }
+
+    #[test]
+    fn domain_browser_type_maps_kinds() {
+        assert_eq!(
+            domain_browser_type(DomainBrowserKind::Browse),
+            avahi_sys::AvahiDomainBrowserType_AVAHI_DOMAIN_BROWSER_BROWSE
+        );
+        assert_eq!(
+            domain_browser_type(DomainBrowserKind::Register),
+            avahi_sys::AvahiDomainBrowserType_AVAHI_DOMAIN_BROWSER_REGISTER
+        );
+    }
 
     #[test]
     fn sys_exec_returns_ok_for_success() {
@@ -115,7 +502,7 @@ mod tests {
     fn sys_exec_returns_error_for_failure() {
         assert_eq!(
             sys_exec(|| avahi_sys::AVAHI_ERR_FAILURE, "uh oh spaghetti-o"),
-            Err("uh oh spaghetti-o: `Operation failed`".into())
+            Err("uh oh spaghetti-o: `Operation failed` (AVAHI_ERR_FAILURE)".into())
         );
     }
 
@@ -123,13 +510,16 @@ mod tests {
     fn interface_index_returns_unspec_for_unspec() {
         assert_eq!(
             interface_index(NetworkInterface::Unspec),
-            avahi_sys::AVAHI_IF_UNSPEC
+            Ok(avahi_sys::AVAHI_IF_UNSPEC)
         );
     }
 
     #[test]
     fn interface_index_returns_index_for_index() {
-        assert_eq!(interface_index(NetworkInterface::AtIndex(1)), 1);
+        assert_eq!(
+            interface_index(NetworkInterface::AtIndex(InterfaceIndex(1))),
+            Ok(1)
+        );
     }
 
     #[test]
@@ -142,7 +532,89 @@ mod tests {
 
     #[test]
     fn interface_from_index_returns_index_for_avahi_index() {
-        assert_eq!(interface_from_index(1), NetworkInterface::AtIndex(1));
+        assert_eq!(
+            interface_from_index(1),
+            NetworkInterface::AtIndex(InterfaceIndex(1))
+        );
+    }
+
+    #[test]
+    fn interface_from_index_never_wraps_negative_index() {
+        assert_eq!(interface_from_index(-2), NetworkInterface::Unspec);
+        assert_eq!(interface_from_index(i32::MIN), NetworkInterface::Unspec);
+    }
+
+    #[test]
+    fn interface_index_round_trips_unspec() {
+        assert_eq!(
+            interface_from_index(interface_index(NetworkInterface::Unspec).unwrap()),
+            NetworkInterface::Unspec
+        );
+        assert_eq!(
+            interface_index(interface_from_index(avahi_sys::AVAHI_IF_UNSPEC)),
+            Ok(avahi_sys::AVAHI_IF_UNSPEC)
+        );
+    }
+
+    #[test]
+    fn interface_index_round_trips_index() {
+        let max = NetworkInterface::AtIndex(InterfaceIndex(i32::MAX as u32));
+        assert_eq!(interface_from_index(interface_index(max).unwrap()), max);
+    }
+
+    #[test]
+    fn interface_index_converts_any_to_avahi_unspec() {
+        assert_eq!(
+            AvahiIfIndex::try_from(InterfaceIndex::ANY),
+            Ok(avahi_sys::AVAHI_IF_UNSPEC)
+        );
+        assert_eq!(
+            InterfaceIndex::from(avahi_sys::AVAHI_IF_UNSPEC),
+            InterfaceIndex::ANY
+        );
+        assert_eq!(
+            interface_index(NetworkInterface::AtIndex(InterfaceIndex::ANY)),
+            Ok(avahi_sys::AVAHI_IF_UNSPEC)
+        );
+        assert_eq!(interface_from_index(0), NetworkInterface::Unspec);
+    }
+
+    #[test]
+    fn interface_index_fails_if_out_of_range() {
+        assert_eq!(
+            interface_index(NetworkInterface::AtIndex(InterfaceIndex(u32::MAX))),
+            Err(format!(
+                "interface index out of range for Avahi: {}",
+                InterfaceIndex(u32::MAX)
+            )
+            .into())
+        );
+    }
+
+    #[test]
+    fn protocol_returns_avahi_protocol() {
+        assert_eq!(
+            protocol(NetworkProtocol::Unspec),
+            avahi_sys::AVAHI_PROTO_UNSPEC
+        );
+        assert_eq!(protocol(NetworkProtocol::Ipv4), AVAHI_PROTO_INET);
+        assert_eq!(protocol(NetworkProtocol::Ipv6), AVAHI_PROTO_INET6);
+    }
+
+    #[test]
+    fn protocol_from_raw_round_trips() {
+        for p in &[
+            NetworkProtocol::Unspec,
+            NetworkProtocol::Ipv4,
+            NetworkProtocol::Ipv6,
+        ] {
+            assert_eq!(protocol_from_raw(protocol(*p)), *p);
+        }
+    }
+
+    #[test]
+    fn protocol_from_raw_returns_unspec_for_unknown() {
+        assert_eq!(protocol_from_raw(42), NetworkProtocol::Unspec);
     }
 
     #[test]
@@ -184,11 +656,56 @@ mod tests {
         );
     }
 
+    #[test]
+    fn service_name_join_escapes_name() {
+        assert_eq!(
+            service_name_join("My Service.1", "_http._tcp", "local")
+                .unwrap()
+                .to_str()
+                .unwrap(),
+            "My\\032Service\\.1._http._tcp.local"
+        );
+    }
+
+    #[test]
+    fn encode_name_returns_wire_format() {
+        assert_eq!(
+            encode_name("My Service._http._tcp.local.".split('.')).unwrap(),
+            b"\x0aMy Service\x05_http\x04_tcp\x05local\x00"
+        );
+    }
+
+    #[test]
+    fn encode_name_rejects_long_label() {
+        assert!(encode_name(vec!["a".repeat(64).as_str()]).is_err());
+    }
+
     #[test]
     fn get_error_returns_valid_error_string() {
         assert_eq!(get_error(avahi_sys::AVAHI_ERR_FAILURE), "Operation failed");
     }
 
+    #[test]
+    fn error_name_returns_constant_name() {
+        assert_eq!(error_name(avahi_sys::AVAHI_OK), "AVAHI_OK");
+        assert_eq!(
+            error_name(avahi_sys::AVAHI_ERR_COLLISION),
+            "AVAHI_ERR_COLLISION"
+        );
+        assert_eq!(
+            error_name(avahi_sys::AVAHI_ERR_NO_CHANGE),
+            "AVAHI_ERR_NO_CHANGE"
+        );
+    }
+
+    #[test]
+    fn error_name_covers_every_error_code() {
+        assert_eq!(ERROR_NAMES.len() as i32, -avahi_sys::AVAHI_ERR_MAX);
+        assert_eq!(error_name(avahi_sys::AVAHI_ERR_MAX), "UNKNOWN");
+        assert_eq!(error_name(1), "UNKNOWN");
+        assert_eq!(error_name(i32::MIN), "UNKNOWN");
+    }
+
     #[test]
     fn address_to_string_returns_correct_ipv4_string() {
         let ipv4_addr = AvahiAddress {
@@ -226,4 +743,96 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn avahi_address_round_trips_ipv4() {
+        let address = avahi_address(&IpAddr::V4(Ipv4Addr::new(192, 168, 1, 2)));
+
+        assert_eq!(address.proto, AVAHI_PROTO_INET);
+        assert_eq!(unsafe { avahi_address_to_string(&address) }, "192.168.1.2");
+    }
+
+    #[test]
+    fn ip_address_round_trips() {
+        for address in &[
+            IpAddr::V4(Ipv4Addr::new(192, 168, 1, 2)),
+            IpAddr::V6(Ipv6Addr::new(0xfe80, 0, 0, 0, 0x1, 0x2, 0x3, 0x4)),
+        ] {
+            assert_eq!(unsafe { ip_address(&avahi_address(address)) }, *address);
+        }
+    }
+
+    #[test]
+    fn address_from_rdata_parses_a_and_aaaa() {
+        let v4 = [192u8, 0, 2, 1];
+        let v6 = Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1).octets();
+
+        unsafe {
+            assert_eq!(
+                address_from_rdata(1, v4.as_ptr() as *const c_void, v4.len()),
+                Ok(IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1)))
+            );
+            assert_eq!(
+                address_from_rdata(28, v6.as_ptr() as *const c_void, v6.len()),
+                Ok(IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1)))
+            );
+        }
+    }
+
+    #[test]
+    fn address_from_rdata_rejects_wrong_size() {
+        let v4 = [192u8, 0, 2];
+
+        assert_eq!(
+            unsafe { address_from_rdata(1, v4.as_ptr() as *const c_void, v4.len()) },
+            Err("invalid address record (type: 1, size: 3)".into())
+        );
+    }
+
+    #[test]
+    fn avahi_address_round_trips_ipv6() {
+        let address = avahi_address(&IpAddr::V6(Ipv6Addr::new(
+            0xfe80, 0, 0, 0, 0x1, 0x2, 0x3, 0x4,
+        )));
+
+        assert_eq!(address.proto, AVAHI_PROTO_INET6);
+        assert_eq!(
+            unsafe { avahi_address_to_string(&address) },
+            "fe80::1:2:3:4"
+        );
+    }
+
+    #[test]
+    fn lookup_flags_forces_multicast_only_when_fast() {
+        assert_eq!(
+            lookup_flags(DiscoveryMode::Fast, Scope::Both),
+            LookupFlags::USE_MULTICAST
+        );
+        assert_eq!(
+            lookup_flags(DiscoveryMode::Balanced, Scope::Both),
+            LookupFlags::empty()
+        );
+        assert_eq!(
+            lookup_flags(DiscoveryMode::PowerSaving, Scope::Both),
+            LookupFlags::empty()
+        );
+    }
+
+    #[test]
+    fn lookup_flags_restricts_to_scope() {
+        for mode in [
+            DiscoveryMode::Fast,
+            DiscoveryMode::Balanced,
+            DiscoveryMode::PowerSaving,
+        ] {
+            assert_eq!(
+                lookup_flags(mode, Scope::LinkLocal),
+                LookupFlags::USE_MULTICAST
+            );
+            assert_eq!(
+                lookup_flags(mode, Scope::WideArea),
+                LookupFlags::USE_WIDE_AREA
+            );
+        }
+    }
 }
@@ -1,186 +1,1369 @@
 //! Avahi implementation for cross-platform browser
 
+use super::address_monitor::AddressMonitor;
 use super::avahi_util;
 use super::client::{ManagedAvahiClient, ManagedAvahiClientParams};
+use super::facade::{ClientListenerGuard, ClientListeners};
+use super::flags::LookupFlags;
 use super::poll::ManagedAvahiSimplePoll;
 use super::raw_browser::{ManagedAvahiServiceBrowser, ManagedAvahiServiceBrowserParams};
+use super::record_browser::{ManagedAvahiRecordBrowser, ManagedAvahiRecordBrowserParams};
+use super::timeout::{ManagedAvahiTimeout, ManagedAvahiTimeoutParams};
+use super::watch::{ManagedAvahiWatch, ManagedAvahiWatchParams};
 use super::{
     resolver::{
         ManagedAvahiServiceResolver, ManagedAvahiServiceResolverParams, ServiceResolverSet,
     },
     string_list::ManagedAvahiStringList,
 };
-use crate::ffi::{c_str, AsRaw, FromRaw};
+use crate::browser::{
+    self, CallbackThrottle, IgnoredServices, Ipv6Fallback, ResolveQueue, ServiceDiscoveryBuilder,
+    TxtRequirements,
+};
+use crate::error::Error;
+use crate::event_loop::{TeardownGuard, TimerGuard};
+use crate::ffi::{self, c_str, AsRaw, FromRaw, UnwrapOrNull};
 use crate::prelude::*;
+use crate::service;
 use crate::Result;
 use crate::{
-    EventLoop, NetworkInterface, ServiceDiscoveredCallback, ServiceDiscovery, ServiceType,
-    TxtRecord,
+    BrowseEvent, CancellationToken, ClientState, ClientStateCallback, DiscoveryMode, EventLoop,
+    NetworkInterface, NetworkProtocol, OnInvalidUtf8, Scope, ServiceAddress, ServiceBrowse,
+    ServiceBrowsedCallback, ServiceDiscoveredCallback, ServiceDiscovery, ServiceType, TxtRecord,
 };
 use avahi_sys::{
     AvahiAddress, AvahiBrowserEvent, AvahiClient, AvahiClientFlags, AvahiClientState, AvahiIfIndex,
-    AvahiLookupResultFlags, AvahiProtocol, AvahiResolverEvent, AvahiServiceBrowser,
-    AvahiServiceResolver, AvahiStringList,
+    AvahiLookupResultFlags, AvahiProtocol, AvahiRecordBrowser, AvahiResolverEvent,
+    AvahiServiceBrowser, AvahiServiceResolver, AvahiStringList, AvahiTimeout, AvahiWatch,
+    AvahiWatchEvent,
 };
-use libc::{c_char, c_void};
+use libc::{c_char, c_int, c_void};
 use std::any::Any;
-use std::ffi::CString;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::ffi::{CStr, CString};
+use std::fmt;
+use std::mem;
 use std::rc::Rc;
 use std::str::FromStr;
-use std::sync::Arc;
-use std::{fmt, ptr};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 #[derive(Debug)]
 pub struct AvahiMdnsBrowser {
     client: Option<Rc<ManagedAvahiClient>>,
     poll: Option<Rc<ManagedAvahiSimplePoll>>,
-    browser: Option<ManagedAvahiServiceBrowser>,
-    kind: CString,
-    interface_index: AvahiIfIndex,
+    service_type: ServiceType,
+    interface: NetworkInterface,
     context: Box<AvahiBrowserContext>,
+    teardown: Option<TeardownGuard>,
+    client_listener: Option<ClientListenerGuard>,
+    // shared with the client of a facade, so declared after every holder of the client
+    client_listeners: Option<Rc<ClientListeners>>,
 }
 
 impl TMdnsBrowser for AvahiMdnsBrowser {
+    type EventLoop<'a> = EventLoop<'a>;
+
     fn new(service_type: ServiceType) -> Self {
         Self {
             client: None,
             poll: None,
-            browser: None,
-            kind: c_string!(avahi_util::format_browser_type(&service_type)),
-            context: Box::default(),
-            interface_index: avahi_sys::AVAHI_IF_UNSPEC,
+            context: Box::new(AvahiBrowserContext {
+                kind: c_string!(avahi_util::format_browser_type(&service_type)),
+                ..AvahiBrowserContext::default()
+            }),
+            service_type,
+            interface: NetworkInterface::Unspec,
+            teardown: None,
+            client_listener: None,
+            client_listeners: None,
         }
     }
 
     fn set_network_interface(&mut self, interface: NetworkInterface) {
-        self.interface_index = avahi_util::interface_index(interface);
+        self.interface = interface;
+    }
+
+    fn network_interface(&self) -> NetworkInterface {
+        self.interface
+    }
+
+    fn set_network_protocol(&mut self, protocol: NetworkProtocol) {
+        self.context.protocol = avahi_util::protocol(protocol);
+    }
+
+    fn network_protocol(&self) -> NetworkProtocol {
+        avahi_util::protocol_from_raw(self.context.protocol)
+    }
+
+    fn set_service_discovered_callback(
+        &mut self,
+        service_discovered_callback: Box<ServiceDiscoveredCallback>,
+    ) {
+        self.context.service_discovered_callback = Some(service_discovered_callback);
+    }
+
+    fn set_service_browsed_callback(
+        &mut self,
+        service_browsed_callback: Box<ServiceBrowsedCallback>,
+    ) {
+        self.context.service_browsed_callback = Some(service_browsed_callback);
+    }
+
+    fn set_client_state_callback(&mut self, client_state_callback: Box<ClientStateCallback>) {
+        self.context.client_state_callback = Some(client_state_callback);
+    }
+
+    fn set_resolve_services(&mut self, resolve_services: bool) {
+        self.context.resolve_services = resolve_services;
+    }
+
+    fn resolve_services(&self) -> bool {
+        self.context.resolve_services
+    }
+
+    fn set_resolve_txt(&mut self, resolve_txt: bool) {
+        self.context.resolve_txt = resolve_txt;
+        self.context.required_txt.warn_if_txt_disabled(resolve_txt);
+    }
+
+    fn resolve_txt(&self) -> bool {
+        self.context.resolve_txt
+    }
+
+    fn set_resolve_all_txt_records(&mut self, resolve_all_txt_records: bool) {
+        self.context.resolve_all_txt_records = resolve_all_txt_records;
+    }
+
+    fn resolve_all_txt_records(&self) -> bool {
+        self.context.resolve_all_txt_records
+    }
+
+    fn set_resolve_all_addresses(&mut self, resolve_all_addresses: bool) {
+        self.context.resolve_all_addresses = resolve_all_addresses;
+    }
+
+    fn resolve_all_addresses(&self) -> bool {
+        self.context.resolve_all_addresses
+    }
+
+    fn set_coalesce_addresses(&mut self, coalesce_addresses: bool) {
+        self.context.coalesce_addresses = coalesce_addresses;
+    }
+
+    fn coalesce_addresses(&self) -> bool {
+        self.context.coalesce_addresses
+    }
+
+    fn set_resolve_srv_priority(&mut self, resolve_srv_priority: bool) {
+        self.context.resolve_srv_priority = resolve_srv_priority;
+    }
+
+    fn resolve_srv_priority(&self) -> bool {
+        self.context.resolve_srv_priority
+    }
+
+    fn set_ipv6_fallback(&mut self, ipv6_fallback: bool) {
+        self.context.ipv6_fallback.set_enabled(ipv6_fallback);
+    }
+
+    fn ipv6_fallback(&self) -> bool {
+        self.context.ipv6_fallback.is_enabled()
+    }
+
+    fn set_resolve_on_browse_interface(&mut self, resolve_on_browse_interface: bool) {
+        self.context.resolve_on_browse_interface = resolve_on_browse_interface;
+    }
+
+    fn resolve_on_browse_interface(&self) -> bool {
+        self.context.resolve_on_browse_interface
+    }
+
+    fn set_restart_on_network_change(&mut self, restart_on_network_change: bool) {
+        self.context.restart_on_network_change = restart_on_network_change;
+    }
+
+    fn restart_on_network_change(&self) -> bool {
+        self.context.restart_on_network_change
+    }
+
+    fn set_long_lived(&mut self, long_lived: bool) {
+        self.context.long_lived = long_lived;
+    }
+
+    fn long_lived(&self) -> bool {
+        self.context.long_lived
+    }
+
+    fn set_domains(&mut self, domains: Vec<String>) {
+        self.context.domains = browser::normalize_browse_domains(domains);
+    }
+
+    fn domains(&self) -> &[String] {
+        &self.context.domains
+    }
+
+    fn set_scope(&mut self, scope: Scope) {
+        self.context.scope = scope;
+    }
+
+    fn scope(&self) -> Scope {
+        self.context.scope
+    }
+
+    fn set_discovery_mode(&mut self, mode: DiscoveryMode) {
+        self.context.discovery_mode = mode;
+    }
+
+    fn discovery_mode(&self) -> DiscoveryMode {
+        self.context.discovery_mode
+    }
+
+    fn set_on_invalid_utf8(&mut self, on_invalid_utf8: OnInvalidUtf8) {
+        self.context.on_invalid_utf8 = on_invalid_utf8;
+    }
+
+    fn on_invalid_utf8(&self) -> OnInvalidUtf8 {
+        self.context.on_invalid_utf8
+    }
+
+    fn set_max_concurrent_resolves(&mut self, max_concurrent_resolves: usize) {
+        self.context
+            .resolve_queue
+            .set_max_active(max_concurrent_resolves);
+    }
+
+    fn max_concurrent_resolves(&self) -> usize {
+        self.context.resolve_queue.max_active()
+    }
+
+    fn set_callback_rate_limit(&mut self, per_second: u32) {
+        self.context.throttle.get_mut().set_per_second(per_second);
+    }
+
+    fn callback_rate_limit(&self) -> u32 {
+        self.context.throttle.borrow().per_second()
+    }
+
+    fn ignore(&mut self, service_key: (String, String, String)) {
+        self.context.ignored.insert(service_key);
+    }
+
+    fn unignore(&mut self, service_key: &(String, String, String)) {
+        self.context.ignored.remove(service_key);
+    }
+
+    fn is_ignored(&self, service_key: &(String, String, String)) -> bool {
+        self.context.ignored.contains(service_key)
+    }
+
+    fn require_txt(&mut self, key: &str, value: &str) {
+        self.context.required_txt.insert(key, value);
+        self.context
+            .required_txt
+            .warn_if_txt_disabled(self.context.resolve_txt);
+    }
+
+    fn required_txt(&self) -> &[(String, String)] {
+        self.context.required_txt.entries()
+    }
+
+    fn resolve(&mut self, service: &ServiceBrowse) -> Result<EventLoop> {
+        browser::check_resolved_type(&self.service_type, service)?;
+
+        if self.context.client.is_none() {
+            return Err("browser must be started before resolving services".into());
+        }
+
+        if self.context.is_cancelled() {
+            return Err("browser has been cancelled".into());
+        }
+
+        let name = c_string!(service.raw_name_bytes());
+        let kind = c_string!(avahi_util::format_service_type(service.service_type()));
+        let domain = c_string!(service.domain().as_str());
+
+        #[cfg(feature = "diagnostics")]
+        {
+            self.context.browsed_at = None;
+        }
+
+        start_resolver(
+            &mut self.context,
+            avahi_util::interface_index(*service.interface())?,
+            avahi_util::protocol(*service.protocol()),
+            name.as_ptr(),
+            kind.as_ptr(),
+            domain.as_ptr(),
+        )?;
+
+        Ok(EventLoop::new(self.poll.as_ref().unwrap().clone()))
+    }
+
+    fn resolve_timeout(
+        &mut self,
+        service: &ServiceBrowse,
+        timeout: Duration,
+    ) -> Result<ServiceDiscovery> {
+        let found: Arc<Mutex<Option<ServiceDiscovery>>> = Arc::default();
+        let found_callback = found.clone();
+        let name = service.name().clone();
+        let domain = service.domain().clone();
+
+        self.set_service_discovered_callback(Box::new(move |result, _| match result {
+            Ok(discovery) if *discovery.name() == name && *discovery.domain() == domain => {
+                *found_callback.lock().unwrap() = Some(discovery);
+            }
+            Ok(_) => {}
+            Err(e) => warn!("error while resolving service: {}", e),
+        }));
+
+        let start = Instant::now();
+
+        let result = self.resolve(service).and_then(|event_loop| loop {
+            if let Some(discovery) = found.lock().unwrap().take() {
+                return Ok(discovery);
+            }
+
+            let elapsed = start.elapsed();
+
+            if elapsed >= timeout {
+                return Err(Error::Timeout);
+            }
+
+            event_loop.poll((timeout - elapsed).min(browser::BLOCKING_POLL_INTERVAL))?;
+        });
+
+        if result == Err(Error::Timeout) {
+            let full_name = avahi_util::service_name_join(
+                service.name(),
+                &avahi_util::format_service_type(service.service_type()),
+                service.domain(),
+            )?;
+
+            self.context.resolvers.remove_name(&full_name);
+
+            self.context
+                .record_lookups
+                .retain(|lookup| lookup.full_name != full_name);
+
+            if let Some(service) = self.context.coalesced.get_mut(&full_name) {
+                service.pending = None;
+            }
+
+            start_queued_resolvers(&mut self.context);
+        }
+
+        result
+    }
+
+    fn set_cancellation_token(&mut self, token: CancellationToken) {
+        self.context.cancellation_token = Some(token);
+    }
+
+    fn cancel(&mut self) {
+        debug!("Cancelling browser: {:?}", self);
+
+        self.context.cancel();
+        self.teardown = None;
+    }
+
+    fn close(mut self) -> Result<()> {
+        debug!("Closing browser: {:?}", self);
+
+        let mut result = Ok(());
+
+        // browsers must be freed first
+        for browser in mem::take(&mut self.context.browsers) {
+            result = result.and(browser.close());
+        }
+
+        self.context.network_watch = None;
+        self.context.network_monitor = None;
+        result = result.and(mem::take(&mut self.context.resolvers).close());
+        self.context.resolve_queue.clear();
+
+        for lookup in mem::take(&mut self.context.record_lookups) {
+            for browser in lookup.browsers {
+                result = result.and(browser.close());
+            }
+        }
+
+        self.context.coalesced.clear();
+        self.context.cancellation_token = None;
+
+        result
+    }
+
+    fn set_context(&mut self, context: Box<dyn Any>) {
+        self.context.user_context = Some(Arc::from(context));
+    }
+
+    fn context(&self) -> Option<&dyn Any> {
+        self.context.user_context.as_ref().map(|c| c.as_ref())
+    }
+
+    fn browse_services(&mut self) -> Result<EventLoop> {
+        debug!("Browsing services: {:?}", self);
+
+        if self.context.resolve_services && self.context.service_discovered_callback.is_none() {
+            return Err("a service discovered callback must be set to resolve services".into());
+        }
+
+        self.context.interface_index = avahi_util::interface_index(self.interface)?;
+
+        if self.client.is_none() {
+            self.poll = Some(Rc::new(ManagedAvahiSimplePoll::new()?));
+
+            self.client = Some(Rc::new(ManagedAvahiClient::new(
+                ManagedAvahiClientParams::builder()
+                    .poll(Rc::clone(self.poll.as_ref().unwrap()))
+                    .flags(AvahiClientFlags(0))
+                    .callback(Some(client_callback))
+                    .userdata(self.context.as_raw())
+                    .build()?,
+            )?));
+        } else if let (Some(listeners), None) = (&self.client_listeners, &self.client_listener) {
+            let context = self.context.as_raw();
+
+            // the context is boxed, so it stays at the same address until the browser is dropped,
+            // which unregisters the listener
+            self.client_listener =
+                Some(listeners.register(move |client, state| unsafe {
+                    client_callback(client, state, context)
+                }));
+        }
+
+        self.context.client = self.client.clone();
+
+        if self.context.restart_on_network_change && self.context.network_watch.is_none() {
+            self.watch_network()?;
+        }
+
+        // Avahi cannot subscribe to wide-area domains, so they are polled instead
+        if self.context.long_lived && self.context.scope != Scope::LinkLocal {
+            self.poll_wide_area()?;
+        } else {
+            self.context.long_lived_timeout = None;
+        }
+
+        self.context.all_for_now_reported = false;
+        self.context.browsed.clear();
+        self.context.repolled = None;
+
+        start_browser(&mut self.context)?;
+
+        let context: *mut AvahiBrowserContext = &mut *self.context;
+        let poll = self.poll.as_ref().unwrap();
+
+        // the context is boxed, so it stays at the same address until the browser is dropped,
+        // which unregisters the teardown
+        self.teardown = Some(
+            poll.loop_state()
+                .register_teardown(move || unsafe { (*context).cancel() }),
+        );
+
+        let throttle_timer = self.context.throttle.get_mut().interval().map(|interval| {
+            poll.loop_state()
+                .register_timer(interval, move || unsafe { (*context).deliver_throttled() })
+        });
+
+        self.context.throttle_timer = throttle_timer;
+
+        Ok(EventLoop::new(self.poll.as_ref().unwrap().clone()))
+    }
+}
+
+impl AvahiMdnsBrowser {
+    /// Creates a new browser that browses using the specified shared client instead of creating
+    /// its own, and receives the states of the client through `listeners`.
+    pub(crate) fn with_client(
+        service_type: ServiceType,
+        poll: Rc<ManagedAvahiSimplePoll>,
+        client: Rc<ManagedAvahiClient>,
+        listeners: Rc<ClientListeners>,
+    ) -> Self {
+        let mut browser = Self::new(service_type);
+        browser.poll = Some(poll);
+        browser.client = Some(client);
+        browser.client_listeners = Some(listeners);
+        browser
+    }
+
+    fn poll_wide_area(&mut self) -> Result<()> {
+        match &self.context.long_lived_timeout {
+            Some(timeout) => timeout.update(Some(browser::LONG_LIVED_POLL_INTERVAL)),
+            None => {
+                self.context.long_lived_timeout = Some(ManagedAvahiTimeout::new(
+                    ManagedAvahiTimeoutParams::builder()
+                        .poll(Rc::clone(self.poll.as_ref().unwrap()))
+                        .timeout(Some(browser::LONG_LIVED_POLL_INTERVAL))
+                        .callback(Some(long_lived_timeout_callback))
+                        .userdata(self.context.as_raw())
+                        .build()?,
+                )?);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn watch_network(&mut self) -> Result<()> {
+        debug!("Watching network changes");
+
+        let monitor = AddressMonitor::new()?;
+
+        self.context.network_watch = Some(ManagedAvahiWatch::new(
+            ManagedAvahiWatchParams::builder()
+                .poll(Rc::clone(self.poll.as_ref().unwrap()))
+                .fd(monitor.fd())
+                .event(avahi_sys::AvahiWatchEvent_AVAHI_WATCH_IN)
+                .callback(Some(network_watch_callback))
+                .userdata(self.context.as_raw())
+                .build()?,
+        )?);
+
+        self.context.network_monitor = Some(monitor);
+
+        Ok(())
     }
+}
+
+impl Drop for AvahiMdnsBrowser {
+    fn drop(&mut self) {
+        // browsers must be freed first
+        self.context.browsers.clear();
+    }
+}
+
+#[derive(FromRaw, AsRaw)]
+struct AvahiBrowserContext {
+    client: Option<Rc<ManagedAvahiClient>>,
+    browsers: Vec<ManagedAvahiServiceBrowser>,
+    kind: CString,
+    domains: Vec<String>,
+    interface_index: AvahiIfIndex,
+    protocol: AvahiProtocol,
+    // declared before the monitor so that the watch on its socket is freed first
+    network_watch: Option<ManagedAvahiWatch>,
+    network_monitor: Option<AddressMonitor>,
+    restart_on_network_change: bool,
+    long_lived: bool,
+    long_lived_timeout: Option<ManagedAvahiTimeout>,
+    // the instances currently browsed, and those browsed before the browsers were last polled
+    // again that have not been browsed since
+    browsed: HashSet<BrowsedInstance>,
+    repolled: Option<HashSet<BrowsedInstance>>,
+    resolvers: ServiceResolverSet,
+    resolve_queue: ResolveQueue<QueuedResolve>,
+    // when the browse event currently being handled was received
+    #[cfg(feature = "diagnostics")]
+    browsed_at: Option<Instant>,
+    record_lookups: Vec<RecordLookup>,
+    coalesced: HashMap<CString, CoalescedService>,
+    resolve_services: bool,
+    resolve_txt: bool,
+    resolve_all_txt_records: bool,
+    resolve_all_addresses: bool,
+    coalesce_addresses: bool,
+    resolve_srv_priority: bool,
+    ipv6_fallback: Ipv6Fallback,
+    resolve_on_browse_interface: bool,
+    all_for_now_reported: bool,
+    all_for_now_pending: Vec<*mut AvahiServiceBrowser>,
+    scope: Scope,
+    discovery_mode: DiscoveryMode,
+    on_invalid_utf8: OnInvalidUtf8,
+    ignored: IgnoredServices,
+    required_txt: TxtRequirements,
+    throttle: RefCell<CallbackThrottle>,
+    throttle_timer: Option<TimerGuard>,
+    service_discovered_callback: Option<Box<ServiceDiscoveredCallback>>,
+    service_browsed_callback: Option<Box<ServiceBrowsedCallback>>,
+    client_state_callback: Option<Box<ClientStateCallback>>,
+    cancellation_token: Option<CancellationToken>,
+    user_context: Option<Arc<dyn Any>>,
+}
+
+impl AvahiBrowserContext {
+    fn cancel(&mut self) {
+        // browsers must be freed first
+        self.browsers.clear();
+        self.network_watch = None;
+        self.network_monitor = None;
+        self.long_lived_timeout = None;
+        self.browsed.clear();
+        self.repolled = None;
+        self.resolvers = ServiceResolverSet::default();
+        self.resolve_queue.clear();
+        self.record_lookups.clear();
+        self.coalesced.clear();
+        self.throttle_timer = None;
+        self.throttle.get_mut().clear();
+        self.cancellation_token = None;
+    }
+
+    /// Frees the browsers along with everything they started, so that they can be started over.
+    fn reset_browsers(&mut self) {
+        // browsers must be freed first
+        self.browsers.clear();
+        self.browsed.clear();
+        self.repolled = None;
+        self.resolvers = ServiceResolverSet::default();
+        self.resolve_queue.clear();
+        self.record_lookups.clear();
+        self.coalesced.clear();
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancellation_token
+            .as_ref()
+            .map_or(false, CancellationToken::is_cancelled)
+    }
+
+    /// Returns `true` if TXT records are resolved, which they are to compare them if TXT
+    /// entries are required.
+    fn resolves_txt(&self) -> bool {
+        self.resolve_txt || !self.required_txt.is_empty()
+    }
+
+    /// Returns the interface and protocol to resolve a service browsed on `interface` with
+    /// `protocol` on, which are left to the daemon unless resolving on the browse interface.
+    fn resolve_interface(
+        &self,
+        interface: AvahiIfIndex,
+        protocol: AvahiProtocol,
+    ) -> (AvahiIfIndex, AvahiProtocol) {
+        if self.resolve_on_browse_interface {
+            (interface, protocol)
+        } else {
+            (avahi_sys::AVAHI_IF_UNSPEC, avahi_sys::AVAHI_PROTO_UNSPEC)
+        }
+    }
+
+    fn invoke_callback(&self, result: Result<ServiceDiscovery>) {
+        if self.ignored.suppresses_discovery(&result)
+            || self.required_txt.suppresses_discovery(&result)
+        {
+            return;
+        }
+
+        if self.throttle_timer.is_some() {
+            self.throttle.borrow_mut().push(result);
+        } else {
+            self.deliver(result);
+        }
+    }
+
+    /// Delivers the discovery that has been held back the longest by the callback rate limit.
+    fn deliver_throttled(&self) {
+        let result = self.throttle.borrow_mut().pop();
+
+        if let Some(result) = result {
+            self.deliver(result);
+        }
+    }
+
+    fn deliver(&self, result: Result<ServiceDiscovery>) {
+        if let Some(f) = &self.service_discovered_callback {
+            ffi::catch_callback_panic("service discovered callback", || {
+                f(result, self.user_context.clone())
+            });
+        } else {
+            warn!("attempted to invoke browser callback but none was set");
+        }
+    }
+
+    fn invoke_browsed_callback(&self, result: Result<BrowseEvent>) {
+        if self.ignored.suppresses_event(&result) {
+            return;
+        }
+
+        if let Some(f) = &self.service_browsed_callback {
+            ffi::catch_callback_panic("service browsed callback", || {
+                f(result, self.user_context.clone())
+            });
+        }
+    }
+
+    fn take_record_lookup(&mut self, browser: *mut AvahiRecordBrowser) -> Option<RecordLookup> {
+        let index = self
+            .record_lookups
+            .iter()
+            .position(|l| l.has_browser(browser))?;
+
+        Some(self.record_lookups.remove(index))
+    }
+
+    /// Reports a resolution of the service with the specified fully qualified name, coalescing it
+    /// with its other resolutions if enabled.
+    fn report_resolved(&mut self, full_name: CString, discovery: ServiceDiscovery) {
+        if !self.coalesce_addresses {
+            debug!("Service resolved: {:?}", discovery);
+            self.invoke_callback(Ok(discovery));
+            return;
+        }
+
+        let service = self.coalesced.entry(full_name.clone()).or_default();
+
+        match &mut service.pending {
+            Some(pending) => pending.merge_addresses(discovery),
+            None => service.pending = Some(discovery),
+        }
+
+        self.flush_coalesced(&full_name);
+    }
+
+    /// Reports the coalesced resolutions of the specified service once none are pending anymore.
+    fn flush_coalesced(&mut self, full_name: &CStr) {
+        let pending = self.resolvers.contains_name(full_name)
+            || self
+                .resolve_queue
+                .iter()
+                .any(|r| r.full_name.as_c_str() == full_name)
+            || self
+                .record_lookups
+                .iter()
+                .any(|l| l.full_name.as_c_str() == full_name);
+
+        if pending {
+            return;
+        }
+
+        let service = match self.coalesced.get_mut(full_name) {
+            Some(service) => service,
+            None => return,
+        };
+
+        let discovery = match service.pending.take() {
+            Some(discovery) => discovery,
+            None => return,
+        };
+
+        service.reported = true;
+
+        // the service was resolved explicitly or has been removed in the meantime
+        if service.instances == 0 {
+            self.coalesced.remove(full_name);
+        }
+
+        debug!("Service resolved: {:?}", discovery);
+
+        self.invoke_callback(Ok(discovery));
+    }
+
+    /// Counts a browsed instance of the specified service, returning `false` if its coalesced
+    /// resolution has already been reported.
+    fn add_instance(&mut self, full_name: CString) -> bool {
+        let service = self.coalesced.entry(full_name).or_default();
+        service.instances += 1;
+        !service.reported
+    }
+
+    /// Forgets a browsed instance of the specified service, forgetting the service once it has
+    /// been removed from every interface and protocol.
+    fn remove_instance(&mut self, full_name: &CStr) {
+        if let Some(service) = self.coalesced.get_mut(full_name) {
+            service.instances = service.instances.saturating_sub(1);
+
+            if service.instances == 0 && service.pending.is_none() {
+                self.coalesced.remove(full_name);
+            }
+        }
+    }
+}
+
+/// An instance of a service browsed on an interface and protocol.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct BrowsedInstance {
+    interface: AvahiIfIndex,
+    protocol: AvahiProtocol,
+    name: CString,
+    kind: CString,
+    domain: CString,
+}
+
+impl BrowsedInstance {
+    unsafe fn new(
+        interface: AvahiIfIndex,
+        protocol: AvahiProtocol,
+        name: *const c_char,
+        kind: *const c_char,
+        domain: *const c_char,
+    ) -> Self {
+        Self {
+            interface,
+            protocol,
+            name: CStr::from_ptr(name).to_owned(),
+            kind: CStr::from_ptr(kind).to_owned(),
+            domain: CStr::from_ptr(domain).to_owned(),
+        }
+    }
+}
+
+/// The state of a service whose resolutions are coalesced.
+#[derive(Default)]
+struct CoalescedService {
+    /// The number of interfaces and protocols the service is currently browsed on.
+    instances: usize,
+    /// The resolutions that have finished so far, waiting for those still pending.
+    pending: Option<ServiceDiscovery>,
+    /// Whether the coalesced resolution has been reported.
+    reported: bool,
+}
+
+/// A resolved service whose TXT or address records are still being browsed for. The service is
+/// reported once every record browser has returned everything it has.
+struct RecordLookup {
+    full_name: CString,
+    browsers: Vec<ManagedAvahiRecordBrowser>,
+    discovery: ServiceDiscoveryBuilder,
+    resolved_txt: Option<TxtRecord>,
+    resolved_address: ServiceAddress,
+    txt_records: Vec<TxtRecord>,
+    addresses: Vec<ServiceAddress>,
+    #[cfg(feature = "diagnostics")]
+    timing: Option<(Instant, Instant)>,
+}
+
+impl RecordLookup {
+    fn has_browser(&self, browser: *mut AvahiRecordBrowser) -> bool {
+        self.browsers.iter().any(|b| b.inner() == browser)
+    }
+
+    /// Frees the specified browser, returning `true` if it was the last one.
+    fn finish_browser(&mut self, browser: *mut AvahiRecordBrowser) -> bool {
+        self.browsers.retain(|b| b.inner() != browser);
+        self.browsers.is_empty()
+    }
+
+    unsafe fn add_record(
+        &mut self,
+        interface: AvahiIfIndex,
+        protocol: AvahiProtocol,
+        kind: u16,
+        rdata: *const c_void,
+        size: usize,
+    ) -> Result<()> {
+        if u32::from(kind) == avahi_sys::AVAHI_DNS_TYPE_TXT {
+            let rdata = std::slice::from_raw_parts(rdata as *const u8, size);
+            self.txt_records.push(TxtRecord::from_bytes(rdata)?);
+            return Ok(());
+        }
+
+        if u32::from(kind) == avahi_sys::AVAHI_DNS_TYPE_SRV {
+            let rdata = std::slice::from_raw_parts(rdata as *const u8, size);
+            let (priority, weight) = browser::srv_priority_weight(rdata)?;
+            self.discovery.priority(priority).weight(weight);
+            return Ok(());
+        }
+
+        let address = ServiceAddress::builder()
+            .address(avahi_util::address_from_rdata(kind, rdata, size)?)
+            .interface(avahi_util::interface_from_index(interface))
+            .protocol(avahi_util::protocol_from_raw(protocol))
+            .build()?;
+
+        if !self.addresses.contains(&address) {
+            self.addresses.push(address);
+        }
+
+        Ok(())
+    }
+
+    fn into_discovery(mut self) -> ServiceDiscovery {
+        // fall back to what was returned by the resolver if nothing was browsed
+        let txt_records = if self.txt_records.is_empty() {
+            self.resolved_txt.into_iter().collect::<Vec<_>>()
+        } else {
+            self.txt_records
+        };
+
+        let addresses = if self.addresses.is_empty() {
+            vec![self.resolved_address]
+        } else {
+            self.addresses
+        };
+
+        #[cfg(feature = "diagnostics")]
+        if let Some((browsed_at, resolve_started_at)) = self.timing {
+            self.discovery.timing(Some(browser::ResolveTiming::new(
+                browsed_at,
+                resolve_started_at,
+                Instant::now(),
+            )));
+        }
+
+        self.discovery
+            .txt(txt_records.first().cloned())
+            .txt_records(txt_records)
+            .addresses(addresses)
+            .build()
+            .expect("could not build ServiceDiscovery")
+    }
+}
+
+impl Default for AvahiBrowserContext {
+    fn default() -> Self {
+        AvahiBrowserContext {
+            client: None,
+            browsers: Vec::new(),
+            kind: CString::default(),
+            domains: Vec::new(),
+            interface_index: avahi_sys::AVAHI_IF_UNSPEC,
+            protocol: avahi_sys::AVAHI_PROTO_UNSPEC,
+            network_watch: None,
+            network_monitor: None,
+            restart_on_network_change: false,
+            long_lived: false,
+            long_lived_timeout: None,
+            browsed: HashSet::new(),
+            repolled: None,
+            resolvers: ServiceResolverSet::default(),
+            resolve_queue: ResolveQueue::default(),
+            #[cfg(feature = "diagnostics")]
+            browsed_at: None,
+            record_lookups: Vec::new(),
+            coalesced: HashMap::new(),
+            resolve_services: true,
+            resolve_txt: true,
+            resolve_all_txt_records: false,
+            resolve_all_addresses: false,
+            coalesce_addresses: true,
+            resolve_srv_priority: false,
+            ipv6_fallback: Ipv6Fallback::default(),
+            resolve_on_browse_interface: true,
+            all_for_now_reported: false,
+            all_for_now_pending: Vec::new(),
+            scope: Scope::default(),
+            discovery_mode: DiscoveryMode::default(),
+            on_invalid_utf8: OnInvalidUtf8::default(),
+            ignored: IgnoredServices::default(),
+            required_txt: TxtRequirements::default(),
+            throttle: RefCell::default(),
+            throttle_timer: None,
+            service_discovered_callback: None,
+            service_browsed_callback: None,
+            client_state_callback: None,
+            cancellation_token: None,
+            user_context: None,
+        }
+    }
+}
+
+impl fmt::Debug for AvahiBrowserContext {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("AvahiBrowserContext")
+            .field("client", &self.client)
+            .field("resolvers", &self.resolvers)
+            .field("resolve_queue", &self.resolve_queue)
+            .field("resolve_services", &self.resolve_services)
+            .field("resolve_txt", &self.resolve_txt)
+            .field("resolve_all_txt_records", &self.resolve_all_txt_records)
+            .field("resolve_all_addresses", &self.resolve_all_addresses)
+            .field("coalesce_addresses", &self.coalesce_addresses)
+            .field("resolve_srv_priority", &self.resolve_srv_priority)
+            .field("ipv6_fallback", &self.ipv6_fallback)
+            .field(
+                "resolve_on_browse_interface",
+                &self.resolve_on_browse_interface,
+            )
+            .field("restart_on_network_change", &self.restart_on_network_change)
+            .field("long_lived", &self.long_lived)
+            .field("domains", &self.domains)
+            .field("scope", &self.scope)
+            .field("discovery_mode", &self.discovery_mode)
+            .field("on_invalid_utf8", &self.on_invalid_utf8)
+            .field("ignored", &self.ignored)
+            .field("required_txt", &self.required_txt)
+            .field("throttle", &self.throttle)
+            .finish()
+    }
+}
+
+unsafe extern "C" fn browse_callback(
+    browser: *mut AvahiServiceBrowser,
+    interface: AvahiIfIndex,
+    protocol: AvahiProtocol,
+    event: AvahiBrowserEvent,
+    name: *const c_char,
+    kind: *const c_char,
+    domain: *const c_char,
+    _flags: AvahiLookupResultFlags,
+    userdata: *mut c_void,
+) {
+    let context = AvahiBrowserContext::from_raw(userdata);
+
+    if context.is_cancelled() {
+        return;
+    }
+
+    match event {
+        avahi_sys::AvahiBrowserEvent_AVAHI_BROWSER_NEW => {
+            #[cfg(feature = "diagnostics")]
+            {
+                context.browsed_at = Some(Instant::now());
+            }
+
+            let instance = BrowsedInstance::new(interface, protocol, name, kind, domain);
+
+            // instances that were browsed before the browsers were polled again have already
+            // been reported
+            let known = context
+                .repolled
+                .as_mut()
+                .map_or(false, |repolled| repolled.remove(&instance));
+
+            context.browsed.insert(instance);
+
+            if known {
+                return;
+            }
+
+            let instance_name = match browser::decode_instance_name(
+                CStr::from_ptr(name).to_bytes(),
+                context.on_invalid_utf8,
+            ) {
+                Ok(Some(instance_name)) => instance_name,
+                Ok(None) => return,
+                Err(e) => {
+                    if context.service_browsed_callback.is_some() {
+                        context.invoke_browsed_callback(Err(e));
+                    } else {
+                        context.invoke_callback(Err(e));
+                    }
+                    return;
+                }
+            };
+
+            if context.service_browsed_callback.is_some() {
+                let result = browse_event(interface, protocol, instance_name, name, kind, domain)
+                    .map(BrowseEvent::New);
+                context.invoke_browsed_callback(result);
+            }
+
+            if !context.resolve_services {
+                return;
+            }
+
+            if context.coalesce_addresses {
+                let full_name = match browse_full_name(name, kind, domain) {
+                    Ok(full_name) => full_name,
+                    Err(e) => {
+                        context.invoke_callback(Err(e));
+                        return;
+                    }
+                };
+
+                if !context.add_instance(full_name) {
+                    return;
+                }
+            }
+
+            // the same service is also browsed over IPv4, where it is resolved instead
+            if context.ipv6_fallback.is_active() && protocol == avahi_sys::AVAHI_PROTO_INET6 {
+                return;
+            }
+
+            if let Err(e) = queue_resolver(context, interface, protocol, name, kind, domain) {
+                context.invoke_callback(Err(e));
+            }
+        }
+        avahi_sys::AvahiBrowserEvent_AVAHI_BROWSER_REMOVE => {
+            context.browsed.remove(&BrowsedInstance::new(
+                interface, protocol, name, kind, domain,
+            ));
+
+            remove_browsed(context, interface, protocol, name, kind, domain);
+        }
+        avahi_sys::AvahiBrowserEvent_AVAHI_BROWSER_ALL_FOR_NOW => {
+            // with several domains, the event is only reported once every browser is done
+            context.all_for_now_pending.retain(|b| *b != browser);
+
+            if !context.all_for_now_pending.is_empty() {
+                return;
+            }
+
+            report_vanished(context);
+
+            if !context.all_for_now_reported {
+                context.all_for_now_reported = true;
+                context.invoke_browsed_callback(Ok(BrowseEvent::AllForNow));
+            }
+        }
+        avahi_sys::AvahiBrowserEvent_AVAHI_BROWSER_FAILURE => {
+            if context.service_browsed_callback.is_some() {
+                context.invoke_browsed_callback(Err("browser failure".into()));
+            } else {
+                context.invoke_callback(Err("browser failure".into()));
+            }
+        }
+        _ => {}
+    };
+}
+
+/// Reports that the specified instance of a service is no longer browsed.
+unsafe fn remove_browsed(
+    context: &mut AvahiBrowserContext,
+    interface: AvahiIfIndex,
+    protocol: AvahiProtocol,
+    name: *const c_char,
+    kind: *const c_char,
+    domain: *const c_char,
+) {
+    if context.service_browsed_callback.is_some() {
+        let instance_name =
+            browser::decode_instance_name(CStr::from_ptr(name).to_bytes(), context.on_invalid_utf8);
 
-    fn network_interface(&self) -> NetworkInterface {
-        avahi_util::interface_from_index(self.interface_index)
+        // services that were skipped or reported as errors when added are not reported
+        if let Ok(Some(instance_name)) = instance_name {
+            let result = browse_event(interface, protocol, instance_name, name, kind, domain)
+                .map(BrowseEvent::Removed);
+            context.invoke_browsed_callback(result);
+        }
     }
 
-    fn set_service_discovered_callback(
-        &mut self,
-        service_discovered_callback: Box<ServiceDiscoveredCallback>,
-    ) {
-        self.context.service_discovered_callback = Some(service_discovered_callback);
-    }
+    if let Ok(full_name) = browse_full_name(name, kind, domain) {
+        context.resolve_queue.retain(|r| {
+            r.full_name != full_name || r.interface != interface || r.protocol != protocol
+        });
 
-    fn set_context(&mut self, context: Box<dyn Any>) {
-        self.context.user_context = Some(Arc::from(context));
+        context.remove_instance(&full_name);
     }
+}
 
-    fn context(&self) -> Option<&dyn Any> {
-        self.context.user_context.as_ref().map(|c| c.as_ref())
-    }
+/// Reports the instances that were browsed before the browsers were last polled again, but have
+/// not been browsed since, as removed.
+fn report_vanished(context: &mut AvahiBrowserContext) {
+    for instance in context.repolled.take().unwrap_or_default() {
+        debug!("Service no longer browsed: {:?}", instance);
 
-    fn browse_services(&mut self) -> Result<EventLoop> {
-        debug!("Browsing services: {:?}", self);
+        unsafe {
+            remove_browsed(
+                context,
+                instance.interface,
+                instance.protocol,
+                instance.name.as_ptr(),
+                instance.kind.as_ptr(),
+                instance.domain.as_ptr(),
+            )
+        };
+    }
+}
 
-        self.poll = Some(Rc::new(ManagedAvahiSimplePoll::new()?));
+/// Prepares for the browsers to be started again by a poll of wide-area domains: instances that
+/// were not browsed again by the previous poll are reported as removed, and those browsed now are
+/// only reported by the next poll if it does not browse them again.
+fn repoll_browsed(context: &mut AvahiBrowserContext) {
+    report_vanished(context);
+    context.repolled = Some(mem::take(&mut context.browsed));
+}
 
-        self.client = Some(Rc::new(ManagedAvahiClient::new(
-            ManagedAvahiClientParams::builder()
-                .poll(Rc::clone(self.poll.as_ref().unwrap()))
-                .flags(AvahiClientFlags(0))
-                .callback(Some(client_callback))
-                .userdata(ptr::null_mut())
-                .build()?,
-        )?));
+/// Starts the service browsers of the specified context, replacing any browsers it already has.
+fn start_browser(context: &mut AvahiBrowserContext) -> Result<()> {
+    context.browsers.clear();
 
-        self.context.client = self.client.clone();
+    let domains = if !context.domains.is_empty() {
+        context
+            .domains
+            .iter()
+            .map(|d| Some(c_string!(d.as_str())))
+            .collect()
+    } else {
+        // the daemon's default domain may be configured to something other than `local`
+        match context.scope {
+            Scope::LinkLocal => vec![Some(c_string!("local"))],
+            Scope::WideArea | Scope::Both => vec![None],
+        }
+    };
 
-        self.browser = Some(ManagedAvahiServiceBrowser::new(
+    for domain in &domains {
+        let browser = ManagedAvahiServiceBrowser::new(
             ManagedAvahiServiceBrowserParams::builder()
-                .interface(self.interface_index)
-                .protocol(avahi_sys::AVAHI_PROTO_UNSPEC)
-                .kind(self.kind.as_ptr())
-                .domain(ptr::null_mut())
-                .flags(0)
+                .interface(context.interface_index)
+                .protocol(context.protocol)
+                .kind(context.kind.as_ptr())
+                .domain(domain.as_ref().map(|d| d.as_ptr()).unwrap_or_null())
+                .flags(avahi_util::lookup_flags(
+                    context.discovery_mode,
+                    context.scope,
+                ))
                 .callback(Some(browse_callback))
-                .userdata(self.context.as_raw())
-                .client(Rc::clone(self.context.client.as_ref().unwrap()))
+                .userdata(context.as_raw())
+                .client(Rc::clone(context.client.as_ref().unwrap()))
                 .build()?,
-        )?);
+        )?;
 
-        Ok(EventLoop::new(self.poll.as_ref().unwrap().clone()))
+        context.browsers.push(browser);
     }
+
+    context.all_for_now_pending = context.browsers.iter().map(|b| b.inner()).collect();
+
+    Ok(())
 }
 
-impl Drop for AvahiMdnsBrowser {
-    fn drop(&mut self) {
-        // browser must be freed first
-        self.browser = None;
+unsafe extern "C" fn network_watch_callback(
+    _watch: *mut AvahiWatch,
+    _fd: c_int,
+    _event: AvahiWatchEvent,
+    userdata: *mut c_void,
+) {
+    let context = AvahiBrowserContext::from_raw(userdata);
+
+    if context.is_cancelled() {
+        return;
+    }
+
+    if let Err(e) = handle_network_change(context) {
+        if context.service_browsed_callback.is_some() {
+            context.invoke_browsed_callback(Err(e));
+        } else {
+            context.invoke_callback(Err(e));
+        }
     }
 }
 
-#[derive(FromRaw, AsRaw)]
-struct AvahiBrowserContext {
-    client: Option<Rc<ManagedAvahiClient>>,
-    resolvers: ServiceResolverSet,
-    service_discovered_callback: Option<Box<ServiceDiscoveredCallback>>,
-    user_context: Option<Arc<dyn Any>>,
+fn handle_network_change(context: &mut AvahiBrowserContext) -> Result<()> {
+    if !context.network_monitor.as_ref().unwrap().drain() {
+        return Ok(());
+    }
+
+    debug!("Network changed, restarting browser");
+
+    context.reset_browsers();
+    context.all_for_now_reported = false;
+    context.invoke_browsed_callback(Ok(BrowseEvent::NetworkChanged));
+
+    start_browser(context)
 }
 
-impl AvahiBrowserContext {
-    fn invoke_callback(&self, result: Result<ServiceDiscovery>) {
-        if let Some(f) = &self.service_discovered_callback {
-            f(result, self.user_context.clone());
+unsafe extern "C" fn long_lived_timeout_callback(
+    _timeout: *mut AvahiTimeout,
+    userdata: *mut c_void,
+) {
+    let context = AvahiBrowserContext::from_raw(userdata);
+
+    if context.is_cancelled() {
+        return;
+    }
+
+    debug!("Polling wide-area domains, restarting browser");
+
+    repoll_browsed(context);
+
+    if let Some(timeout) = &context.long_lived_timeout {
+        timeout.update(Some(browser::LONG_LIVED_POLL_INTERVAL));
+    }
+
+    if let Err(e) = start_browser(context) {
+        if context.service_browsed_callback.is_some() {
+            context.invoke_browsed_callback(Err(e));
         } else {
-            panic!("attempted to invoke browser callback but none was set");
+            context.invoke_callback(Err(e));
         }
     }
 }
 
-#[allow(clippy::derivable_impls)]
-impl Default for AvahiBrowserContext {
-    fn default() -> Self {
-        AvahiBrowserContext {
-            client: None,
-            resolvers: ServiceResolverSet::default(),
-            service_discovered_callback: None,
-            user_context: None,
-        }
-    }
+unsafe fn browse_event(
+    interface: AvahiIfIndex,
+    protocol: AvahiProtocol,
+    instance_name: String,
+    name: *const c_char,
+    kind: *const c_char,
+    domain: *const c_char,
+) -> Result<ServiceBrowse> {
+    Ok(ServiceBrowse::builder()
+        .name(instance_name)
+        .raw_name(browser::invalid_utf8_name(CStr::from_ptr(name).to_bytes()))
+        .service_type(ServiceType::from_str(c_str::raw_to_str(kind))?)
+        .domain(c_str::copy_raw(domain))
+        .interface(avahi_util::interface_from_index(interface))
+        .protocol(avahi_util::protocol_from_raw(protocol))
+        .build()
+        .expect("could not build ServiceBrowse"))
 }
 
-impl fmt::Debug for AvahiBrowserContext {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        f.debug_struct("AvahiBrowserContext")
-            .field("client", &self.client)
-            .field("resolvers", &self.resolvers)
-            .finish()
-    }
+unsafe fn browse_full_name(
+    name: *const c_char,
+    kind: *const c_char,
+    domain: *const c_char,
+) -> Result<CString> {
+    // invalid UTF-8 is replaced consistently, so the joined name still identifies the service
+    avahi_util::service_name_join(
+        &CStr::from_ptr(name).to_string_lossy(),
+        c_str::raw_to_str(kind),
+        c_str::raw_to_str(domain),
+    )
 }
 
-unsafe extern "C" fn browse_callback(
-    _browser: *mut AvahiServiceBrowser,
+/// A browsed service waiting for [`ResolveQueue`] to let it be resolved.
+#[derive(Debug)]
+struct QueuedResolve {
+    full_name: CString,
+    interface: AvahiIfIndex,
+    protocol: AvahiProtocol,
+    name: CString,
+    kind: CString,
+    domain: CString,
+    #[cfg(feature = "diagnostics")]
+    browsed_at: Option<Instant>,
+}
+
+/// Resolves the specified browsed service once fewer resolvers than the browser's limit are
+/// pending.
+unsafe fn queue_resolver(
+    context: &mut AvahiBrowserContext,
     interface: AvahiIfIndex,
     protocol: AvahiProtocol,
-    event: AvahiBrowserEvent,
     name: *const c_char,
     kind: *const c_char,
     domain: *const c_char,
-    _flags: AvahiLookupResultFlags,
-    userdata: *mut c_void,
-) {
-    let context = AvahiBrowserContext::from_raw(userdata);
+) -> Result<()> {
+    let resolve = QueuedResolve {
+        full_name: browse_full_name(name, kind, domain)?,
+        interface,
+        protocol,
+        name: CStr::from_ptr(name).to_owned(),
+        kind: CStr::from_ptr(kind).to_owned(),
+        domain: CStr::from_ptr(domain).to_owned(),
+        #[cfg(feature = "diagnostics")]
+        browsed_at: context.browsed_at.take(),
+    };
 
-    match event {
-        avahi_sys::AvahiBrowserEvent_AVAHI_BROWSER_NEW => {
-            if let Err(e) = handle_browser_new(context, interface, protocol, name, kind, domain) {
-                context.invoke_callback(Err(e));
-            }
-        }
-        avahi_sys::AvahiBrowserEvent_AVAHI_BROWSER_FAILURE => {
-            context.invoke_callback(Err("browser failure".into()))
+    match context.resolve_queue.push(context.resolvers.len(), resolve) {
+        Some(resolve) => start_queued_resolver(context, resolve),
+        // resolved by `start_queued_resolvers()` once earlier resolutions finish
+        None => Ok(()),
+    }
+}
+
+fn start_queued_resolver(context: &mut AvahiBrowserContext, resolve: QueuedResolve) -> Result<()> {
+    #[cfg(feature = "diagnostics")]
+    {
+        context.browsed_at = resolve.browsed_at;
+    }
+
+    start_resolver(
+        context,
+        resolve.interface,
+        resolve.protocol,
+        resolve.name.as_ptr(),
+        resolve.kind.as_ptr(),
+        resolve.domain.as_ptr(),
+    )
+}
+
+/// Starts resolving queued services until the browser's limit of pending resolvers is reached.
+fn start_queued_resolvers(context: &mut AvahiBrowserContext) {
+    while let Some(resolve) = context.resolve_queue.pop(context.resolvers.len()) {
+        if let Err(e) = start_queued_resolver(context, resolve) {
+            context.invoke_callback(Err(e));
         }
-        _ => {}
-    };
+    }
 }
 
-fn handle_browser_new(
+fn start_resolver(
     context: &mut AvahiBrowserContext,
     interface: AvahiIfIndex,
     protocol: AvahiProtocol,
@@ -188,8 +1371,26 @@ fn handle_browser_new(
     kind: *const c_char,
     domain: *const c_char,
 ) -> Result<()> {
-    let raw_context = context.as_raw();
-    context.resolvers.insert(ManagedAvahiServiceResolver::new(
+    let mut flags = avahi_util::lookup_flags(context.discovery_mode, context.scope);
+
+    let (interface, protocol) = context.resolve_interface(interface, protocol);
+
+    let (protocol, aprotocol) = if context.ipv6_fallback.is_active() {
+        (avahi_sys::AVAHI_PROTO_INET, avahi_sys::AVAHI_PROTO_INET)
+    } else {
+        (protocol, avahi_sys::AVAHI_PROTO_UNSPEC)
+    };
+
+    if !context.resolves_txt() {
+        flags |= LookupFlags::NO_TXT;
+    }
+
+    let full_name = unsafe { browse_full_name(name, kind, domain) }?;
+
+    #[cfg(feature = "diagnostics")]
+    let resolve_started_at = Instant::now();
+
+    let resolver = ManagedAvahiServiceResolver::new(
         ManagedAvahiServiceResolverParams::builder()
             .client(Rc::clone(context.client.as_ref().unwrap()))
             .interface(interface)
@@ -197,19 +1398,29 @@ fn handle_browser_new(
             .name(name)
             .kind(kind)
             .domain(domain)
-            .aprotocol(avahi_sys::AVAHI_PROTO_UNSPEC)
-            .flags(0)
+            .aprotocol(aprotocol)
+            .flags(flags)
             .callback(Some(resolve_callback))
-            .userdata(raw_context)
+            .userdata(context.as_raw())
             .build()?,
-    )?);
+    )?;
+
+    #[cfg(feature = "diagnostics")]
+    {
+        let browsed_at = context.browsed_at.take().unwrap_or(resolve_started_at);
+        context
+            .resolvers
+            .set_timing(&resolver, browsed_at, resolve_started_at);
+    }
+
+    context.resolvers.insert(full_name, resolver);
     Ok(())
 }
 
 unsafe extern "C" fn resolve_callback(
     resolver: *mut AvahiServiceResolver,
-    _interface: AvahiIfIndex,
-    _protocol: AvahiProtocol,
+    interface: AvahiIfIndex,
+    protocol: AvahiProtocol,
     event: AvahiResolverEvent,
     name: *const c_char,
     kind: *const c_char,
@@ -218,33 +1429,82 @@ unsafe extern "C" fn resolve_callback(
     addr: *const AvahiAddress,
     port: u16,
     txt: *mut AvahiStringList,
-    _flags: AvahiLookupResultFlags,
+    flags: AvahiLookupResultFlags,
     userdata: *mut c_void,
 ) {
-    let name = c_str::raw_to_str(name);
+    let raw_name = CStr::from_ptr(name).to_bytes();
+    let lossy_name = String::from_utf8_lossy(raw_name);
+    let name = lossy_name.as_ref();
     let kind = c_str::raw_to_str(kind);
     let domain = c_str::raw_to_str(domain);
 
     let context = AvahiBrowserContext::from_raw(userdata);
 
+    // freed once the callback returns, but no longer pending for other resolutions
+    let _resolver = context.resolvers.remove_raw(resolver);
+
+    #[cfg(feature = "diagnostics")]
+    let timing = context.resolvers.take_timing(resolver);
+
+    if context.is_cancelled() {
+        return;
+    }
+
     match event {
         avahi_sys::AvahiResolverEvent_AVAHI_RESOLVER_FAILURE => {
-            context.invoke_callback(Err(format!(
+            if protocol == avahi_sys::AVAHI_PROTO_INET6 {
+                context.ipv6_fallback.record_failure();
+            }
+
+            let message = format!(
                 "failed to resolve service `{}` of type `{}` in domain `{}`",
                 name, kind, domain
-            )
-            .into()));
+            );
+
+            #[cfg(feature = "diagnostics")]
+            let diagnostics = {
+                let client = context.client.as_ref().unwrap();
+                let reason = avahi_util::get_error(avahi_sys::avahi_client_errno(client.inner));
+
+                let aprotocol = _resolver
+                    .as_ref()
+                    .map_or(avahi_sys::AVAHI_PROTO_UNSPEC, |r| r.aprotocol());
+
+                browser::ResolveDiagnostics::new(
+                    avahi_util::interface_from_index(interface),
+                    avahi_util::protocol_from_raw(protocol),
+                    avahi_util::protocol_from_raw(aprotocol),
+                    None,
+                    reason.to_string(),
+                )
+            };
+
+            let error = browser::resolve_failed(message);
+            #[cfg(feature = "diagnostics")]
+            let error = error.with_diagnostics(diagnostics);
+
+            context.invoke_callback(Err(error));
         }
         avahi_sys::AvahiResolverEvent_AVAHI_RESOLVER_FOUND => {
+            if protocol == avahi_sys::AVAHI_PROTO_INET6 {
+                context.ipv6_fallback.record_success();
+            }
+
             let result = handle_resolver_found(
                 context,
+                interface,
+                protocol,
                 c_str::raw_to_str(host_name),
                 addr,
                 name,
+                browser::invalid_utf8_name(raw_name),
                 kind,
                 domain,
                 port,
                 txt,
+                flags,
+                #[cfg(feature = "diagnostics")]
+                timing,
             );
 
             if let Err(e) = result {
@@ -254,53 +1514,350 @@ unsafe extern "C" fn resolve_callback(
         _ => {}
     };
 
-    context.resolvers.remove_raw(resolver);
+    // report what the other resolutions of the service found if this one failed
+    if let Ok(full_name) = avahi_util::service_name_join(name, kind, domain) {
+        context.flush_coalesced(&full_name);
+    }
+
+    start_queued_resolvers(context);
 }
 
 #[allow(clippy::too_many_arguments)]
 unsafe fn handle_resolver_found(
-    context: &AvahiBrowserContext,
+    context: &mut AvahiBrowserContext,
+    interface: AvahiIfIndex,
+    protocol: AvahiProtocol,
     host_name: &str,
     addr: *const AvahiAddress,
     name: &str,
+    raw_name: Option<Vec<u8>>,
     kind: &str,
     domain: &str,
     port: u16,
     txt: *mut AvahiStringList,
+    flags: AvahiLookupResultFlags,
+    #[cfg(feature = "diagnostics")] timing: Option<(Instant, Instant)>,
 ) -> Result<()> {
     let address = avahi_util::avahi_address_to_string(addr);
 
-    let txt = if txt.is_null() {
+    let resolved_address = ServiceAddress::builder()
+        .address(avahi_util::ip_address(addr))
+        .interface(avahi_util::interface_from_index(interface))
+        .protocol(avahi_util::protocol_from_raw(protocol))
+        .build()?;
+
+    let txt = if txt.is_null() || !context.resolves_txt() {
         None
     } else {
-        Some(TxtRecord::from(ManagedAvahiStringList::clone_raw(txt)))
+        let data = ManagedAvahiStringList::clone_raw(txt).serialize();
+        Some(TxtRecord::from_bytes(&data)?)
     };
 
-    let result = ServiceDiscovery::builder()
+    let service_type = ServiceType::from_str(kind)?;
+
+    let our_own = flags & avahi_sys::AvahiLookupResultFlags_AVAHI_LOOKUP_RESULT_OUR_OWN != 0
+        || service::is_own_service(&browser::service_key(name, &service_type, domain));
+
+    let mut discovery = ServiceDiscovery::builder();
+
+    discovery
         .name(name.to_string())
-        .service_type(ServiceType::from_str(kind)?)
+        .raw_name(raw_name)
+        .service_type(service_type)
         .domain(domain.to_string())
         .host_name(host_name.to_string())
         .address(address)
         .port(port)
-        .txt(txt)
-        .build()
-        .unwrap();
+        .our_own(our_own);
+
+    let mut lookup = RecordLookup {
+        full_name: avahi_util::service_name_join(name, kind, domain)?,
+        browsers: Vec::new(),
+        discovery,
+        resolved_txt: txt,
+        resolved_address,
+        txt_records: Vec::new(),
+        addresses: Vec::new(),
+        #[cfg(feature = "diagnostics")]
+        timing,
+    };
+
+    if context.resolves_txt() && context.resolve_all_txt_records {
+        let full_name = lookup.full_name.clone();
+
+        lookup.browsers.push(browse_records(
+            context,
+            interface,
+            protocol,
+            &full_name,
+            avahi_sys::AVAHI_DNS_TYPE_TXT,
+        )?);
+    }
+
+    if context.resolve_srv_priority {
+        let full_name = lookup.full_name.clone();
+
+        lookup.browsers.push(browse_records(
+            context,
+            interface,
+            protocol,
+            &full_name,
+            avahi_sys::AVAHI_DNS_TYPE_SRV,
+        )?);
+    }
+
+    if context.resolve_all_addresses {
+        let host_name = c_string!(host_name);
+
+        // addresses of either family may be received over either protocol
+        for kind in &[avahi_sys::AVAHI_DNS_TYPE_A, avahi_sys::AVAHI_DNS_TYPE_AAAA] {
+            lookup.browsers.push(browse_records(
+                context,
+                interface,
+                avahi_sys::AVAHI_PROTO_UNSPEC,
+                &host_name,
+                *kind,
+            )?);
+        }
+    }
+
+    if !lookup.browsers.is_empty() {
+        // reported from `record_callback()` once the browsers are done
+        context.record_lookups.push(lookup);
+        return Ok(());
+    }
 
-    debug!("Service resolved: {:?}", result);
+    let full_name = lookup.full_name.clone();
 
-    context.invoke_callback(Ok(result));
+    context.report_resolved(full_name, lookup.into_discovery());
 
     Ok(())
 }
 
-extern "C" fn client_callback(
+fn browse_records(
+    context: &mut AvahiBrowserContext,
+    interface: AvahiIfIndex,
+    protocol: AvahiProtocol,
+    name: &CStr,
+    kind: u32,
+) -> Result<ManagedAvahiRecordBrowser> {
+    let raw_context = context.as_raw();
+
+    ManagedAvahiRecordBrowser::new(
+        ManagedAvahiRecordBrowserParams::builder()
+            .client(Rc::clone(context.client.as_ref().unwrap()))
+            .interface(interface)
+            .protocol(protocol)
+            .name(name.as_ptr())
+            .clazz(avahi_sys::AVAHI_DNS_CLASS_IN as u16)
+            .kind(kind as u16)
+            .flags(avahi_util::lookup_flags(
+                context.discovery_mode,
+                context.scope,
+            ))
+            .callback(Some(record_callback))
+            .userdata(raw_context)
+            .build()?,
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+unsafe extern "C" fn record_callback(
+    browser: *mut AvahiRecordBrowser,
+    interface: AvahiIfIndex,
+    protocol: AvahiProtocol,
+    event: AvahiBrowserEvent,
+    _name: *const c_char,
+    _clazz: u16,
+    kind: u16,
+    rdata: *const c_void,
+    size: usize,
+    _flags: AvahiLookupResultFlags,
+    userdata: *mut c_void,
+) {
+    let context = AvahiBrowserContext::from_raw(userdata);
+
+    if context.is_cancelled() {
+        context.take_record_lookup(browser);
+        return;
+    }
+
+    let index = match context
+        .record_lookups
+        .iter()
+        .position(|l| l.has_browser(browser))
+    {
+        Some(index) => index,
+        None => return,
+    };
+
+    match event {
+        avahi_sys::AvahiBrowserEvent_AVAHI_BROWSER_NEW => {
+            let lookup = &mut context.record_lookups[index];
+
+            if let Err(e) = lookup.add_record(interface, protocol, kind, rdata, size) {
+                warn!("{}", e);
+            }
+
+            // a service has a single SRV record, so there is no need to wait for more
+            if u32::from(kind) == avahi_sys::AVAHI_DNS_TYPE_SRV {
+                finish_record_browser(context, index, browser);
+            }
+        }
+        avahi_sys::AvahiBrowserEvent_AVAHI_BROWSER_ALL_FOR_NOW
+        | avahi_sys::AvahiBrowserEvent_AVAHI_BROWSER_FAILURE => {
+            finish_record_browser(context, index, browser);
+        }
+        _ => {}
+    };
+}
+
+/// Frees `browser` of the record lookup at `index`, reporting the service if it was the last.
+fn finish_record_browser(
+    context: &mut AvahiBrowserContext,
+    index: usize,
+    browser: *mut AvahiRecordBrowser,
+) {
+    let done = context.record_lookups[index].finish_browser(browser);
+
+    if done {
+        let lookup = context.record_lookups.remove(index);
+        let full_name = lookup.full_name.clone();
+        context.report_resolved(full_name, lookup.into_discovery());
+    }
+}
+
+unsafe extern "C" fn client_callback(
     _client: *mut AvahiClient,
     state: AvahiClientState,
-    _userdata: *mut c_void,
+    userdata: *mut c_void,
 ) {
-    // TODO: handle this better
-    if let avahi_sys::AvahiClientState_AVAHI_CLIENT_FAILURE = state {
-        panic!("client failure");
+    let context = AvahiBrowserContext::from_raw(userdata);
+    let state = avahi_util::client_state_from_raw(state);
+
+    if let Some(f) = &context.client_state_callback {
+        ffi::catch_callback_panic("client state callback", || {
+            f(state, context.user_context.clone())
+        });
+    } else if state == ClientState::Failure {
+        warn!("Avahi client failure: the daemon has disconnected or restarted");
+
+        let error = Error::from("client failure");
+
+        if context.service_browsed_callback.is_some() {
+            context.invoke_browsed_callback(Err(error));
+        } else {
+            context.invoke_callback(Err(error));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn client_failure_without_state_callback_is_reported_as_error() {
+        let errors: Arc<Mutex<Vec<Error>>> = Arc::default();
+        let errors_callback = Arc::clone(&errors);
+
+        let mut context = AvahiBrowserContext {
+            service_discovered_callback: Some(Box::new(move |result, _| {
+                errors_callback.lock().unwrap().push(result.unwrap_err());
+            })),
+            ..AvahiBrowserContext::default()
+        };
+
+        unsafe {
+            client_callback(
+                std::ptr::null_mut(),
+                avahi_sys::AvahiClientState_AVAHI_CLIENT_FAILURE,
+                context.as_raw(),
+            )
+        };
+
+        assert_eq!(*errors.lock().unwrap(), vec![Error::from("client failure")]);
+    }
+
+    #[test]
+    fn resolves_on_browse_interface_unless_disabled() {
+        let mut context = AvahiBrowserContext::default();
+
+        assert_eq!(
+            context.resolve_interface(2, avahi_sys::AVAHI_PROTO_INET6),
+            (2, avahi_sys::AVAHI_PROTO_INET6)
+        );
+
+        context.resolve_on_browse_interface = false;
+
+        assert_eq!(
+            context.resolve_interface(2, avahi_sys::AVAHI_PROTO_INET6),
+            (avahi_sys::AVAHI_IF_UNSPEC, avahi_sys::AVAHI_PROTO_UNSPEC)
+        );
+    }
+
+    unsafe fn browse(context: &mut AvahiBrowserContext, event: AvahiBrowserEvent, name: &str) {
+        browse_callback(
+            std::ptr::null_mut(),
+            1,
+            avahi_sys::AVAHI_PROTO_INET,
+            event,
+            c_string!(name).as_ptr(),
+            c_string!("_http._tcp").as_ptr(),
+            c_string!("local").as_ptr(),
+            0,
+            context.as_raw(),
+        )
+    }
+
+    #[test]
+    fn repolled_browsers_only_report_changes() {
+        let events: Arc<Mutex<Vec<String>>> = Arc::default();
+        let events_callback = Arc::clone(&events);
+
+        let mut context = AvahiBrowserContext {
+            resolve_services: false,
+            service_browsed_callback: Some(Box::new(move |result, _| {
+                let event = match result.unwrap() {
+                    BrowseEvent::New(service) => format!("new {}", service.name()),
+                    BrowseEvent::Removed(service) => format!("removed {}", service.name()),
+                    event => format!("{:?}", event),
+                };
+
+                events_callback.lock().unwrap().push(event);
+            })),
+            ..AvahiBrowserContext::default()
+        };
+
+        let new = avahi_sys::AvahiBrowserEvent_AVAHI_BROWSER_NEW;
+        let all_for_now = avahi_sys::AvahiBrowserEvent_AVAHI_BROWSER_ALL_FOR_NOW;
+
+        unsafe {
+            browse(&mut context, new, "kept");
+            browse(&mut context, new, "gone");
+            browse(&mut context, all_for_now, "");
+
+            repoll_browsed(&mut context);
+            browse(&mut context, new, "kept");
+            browse(&mut context, new, "added");
+            browse(&mut context, all_for_now, "");
+
+            // without all-for-now, removals are reported once the next poll starts
+            repoll_browsed(&mut context);
+            browse(&mut context, new, "kept");
+            repoll_browsed(&mut context);
+        }
+
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![
+                "new kept",
+                "new gone",
+                "AllForNow",
+                "new added",
+                "removed gone",
+                "removed added",
+            ]
+        );
     }
 }
@@ -5,12 +5,13 @@ use std::rc::Rc;
 use super::avahi_util;
 use super::poll::ManagedAvahiSimplePoll;
 use crate::ffi::c_str;
-use crate::Result;
+use crate::{ClientState, Result};
 use avahi_sys::{
-    avahi_client_free, avahi_client_get_host_name, avahi_client_new, avahi_simple_poll_get,
-    AvahiClient, AvahiClientCallback, AvahiClientFlags,
+    avahi_client_free, avahi_client_get_domain_name, avahi_client_get_host_name,
+    avahi_client_get_host_name_fqdn, avahi_client_get_state, avahi_client_new,
+    avahi_simple_poll_get, AvahiClient, AvahiClientCallback, AvahiClientFlags,
 };
-use libc::{c_int, c_void};
+use libc::{c_char, c_int, c_void};
 
 /// Wraps the `AvahiClient` type from the raw Avahi bindings.
 ///
@@ -19,7 +20,8 @@ use libc::{c_int, c_void};
 #[derive(Debug)]
 pub struct ManagedAvahiClient {
     pub(crate) inner: *mut AvahiClient,
-    _poll: Rc<ManagedAvahiSimplePoll>,
+    poll: Rc<ManagedAvahiSimplePoll>,
+    flags: AvahiClientFlags,
 }
 
 impl ManagedAvahiClient {
@@ -50,10 +52,11 @@ impl ManagedAvahiClient {
         }
 
         match err {
-            0 => Ok(Self { inner, _poll: poll }),
+            0 => Ok(Self { inner, poll, flags }),
             _ => Err(format!(
-                "could not initialize AvahiClient: {}",
-                avahi_util::get_error(err)
+                "could not initialize AvahiClient: {} ({})",
+                avahi_util::get_error(err),
+                avahi_util::error_name(err)
             )
             .into()),
         }
@@ -65,6 +68,37 @@ impl ManagedAvahiClient {
     pub fn host_name<'a>(&self) -> Result<&'a str> {
         unsafe { get_host_name(self.inner) }
     }
+
+    /// Delegate function for [`avahi_client_get_host_name_fqdn()`].
+    ///
+    /// [`avahi_client_get_host_name_fqdn()`]: https://avahi.org/doxygen/html/client_8h.html
+    pub fn host_name_fqdn<'a>(&self) -> Result<&'a str> {
+        unsafe { get_host_name_fqdn(self.inner) }
+    }
+
+    /// Delegate function for [`avahi_client_get_domain_name()`].
+    ///
+    /// [`avahi_client_get_domain_name()`]: https://avahi.org/doxygen/html/client_8h.html
+    pub fn domain_name<'a>(&self) -> Result<&'a str> {
+        unsafe { get_string(avahi_client_get_domain_name(self.inner), "domain name") }
+    }
+
+    /// Delegate function for [`avahi_client_get_state()`].
+    ///
+    /// [`avahi_client_get_state()`]: https://avahi.org/doxygen/html/client_8h.html
+    pub fn state(&self) -> ClientState {
+        avahi_util::client_state_from_raw(unsafe { avahi_client_get_state(self.inner) })
+    }
+
+    /// Returns the flags this client was created with.
+    pub fn flags(&self) -> AvahiClientFlags {
+        self.flags
+    }
+
+    /// Returns the poll this client was created with.
+    pub(super) fn poll(&self) -> &ManagedAvahiSimplePoll {
+        &self.poll
+    }
 }
 
 impl Drop for ManagedAvahiClient {
@@ -88,11 +122,18 @@ pub struct ManagedAvahiClientParams {
 
 pub(super) unsafe fn get_host_name<'a>(client: *mut AvahiClient) -> Result<&'a str> {
     assert_not_null!(client);
-    let host_name = avahi_client_get_host_name(client);
+    get_string(avahi_client_get_host_name(client), "host name")
+}
+
+pub(super) unsafe fn get_host_name_fqdn<'a>(client: *mut AvahiClient) -> Result<&'a str> {
+    assert_not_null!(client);
+    get_string(avahi_client_get_host_name_fqdn(client), "host name")
+}
 
-    if !host_name.is_null() {
-        Ok(c_str::raw_to_str(host_name))
+unsafe fn get_string<'a>(s: *const c_char, description: &str) -> Result<&'a str> {
+    if !s.is_null() {
+        Ok(c_str::raw_to_str(s))
     } else {
-        Err("could not get host name from AvahiClient".into())
+        Err(format!("could not get {} from AvahiClient", description).into())
     }
 }
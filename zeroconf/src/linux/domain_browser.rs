@@ -0,0 +1,195 @@
+//! Avahi implementation for cross-platform domain browser
+
+use super::avahi_util;
+use super::client::{ManagedAvahiClient, ManagedAvahiClientParams};
+use super::flags::LookupFlags;
+use super::poll::ManagedAvahiSimplePoll;
+use super::raw_domain_browser::{ManagedAvahiDomainBrowser, ManagedAvahiDomainBrowserParams};
+use crate::event_loop::TeardownGuard;
+use crate::ffi::{self, c_str, AsRaw, FromRaw};
+use crate::prelude::*;
+use crate::{DomainBrowserKind, DomainDiscoveredCallback, EventLoop, NetworkInterface, Result};
+use avahi_sys::{
+    AvahiBrowserEvent, AvahiClient, AvahiClientFlags, AvahiClientState, AvahiDomainBrowser,
+    AvahiIfIndex, AvahiLookupResultFlags, AvahiProtocol,
+};
+use libc::{c_char, c_void};
+use std::any::Any;
+use std::collections::HashSet;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::{fmt, ptr};
+
+/// Enumerates browse or registration domains using Avahi's domain browser.
+#[derive(Debug)]
+pub struct AvahiMdnsDomainBrowser {
+    client: Option<Rc<ManagedAvahiClient>>,
+    poll: Option<Rc<ManagedAvahiSimplePoll>>,
+    kind: DomainBrowserKind,
+    interface: NetworkInterface,
+    context: Box<AvahiDomainBrowserContext>,
+    teardown: Option<TeardownGuard>,
+}
+
+impl TDomainBrowser for AvahiMdnsDomainBrowser {
+    type EventLoop<'a> = EventLoop<'a>;
+
+    fn new() -> Self {
+        Self {
+            client: None,
+            poll: None,
+            kind: DomainBrowserKind::default(),
+            interface: NetworkInterface::Unspec,
+            context: Box::default(),
+            teardown: None,
+        }
+    }
+
+    fn set_kind(&mut self, kind: DomainBrowserKind) {
+        self.kind = kind;
+    }
+
+    fn kind(&self) -> DomainBrowserKind {
+        self.kind
+    }
+
+    fn set_network_interface(&mut self, interface: NetworkInterface) {
+        self.interface = interface;
+    }
+
+    fn network_interface(&self) -> NetworkInterface {
+        self.interface
+    }
+
+    fn set_domain_discovered_callback(
+        &mut self,
+        domain_discovered_callback: Box<DomainDiscoveredCallback>,
+    ) {
+        self.context.domain_discovered_callback = Some(domain_discovered_callback);
+    }
+
+    fn set_context(&mut self, context: Box<dyn Any>) {
+        self.context.user_context = Some(Arc::from(context));
+    }
+
+    fn context(&self) -> Option<&dyn Any> {
+        self.context.user_context.as_ref().map(|c| c.as_ref())
+    }
+
+    fn browse_domains(&mut self) -> Result<EventLoop> {
+        debug!("Browsing domains: {:?}", self);
+
+        let interface_index = avahi_util::interface_index(self.interface)?;
+
+        self.poll = Some(Rc::new(ManagedAvahiSimplePoll::new()?));
+
+        self.client = Some(Rc::new(ManagedAvahiClient::new(
+            ManagedAvahiClientParams::builder()
+                .poll(Rc::clone(self.poll.as_ref().unwrap()))
+                .flags(AvahiClientFlags(0))
+                .callback(Some(client_callback))
+                .userdata(self.context.as_raw())
+                .build()?,
+        )?));
+
+        self.context.discovered.clear();
+
+        self.context.browser = Some(ManagedAvahiDomainBrowser::new(
+            ManagedAvahiDomainBrowserParams::builder()
+                .interface(interface_index)
+                .protocol(avahi_sys::AVAHI_PROTO_UNSPEC)
+                .domain(ptr::null_mut())
+                .kind(avahi_util::domain_browser_type(self.kind))
+                .flags(LookupFlags::empty())
+                .callback(Some(browse_callback))
+                .userdata(self.context.as_raw())
+                .client(Rc::clone(self.client.as_ref().unwrap()))
+                .build()?,
+        )?);
+
+        let context: *mut AvahiDomainBrowserContext = &mut *self.context;
+        let poll = self.poll.as_ref().unwrap();
+
+        // the context is boxed, so it stays at the same address until the browser is dropped,
+        // which unregisters the teardown
+        self.teardown = Some(
+            poll.loop_state()
+                .register_teardown(move || unsafe { (*context).browser = None }),
+        );
+
+        Ok(EventLoop::new(self.poll.as_ref().unwrap().clone()))
+    }
+}
+
+impl Drop for AvahiMdnsDomainBrowser {
+    fn drop(&mut self) {
+        // browser must be freed first
+        self.context.browser = None;
+    }
+}
+
+#[derive(Default, FromRaw, AsRaw)]
+struct AvahiDomainBrowserContext {
+    browser: Option<ManagedAvahiDomainBrowser>,
+    discovered: HashSet<String>,
+    domain_discovered_callback: Option<Box<DomainDiscoveredCallback>>,
+    user_context: Option<Arc<dyn Any>>,
+}
+
+impl AvahiDomainBrowserContext {
+    fn invoke_callback(&self, result: Result<String>) {
+        if let Some(f) = &self.domain_discovered_callback {
+            ffi::catch_callback_panic("domain discovered callback", || {
+                f(result, self.user_context.clone())
+            });
+        } else {
+            warn!("attempted to invoke domain browser callback but none was set");
+        }
+    }
+}
+
+impl fmt::Debug for AvahiDomainBrowserContext {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("AvahiDomainBrowserContext")
+            .field("browser", &self.browser)
+            .field("discovered", &self.discovered)
+            .finish()
+    }
+}
+
+unsafe extern "C" fn browse_callback(
+    _browser: *mut AvahiDomainBrowser,
+    _interface: AvahiIfIndex,
+    _protocol: AvahiProtocol,
+    event: AvahiBrowserEvent,
+    domain: *const c_char,
+    _flags: AvahiLookupResultFlags,
+    userdata: *mut c_void,
+) {
+    let context = AvahiDomainBrowserContext::from_raw(userdata);
+
+    match event {
+        avahi_sys::AvahiBrowserEvent_AVAHI_BROWSER_NEW => {
+            let domain = c_str::raw_to_str(domain).trim_end_matches('.').to_string();
+
+            if context.discovered.insert(domain.to_ascii_lowercase()) {
+                context.invoke_callback(Ok(domain));
+            }
+        }
+        avahi_sys::AvahiBrowserEvent_AVAHI_BROWSER_FAILURE => {
+            context.invoke_callback(Err("domain browser failure".into()))
+        }
+        _ => {}
+    };
+}
+
+unsafe extern "C" fn client_callback(
+    _client: *mut AvahiClient,
+    state: AvahiClientState,
+    userdata: *mut c_void,
+) {
+    if let avahi_sys::AvahiClientState_AVAHI_CLIENT_FAILURE = state {
+        warn!("Avahi client failure: the daemon has disconnected or restarted");
+        AvahiDomainBrowserContext::from_raw(userdata).invoke_callback(Err("client failure".into()));
+    }
+}
@@ -2,15 +2,18 @@
 
 use std::rc::Rc;
 
+use super::flags::PublishFlags;
 use super::{client::ManagedAvahiClient, string_list::ManagedAvahiStringList};
 use crate::ffi::UnwrapMutOrNull;
 use crate::linux::avahi_util;
 use crate::Result;
 use avahi_sys::{
-    avahi_client_errno, avahi_entry_group_add_service_strlst,
-    avahi_entry_group_add_service_subtype, avahi_entry_group_commit, avahi_entry_group_free,
-    avahi_entry_group_is_empty, avahi_entry_group_new, avahi_entry_group_reset, AvahiEntryGroup,
-    AvahiEntryGroupCallback, AvahiIfIndex, AvahiProtocol, AvahiPublishFlags,
+    avahi_client_errno, avahi_entry_group_add_address, avahi_entry_group_add_record,
+    avahi_entry_group_add_service_strlst, avahi_entry_group_add_service_subtype,
+    avahi_entry_group_commit, avahi_entry_group_free, avahi_entry_group_get_state,
+    avahi_entry_group_is_empty, avahi_entry_group_new, avahi_entry_group_reset,
+    avahi_entry_group_update_service_txt_strlst, AvahiAddress, AvahiEntryGroup,
+    AvahiEntryGroupCallback, AvahiEntryGroupState, AvahiIfIndex, AvahiProtocol,
 };
 use libc::{c_char, c_void};
 
@@ -37,8 +40,14 @@ impl ManagedAvahiEntryGroup {
         let inner = unsafe { avahi_entry_group_new(client.inner, callback, userdata) };
 
         if inner.is_null() {
-            let err = avahi_util::get_error(unsafe { avahi_client_errno(client.inner) });
-            Err(format!("could not initialize AvahiEntryGroup: {}", err).into())
+            let err = unsafe { avahi_client_errno(client.inner) };
+
+            Err(format!(
+                "could not initialize AvahiEntryGroup: {} ({})",
+                avahi_util::get_error(err),
+                avahi_util::error_name(err)
+            )
+            .into())
         } else {
             Ok(Self {
                 inner,
@@ -54,6 +63,13 @@ impl ManagedAvahiEntryGroup {
         unsafe { avahi_entry_group_is_empty(self.inner) != 0 }
     }
 
+    /// Delegate function for [`avahi_entry_group_get_state()`].
+    ///
+    /// [`avahi_entry_group_get_state()`]: https://avahi.org/doxygen/html/publish_8h.html
+    pub fn state(&self) -> AvahiEntryGroupState {
+        unsafe { avahi_entry_group_get_state(self.inner) as AvahiEntryGroupState }
+    }
+
     /// Delegate function for [`avahi_entry_group_add_service()`].
     ///
     /// Also propagates any error returned into a `Result`.
@@ -73,13 +89,15 @@ impl ManagedAvahiEntryGroup {
             txt,
         }: AddServiceParams,
     ) -> Result<()> {
+        flags.validate()?;
+
         avahi_util::sys_exec(
             || unsafe {
                 avahi_entry_group_add_service_strlst(
                     self.inner,
                     interface,
                     protocol,
-                    flags,
+                    flags.bits(),
                     name,
                     kind,
                     domain,
@@ -109,16 +127,133 @@ impl ManagedAvahiEntryGroup {
             subtype,
         }: AddServiceSubtypeParams,
     ) -> Result<()> {
+        flags.validate()?;
+
         avahi_util::sys_exec(
             || unsafe {
                 avahi_entry_group_add_service_subtype(
-                    self.inner, interface, protocol, flags, name, kind, domain, subtype,
+                    self.inner,
+                    interface,
+                    protocol,
+                    flags.bits(),
+                    name,
+                    kind,
+                    domain,
+                    subtype,
                 )
             },
             "could not register service subtype",
         )
     }
 
+    /// Delegate function for [`avahi_entry_group_add_address()`].
+    ///
+    /// Also propagates any error returned into a `Result`.
+    ///
+    /// [`avahi_entry_group_add_address()`]: https://avahi.org/doxygen/html/publish_8h.html
+    pub fn add_address(
+        &mut self,
+        AddAddressParams {
+            interface,
+            protocol,
+            flags,
+            name,
+            address,
+        }: AddAddressParams,
+    ) -> Result<()> {
+        flags.validate()?;
+
+        avahi_util::sys_exec(
+            || unsafe {
+                avahi_entry_group_add_address(
+                    self.inner,
+                    interface,
+                    protocol,
+                    flags.bits(),
+                    name,
+                    address,
+                )
+            },
+            "could not register address",
+        )
+    }
+
+    /// Delegate function for [`avahi_entry_group_add_record()`].
+    ///
+    /// Also propagates any error returned into a `Result`.
+    ///
+    /// [`avahi_entry_group_add_record()`]: https://avahi.org/doxygen/html/publish_8h.html
+    pub fn add_record(
+        &mut self,
+        AddRecordParams {
+            interface,
+            protocol,
+            flags,
+            name,
+            clazz,
+            kind,
+            ttl,
+            rdata,
+            size,
+        }: AddRecordParams,
+    ) -> Result<()> {
+        flags.validate()?;
+
+        avahi_util::sys_exec(
+            || unsafe {
+                avahi_entry_group_add_record(
+                    self.inner,
+                    interface,
+                    protocol,
+                    flags.bits(),
+                    name,
+                    clazz,
+                    kind,
+                    ttl,
+                    rdata,
+                    size,
+                )
+            },
+            "could not register record",
+        )
+    }
+
+    /// Delegate function for [`avahi_entry_group_update_service_txt_strlst()`].
+    ///
+    /// Also propagates any error returned into a `Result`.
+    ///
+    /// [`avahi_entry_group_update_service_txt_strlst()`]: https://avahi.org/doxygen/html/publish_8h.html
+    pub fn update_service_txt(
+        &mut self,
+        UpdateServiceTxtParams {
+            interface,
+            protocol,
+            flags,
+            name,
+            kind,
+            domain,
+            txt,
+        }: UpdateServiceTxtParams,
+    ) -> Result<()> {
+        flags.validate()?;
+
+        avahi_util::sys_exec(
+            || unsafe {
+                avahi_entry_group_update_service_txt_strlst(
+                    self.inner,
+                    interface,
+                    protocol,
+                    flags.bits(),
+                    name,
+                    kind,
+                    domain,
+                    txt.map(|t| t.inner()).unwrap_mut_or_null(),
+                )
+            },
+            "could not update service TXT record",
+        )
+    }
+
     /// Delegate function for [`avahi_entry_group_commit()`].
     ///
     /// Also propagates any error returned into a `Result`.
@@ -133,9 +268,14 @@ impl ManagedAvahiEntryGroup {
 
     /// Delegate function for [`avahi_entry_group_reset()`].
     ///
+    /// Also propagates any error returned into a `Result`.
+    ///
     /// [`avahi_entry_group_reset()`]: https://avahi.org/doxygen/html/publish_8h.html#a1293bbccf878dbeb9916660022bc71b2
-    pub fn reset(&mut self) {
-        unsafe { avahi_entry_group_reset(self.inner) };
+    pub fn reset(&mut self) -> Result<()> {
+        avahi_util::sys_exec(
+            || unsafe { avahi_entry_group_reset(self.inner) },
+            "could not reset entry group",
+        )
     }
 }
 
@@ -167,7 +307,7 @@ pub struct ManagedAvahiEntryGroupParams {
 pub struct AddServiceParams<'a> {
     interface: AvahiIfIndex,
     protocol: AvahiProtocol,
-    flags: AvahiPublishFlags,
+    flags: PublishFlags,
     name: *const c_char,
     kind: *const c_char,
     domain: *const c_char,
@@ -185,9 +325,58 @@ pub struct AddServiceParams<'a> {
 pub struct AddServiceSubtypeParams {
     interface: AvahiIfIndex,
     protocol: AvahiProtocol,
-    flags: AvahiPublishFlags,
+    flags: PublishFlags,
     name: *const c_char,
     kind: *const c_char,
     domain: *const c_char,
     subtype: *const c_char,
 }
+
+/// Holds parameters for `ManagedAvahiEntryGroup::add_address()`.
+///
+/// See [`avahi_entry_group_add_address()`] for more information about these parameters.
+///
+/// [`avahi_entry_group_add_address()`]: https://avahi.org/doxygen/html/publish_8h.html
+#[derive(Builder, BuilderDelegate)]
+pub struct AddAddressParams {
+    interface: AvahiIfIndex,
+    protocol: AvahiProtocol,
+    flags: PublishFlags,
+    name: *const c_char,
+    address: *const AvahiAddress,
+}
+
+/// Holds parameters for `ManagedAvahiEntryGroup::add_record()`.
+///
+/// See [`avahi_entry_group_add_record()`] for more information about these parameters.
+///
+/// [`avahi_entry_group_add_record()`]: https://avahi.org/doxygen/html/publish_8h.html
+#[derive(Builder, BuilderDelegate)]
+pub struct AddRecordParams {
+    interface: AvahiIfIndex,
+    protocol: AvahiProtocol,
+    flags: PublishFlags,
+    name: *const c_char,
+    clazz: u16,
+    kind: u16,
+    ttl: u32,
+    rdata: *const c_void,
+    size: usize,
+}
+
+/// Holds parameters for `ManagedAvahiEntryGroup::update_service_txt()`.
+///
+/// See [`avahi_entry_group_update_service_txt_strlst()`] for more information about these
+/// parameters.
+///
+/// [`avahi_entry_group_update_service_txt_strlst()`]: https://avahi.org/doxygen/html/publish_8h.html
+#[derive(Builder, BuilderDelegate)]
+pub struct UpdateServiceTxtParams<'a> {
+    interface: AvahiIfIndex,
+    protocol: AvahiProtocol,
+    flags: PublishFlags,
+    name: *const c_char,
+    kind: *const c_char,
+    domain: *const c_char,
+    txt: Option<&'a ManagedAvahiStringList>,
+}
@@ -1,8 +1,9 @@
 //! Event loop for running a `MdnsService` or `MdnsBrowser`.
 
 use super::poll::ManagedAvahiSimplePoll;
-use crate::event_loop::TEventLoop;
+use crate::event_loop::{self, SuspendPolicy, TEventLoop};
 use crate::Result;
+use std::cell::Cell;
 use std::marker::PhantomData;
 use std::rc::Rc;
 use std::time::Duration;
@@ -10,6 +11,8 @@ use std::time::Duration;
 #[derive(new)]
 pub struct AvahiEventLoop<'a> {
     poll: Rc<ManagedAvahiSimplePoll>,
+    #[new(default)]
+    suspension: Cell<Option<SuspendPolicy>>,
     phantom: PhantomData<&'a ManagedAvahiSimplePoll>,
 }
 
@@ -21,6 +24,38 @@ impl<'a> TEventLoop for AvahiEventLoop<'a> {
     /// does not respect the `timeout` parameter, the `timeout` passed
     /// here will have no effect -- ie will return immediately.
     fn poll(&self, timeout: Duration) -> Result<()> {
-        self.poll.iterate(timeout)
+        event_loop::poll_with_timers(
+            self.poll.loop_state(),
+            self.suspension.get(),
+            timeout,
+            |timeout| self.poll.iterate(timeout),
+        )
+    }
+
+    fn suspend(&self, policy: SuspendPolicy) {
+        debug!("Suspending event loop: {:?}", policy);
+        self.suspension.set(Some(policy));
+    }
+
+    fn resume(&self) {
+        debug!("Resuming event loop");
+        self.suspension.set(None);
+    }
+
+    fn suspension(&self) -> Option<SuspendPolicy> {
+        self.suspension.get()
+    }
+
+    fn active_resolvers(&self) -> usize {
+        self.poll.loop_state().resolvers()
+    }
+
+    fn active_browsers(&self) -> usize {
+        self.poll.loop_state().browsers()
+    }
+
+    fn cancel_all(&self) {
+        debug!("Cancelling all operations");
+        self.poll.loop_state().cancel_all();
     }
 }
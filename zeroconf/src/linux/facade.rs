@@ -0,0 +1,220 @@
+//! Avahi implementation for cross-platform facade
+
+use super::client::{ManagedAvahiClient, ManagedAvahiClientParams};
+use super::poll::ManagedAvahiSimplePoll;
+use crate::linux::browser::AvahiMdnsBrowser;
+use crate::linux::service::AvahiMdnsService;
+use crate::prelude::*;
+use crate::{EventLoop, Result, ServiceType};
+use avahi_sys::{AvahiClient, AvahiClientFlags, AvahiClientState};
+use libc::c_void;
+use std::cell::{Cell, RefCell};
+use std::fmt;
+use std::rc::{Rc, Weak};
+
+/// Shares a single `AvahiClient` and `AvahiSimplePoll` between every service and browser it
+/// creates.
+#[derive(Debug)]
+pub struct AvahiZeroconf {
+    client: Rc<ManagedAvahiClient>,
+    poll: Rc<ManagedAvahiSimplePoll>,
+    // the userdata of the client, so declared after it
+    listeners: Rc<ClientListeners>,
+}
+
+impl TZeroconf for AvahiZeroconf {
+    fn new() -> Result<Self> {
+        let poll = Rc::new(ManagedAvahiSimplePoll::new()?);
+        let listeners = Rc::new(ClientListeners::default());
+
+        let client = Rc::new(ManagedAvahiClient::new(
+            ManagedAvahiClientParams::builder()
+                .poll(Rc::clone(&poll))
+                .flags(AvahiClientFlags(0))
+                .callback(Some(client_callback))
+                .userdata(Rc::as_ptr(&listeners) as *mut c_void)
+                .build()?,
+        )?);
+
+        Ok(Self {
+            client,
+            poll,
+            listeners,
+        })
+    }
+
+    fn new_service(&self, service_type: ServiceType, port: u16) -> AvahiMdnsService {
+        AvahiMdnsService::with_client(
+            service_type,
+            port,
+            Rc::clone(&self.poll),
+            Rc::clone(&self.client),
+            Rc::clone(&self.listeners),
+        )
+    }
+
+    fn new_browser(&self, service_type: ServiceType) -> AvahiMdnsBrowser {
+        AvahiMdnsBrowser::with_client(
+            service_type,
+            Rc::clone(&self.poll),
+            Rc::clone(&self.client),
+            Rc::clone(&self.listeners),
+        )
+    }
+
+    fn event_loop(&self) -> EventLoop {
+        EventLoop::new(Rc::clone(&self.poll))
+    }
+}
+
+type ClientListener = dyn Fn(*mut AvahiClient, AvahiClientState);
+
+/// The services and browsers sharing the client of an [`AvahiZeroconf`], which the state of the
+/// client is forwarded to, as a client has a single callback.
+///
+/// Every holder of the shared client also holds the listeners, declared after the client, so that
+/// they outlive it.
+///
+/// [`AvahiZeroconf`]: struct.AvahiZeroconf.html
+#[derive(Default)]
+pub(crate) struct ClientListeners {
+    next_id: Cell<u64>,
+    entries: RefCell<Vec<(u64, Rc<ClientListener>)>>,
+}
+
+impl ClientListeners {
+    /// Registers `listener` to be invoked with each state of the shared client for as long as the
+    /// returned guard is alive.
+    pub(crate) fn register<F>(self: &Rc<Self>, listener: F) -> ClientListenerGuard
+    where
+        F: Fn(*mut AvahiClient, AvahiClientState) + 'static,
+    {
+        let id = self.next_id.get();
+        self.next_id.set(id + 1);
+
+        self.entries.borrow_mut().push((id, Rc::new(listener)));
+
+        ClientListenerGuard {
+            listeners: Rc::downgrade(self),
+            id,
+        }
+    }
+
+    fn notify(&self, client: *mut AvahiClient, state: AvahiClientState) {
+        let ids = self
+            .entries
+            .borrow()
+            .iter()
+            .map(|(id, _)| *id)
+            .collect::<Vec<_>>();
+
+        // listeners may drop guards, including those of the listeners after them, so each one is
+        // looked up again and invoked outside the borrow
+        for id in ids {
+            let listener = self
+                .entries
+                .borrow()
+                .iter()
+                .find(|(i, _)| *i == id)
+                .map(|(_, listener)| Rc::clone(listener));
+
+            if let Some(listener) = listener {
+                listener(client, state);
+            }
+        }
+    }
+}
+
+impl fmt::Debug for ClientListeners {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ClientListeners")
+            .field("len", &self.entries.borrow().len())
+            .finish()
+    }
+}
+
+/// Keeps a listener registered with [`ClientListeners::register()`] for as long as the guard is
+/// alive.
+///
+/// [`ClientListeners::register()`]: struct.ClientListeners.html#method.register
+#[derive(Debug)]
+pub(crate) struct ClientListenerGuard {
+    listeners: Weak<ClientListeners>,
+    id: u64,
+}
+
+impl Drop for ClientListenerGuard {
+    fn drop(&mut self) {
+        if let Some(listeners) = self.listeners.upgrade() {
+            let removed = {
+                let mut entries = listeners.entries.borrow_mut();
+                let index = entries.iter().position(|(id, _)| *id == self.id);
+                index.map(|i| entries.remove(i))
+            };
+
+            drop(removed);
+        }
+    }
+}
+
+unsafe extern "C" fn client_callback(
+    client: *mut AvahiClient,
+    state: AvahiClientState,
+    userdata: *mut c_void,
+) {
+    if let avahi_sys::AvahiClientState_AVAHI_CLIENT_FAILURE = state {
+        warn!("Avahi client failure: the daemon has disconnected or restarted");
+    }
+
+    (*(userdata as *const ClientListeners)).notify(client, state);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn client_listeners_are_notified_while_registered() {
+        let listeners = Rc::new(ClientListeners::default());
+        let states = Rc::new(RefCell::new(Vec::new()));
+
+        let first_states = Rc::clone(&states);
+        let first = listeners.register(move |_, state| first_states.borrow_mut().push((1, state)));
+
+        let second_states = Rc::clone(&states);
+        let _second =
+            listeners.register(move |_, state| second_states.borrow_mut().push((2, state)));
+
+        let running = avahi_sys::AvahiClientState_AVAHI_CLIENT_S_RUNNING;
+        let failure = avahi_sys::AvahiClientState_AVAHI_CLIENT_FAILURE;
+
+        listeners.notify(std::ptr::null_mut(), running);
+        drop(first);
+        listeners.notify(std::ptr::null_mut(), failure);
+
+        assert_eq!(
+            *states.borrow(),
+            vec![(1, running), (2, running), (2, failure)]
+        );
+    }
+
+    #[test]
+    fn client_listener_can_unregister_later_listener() {
+        let listeners = Rc::new(ClientListeners::default());
+        let later: Rc<RefCell<Option<ClientListenerGuard>>> = Rc::default();
+        let notified = Rc::new(Cell::new(false));
+
+        let first_later = Rc::clone(&later);
+        let _first = listeners.register(move |_, _| drop(first_later.borrow_mut().take()));
+
+        let later_notified = Rc::clone(&notified);
+        *later.borrow_mut() = Some(listeners.register(move |_, _| later_notified.set(true)));
+
+        listeners.notify(
+            std::ptr::null_mut(),
+            avahi_sys::AvahiClientState_AVAHI_CLIENT_S_RUNNING,
+        );
+
+        assert!(!notified.get());
+    }
+}
@@ -0,0 +1,211 @@
+//! Type-safe wrappers for `AvahiPublishFlags` and `AvahiLookupFlags`
+
+use crate::Result;
+use avahi_sys::{AvahiLookupFlags, AvahiPublishFlags};
+use std::convert::TryFrom;
+
+bitflags! {
+    /// Flags for publishing entries with an `AvahiEntryGroup`, wrapping `AvahiPublishFlags`.
+    ///
+    /// See [`AvahiPublishFlags`] for more information about each flag.
+    ///
+    /// [`AvahiPublishFlags`]: https://avahi.org/doxygen/html/defs_8h.html
+    #[derive(Default)]
+    pub struct PublishFlags: AvahiPublishFlags {
+        /// The name of the record is unique to this host and is probed for before announcing it
+        const UNIQUE = avahi_sys::AvahiPublishFlags_AVAHI_PUBLISH_UNIQUE;
+        /// Skips probing for a unique record
+        const NO_PROBE = avahi_sys::AvahiPublishFlags_AVAHI_PUBLISH_NO_PROBE;
+        /// Skips announcing the record
+        const NO_ANNOUNCE = avahi_sys::AvahiPublishFlags_AVAHI_PUBLISH_NO_ANNOUNCE;
+        /// Allows other hosts to publish records with the same name
+        const ALLOW_MULTIPLE = avahi_sys::AvahiPublishFlags_AVAHI_PUBLISH_ALLOW_MULTIPLE;
+        /// Skips the reverse (PTR) entry of an address
+        const NO_REVERSE = avahi_sys::AvahiPublishFlags_AVAHI_PUBLISH_NO_REVERSE;
+        /// Skips the cookie TXT entry of a service
+        const NO_COOKIE = avahi_sys::AvahiPublishFlags_AVAHI_PUBLISH_NO_COOKIE;
+        /// Updates an existing record instead of adding a new one
+        const UPDATE = avahi_sys::AvahiPublishFlags_AVAHI_PUBLISH_UPDATE;
+        /// Publishes the record over wide-area DNS only
+        const USE_WIDE_AREA = avahi_sys::AvahiPublishFlags_AVAHI_PUBLISH_USE_WIDE_AREA;
+        /// Publishes the record over multicast DNS only
+        const USE_MULTICAST = avahi_sys::AvahiPublishFlags_AVAHI_PUBLISH_USE_MULTICAST;
+    }
+}
+
+impl PublishFlags {
+    /// Returns an error if the flags combine options that contradict each other, which Avahi
+    /// either rejects or silently ignores:
+    ///
+    /// * `UNIQUE` or `NO_PROBE` with `ALLOW_MULTIPLE`, as only unique records are probed
+    /// * `USE_WIDE_AREA` with `USE_MULTICAST`
+    pub fn validate(self) -> Result<()> {
+        if self.contains(Self::ALLOW_MULTIPLE) && self.intersects(Self::UNIQUE | Self::NO_PROBE) {
+            return Err(format!(
+                "publish flags {:?} combine ALLOW_MULTIPLE with flags for unique records",
+                self
+            )
+            .into());
+        }
+
+        if self.contains(Self::USE_WIDE_AREA | Self::USE_MULTICAST) {
+            return Err(format!(
+                "publish flags {:?} force both wide-area and multicast",
+                self
+            )
+            .into());
+        }
+
+        Ok(())
+    }
+}
+
+impl From<PublishFlags> for AvahiPublishFlags {
+    fn from(flags: PublishFlags) -> Self {
+        flags.bits()
+    }
+}
+
+impl TryFrom<AvahiPublishFlags> for PublishFlags {
+    type Error = crate::error::Error;
+
+    /// Converts raw flags, returning an error if they contain unknown bits or are not valid
+    /// according to [`validate()`].
+    ///
+    /// [`validate()`]: #method.validate
+    fn try_from(bits: AvahiPublishFlags) -> Result<Self> {
+        let flags =
+            Self::from_bits(bits).ok_or_else(|| format!("unknown publish flags: {:#x}", bits))?;
+
+        flags.validate()?;
+        Ok(flags)
+    }
+}
+
+bitflags! {
+    /// Flags for browsers and resolvers, wrapping `AvahiLookupFlags`.
+    ///
+    /// See [`AvahiLookupFlags`] for more information about each flag.
+    ///
+    /// [`AvahiLookupFlags`]: https://avahi.org/doxygen/html/defs_8h.html
+    #[derive(Default)]
+    pub struct LookupFlags: AvahiLookupFlags {
+        /// Looks up over wide-area DNS only
+        const USE_WIDE_AREA = avahi_sys::AvahiLookupFlags_AVAHI_LOOKUP_USE_WIDE_AREA;
+        /// Looks up over multicast DNS only
+        const USE_MULTICAST = avahi_sys::AvahiLookupFlags_AVAHI_LOOKUP_USE_MULTICAST;
+        /// Skips the TXT record when resolving a service
+        const NO_TXT = avahi_sys::AvahiLookupFlags_AVAHI_LOOKUP_NO_TXT;
+        /// Skips the address when resolving a service
+        const NO_ADDRESS = avahi_sys::AvahiLookupFlags_AVAHI_LOOKUP_NO_ADDRESS;
+    }
+}
+
+impl LookupFlags {
+    /// Returns an error if the flags force both wide-area and multicast, which Avahi rejects.
+    pub fn validate(self) -> Result<()> {
+        if self.contains(Self::USE_WIDE_AREA | Self::USE_MULTICAST) {
+            return Err(
+                format!("lookup flags {:?} force both wide-area and multicast", self).into(),
+            );
+        }
+
+        Ok(())
+    }
+}
+
+impl From<LookupFlags> for AvahiLookupFlags {
+    fn from(flags: LookupFlags) -> Self {
+        flags.bits()
+    }
+}
+
+impl TryFrom<AvahiLookupFlags> for LookupFlags {
+    type Error = crate::error::Error;
+
+    /// Converts raw flags, returning an error if they contain unknown bits or are not valid
+    /// according to [`validate()`].
+    ///
+    /// [`validate()`]: #method.validate
+    fn try_from(bits: AvahiLookupFlags) -> Result<Self> {
+        let flags =
+            Self::from_bits(bits).ok_or_else(|| format!("unknown lookup flags: {:#x}", bits))?;
+
+        flags.validate()?;
+        Ok(flags)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn publish_flags_convert_to_raw() {
+        let flags = PublishFlags::UNIQUE | PublishFlags::NO_PROBE;
+
+        assert_eq!(
+            AvahiPublishFlags::from(flags),
+            avahi_sys::AvahiPublishFlags_AVAHI_PUBLISH_UNIQUE
+                | avahi_sys::AvahiPublishFlags_AVAHI_PUBLISH_NO_PROBE
+        );
+        assert_eq!(AvahiPublishFlags::from(PublishFlags::default()), 0);
+    }
+
+    #[test]
+    fn publish_flags_convert_from_raw() {
+        let raw = avahi_sys::AvahiPublishFlags_AVAHI_PUBLISH_NO_REVERSE
+            | avahi_sys::AvahiPublishFlags_AVAHI_PUBLISH_NO_PROBE;
+
+        assert_eq!(
+            PublishFlags::try_from(raw),
+            Ok(PublishFlags::NO_REVERSE | PublishFlags::NO_PROBE)
+        );
+        assert!(PublishFlags::try_from(1 << 20).is_err());
+    }
+
+    #[test]
+    fn publish_flags_reject_conflicts() {
+        assert!(PublishFlags::UNIQUE.validate().is_ok());
+        assert!((PublishFlags::NO_PROBE | PublishFlags::ALLOW_MULTIPLE)
+            .validate()
+            .is_err());
+        assert!((PublishFlags::UNIQUE | PublishFlags::ALLOW_MULTIPLE)
+            .validate()
+            .is_err());
+        assert!((PublishFlags::USE_WIDE_AREA | PublishFlags::USE_MULTICAST)
+            .validate()
+            .is_err());
+        assert!(PublishFlags::try_from(
+            avahi_sys::AvahiPublishFlags_AVAHI_PUBLISH_UNIQUE
+                | avahi_sys::AvahiPublishFlags_AVAHI_PUBLISH_ALLOW_MULTIPLE
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn lookup_flags_convert_to_and_from_raw() {
+        let flags = LookupFlags::USE_MULTICAST | LookupFlags::NO_TXT;
+        let raw = AvahiLookupFlags::from(flags);
+
+        assert_eq!(
+            raw,
+            avahi_sys::AvahiLookupFlags_AVAHI_LOOKUP_USE_MULTICAST
+                | avahi_sys::AvahiLookupFlags_AVAHI_LOOKUP_NO_TXT
+        );
+        assert_eq!(LookupFlags::try_from(raw), Ok(flags));
+        assert!(LookupFlags::try_from(1 << 20).is_err());
+    }
+
+    #[test]
+    fn lookup_flags_reject_conflicts() {
+        assert!((LookupFlags::USE_WIDE_AREA | LookupFlags::USE_MULTICAST)
+            .validate()
+            .is_err());
+        assert!(LookupFlags::try_from(
+            avahi_sys::AvahiLookupFlags_AVAHI_LOOKUP_USE_WIDE_AREA
+                | avahi_sys::AvahiLookupFlags_AVAHI_LOOKUP_USE_MULTICAST
+        )
+        .is_err());
+    }
+}
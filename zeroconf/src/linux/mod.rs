@@ -6,14 +6,24 @@
 //! [Bonjour]: https://en.wikipedia.org/wiki/Bonjour_(software)
 //! [Avahi]: https://en.wikipedia.org/wiki/Avahi_(software)
 
+pub mod address_monitor;
 pub mod avahi_util;
 pub mod browser;
 pub mod client;
+pub mod domain_browser;
 pub mod entry_group;
 pub mod event_loop;
+pub mod facade;
+pub mod flags;
 pub mod poll;
 pub mod raw_browser;
+pub mod raw_domain_browser;
+pub mod raw_type_browser;
+pub mod record_browser;
 pub mod resolver;
 pub mod service;
 pub mod string_list;
+pub mod timeout;
 pub mod txt_record;
+pub mod type_browser;
+pub mod watch;
@@ -1,11 +1,13 @@
 //! Rust friendly `AvahiSimplePoll` wrappers/helpers
 
+use crate::event_loop::LoopState;
 use crate::Result;
 use crate::{error::Error, linux::avahi_util};
 use avahi_sys::{
     avahi_simple_poll_free, avahi_simple_poll_iterate, avahi_simple_poll_loop,
     avahi_simple_poll_new, AvahiSimplePoll,
 };
+use std::rc::Rc;
 use std::{convert::TryInto, time::Duration};
 
 /// Wraps the `AvahiSimplePoll` type from the raw Avahi bindings.
@@ -13,7 +15,7 @@ use std::{convert::TryInto, time::Duration};
 /// This struct allocates a new `*mut AvahiSimplePoll` when `ManagedAvahiClient::new()` is invoked
 /// and calls the Avahi function responsible for freeing the poll on `trait Drop`.
 #[derive(Debug)]
-pub struct ManagedAvahiSimplePoll(*mut AvahiSimplePoll);
+pub struct ManagedAvahiSimplePoll(*mut AvahiSimplePoll, Rc<LoopState>);
 
 impl ManagedAvahiSimplePoll {
     /// Initializes the underlying `*mut AvahiSimplePoll` and verifies it was created; returning
@@ -23,7 +25,7 @@ impl ManagedAvahiSimplePoll {
         if poll.is_null() {
             Err("could not initialize AvahiSimplePoll".into())
         } else {
-            Ok(Self(poll))
+            Ok(Self(poll, Rc::default()))
         }
     }
 
@@ -61,6 +63,12 @@ impl ManagedAvahiSimplePoll {
     pub(super) fn inner(&self) -> *mut AvahiSimplePoll {
         self.0
     }
+
+    /// Returns the state shared by the resolvers, browsers and services created on clients of this
+    /// poll.
+    pub(crate) fn loop_state(&self) -> &Rc<LoopState> {
+        &self.1
+    }
 }
 
 impl Drop for ManagedAvahiSimplePoll {
@@ -1,12 +1,16 @@
 //! Rust friendly `AvahiServiceBrowser` wrappers/helpers
 
 use std::rc::Rc;
+use std::{mem, ptr};
 
+use super::avahi_util;
 use super::client::ManagedAvahiClient;
+use super::flags::LookupFlags;
+use crate::event_loop::{ActiveGuard, ActiveKind};
 use crate::Result;
 use avahi_sys::{
-    avahi_service_browser_free, avahi_service_browser_new, AvahiIfIndex, AvahiLookupFlags,
-    AvahiProtocol, AvahiServiceBrowser, AvahiServiceBrowserCallback,
+    avahi_service_browser_free, avahi_service_browser_new, AvahiIfIndex, AvahiProtocol,
+    AvahiServiceBrowser, AvahiServiceBrowserCallback,
 };
 use libc::{c_char, c_void};
 
@@ -18,6 +22,7 @@ use libc::{c_char, c_void};
 pub struct ManagedAvahiServiceBrowser {
     inner: *mut AvahiServiceBrowser,
     _client: Rc<ManagedAvahiClient>,
+    _active: ActiveGuard,
 }
 
 impl ManagedAvahiServiceBrowser {
@@ -35,6 +40,8 @@ impl ManagedAvahiServiceBrowser {
             userdata,
         }: ManagedAvahiServiceBrowserParams,
     ) -> Result<Self> {
+        flags.validate()?;
+
         let inner = unsafe {
             avahi_service_browser_new(
                 client.inner,
@@ -42,7 +49,7 @@ impl ManagedAvahiServiceBrowser {
                 protocol,
                 kind,
                 domain,
-                flags,
+                flags.bits(),
                 callback,
                 userdata,
             )
@@ -51,17 +58,44 @@ impl ManagedAvahiServiceBrowser {
         if inner.is_null() {
             Err("could not initialize Avahi service browser".into())
         } else {
+            let active = ActiveGuard::new(client.poll().loop_state(), ActiveKind::Browser);
+
             Ok(Self {
                 inner,
                 _client: client,
+                _active: active,
             })
         }
     }
+
+    pub(super) fn inner(&self) -> *mut AvahiServiceBrowser {
+        self.inner
+    }
+
+    /// Frees the underlying `*mut AvahiServiceBrowser`, returning an error if Avahi reports that freeing it
+    /// failed, which dropping it only logs.
+    pub fn close(mut self) -> Result<()> {
+        let inner = mem::replace(&mut self.inner, ptr::null_mut());
+
+        avahi_util::sys_exec(
+            || unsafe { avahi_service_browser_free(inner) },
+            "could not free Avahi service browser",
+        )
+    }
 }
 
 impl Drop for ManagedAvahiServiceBrowser {
     fn drop(&mut self) {
-        unsafe { avahi_service_browser_free(self.inner) };
+        if self.inner.is_null() {
+            return;
+        }
+
+        if let Err(e) = avahi_util::sys_exec(
+            || unsafe { avahi_service_browser_free(self.inner) },
+            "could not free Avahi service browser",
+        ) {
+            warn!("{}", e);
+        }
     }
 }
 
@@ -78,7 +112,7 @@ pub struct ManagedAvahiServiceBrowserParams {
     protocol: AvahiProtocol,
     kind: *const c_char,
     domain: *const c_char,
-    flags: AvahiLookupFlags,
+    flags: LookupFlags,
     callback: AvahiServiceBrowserCallback,
     userdata: *mut c_void,
 }
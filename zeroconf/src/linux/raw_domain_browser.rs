@@ -0,0 +1,92 @@
+//! Rust friendly `AvahiDomainBrowser` wrappers/helpers
+
+use std::rc::Rc;
+
+use super::client::ManagedAvahiClient;
+use super::flags::LookupFlags;
+use crate::event_loop::{ActiveGuard, ActiveKind};
+use crate::Result;
+use avahi_sys::{
+    avahi_domain_browser_free, avahi_domain_browser_new, AvahiDomainBrowser,
+    AvahiDomainBrowserCallback, AvahiDomainBrowserType, AvahiIfIndex, AvahiProtocol,
+};
+use libc::{c_char, c_void};
+
+/// Wraps the `AvahiDomainBrowser` type from the raw Avahi bindings.
+///
+/// This struct allocates a new `*mut AvahiDomainBrowser` when `ManagedAvahiDomainBrowser::new()`
+/// is invoked and calls the Avahi function responsible for freeing the browser on `trait Drop`.
+#[derive(Debug)]
+pub struct ManagedAvahiDomainBrowser {
+    inner: *mut AvahiDomainBrowser,
+    _client: Rc<ManagedAvahiClient>,
+    _active: ActiveGuard,
+}
+
+impl ManagedAvahiDomainBrowser {
+    /// Initializes the underlying `*mut AvahiDomainBrowser` and verifies it was created;
+    /// returning `Err(String)` if unsuccessful.
+    pub fn new(
+        ManagedAvahiDomainBrowserParams {
+            client,
+            interface,
+            protocol,
+            domain,
+            kind,
+            flags,
+            callback,
+            userdata,
+        }: ManagedAvahiDomainBrowserParams,
+    ) -> Result<Self> {
+        flags.validate()?;
+
+        let inner = unsafe {
+            avahi_domain_browser_new(
+                client.inner,
+                interface,
+                protocol,
+                domain,
+                kind,
+                flags.bits(),
+                callback,
+                userdata,
+            )
+        };
+
+        if inner.is_null() {
+            Err("could not initialize Avahi domain browser".into())
+        } else {
+            let active = ActiveGuard::new(client.poll().loop_state(), ActiveKind::Browser);
+
+            Ok(Self {
+                inner,
+                _client: client,
+                _active: active,
+            })
+        }
+    }
+}
+
+impl Drop for ManagedAvahiDomainBrowser {
+    fn drop(&mut self) {
+        unsafe { avahi_domain_browser_free(self.inner) };
+    }
+}
+
+/// Holds parameters for initializing a new `ManagedAvahiDomainBrowser` with
+/// `ManagedAvahiDomainBrowser::new()`.
+///
+/// See [`avahi_domain_browser_new()`] for more information about these parameters.
+///
+/// [`avahi_domain_browser_new()`]: https://avahi.org/doxygen/html/lookup_8h.html
+#[derive(Builder, BuilderDelegate)]
+pub struct ManagedAvahiDomainBrowserParams {
+    client: Rc<ManagedAvahiClient>,
+    interface: AvahiIfIndex,
+    protocol: AvahiProtocol,
+    domain: *const c_char,
+    kind: AvahiDomainBrowserType,
+    flags: LookupFlags,
+    callback: AvahiDomainBrowserCallback,
+    userdata: *mut c_void,
+}
@@ -0,0 +1,90 @@
+//! Rust friendly `AvahiServiceTypeBrowser` wrappers/helpers
+
+use std::rc::Rc;
+
+use super::client::ManagedAvahiClient;
+use super::flags::LookupFlags;
+use crate::event_loop::{ActiveGuard, ActiveKind};
+use crate::Result;
+use avahi_sys::{
+    avahi_service_type_browser_free, avahi_service_type_browser_new, AvahiIfIndex, AvahiProtocol,
+    AvahiServiceTypeBrowser, AvahiServiceTypeBrowserCallback,
+};
+use libc::{c_char, c_void};
+
+/// Wraps the `AvahiServiceTypeBrowser` type from the raw Avahi bindings.
+///
+/// This struct allocates a new `*mut AvahiServiceTypeBrowser` when
+/// `ManagedAvahiServiceTypeBrowser::new()` is invoked and calls the Avahi function responsible
+/// for freeing the browser on `trait Drop`.
+#[derive(Debug)]
+pub struct ManagedAvahiServiceTypeBrowser {
+    inner: *mut AvahiServiceTypeBrowser,
+    _client: Rc<ManagedAvahiClient>,
+    _active: ActiveGuard,
+}
+
+impl ManagedAvahiServiceTypeBrowser {
+    /// Initializes the underlying `*mut AvahiServiceTypeBrowser` and verifies it was created;
+    /// returning `Err(String)` if unsuccessful.
+    pub fn new(
+        ManagedAvahiServiceTypeBrowserParams {
+            client,
+            interface,
+            protocol,
+            domain,
+            flags,
+            callback,
+            userdata,
+        }: ManagedAvahiServiceTypeBrowserParams,
+    ) -> Result<Self> {
+        flags.validate()?;
+
+        let inner = unsafe {
+            avahi_service_type_browser_new(
+                client.inner,
+                interface,
+                protocol,
+                domain,
+                flags.bits(),
+                callback,
+                userdata,
+            )
+        };
+
+        if inner.is_null() {
+            Err("could not initialize Avahi service type browser".into())
+        } else {
+            let active = ActiveGuard::new(client.poll().loop_state(), ActiveKind::Browser);
+
+            Ok(Self {
+                inner,
+                _client: client,
+                _active: active,
+            })
+        }
+    }
+}
+
+impl Drop for ManagedAvahiServiceTypeBrowser {
+    fn drop(&mut self) {
+        unsafe { avahi_service_type_browser_free(self.inner) };
+    }
+}
+
+/// Holds parameters for initializing a new `ManagedAvahiServiceTypeBrowser` with
+/// `ManagedAvahiServiceTypeBrowser::new()`.
+///
+/// See [`avahi_service_type_browser_new()`] for more information about these parameters.
+///
+/// [`avahi_service_type_browser_new()`]: https://avahi.org/doxygen/html/lookup_8h.html
+#[derive(Builder, BuilderDelegate)]
+pub struct ManagedAvahiServiceTypeBrowserParams {
+    client: Rc<ManagedAvahiClient>,
+    interface: AvahiIfIndex,
+    protocol: AvahiProtocol,
+    domain: *const c_char,
+    flags: LookupFlags,
+    callback: AvahiServiceTypeBrowserCallback,
+    userdata: *mut c_void,
+}
@@ -0,0 +1,116 @@
+//! Rust friendly `AvahiRecordBrowser` wrappers/helpers
+
+use std::rc::Rc;
+use std::{mem, ptr};
+
+use super::avahi_util;
+use super::client::ManagedAvahiClient;
+use super::flags::LookupFlags;
+use crate::Result;
+use avahi_sys::{
+    avahi_record_browser_free, avahi_record_browser_new, AvahiIfIndex, AvahiProtocol,
+    AvahiRecordBrowser, AvahiRecordBrowserCallback,
+};
+use libc::{c_char, c_void};
+
+/// Wraps the `AvahiRecordBrowser` type from the raw Avahi bindings.
+///
+/// This struct allocates a new `*mut AvahiRecordBrowser` when `ManagedAvahiRecordBrowser::new()`
+/// is invoked and calls the Avahi function responsible for freeing the browser on `trait Drop`.
+#[derive(Debug)]
+pub struct ManagedAvahiRecordBrowser {
+    inner: *mut AvahiRecordBrowser,
+    _client: Rc<ManagedAvahiClient>,
+}
+
+impl ManagedAvahiRecordBrowser {
+    /// Initializes the underlying `*mut AvahiRecordBrowser` and verifies it was created;
+    /// returning `Err(String)` if unsuccessful.
+    pub fn new(
+        ManagedAvahiRecordBrowserParams {
+            client,
+            interface,
+            protocol,
+            name,
+            clazz,
+            kind,
+            flags,
+            callback,
+            userdata,
+        }: ManagedAvahiRecordBrowserParams,
+    ) -> Result<Self> {
+        flags.validate()?;
+
+        let inner = unsafe {
+            avahi_record_browser_new(
+                client.inner,
+                interface,
+                protocol,
+                name,
+                clazz,
+                kind,
+                flags.bits(),
+                callback,
+                userdata,
+            )
+        };
+
+        if inner.is_null() {
+            Err("could not initialize Avahi record browser".into())
+        } else {
+            Ok(Self {
+                inner,
+                _client: client,
+            })
+        }
+    }
+
+    pub(super) fn inner(&self) -> *mut AvahiRecordBrowser {
+        self.inner
+    }
+
+    /// Frees the underlying `*mut AvahiRecordBrowser`, returning an error if Avahi reports that freeing it
+    /// failed, which dropping it only logs.
+    pub fn close(mut self) -> Result<()> {
+        let inner = mem::replace(&mut self.inner, ptr::null_mut());
+
+        avahi_util::sys_exec(
+            || unsafe { avahi_record_browser_free(inner) },
+            "could not free Avahi record browser",
+        )
+    }
+}
+
+impl Drop for ManagedAvahiRecordBrowser {
+    fn drop(&mut self) {
+        if self.inner.is_null() {
+            return;
+        }
+
+        if let Err(e) = avahi_util::sys_exec(
+            || unsafe { avahi_record_browser_free(self.inner) },
+            "could not free Avahi record browser",
+        ) {
+            warn!("{}", e);
+        }
+    }
+}
+
+/// Holds parameters for initializing a new `ManagedAvahiRecordBrowser` with
+/// `ManagedAvahiRecordBrowser::new()`.
+///
+/// See [`avahi_record_browser_new()`] for more information about these parameters.
+///
+/// [`avahi_record_browser_new()`]: https://avahi.org/doxygen/html/lookup_8h.html
+#[derive(Builder, BuilderDelegate)]
+pub struct ManagedAvahiRecordBrowserParams {
+    client: Rc<ManagedAvahiClient>,
+    interface: AvahiIfIndex,
+    protocol: AvahiProtocol,
+    name: *const c_char,
+    clazz: u16,
+    kind: u16,
+    flags: LookupFlags,
+    callback: AvahiRecordBrowserCallback,
+    userdata: *mut c_void,
+}
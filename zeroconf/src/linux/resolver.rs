@@ -1,13 +1,19 @@
 //! Rust friendly `AvahiServiceResolver` wrappers/helpers
 
+use super::avahi_util;
 use super::client::ManagedAvahiClient;
+use super::flags::LookupFlags;
+use crate::event_loop::{ActiveGuard, ActiveKind};
 use crate::Result;
 use avahi_sys::{
-    avahi_service_resolver_free, avahi_service_resolver_new, AvahiIfIndex, AvahiLookupFlags,
-    AvahiProtocol, AvahiServiceResolver, AvahiServiceResolverCallback,
+    avahi_service_resolver_free, avahi_service_resolver_new, AvahiIfIndex, AvahiProtocol,
+    AvahiServiceResolver, AvahiServiceResolverCallback,
 };
 use libc::{c_char, c_void};
-use std::{collections::HashMap, rc::Rc};
+use std::ffi::{CStr, CString};
+#[cfg(feature = "diagnostics")]
+use std::time::Instant;
+use std::{collections::HashMap, mem, ptr, rc::Rc};
 
 /// Wraps the `AvahiServiceResolver` type from the raw Avahi bindings.
 ///
@@ -18,6 +24,9 @@ use std::{collections::HashMap, rc::Rc};
 pub struct ManagedAvahiServiceResolver {
     inner: *mut AvahiServiceResolver,
     _client: Rc<ManagedAvahiClient>,
+    _active: ActiveGuard,
+    #[cfg(feature = "diagnostics")]
+    aprotocol: AvahiProtocol,
 }
 
 impl ManagedAvahiServiceResolver {
@@ -37,6 +46,8 @@ impl ManagedAvahiServiceResolver {
             userdata,
         }: ManagedAvahiServiceResolverParams,
     ) -> Result<Self> {
+        flags.validate()?;
+
         let inner = unsafe {
             avahi_service_resolver_new(
                 client.inner,
@@ -46,7 +57,7 @@ impl ManagedAvahiServiceResolver {
                 kind,
                 domain,
                 aprotocol,
-                flags,
+                flags.bits(),
                 callback,
                 userdata,
             )
@@ -55,17 +66,48 @@ impl ManagedAvahiServiceResolver {
         if inner.is_null() {
             Err("could not initialize AvahiServiceResolver".into())
         } else {
+            let active = ActiveGuard::new(client.poll().loop_state(), ActiveKind::Resolver);
+
             Ok(Self {
                 inner,
                 _client: client,
+                _active: active,
+                #[cfg(feature = "diagnostics")]
+                aprotocol,
             })
         }
     }
+
+    /// Returns the protocol of the addresses the service is resolved to.
+    #[cfg(feature = "diagnostics")]
+    pub fn aprotocol(&self) -> AvahiProtocol {
+        self.aprotocol
+    }
+
+    /// Frees the underlying `*mut AvahiServiceResolver`, returning an error if Avahi reports that freeing it
+    /// failed, which dropping it only logs.
+    pub fn close(mut self) -> Result<()> {
+        let inner = mem::replace(&mut self.inner, ptr::null_mut());
+
+        avahi_util::sys_exec(
+            || unsafe { avahi_service_resolver_free(inner) },
+            "could not free AvahiServiceResolver",
+        )
+    }
 }
 
 impl Drop for ManagedAvahiServiceResolver {
     fn drop(&mut self) {
-        unsafe { avahi_service_resolver_free(self.inner) };
+        if self.inner.is_null() {
+            return;
+        }
+
+        if let Err(e) = avahi_util::sys_exec(
+            || unsafe { avahi_service_resolver_free(self.inner) },
+            "could not free AvahiServiceResolver",
+        ) {
+            warn!("{}", e);
+        }
     }
 }
 
@@ -84,22 +126,85 @@ pub struct ManagedAvahiServiceResolverParams {
     kind: *const c_char,
     domain: *const c_char,
     aprotocol: AvahiProtocol,
-    flags: AvahiLookupFlags,
+    flags: LookupFlags,
     callback: AvahiServiceResolverCallback,
     userdata: *mut c_void,
 }
 
 #[derive(Default, Debug)]
 pub(crate) struct ServiceResolverSet {
-    resolvers: HashMap<*mut AvahiServiceResolver, ManagedAvahiServiceResolver>,
+    resolvers: HashMap<*mut AvahiServiceResolver, (CString, ManagedAvahiServiceResolver)>,
+    // when the service of each resolver was browsed and resolving it was started
+    #[cfg(feature = "diagnostics")]
+    timings: HashMap<*mut AvahiServiceResolver, (Instant, Instant)>,
 }
 
 impl ServiceResolverSet {
-    pub fn insert(&mut self, resolver: ManagedAvahiServiceResolver) {
-        self.resolvers.insert(resolver.inner, resolver);
+    /// Inserts a `resolver` for the service with the specified fully qualified name.
+    pub fn insert(&mut self, full_name: CString, resolver: ManagedAvahiServiceResolver) {
+        self.resolvers.insert(resolver.inner, (full_name, resolver));
     }
 
-    pub fn remove_raw(&mut self, raw: *mut AvahiServiceResolver) {
-        self.resolvers.remove(&raw);
+    /// Removes the specified resolver, returning it so that it can outlive its own callback.
+    pub fn remove_raw(
+        &mut self,
+        raw: *mut AvahiServiceResolver,
+    ) -> Option<ManagedAvahiServiceResolver> {
+        self.resolvers.remove(&raw).map(|(_, resolver)| resolver)
+    }
+
+    /// Records when the service of the specified resolver was browsed and resolving it was
+    /// started.
+    #[cfg(feature = "diagnostics")]
+    pub fn set_timing(
+        &mut self,
+        resolver: &ManagedAvahiServiceResolver,
+        browsed_at: Instant,
+        resolve_started_at: Instant,
+    ) {
+        self.timings
+            .insert(resolver.inner, (browsed_at, resolve_started_at));
+    }
+
+    /// Removes and returns what was recorded with `set_timing()` for the specified resolver.
+    #[cfg(feature = "diagnostics")]
+    pub fn take_timing(&mut self, raw: *mut AvahiServiceResolver) -> Option<(Instant, Instant)> {
+        self.timings.remove(&raw)
+    }
+
+    /// Frees every pending resolver, returning the first error reported while freeing them.
+    pub fn close(self) -> Result<()> {
+        let mut result = Ok(());
+
+        for (_, resolver) in self.resolvers.into_values() {
+            result = result.and(resolver.close());
+        }
+
+        result
+    }
+
+    /// Returns the number of pending resolvers.
+    pub fn len(&self) -> usize {
+        self.resolvers.len()
+    }
+
+    /// Returns `true` if a resolver for the service with the specified fully qualified name is
+    /// pending.
+    pub fn contains_name(&self, full_name: &CStr) -> bool {
+        self.resolvers
+            .values()
+            .any(|(name, _)| name.as_c_str() == full_name)
+    }
+
+    /// Removes every resolver for the service with the specified fully qualified name.
+    pub fn remove_name(&mut self, full_name: &CStr) {
+        self.resolvers
+            .retain(|_, (name, _)| name.as_c_str() != full_name);
+
+        #[cfg(feature = "diagnostics")]
+        {
+            let resolvers = &self.resolvers;
+            self.timings.retain(|raw, _| resolvers.contains_key(raw));
+        }
     }
 }
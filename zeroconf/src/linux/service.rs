@@ -1,37 +1,62 @@
 //! Avahi implementation for cross-platform service.
 
+use super::address_monitor::AddressMonitor;
 use super::avahi_util;
 use super::client::{self, ManagedAvahiClient, ManagedAvahiClientParams};
 use super::entry_group::{
-    AddServiceParams, AddServiceSubtypeParams, ManagedAvahiEntryGroup, ManagedAvahiEntryGroupParams,
+    AddAddressParams, AddRecordParams, AddServiceParams, AddServiceSubtypeParams,
+    ManagedAvahiEntryGroup, ManagedAvahiEntryGroupParams, UpdateServiceTxtParams,
 };
+use super::facade::{ClientListenerGuard, ClientListeners};
+use super::flags::PublishFlags;
 use super::poll::ManagedAvahiSimplePoll;
-use crate::ffi::{c_str, AsRaw, FromRaw, UnwrapOrNull};
+use super::watch::{ManagedAvahiWatch, ManagedAvahiWatchParams};
+use crate::error::Error;
+use crate::event_loop::{TeardownGuard, TimerGuard};
+use crate::ffi::{self, c_str, AsRaw, FromRaw, UnwrapOrNull};
+use crate::interface;
 use crate::prelude::*;
+use crate::service::{self, OwnService, RegistrationStates};
 use crate::{
-    EventLoop, NetworkInterface, Result, ServiceRegisteredCallback, ServiceRegistration,
-    ServiceType, TxtRecord,
+    ClientFlags, ClientState, ClientStateCallback, EventLoop, HostNameChangedCallback,
+    NetworkInterface, NetworkProtocol, RegistrationState, Result, Scope, ServiceHandle,
+    ServiceRegisteredCallback, ServiceRegistration, ServiceType, TxtProvider, TxtRecord,
+    MIN_TXT_PROVIDER_INTERVAL,
 };
 use avahi_sys::{
-    AvahiClient, AvahiClientFlags, AvahiClientState, AvahiEntryGroup, AvahiEntryGroupState,
-    AvahiIfIndex,
+    AvahiClient, AvahiClientState, AvahiEntryGroup, AvahiEntryGroupState, AvahiIfIndex,
+    AvahiProtocol, AvahiWatch, AvahiWatchEvent,
 };
-use libc::c_void;
+use libc::{c_int, c_void};
 use std::any::Any;
-use std::ffi::CString;
+use std::cell::RefCell;
+use std::ffi::{CStr, CString};
 use std::fmt::{self, Formatter};
-use std::rc::Rc;
+use std::iter;
+use std::net::IpAddr;
+use std::ptr::NonNull;
+use std::rc::{Rc, Weak};
 use std::str::FromStr;
+use std::sync::mpsc::Receiver;
 use std::sync::Arc;
+use std::time::Duration;
 
 #[derive(Debug)]
 pub struct AvahiMdnsService {
     client: Option<Rc<ManagedAvahiClient>>,
     poll: Option<Rc<ManagedAvahiSimplePoll>>,
+    interface: NetworkInterface,
     context: Box<AvahiServiceContext>,
+    teardown: Option<TeardownGuard>,
+    client_listener: Option<ClientListenerGuard>,
+    // shared with the client of a facade, so declared after every holder of the client
+    client_listeners: Option<Rc<ClientListeners>>,
 }
 
 impl TMdnsService for AvahiMdnsService {
+    type EventLoop<'a> = EventLoop<'a>;
+    type Handle = ServiceHandle;
+
     fn new(service_type: ServiceType, port: u16) -> Self {
         let kind = avahi_util::format_service_type(&service_type);
 
@@ -44,7 +69,11 @@ impl TMdnsService for AvahiMdnsService {
         Self {
             client: None,
             poll: None,
+            interface: NetworkInterface::Unspec,
             context: Box::new(AvahiServiceContext::new(c_string!(kind), port, sub_types)),
+            teardown: None,
+            client_listener: None,
+            client_listeners: None,
         }
     }
 
@@ -63,11 +92,19 @@ impl TMdnsService for AvahiMdnsService {
     }
 
     fn set_network_interface(&mut self, interface: NetworkInterface) {
-        self.context.interface_index = avahi_util::interface_index(interface)
+        self.interface = interface;
     }
 
     fn network_interface(&self) -> NetworkInterface {
-        avahi_util::interface_from_index(self.context.interface_index)
+        self.interface
+    }
+
+    fn set_network_protocol(&mut self, protocol: NetworkProtocol) {
+        self.context.protocol = avahi_util::protocol(protocol)
+    }
+
+    fn network_protocol(&self) -> NetworkProtocol {
+        avahi_util::protocol_from_raw(self.context.protocol)
     }
 
     fn set_domain(&mut self, domain: &str) {
@@ -78,6 +115,19 @@ impl TMdnsService for AvahiMdnsService {
         self.context.domain.as_ref().map(|d| d.to_str().unwrap())
     }
 
+    /// Sets the [`Scope`] on which to advertise the service. Avahi only publishes on the local
+    /// link, so registering with `Scope::WideArea` fails and `Scope::Both` behaves like
+    /// `Scope::LinkLocal`.
+    ///
+    /// [`Scope`]: ../../enum.Scope.html
+    fn set_scope(&mut self, scope: Scope) {
+        self.context.scope = scope;
+    }
+
+    fn scope(&self) -> Scope {
+        self.context.scope
+    }
+
     fn set_host(&mut self, host: &str) {
         self.context.host = c_string!(host).into()
     }
@@ -94,10 +144,152 @@ impl TMdnsService for AvahiMdnsService {
         self.context.txt_record.as_ref()
     }
 
+    fn current_txt(&self) -> Option<TxtRecord> {
+        let published = self.context.published.try_borrow().ok()?;
+
+        published.group.as_ref()?;
+        published.txt_record.clone()
+    }
+
+    fn set_publish_empty_txt(&mut self, publish_empty_txt: bool) {
+        self.context.publish_empty_txt = publish_empty_txt;
+    }
+
+    fn publish_empty_txt(&self) -> bool {
+        self.context.publish_empty_txt
+    }
+
+    fn set_txt_provider(&mut self, interval: Duration, txt_provider: Box<TxtProvider>) {
+        self.context.txt_provider = Some((
+            interval.max(MIN_TXT_PROVIDER_INTERVAL),
+            Rc::from(txt_provider),
+        ));
+    }
+
+    fn txt_provider_interval(&self) -> Option<Duration> {
+        self.context
+            .txt_provider
+            .as_ref()
+            .map(|(interval, _)| *interval)
+    }
+
+    fn set_addresses(&mut self, addresses: Vec<IpAddr>) {
+        self.context.addresses = addresses;
+    }
+
+    fn addresses(&self) -> &[IpAddr] {
+        &self.context.addresses
+    }
+
+    fn set_publish_reverse_ptr(&mut self, publish_reverse_ptr: bool) {
+        self.context.publish_reverse_ptr = publish_reverse_ptr;
+    }
+
+    fn publish_reverse_ptr(&self) -> bool {
+        self.context.publish_reverse_ptr
+    }
+
+    fn set_no_probe(&mut self, no_probe: bool) {
+        self.context.no_probe = no_probe;
+    }
+
+    fn no_probe(&self) -> bool {
+        self.context.no_probe
+    }
+
+    fn set_srv_priority(&mut self, priority: u16) {
+        self.context.srv_priority = priority;
+    }
+
+    fn srv_priority(&self) -> u16 {
+        self.context.srv_priority
+    }
+
+    fn set_srv_weight(&mut self, weight: u16) {
+        self.context.srv_weight = weight;
+    }
+
+    fn srv_weight(&self) -> u16 {
+        self.context.srv_weight
+    }
+
+    fn set_ptr_ttl(&mut self, ttl: u32) {
+        self.context.ptr_ttl = Some(ttl);
+    }
+
+    fn ptr_ttl(&self) -> Option<u32> {
+        self.context.ptr_ttl
+    }
+
+    fn set_srv_ttl(&mut self, ttl: u32) {
+        self.context.srv_ttl = Some(ttl);
+    }
+
+    fn srv_ttl(&self) -> Option<u32> {
+        self.context.srv_ttl
+    }
+
+    fn set_txt_ttl(&mut self, ttl: u32) {
+        self.context.txt_ttl = Some(ttl);
+    }
+
+    fn txt_ttl(&self) -> Option<u32> {
+        self.context.txt_ttl
+    }
+
+    fn set_address_ttl(&mut self, ttl: u32) {
+        self.context.address_ttl = Some(ttl);
+    }
+
+    fn address_ttl(&self) -> Option<u32> {
+        self.context.address_ttl
+    }
+
+    /// Sets whether the published addresses follow the addresses of the service's network
+    /// interface, which is noticed through a `NETLINK_ROUTE` socket subscribed to
+    /// `RTMGRP_IPV4_IFADDR` and `RTMGRP_IPV6_IFADDR` and watched on the service's `AvahiPoll`.
+    fn set_track_interface_changes(&mut self, track_interface_changes: bool) {
+        self.context.track_interface_changes = track_interface_changes;
+    }
+
+    fn track_interface_changes(&self) -> bool {
+        self.context.track_interface_changes
+    }
+
+    fn set_follow_interfaces(&mut self, follow_interfaces: bool) {
+        self.context.follow_interfaces = follow_interfaces;
+    }
+
+    fn follow_interfaces(&self) -> bool {
+        self.context.follow_interfaces
+    }
+
     fn set_registered_callback(&mut self, registered_callback: Box<ServiceRegisteredCallback>) {
         self.context.registered_callback = registered_callback.into()
     }
 
+    fn set_client_flags(&mut self, flags: ClientFlags) {
+        self.context.client_flags = flags;
+    }
+
+    fn client_flags(&self) -> ClientFlags {
+        match &self.client {
+            Some(client) => avahi_util::client_flags_from_raw(client.flags()),
+            None => self.context.client_flags,
+        }
+    }
+
+    fn set_client_state_callback(&mut self, client_state_callback: Box<ClientStateCallback>) {
+        self.context.client_state_callback = Some(client_state_callback);
+    }
+
+    fn set_host_name_changed_callback(
+        &mut self,
+        host_name_changed_callback: Box<HostNameChangedCallback>,
+    ) {
+        self.context.host_name_changed_callback = Some(host_name_changed_callback);
+    }
+
     fn set_context(&mut self, context: Box<dyn Any>) {
         self.context.user_context = Some(Arc::from(context))
     }
@@ -106,25 +298,325 @@ impl TMdnsService for AvahiMdnsService {
         self.context.user_context.as_ref().map(|c| c.as_ref())
     }
 
+    fn handle(&self) -> ServiceHandle {
+        AvahiServiceHandle {
+            published: Rc::downgrade(&self.context.published),
+        }
+    }
+
+    fn state_stream(&self) -> Receiver<RegistrationState> {
+        self.context.states.borrow_mut().subscribe()
+    }
+
+    fn is_registered(&self) -> bool {
+        let running = self
+            .client
+            .as_ref()
+            .map_or(false, |c| c.state() == ClientState::Running);
+
+        let established = self
+            .context
+            .published
+            .try_borrow()
+            .map_or(false, |published| {
+                published.group.as_ref().map_or(false, |g| {
+                    g.state() == avahi_sys::AvahiEntryGroupState_AVAHI_ENTRY_GROUP_ESTABLISHED
+                })
+            });
+
+        running && established
+    }
+
     fn register(&mut self) -> Result<EventLoop> {
         debug!("Registering service: {:?}", self);
 
-        self.poll = Some(Rc::new(ManagedAvahiSimplePoll::new()?));
+        if self.context.registered_callback.is_none() {
+            return Err("a registered callback must be set before registering the service".into());
+        }
+
+        self.context.interface_index = avahi_util::interface_index(self.interface)?;
+
+        if self.context.scope == Scope::WideArea {
+            return Err("Avahi does not support publishing services on a wide-area scope".into());
+        }
+
+        service::check_addresses(
+            self.network_interface(),
+            self.host(),
+            &self.context.addresses,
+        )?;
+
+        if self.context.follow_interfaces
+            && self.context.interface_index != avahi_sys::AVAHI_IF_UNSPEC
+        {
+            return Err("following interfaces requires publishing on all interfaces".into());
+        }
+
+        if self
+            .client
+            .as_ref()
+            .map_or(false, |c| c.state() == ClientState::Failure)
+        {
+            debug!("Reconnecting to the daemon");
+
+            // the entry group belongs to the failed client
+            self.context.withdraw();
+            self.context.client = None;
+            self.client = None;
+            self.client_listener = None;
+        }
+
+        if self.client.is_none() {
+            if self.poll.is_none() {
+                self.poll = Some(Rc::new(ManagedAvahiSimplePoll::new()?));
+            }
+
+            self.client = Some(Rc::new(ManagedAvahiClient::new(
+                ManagedAvahiClientParams::builder()
+                    .poll(Rc::clone(self.poll.as_ref().unwrap()))
+                    .flags(avahi_util::client_flags(self.context.client_flags))
+                    .callback(Some(client_callback))
+                    .userdata(self.context.as_raw())
+                    .build()?,
+            )?));
+        } else if let (Some(listeners), None) = (&self.client_listeners, &self.client_listener) {
+            let context = self.context.as_raw();
+
+            // the context is boxed, so it stays at the same address until the service is dropped,
+            // which unregisters the listener
+            self.client_listener =
+                Some(listeners.register(move |client, state| unsafe {
+                    client_callback(client, state, context)
+                }));
+        }
+
+        self.context.client = self.client.clone();
+
+        if self.context.follow_interfaces {
+            self.context.followed_interfaces = self.context.active_interfaces();
+        }
+
+        if (self.context.tracks_addresses() || self.context.follow_interfaces)
+            && self.context.address_watch.is_none()
+        {
+            self.watch_addresses()?;
+        }
+
+        if let Some((_, txt_provider)) = &self.context.txt_provider {
+            if let Some(txt_record) = service::provide_txt(&**txt_provider) {
+                self.context.txt_record = Some(txt_record);
+            }
+        }
+
+        self.context.registered = true;
+
+        if self.context.client.as_ref().unwrap().state() == ClientState::Connecting {
+            // only possible with `NO_FAIL`, published once the daemon is running
+            debug!("Waiting for the daemon to publish the service");
+        } else {
+            unsafe { create_service(&mut self.context) }?;
+        }
+
+        let context: *mut AvahiServiceContext = &mut *self.context;
+        let poll = self.poll.as_ref().unwrap();
+
+        // the context is boxed, so it stays at the same address until the service is dropped,
+        // which unregisters the teardown
+        self.teardown = Some(
+            poll.loop_state()
+                .register_teardown(move || unsafe { (*context).withdraw() }),
+        );
+
+        let txt_timer = self
+            .context
+            .txt_provider
+            .as_ref()
+            .map(|(interval, txt_provider)| {
+                poll.loop_state().register_timer(
+                    *interval,
+                    service::txt_provider_tick(
+                        self.handle(),
+                        Rc::clone(txt_provider),
+                        self.context.txt_record.clone(),
+                    ),
+                )
+            });
+
+        self.context.txt_timer = txt_timer;
+
+        Ok(EventLoop::new(self.poll.as_ref().unwrap().clone()))
+    }
+
+    fn unregister(&mut self) -> Result<()> {
+        debug!("Unregistering service: {:?}", self);
+
+        self.teardown = None;
+        self.context.unregister()
+    }
+}
+
+impl AvahiMdnsService {
+    /// Creates a new service that registers using the specified shared client instead of
+    /// creating its own, and receives the states of the client through `listeners`.
+    pub(crate) fn with_client(
+        service_type: ServiceType,
+        port: u16,
+        poll: Rc<ManagedAvahiSimplePoll>,
+        client: Rc<ManagedAvahiClient>,
+        listeners: Rc<ClientListeners>,
+    ) -> Self {
+        let mut service = Self::new(service_type, port);
+        service.poll = Some(poll);
+        service.client = Some(client);
+        service.client_listeners = Some(listeners);
+        service
+    }
+
+    fn watch_addresses(&mut self) -> Result<()> {
+        debug!("Watching interface addresses");
 
-        self.client = Some(Rc::new(ManagedAvahiClient::new(
-            ManagedAvahiClientParams::builder()
+        let monitor = AddressMonitor::new()?;
+
+        self.context.address_watch = Some(ManagedAvahiWatch::new(
+            ManagedAvahiWatchParams::builder()
                 .poll(Rc::clone(self.poll.as_ref().unwrap()))
-                .flags(AvahiClientFlags(0))
-                .callback(Some(client_callback))
+                .fd(monitor.fd())
+                .event(avahi_sys::AvahiWatchEvent_AVAHI_WATCH_IN)
+                .callback(Some(address_watch_callback))
                 .userdata(self.context.as_raw())
                 .build()?,
-        )?));
+        )?);
 
-        self.context.client = self.client.clone();
+        self.context.address_monitor = Some(monitor);
 
-        unsafe { create_service(&mut self.context) }?;
+        Ok(())
+    }
+}
 
-        Ok(EventLoop::new(self.poll.as_ref().unwrap().clone()))
+/// Avahi implementation of [`TServiceHandle`].
+///
+/// [`TServiceHandle`]: ../../service/trait.TServiceHandle.html
+#[derive(Debug, Clone)]
+pub struct AvahiServiceHandle {
+    published: Weak<RefCell<AvahiPublishedService>>,
+}
+
+impl TServiceHandle for AvahiServiceHandle {
+    fn update_txt_record(&self, txt_record: TxtRecord) -> Result<()> {
+        let published = self
+            .published
+            .upgrade()
+            .ok_or_else(|| Error::from("service has been dropped"))?;
+
+        let mut published = published.try_borrow_mut().map_err(|_| Error::Reentrant)?;
+
+        published.update_txt_record(txt_record)
+    }
+
+    fn set_txt_key(&self, key: &str, value: &str) -> Result<()> {
+        self.update_txt_entries(|txt_record| txt_record.insert(key, value))
+    }
+
+    fn remove_txt_key(&self, key: &str) -> Result<()> {
+        self.update_txt_entries(|txt_record| {
+            txt_record.remove(key);
+            Ok(())
+        })
+    }
+
+    fn update_port(&self, port: u16) -> Result<()> {
+        let published = self
+            .published
+            .upgrade()
+            .ok_or_else(|| Error::from("service has been dropped"))?;
+
+        let (context, txt_record) = {
+            let mut published = published.try_borrow_mut().map_err(|_| Error::Reentrant)?;
+
+            let group = published
+                .group
+                .as_mut()
+                .ok_or_else(|| Error::from("service has not been registered"))?;
+
+            debug!("Updating port to {}", port);
+
+            // the port is part of the SRV record, which can only be replaced by adding the
+            // service again
+            group.reset()?;
+
+            (published.context.unwrap(), published.txt_record.clone())
+        };
+
+        // the context is owned by the service, which is alive while the handle can be upgraded
+        let context = unsafe { &mut *context.as_ptr() };
+        context.port = port;
+        // keeps the TXT record updated through handles
+        context.txt_record = txt_record;
+
+        unsafe { create_service(context) }
+    }
+}
+
+impl AvahiServiceHandle {
+    fn update_txt_entries<F>(&self, f: F) -> Result<()>
+    where
+        F: FnOnce(&mut TxtRecord) -> Result<()>,
+    {
+        let published = self
+            .published
+            .upgrade()
+            .ok_or_else(|| Error::from("service has been dropped"))?;
+
+        let mut published = published.try_borrow_mut().map_err(|_| Error::Reentrant)?;
+
+        if published.group.is_none() {
+            return Err("service has not been registered".into());
+        }
+
+        let mut txt_record = published.txt_record.clone().unwrap_or_default();
+        f(&mut txt_record)?;
+
+        published.update_txt_record(txt_record)
+    }
+}
+
+/// State of a registered service that is shared with its `AvahiServiceHandle`s.
+#[derive(Debug, Default)]
+struct AvahiPublishedService {
+    group: Option<ManagedAvahiEntryGroup>,
+    context: Option<NonNull<AvahiServiceContext>>,
+    interfaces: Vec<AvahiIfIndex>,
+    protocol: AvahiProtocol,
+    name: Option<CString>,
+    kind: Option<CString>,
+    domain: Option<CString>,
+    txt_record: Option<TxtRecord>,
+}
+
+impl AvahiPublishedService {
+    fn update_txt_record(&mut self, txt_record: TxtRecord) -> Result<()> {
+        let group = self
+            .group
+            .as_mut()
+            .ok_or_else(|| Error::from("service has not been registered"))?;
+
+        for interface in &self.interfaces {
+            group.update_service_txt(
+                UpdateServiceTxtParams::builder()
+                    .interface(*interface)
+                    .protocol(self.protocol)
+                    .flags(PublishFlags::empty())
+                    .name(self.name.as_ref().unwrap().as_ptr())
+                    .kind(self.kind.as_ref().unwrap().as_ptr())
+                    .domain(self.domain.as_ref().map(|d| d.as_ptr()).unwrap_or_null())
+                    .txt(Some(txt_record.inner()))
+                    .build()?,
+            )?;
+        }
+
+        self.txt_record = Some(txt_record);
+
+        Ok(())
     }
 }
 
@@ -135,12 +627,41 @@ struct AvahiServiceContext {
     kind: CString,
     sub_types: Vec<CString>,
     port: u16,
-    group: Option<ManagedAvahiEntryGroup>,
+    published: Rc<RefCell<AvahiPublishedService>>,
     txt_record: Option<TxtRecord>,
+    publish_empty_txt: bool,
+    txt_provider: Option<(Duration, Rc<TxtProvider>)>,
+    txt_timer: Option<TimerGuard>,
     interface_index: AvahiIfIndex,
+    protocol: AvahiProtocol,
     domain: Option<CString>,
     host: Option<CString>,
+    addresses: Vec<IpAddr>,
+    tracked_addresses: Option<Vec<IpAddr>>,
+    publish_reverse_ptr: bool,
+    // declared before the monitor so that the watch on its socket is freed first
+    address_watch: Option<ManagedAvahiWatch>,
+    address_monitor: Option<AddressMonitor>,
+    scope: Scope,
+    no_probe: bool,
+    srv_priority: u16,
+    srv_weight: u16,
+    ptr_ttl: Option<u32>,
+    srv_ttl: Option<u32>,
+    txt_ttl: Option<u32>,
+    address_ttl: Option<u32>,
+    track_interface_changes: bool,
+    follow_interfaces: bool,
+    followed_interfaces: Vec<AvahiIfIndex>,
     registered_callback: Option<Box<ServiceRegisteredCallback>>,
+    client_flags: ClientFlags,
+    // whether the service is to be published whenever the daemon is running
+    registered: bool,
+    client_state_callback: Option<Box<ClientStateCallback>>,
+    host_name_changed_callback: Option<Box<HostNameChangedCallback>>,
+    host_name_fqdn: Option<String>,
+    own_service: OwnService,
+    states: RefCell<RegistrationStates>,
     user_context: Option<Arc<dyn Any>>,
 }
 
@@ -152,21 +673,137 @@ impl AvahiServiceContext {
             kind,
             port,
             sub_types,
-            group: None,
+            published: Rc::default(),
             txt_record: None,
+            publish_empty_txt: true,
+            txt_provider: None,
+            txt_timer: None,
             interface_index: avahi_sys::AVAHI_IF_UNSPEC,
+            protocol: avahi_sys::AVAHI_PROTO_UNSPEC,
             domain: None,
             host: None,
+            addresses: Vec::new(),
+            tracked_addresses: None,
+            publish_reverse_ptr: false,
+            address_watch: None,
+            address_monitor: None,
+            scope: Scope::default(),
+            no_probe: false,
+            srv_priority: 0,
+            srv_weight: 0,
+            ptr_ttl: None,
+            srv_ttl: None,
+            txt_ttl: None,
+            address_ttl: None,
+            track_interface_changes: false,
+            follow_interfaces: false,
+            followed_interfaces: Vec::new(),
             registered_callback: None,
+            client_flags: ClientFlags::empty(),
+            registered: false,
+            client_state_callback: None,
+            host_name_changed_callback: None,
+            host_name_fqdn: None,
+            own_service: OwnService::default(),
+            states: RefCell::default(),
             user_context: None,
         }
     }
 
-    fn invoke_callback(&self, result: Result<ServiceRegistration>) {
-        if let Some(f) = &self.registered_callback {
-            f(result, self.user_context.clone());
+    /// Withdraws the service by freeing its entry group, and stops tracking interface addresses.
+    fn withdraw(&mut self) {
+        debug!("Withdrawing service: {:?}", self.name);
+
+        self.registered = false;
+        self.address_watch = None;
+        self.address_monitor = None;
+        self.txt_timer = None;
+
+        match self.published.try_borrow_mut() {
+            Ok(mut published) => published.group = None,
+            Err(_) => warn!("could not withdraw service while it is being updated"),
+        }
+    }
+
+    /// Withdraws the service like [`withdraw()`], but resets the entry group before it is freed,
+    /// to report whether its records could be removed.
+    ///
+    /// [`withdraw()`]: #method.withdraw
+    fn unregister(&mut self) -> Result<()> {
+        let result = self
+            .published
+            .try_borrow_mut()
+            .map_err(|_| Error::Reentrant)?
+            .group
+            .as_mut()
+            .map_or(Ok(()), |group| group.reset());
+
+        self.withdraw();
+        result
+    }
+
+    /// Returns `true` if the explicit addresses follow the addresses of the interface.
+    fn tracks_addresses(&self) -> bool {
+        self.track_interface_changes && !self.addresses.is_empty()
+    }
+
+    /// Returns the interfaces that a service following interfaces is to be published on.
+    fn active_interfaces(&self) -> Vec<AvahiIfIndex> {
+        interface::active_interfaces(avahi_util::protocol_from_raw(self.protocol))
+            .into_iter()
+            // an interface whose index Avahi cannot represent cannot be published on
+            .filter_map(|index| avahi_util::interface_index(index.into()).ok())
+            .collect()
+    }
+
+    /// Returns the interfaces to publish the service on, which is each followed interface or
+    /// otherwise the service's single interface.
+    fn published_interfaces(&self) -> Vec<AvahiIfIndex> {
+        if self.follow_interfaces {
+            self.followed_interfaces.clone()
         } else {
-            panic!("attempted to invoke service callback but none was set");
+            vec![self.interface_index]
+        }
+    }
+
+    /// Returns the addresses to publish, which are the interface's addresses once they have
+    /// changed while tracking.
+    fn published_addresses(&self) -> &[IpAddr] {
+        self.tracked_addresses.as_deref().unwrap_or(&self.addresses)
+    }
+
+    /// Returns `true` if the service has settings that Avahi does not accept for services, in
+    /// which case its records are published individually.
+    fn publishes_records(&self) -> bool {
+        self.no_probe
+            || self.srv_priority != 0
+            || self.srv_weight != 0
+            || self.ptr_ttl.is_some()
+            || self.srv_ttl.is_some()
+            || self.txt_ttl.is_some()
+            || self.omits_txt()
+    }
+
+    /// Returns `true` if the service has no TXT keys and should not publish an empty TXT record,
+    /// which Avahi always does for services.
+    fn omits_txt(&self) -> bool {
+        service::omits_txt(self.publish_empty_txt, self.txt_record.as_ref())
+    }
+
+    fn set_state(&self, state: RegistrationState) {
+        self.states.borrow_mut().set(state);
+    }
+
+    /// Invokes the registered callback, returning `false` if it panicked.
+    fn invoke_callback(&self, result: Result<ServiceRegistration>) -> bool {
+        match &self.registered_callback {
+            Some(f) => ffi::catch_callback_panic("service registered callback", || {
+                f(result, self.user_context.clone())
+            }),
+            None => {
+                warn!("attempted to invoke service callback but none was set");
+                true
+            }
         }
     }
 }
@@ -177,7 +814,7 @@ impl fmt::Debug for AvahiServiceContext {
             .field("name", &self.name)
             .field("kind", &self.kind)
             .field("port", &self.port)
-            .field("group", &self.group)
+            .field("published", &self.published)
             .finish()
     }
 }
@@ -188,10 +825,13 @@ unsafe fn create_service(context: &mut AvahiServiceContext) -> Result<()> {
         context.name = Some(c_string!(host_name.to_string()));
     }
 
-    if context.group.is_none() {
+    let published = Rc::clone(&context.published);
+    let mut published = published.try_borrow_mut().map_err(|_| Error::Reentrant)?;
+
+    if published.group.is_none() {
         debug!("Creating group");
 
-        context.group = Some(ManagedAvahiEntryGroup::new(
+        published.group = Some(ManagedAvahiEntryGroup::new(
             ManagedAvahiEntryGroupParams::builder()
                 .client(Rc::clone(context.client.as_ref().unwrap()))
                 .callback(Some(entry_group_callback))
@@ -200,19 +840,87 @@ unsafe fn create_service(context: &mut AvahiServiceContext) -> Result<()> {
         )?);
     }
 
-    let group = context.group.as_mut().unwrap();
+    published.context = Some(NonNull::from(&mut *context));
+    let interfaces = context.published_interfaces();
+    published.interfaces = interfaces.clone();
+    published.protocol = context.protocol;
+    published.name = context.name.clone();
+    published.kind = Some(context.kind.clone());
+    published.domain = context.domain.clone();
+    published.txt_record = context.txt_record.clone();
+
+    let group = published.group.as_mut().unwrap();
 
     if !group.is_empty() {
         return Ok(());
     }
 
-    debug!("Adding service: {}", context.kind.to_string_lossy());
+    if interfaces.is_empty() {
+        // published once an interface appears
+        debug!("No interfaces to publish on");
+        return Ok(());
+    }
+
+    let mut address_flags = PublishFlags::empty();
+
+    if !context.publish_reverse_ptr {
+        address_flags |= PublishFlags::NO_REVERSE;
+    }
+
+    if context.no_probe {
+        address_flags |= PublishFlags::NO_PROBE;
+    }
+
+    for interface in interfaces {
+        debug!(
+            "Adding service: {} (interface: {})",
+            context.kind.to_string_lossy(),
+            interface
+        );
+
+        if context.publishes_records() {
+            add_service_records(group, context, interface)?;
+        } else {
+            add_service(group, context, interface)?;
+        }
+
+        for address in context.published_addresses() {
+            debug!("Adding address: {}", address);
+
+            if let Some(ttl) = context.address_ttl {
+                add_address_record(group, context, interface, address, ttl)?;
+                continue;
+            }
+
+            let address = avahi_util::avahi_address(address);
+
+            // the reverse record of an address may only be published once, which is left to the
+            // daemon for addresses of the machine itself unless asked for
+            group.add_address(
+                AddAddressParams::builder()
+                    .interface(interface)
+                    .protocol(context.protocol)
+                    .flags(address_flags)
+                    .name(context.host.as_ref().unwrap().as_ptr())
+                    .address(&address)
+                    .build()?,
+            )?;
+        }
+    }
+
+    group.commit()
+}
 
+unsafe fn add_service(
+    group: &mut ManagedAvahiEntryGroup,
+    context: &AvahiServiceContext,
+    interface: AvahiIfIndex,
+) -> Result<()> {
     group.add_service(
         AddServiceParams::builder()
-            .interface(context.interface_index)
-            .protocol(avahi_sys::AVAHI_PROTO_UNSPEC)
-            .flags(0)
+            .interface(interface)
+            .protocol(context.protocol)
+            .flags(PublishFlags::empty())
             .name(context.name.as_ref().unwrap().as_ptr())
             .kind(context.kind.as_ptr())
             .domain(context.domain.as_ref().map(|d| d.as_ptr()).unwrap_or_null())
@@ -227,9 +935,9 @@ unsafe fn create_service(context: &mut AvahiServiceContext) -> Result<()> {
 
         group.add_service_subtype(
             AddServiceSubtypeParams::builder()
-                .interface(context.interface_index)
-                .protocol(avahi_sys::AVAHI_PROTO_UNSPEC)
-                .flags(0)
+                .interface(interface)
+                .protocol(context.protocol)
+                .flags(PublishFlags::empty())
                 .name(context.name.as_ref().unwrap().as_ptr())
                 .kind(context.kind.as_ptr())
                 .domain(context.domain.as_ref().map(|d| d.as_ptr()).unwrap_or_null())
@@ -238,7 +946,241 @@ unsafe fn create_service(context: &mut AvahiServiceContext) -> Result<()> {
         )?;
     }
 
-    group.commit()
+    Ok(())
+}
+
+/// Publishes the SRV, TXT and PTR records that `add_service()` would, but individually so that
+/// `AVAHI_PUBLISH_NO_PROBE`, the SRV priority and weight and the TTLs can be set, which Avahi
+/// does not accept for services.
+unsafe fn add_service_records(
+    group: &mut ManagedAvahiEntryGroup,
+    context: &AvahiServiceContext,
+    interface: AvahiIfIndex,
+) -> Result<()> {
+    let client = context.client.as_ref().unwrap();
+    let name = context.name.as_ref().unwrap().to_str().unwrap();
+    let kind = context.kind.to_str().unwrap();
+
+    let domain = match &context.domain {
+        Some(domain) => domain.to_str().unwrap(),
+        None => client.domain_name()?,
+    };
+
+    let host = match &context.host {
+        Some(host) => host.to_str().unwrap(),
+        None => client.host_name_fqdn()?,
+    };
+
+    let mut unique = PublishFlags::UNIQUE;
+
+    if context.no_probe {
+        unique |= PublishFlags::NO_PROBE;
+    }
+
+    let service_name = avahi_util::service_name_join(name, kind, domain)?;
+
+    // priority and weight, followed by the port and target
+    let mut srv = Vec::new();
+    srv.extend_from_slice(&context.srv_priority.to_be_bytes());
+    srv.extend_from_slice(&context.srv_weight.to_be_bytes());
+    srv.extend_from_slice(&context.port.to_be_bytes());
+    srv.extend(avahi_util::encode_name(host.split('.'))?);
+
+    add_record(
+        group,
+        context,
+        interface,
+        unique,
+        &service_name,
+        avahi_sys::AVAHI_DNS_TYPE_SRV,
+        context
+            .srv_ttl
+            .unwrap_or(avahi_sys::AVAHI_DEFAULT_TTL_HOST_NAME),
+        &srv,
+    )?;
+
+    if !context.omits_txt() {
+        let txt = match &context.txt_record {
+            Some(txt_record) => txt_record.inner().serialize(),
+            None => vec![0],
+        };
+
+        add_record(
+            group,
+            context,
+            interface,
+            unique,
+            &service_name,
+            avahi_sys::AVAHI_DNS_TYPE_TXT,
+            context.txt_ttl.unwrap_or(avahi_sys::AVAHI_DEFAULT_TTL),
+            &txt,
+        )?;
+    }
+
+    let service_ptr = avahi_util::encode_name(
+        iter::once(name)
+            .chain(kind.split('.'))
+            .chain(domain.split('.')),
+    )?;
+
+    let ptr_names =
+        iter::once(context.kind.as_c_str()).chain(context.sub_types.iter().map(|s| s.as_c_str()));
+
+    for ptr_name in ptr_names {
+        let ptr_name = c_string!(format!("{}.{}", ptr_name.to_str().unwrap(), domain));
+
+        add_record(
+            group,
+            context,
+            interface,
+            PublishFlags::empty(),
+            &ptr_name,
+            avahi_sys::AVAHI_DNS_TYPE_PTR,
+            context.ptr_ttl.unwrap_or(avahi_sys::AVAHI_DEFAULT_TTL),
+            &service_ptr,
+        )?;
+    }
+
+    // makes the service type discoverable by type browsers
+    let enum_name = c_string!(format!("_services._dns-sd._udp.{}", domain));
+    let enum_ptr = avahi_util::encode_name(kind.split('.').chain(domain.split('.')))?;
+
+    add_record(
+        group,
+        context,
+        interface,
+        PublishFlags::empty(),
+        &enum_name,
+        avahi_sys::AVAHI_DNS_TYPE_PTR,
+        context.ptr_ttl.unwrap_or(avahi_sys::AVAHI_DEFAULT_TTL),
+        &enum_ptr,
+    )
+}
+
+/// Publishes an A or AAAA record for the service's host, since `add_address()` does not accept a
+/// TTL, along with its reverse PTR record if enabled.
+fn add_address_record(
+    group: &mut ManagedAvahiEntryGroup,
+    context: &AvahiServiceContext,
+    interface: AvahiIfIndex,
+    address: &IpAddr,
+    ttl: u32,
+) -> Result<()> {
+    let (kind, rdata) = match address {
+        IpAddr::V4(v4) => (avahi_sys::AVAHI_DNS_TYPE_A, v4.octets().to_vec()),
+        IpAddr::V6(v6) => (avahi_sys::AVAHI_DNS_TYPE_AAAA, v6.octets().to_vec()),
+    };
+
+    let mut flags = PublishFlags::UNIQUE;
+
+    if context.no_probe {
+        flags |= PublishFlags::NO_PROBE;
+    }
+
+    let host = context.host.as_ref().unwrap();
+
+    add_record(group, context, interface, flags, host, kind, ttl, &rdata)?;
+
+    if !context.publish_reverse_ptr {
+        return Ok(());
+    }
+
+    let reverse_name = c_string!(service::reverse_lookup_name(address));
+    let host_name = avahi_util::encode_name(host.to_str().unwrap().split('.'))?;
+
+    add_record(
+        group,
+        context,
+        interface,
+        flags,
+        &reverse_name,
+        avahi_sys::AVAHI_DNS_TYPE_PTR,
+        ttl,
+        &host_name,
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+fn add_record(
+    group: &mut ManagedAvahiEntryGroup,
+    context: &AvahiServiceContext,
+    interface: AvahiIfIndex,
+    flags: PublishFlags,
+    name: &CStr,
+    kind: u32,
+    ttl: u32,
+    rdata: &[u8],
+) -> Result<()> {
+    group.add_record(
+        AddRecordParams::builder()
+            .interface(interface)
+            .protocol(context.protocol)
+            .flags(flags)
+            .name(name.as_ptr())
+            .clazz(avahi_sys::AVAHI_DNS_CLASS_IN as u16)
+            .kind(kind as u16)
+            .ttl(ttl)
+            .rdata(rdata.as_ptr() as *const c_void)
+            .size(rdata.len())
+            .build()?,
+    )
+}
+
+unsafe extern "C" fn address_watch_callback(
+    _watch: *mut AvahiWatch,
+    _fd: c_int,
+    _event: AvahiWatchEvent,
+    userdata: *mut c_void,
+) {
+    let context = AvahiServiceContext::from_raw(userdata);
+    if let Err(e) = handle_address_change(context) {
+        context.invoke_callback(Err(e));
+    }
+}
+
+unsafe fn handle_address_change(context: &mut AvahiServiceContext) -> Result<()> {
+    if !context.address_monitor.as_ref().unwrap().drain() {
+        return Ok(());
+    }
+
+    let mut changed = false;
+
+    if context.tracks_addresses() {
+        let interface = avahi_util::interface_from_index(context.interface_index);
+        let addresses = service::tracked_addresses(&context.addresses, &interface.addresses());
+
+        if addresses != context.published_addresses() {
+            debug!("Interface addresses changed: {:?}", addresses);
+            context.tracked_addresses = Some(addresses);
+            changed = true;
+        }
+    }
+
+    if context.follow_interfaces {
+        let interfaces = context.active_interfaces();
+
+        if interfaces != context.followed_interfaces {
+            debug!("Interfaces changed: {:?}", interfaces);
+            context.followed_interfaces = interfaces;
+            changed = true;
+        }
+    }
+
+    if !changed {
+        return Ok(());
+    }
+
+    if let Some(group) = context
+        .published
+        .try_borrow_mut()
+        .map_err(|_| Error::Reentrant)?
+        .group
+        .as_mut()
+    {
+        group.reset()?;
+    }
+
+    create_service(context)
 }
 
 unsafe extern "C" fn entry_group_callback(
@@ -246,15 +1188,93 @@ unsafe extern "C" fn entry_group_callback(
     state: AvahiEntryGroupState,
     userdata: *mut c_void,
 ) {
-    if let avahi_sys::AvahiEntryGroupState_AVAHI_ENTRY_GROUP_ESTABLISHED = state {
-        let context = AvahiServiceContext::from_raw(userdata);
-        if let Err(e) = handle_group_established(context) {
-            context.invoke_callback(Err(e));
+    let context = AvahiServiceContext::from_raw(userdata);
+
+    let result = match state {
+        avahi_sys::AvahiEntryGroupState_AVAHI_ENTRY_GROUP_REGISTERING => {
+            context.set_state(RegistrationState::Registering);
+            Ok(())
+        }
+        avahi_sys::AvahiEntryGroupState_AVAHI_ENTRY_GROUP_ESTABLISHED => {
+            handle_group_established(context)
+        }
+        avahi_sys::AvahiEntryGroupState_AVAHI_ENTRY_GROUP_COLLISION => {
+            handle_group_collision(context)
+        }
+        avahi_sys::AvahiEntryGroupState_AVAHI_ENTRY_GROUP_FAILURE => {
+            let err = avahi_sys::avahi_client_errno(context.client.as_ref().unwrap().inner);
+
+            Err(format!(
+                "entry group failure: {} ({})",
+                avahi_util::get_error(err),
+                avahi_util::error_name(err)
+            )
+            .into())
+        }
+        _ => Ok(()),
+    };
+
+    if let Err(e) = result {
+        context.set_state(RegistrationState::Failure(e.clone()));
+        context.invoke_callback(Err(e));
+    }
+}
+
+/// Publishes a registered service again once the daemon is back after its connection was lost,
+/// which the client only survives with `NO_FAIL`. The service's entry group belonged to the lost
+/// connection, so it is dropped while the client is connecting.
+unsafe fn republish(context: &mut AvahiServiceContext, state: ClientState) {
+    let has_group = match context.published.try_borrow_mut() {
+        Ok(mut published) => {
+            if state == ClientState::Connecting {
+                published.group = None;
+            }
+
+            published.group.is_some()
         }
+        Err(_) => return,
+    };
+
+    if state != ClientState::Running || has_group {
+        return;
+    }
+
+    debug!("Publishing service after reconnecting to the daemon");
+
+    if let Err(e) = create_service(context) {
+        context.set_state(RegistrationState::Failure(e.clone()));
+        context.invoke_callback(Err(e));
     }
 }
 
-unsafe fn handle_group_established(context: &AvahiServiceContext) -> Result<()> {
+/// Registers the service again under an alternative name after its name collided with another
+/// service.
+unsafe fn handle_group_collision(context: &mut AvahiServiceContext) -> Result<()> {
+    let name = service::alternative_service_name(&c_str::copy_raw(
+        context.name.as_ref().unwrap().as_ptr(),
+    ));
+
+    debug!("Group collision, renaming service to {}", name);
+
+    context.set_state(RegistrationState::Collision(name.clone()));
+    context.name = Some(c_string!(name));
+
+    {
+        let mut published = context
+            .published
+            .try_borrow_mut()
+            .map_err(|_| Error::Reentrant)?;
+
+        // the published records are only removed from the group by resetting it
+        if let Some(group) = published.group.as_mut() {
+            group.reset()?;
+        }
+    }
+
+    create_service(context)
+}
+
+unsafe fn handle_group_established(context: &mut AvahiServiceContext) -> Result<()> {
     debug!("Group established");
 
     let result = ServiceRegistration::builder()
@@ -265,18 +1285,92 @@ unsafe fn handle_group_established(context: &AvahiServiceContext) -> Result<()>
         .domain("local".to_string())
         .build()?;
 
-    context.invoke_callback(Ok(result));
+    context.own_service.set(&result);
+    context.set_state(RegistrationState::Established);
+
+    if !context.invoke_callback(Ok(result)) {
+        context.set_state(RegistrationState::Failure(
+            "service registered callback panicked".into(),
+        ));
+    }
 
     Ok(())
 }
 
+/// Invokes the host name changed callback if the host has been renamed since the client was last
+/// running.
+unsafe fn check_host_name(context: &mut AvahiServiceContext, client: *mut AvahiClient) {
+    let host_name = match client::get_host_name_fqdn(client) {
+        Ok(host_name) => host_name,
+        Err(e) => {
+            warn!("{}", e);
+            return;
+        }
+    };
+
+    let previous = context.host_name_fqdn.replace(host_name.to_string());
+
+    if previous.is_none() || previous.as_deref() == Some(host_name) {
+        return;
+    }
+
+    debug!("Host name changed to {}", host_name);
+
+    if let Some(f) = &context.host_name_changed_callback {
+        ffi::catch_callback_panic("host name changed callback", || {
+            f(host_name, context.user_context.clone())
+        });
+    }
+}
+
 unsafe extern "C" fn client_callback(
-    _client: *mut AvahiClient,
+    client: *mut AvahiClient,
     state: AvahiClientState,
-    _userdata: *mut c_void,
+    userdata: *mut c_void,
 ) {
-    // TODO: handle this better
-    if let avahi_sys::AvahiClientState_AVAHI_CLIENT_FAILURE = state {
-        panic!("client failure");
+    let context = AvahiServiceContext::from_raw(userdata);
+    let state = avahi_util::client_state_from_raw(state);
+
+    if state == ClientState::Running {
+        check_host_name(context, client);
+    }
+
+    if context.registered {
+        republish(context, state);
+    }
+
+    if state == ClientState::Failure {
+        warn!("Avahi client failure: the daemon has disconnected or restarted");
+        context.set_state(RegistrationState::Failure("client failure".into()));
+    }
+
+    if let Some(f) = &context.client_state_callback {
+        ffi::catch_callback_panic("client state callback", || {
+            f(state, context.user_context.clone())
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn client_failure_without_state_callback_sets_failure() {
+        let mut context = AvahiServiceContext::new(c_string!("_http._tcp"), 8080, vec![]);
+        let states = context.states.borrow_mut().subscribe();
+
+        unsafe {
+            client_callback(
+                std::ptr::null_mut(),
+                avahi_sys::AvahiClientState_AVAHI_CLIENT_FAILURE,
+                context.as_raw(),
+            )
+        };
+
+        assert_eq!(
+            states.try_recv(),
+            Ok(RegistrationState::Failure("client failure".into()))
+        );
     }
 }
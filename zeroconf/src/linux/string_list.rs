@@ -1,15 +1,19 @@
 //! Low level interface for interacting with `AvahiStringList`.
 
+use super::avahi_util;
 use crate::ffi::c_str;
+use crate::Result;
 use avahi_sys::{
-    avahi_free, avahi_string_list_add_pair, avahi_string_list_copy, avahi_string_list_equal,
+    avahi_free, avahi_string_list_add_arbitrary, avahi_string_list_add_pair,
+    avahi_string_list_add_pair_arbitrary, avahi_string_list_copy, avahi_string_list_equal,
     avahi_string_list_find, avahi_string_list_free, avahi_string_list_get_next,
     avahi_string_list_get_pair, avahi_string_list_length, avahi_string_list_new,
+    avahi_string_list_new_from_array, avahi_string_list_parse, avahi_string_list_serialize,
     avahi_string_list_to_string, AvahiStringList,
 };
-use libc::{c_char, c_void};
+use libc::{c_char, c_int, c_void};
 use std::marker::PhantomData;
-use std::ptr;
+use std::{ptr, slice};
 
 /// Wraps the `AvahiStringList` pointer from the raw Avahi bindings.
 ///
@@ -23,6 +27,21 @@ impl ManagedAvahiStringList {
         Self(unsafe { avahi_string_list_new(ptr::null()) })
     }
 
+    /// Delegate function for [`avahi_string_list_new_from_array()`]. Creates a list of the
+    /// specified `key=value` strings in a single call, as if they were added in order.
+    ///
+    /// # Safety
+    /// This function is unsafe because it provides no guarantees about the given pointers that are
+    /// dereferenced.
+    ///
+    /// [`avahi_string_list_new_from_array()`]: https://avahi.org/doxygen/html/strlst_8h.html
+    pub unsafe fn new_from_array(array: &[*const c_char]) -> Self {
+        Self(avahi_string_list_new_from_array(
+            array.as_ptr() as *mut *const c_char,
+            array.len() as c_int,
+        ))
+    }
+
     /// Delegate function for [`avahi_string_list_add_pair()`].
     ///
     /// # Safety
@@ -34,6 +53,29 @@ impl ManagedAvahiStringList {
         self.0 = avahi_string_list_add_pair(self.0, key, value);
     }
 
+    /// Delegate function for [`avahi_string_list_add_pair_arbitrary()`].
+    ///
+    /// # Safety
+    /// This function is unsafe because it provides no guarantees about the given pointers that are
+    /// dereferenced.
+    ///
+    /// [`avahi_string_list_add_pair_arbitrary()`]: https://avahi.org/doxygen/html/strlst_8h.html
+    pub unsafe fn add_pair_arbitrary(&mut self, key: *const c_char, value: *const u8, size: usize) {
+        self.0 = avahi_string_list_add_pair_arbitrary(self.0, key, value, size);
+    }
+
+    /// Delegate function for [`avahi_string_list_add_arbitrary()`]. Adds the raw `key=value` text
+    /// of an entry as is.
+    ///
+    /// # Safety
+    /// This function is unsafe because it provides no guarantees that `text` points to at least
+    /// `size` readable bytes.
+    ///
+    /// [`avahi_string_list_add_arbitrary()`]: https://avahi.org/doxygen/html/strlst_8h.html
+    pub unsafe fn add_arbitrary(&mut self, text: *const u8, size: usize) {
+        self.0 = avahi_string_list_add_arbitrary(self.0, text, size);
+    }
+
     /// Delegate function for [`avahi_string_list_find()`]. Returns a new `AvahiStringListNode`.
     ///
     /// # Safety
@@ -65,11 +107,46 @@ impl ManagedAvahiStringList {
         unsafe { avahi_string_list_to_string(self.0).into() }
     }
 
-    /// Returns the first node in the list.
+    /// Returns the first node in the list. Avahi prepends new entries, so this is the most
+    /// recently added entry.
     pub fn head(&mut self) -> AvahiStringListNode {
         AvahiStringListNode::new(self.0)
     }
 
+    /// Delegate function for [`avahi_string_list_parse()`]. Parses the wire format of a TXT
+    /// record.
+    ///
+    /// # Safety
+    /// This function is unsafe because it provides no guarantees that `data` points to at least
+    /// `size` readable bytes.
+    ///
+    /// [`avahi_string_list_parse()`]: https://avahi.org/doxygen/html/strlst_8h.html
+    pub unsafe fn parse(data: *const c_void, size: usize) -> Result<Self> {
+        let mut list: *mut AvahiStringList = ptr::null_mut();
+
+        avahi_util::sys_exec(
+            || avahi_string_list_parse(data, size, &mut list),
+            "could not parse TXT record",
+        )?;
+
+        Ok(Self(list))
+    }
+
+    /// Delegate function for [`avahi_string_list_serialize()`]. Returns the wire format of a TXT
+    /// record, which is a single empty string if the list is empty.
+    ///
+    /// [`avahi_string_list_serialize()`]: https://avahi.org/doxygen/html/strlst_8h.html
+    pub fn serialize(&self) -> Vec<u8> {
+        let size = unsafe { avahi_string_list_serialize(self.0, ptr::null_mut(), 0) };
+        let mut data = vec![0u8; size];
+
+        let written =
+            unsafe { avahi_string_list_serialize(self.0, data.as_mut_ptr() as *mut c_void, size) };
+
+        data.truncate(written);
+        data
+    }
+
     pub(super) fn clone_raw(raw: *mut AvahiStringList) -> Self {
         Self(unsafe { avahi_string_list_copy(raw) })
     }
@@ -111,7 +188,7 @@ unsafe impl Sync for ManagedAvahiStringList {}
 /// Represents a node or sub-list in an `AvahiStringList`. This struct is similar to it's parent,
 /// but it does not free the `AvahiStringList` once dropped and is bound to the lifetime of it's
 /// parent.
-#[derive(new, Getters)]
+#[derive(new, Getters, Clone, Copy)]
 pub struct AvahiStringListNode<'a> {
     list: *mut AvahiStringList,
     #[getter(skip)]
@@ -130,6 +207,11 @@ impl<'a> AvahiStringListNode<'a> {
         }
     }
 
+    /// Returns the raw `key=value` text of this node without copying it.
+    pub fn text(&self) -> &'a [u8] {
+        unsafe { slice::from_raw_parts((*self.list).text.as_ptr(), (*self.list).size) }
+    }
+
     /// Returns the `AvahiPair` for this list.
     pub fn get_pair(&mut self) -> AvahiPair {
         let mut key: *mut c_char = ptr::null_mut();
@@ -355,4 +437,53 @@ mod tests {
 
         assert_eq!(map, expected);
     }
+
+    #[test]
+    fn parse_success() {
+        crate::tests::setup();
+
+        let data = b"\x07foo=bar\x0bhello=world";
+        let mut list =
+            unsafe { ManagedAvahiStringList::parse(data.as_ptr() as *const c_void, data.len()) }
+                .unwrap();
+
+        let key = c_string!("hello");
+        let pair = unsafe { list.find(key.as_ptr() as *const c_char) }
+            .unwrap()
+            .get_pair();
+
+        assert_eq!(list.length(), 2);
+        assert_eq!(pair.value().as_str().unwrap(), "world");
+    }
+
+    #[test]
+    fn serialize_round_trips() {
+        crate::tests::setup();
+
+        let mut list = ManagedAvahiStringList::new();
+        let key = c_string!("foo");
+        let value = c_string!("bar");
+
+        unsafe {
+            list.add_pair(
+                key.as_ptr() as *const c_char,
+                value.as_ptr() as *const c_char,
+            );
+        }
+
+        let data = list.serialize();
+        let parsed =
+            unsafe { ManagedAvahiStringList::parse(data.as_ptr() as *const c_void, data.len()) }
+                .unwrap();
+
+        assert_eq!(data, b"\x07foo=bar");
+        assert_eq!(parsed, list);
+    }
+
+    #[test]
+    fn serialize_returns_empty_string_if_empty() {
+        crate::tests::setup();
+
+        assert_eq!(ManagedAvahiStringList::new().serialize(), vec![0]);
+    }
 }
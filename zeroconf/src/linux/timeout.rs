@@ -0,0 +1,124 @@
+//! Rust friendly `AvahiTimeout` wrappers/helpers
+
+use std::rc::Rc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use super::poll::ManagedAvahiSimplePoll;
+use crate::Result;
+use avahi_sys::{avahi_simple_poll_get, timeval, AvahiPoll, AvahiTimeout, AvahiTimeoutCallback};
+use libc::c_void;
+
+/// Wraps the `AvahiTimeout` type from the raw Avahi bindings.
+///
+/// This struct registers a new `*mut AvahiTimeout` on the poll's `AvahiPoll` API when
+/// `ManagedAvahiTimeout::new()` is invoked and calls the API's function responsible for freeing
+/// the timeout on `trait Drop`. The callback is invoked once while the poll is iterated after the
+/// timeout has elapsed, and again only if the timeout is re-armed with `update()`.
+#[derive(Debug)]
+pub struct ManagedAvahiTimeout {
+    inner: *mut AvahiTimeout,
+    api: *const AvahiPoll,
+    _poll: Rc<ManagedAvahiSimplePoll>,
+}
+
+impl ManagedAvahiTimeout {
+    /// Initializes the underlying `*mut AvahiTimeout` and verifies it was created;
+    /// returning `Err(String)` if unsuccessful.
+    pub fn new(
+        ManagedAvahiTimeoutParams {
+            poll,
+            timeout,
+            callback,
+            userdata,
+        }: ManagedAvahiTimeoutParams,
+    ) -> Result<Self> {
+        let api = unsafe { avahi_simple_poll_get(poll.inner()) };
+        let tv = timeout.map(deadline);
+
+        let inner = match unsafe { (*api).timeout_new } {
+            Some(timeout_new) => unsafe { timeout_new(api, as_ptr(&tv), callback, userdata) },
+            None => return Err("AvahiPoll does not support timeouts".into()),
+        };
+
+        if inner.is_null() {
+            Err("could not initialize Avahi timeout".into())
+        } else {
+            Ok(Self {
+                inner,
+                api,
+                _poll: poll,
+            })
+        }
+    }
+
+    /// Re-arms the timeout to elapse after `timeout` from now, or disables it if `None`.
+    pub fn update(&self, timeout: Option<Duration>) {
+        let tv = timeout.map(deadline);
+
+        if let Some(timeout_update) = unsafe { (*self.api).timeout_update } {
+            unsafe { timeout_update(self.inner, as_ptr(&tv)) };
+        }
+    }
+}
+
+impl Drop for ManagedAvahiTimeout {
+    fn drop(&mut self) {
+        if let Some(timeout_free) = unsafe { (*self.api).timeout_free } {
+            unsafe { timeout_free(self.inner) };
+        }
+    }
+}
+
+/// Returns the absolute wall-clock time `timeout` from now, which is what `AvahiPoll` expects.
+fn deadline(timeout: Duration) -> timeval {
+    let at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        + timeout;
+
+    timeval {
+        tv_sec: at.as_secs() as _,
+        tv_usec: at.subsec_micros() as _,
+    }
+}
+
+fn as_ptr(tv: &Option<timeval>) -> *const timeval {
+    tv.as_ref().map_or(std::ptr::null(), |tv| tv as *const _)
+}
+
+/// Holds parameters for initializing a new `ManagedAvahiTimeout` with
+/// `ManagedAvahiTimeout::new()`.
+///
+/// See [`AvahiPoll`] for more information about these parameters.
+///
+/// [`AvahiPoll`]: https://avahi.org/doxygen/html/structAvahiPoll.html
+#[derive(Builder, BuilderDelegate)]
+pub struct ManagedAvahiTimeoutParams {
+    poll: Rc<ManagedAvahiSimplePoll>,
+    timeout: Option<Duration>,
+    callback: AvahiTimeoutCallback,
+    userdata: *mut c_void,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deadline_is_absolute_time_from_now() {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+        let tv = deadline(Duration::from_secs(60));
+        // the deadline is truncated to microseconds
+        let at =
+            Duration::new(tv.tv_sec as u64, tv.tv_usec as u32 * 1000) + Duration::from_micros(1);
+
+        assert!(at > now + Duration::from_secs(60));
+        assert!(at < now + Duration::from_secs(61));
+    }
+
+    #[test]
+    fn disabled_timeout_is_null() {
+        assert!(as_ptr(&None).is_null());
+        assert!(!as_ptr(&Some(deadline(Duration::ZERO))).is_null());
+    }
+}
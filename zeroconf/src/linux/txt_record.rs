@@ -1,19 +1,81 @@
 //! Avahi implementation for cross-platform TXT record.
 
 use super::string_list::{AvahiStringListNode, ManagedAvahiStringList};
-use crate::txt_record::TTxtRecord;
+use crate::txt_record::{self, TTxtRecord};
 use crate::Result;
 use libc::c_char;
 use std::cell::UnsafeCell;
+use std::collections::HashSet;
+use std::ops::Index;
+use std::str;
 
-pub struct AvahiTxtRecord(UnsafeCell<ManagedAvahiStringList>);
+pub struct AvahiTxtRecord {
+    list: UnsafeCell<ManagedAvahiStringList>,
+    duplicate_keys: Vec<String>,
+}
 
 impl TTxtRecord for AvahiTxtRecord {
     fn new() -> Self {
-        Self(UnsafeCell::default())
+        Self::from(ManagedAvahiStringList::new())
+    }
+
+    fn from_pairs(pairs: &[(&str, &str)]) -> Result<Self> {
+        for (key, value) in pairs {
+            txt_record::check_entry_length(key, value.as_bytes())?;
+        }
+
+        // a repeated key replaces the earlier value, which the list would keep
+        let mut keys = HashSet::new();
+
+        if !pairs.iter().all(|(key, _)| keys.insert(*key)) {
+            let mut record = Self::new();
+
+            for (key, value) in pairs {
+                record.insert(key, value)?;
+            }
+
+            return Ok(record);
+        }
+
+        let entries = pairs
+            .iter()
+            .map(|(key, value)| c_string!(format!("{}={}", key, value)))
+            .collect::<Vec<_>>();
+
+        let array = entries
+            .iter()
+            .map(|e| e.as_ptr() as *const c_char)
+            .collect::<Vec<_>>();
+
+        Ok(Self::from(unsafe {
+            ManagedAvahiStringList::new_from_array(&array)
+        }))
+    }
+
+    fn from_bytes(data: &[u8]) -> Result<Self> {
+        let (entries, duplicate_keys) = txt_record::parse_entries(data)?;
+        let mut list = ManagedAvahiStringList::new();
+
+        for entry in entries {
+            unsafe { list.add_arbitrary(entry.as_ptr(), entry.len()) };
+        }
+
+        Ok(Self {
+            list: UnsafeCell::new(list),
+            duplicate_keys,
+        })
+    }
+
+    fn duplicate_keys(&self) -> Vec<String> {
+        self.duplicate_keys.clone()
     }
 
     fn insert(&mut self, key: &str, value: &str) -> Result<()> {
+        txt_record::check_entry_length(key, value.as_bytes())?;
+
+        // replacing a value moves the key to the end, like `TXTRecordSetValue()` on Bonjour
+        self.remove(key);
+
         let c_key = c_string!(key);
         let c_value = c_string!(value);
 
@@ -26,6 +88,23 @@ impl TTxtRecord for AvahiTxtRecord {
         Ok(())
     }
 
+    fn insert_bytes(&mut self, key: &str, value: &[u8]) -> Result<()> {
+        txt_record::check_entry_length(key, value)?;
+
+        self.remove(key);
+
+        let c_key = c_string!(key);
+
+        unsafe {
+            self.inner_mut().add_pair_arbitrary(
+                c_key.as_ptr() as *const c_char,
+                value.as_ptr(),
+                value.len(),
+            );
+        }
+        Ok(())
+    }
+
     fn get(&self, key: &str) -> Option<String> {
         let c_str = c_string!(key);
         unsafe {
@@ -39,23 +118,24 @@ impl TTxtRecord for AvahiTxtRecord {
     }
 
     fn remove(&mut self, key: &str) -> Option<String> {
+        if !self.contains_key(key) {
+            return None;
+        }
+
+        let prev = self.get(key);
         let mut list = ManagedAvahiStringList::new();
-        let mut map = self.to_map();
-        let prev = map.remove(key);
-
-        for (key, value) in map {
-            let c_key = c_string!(key);
-            let c_value = c_string!(value);
-
-            unsafe {
-                list.add_pair(
-                    c_key.as_ptr() as *const c_char,
-                    c_value.as_ptr() as *const c_char,
-                );
+        let mut nodes = Iter::new(self.inner_mut().head());
+
+        // re-adding the remaining entries oldest first keeps their order
+        while let Some(node) = nodes.next_node() {
+            let text = node.text();
+
+            if split_entry(text).0 != key.as_bytes() {
+                unsafe { list.add_arbitrary(text.as_ptr(), text.len()) };
             }
         }
 
-        self.0 = UnsafeCell::new(list);
+        self.list = UnsafeCell::new(list);
 
         prev
     }
@@ -84,28 +164,64 @@ impl TTxtRecord for AvahiTxtRecord {
     fn values<'a>(&'a self) -> Box<dyn Iterator<Item = String> + 'a> {
         Box::new(Values(Iter::new(self.inner_mut().head())))
     }
+
+    fn iter_bytes<'a>(&'a self) -> Box<dyn Iterator<Item = (String, &'a [u8])> + 'a> {
+        Box::new(BytesIter(Iter::new(self.inner_mut().head())))
+    }
 }
 
 impl AvahiTxtRecord {
     #[allow(clippy::mut_from_ref)]
     fn inner_mut(&self) -> &mut ManagedAvahiStringList {
-        unsafe { &mut *self.0.get() }
+        unsafe { &mut *self.list.get() }
     }
 
     pub(crate) fn inner(&self) -> &ManagedAvahiStringList {
-        unsafe { &*self.0.get() }
+        unsafe { &*self.list.get() }
+    }
+}
+
+/// Returns the value at the specified key.
+///
+/// # Panics
+///
+/// Panics if the key does not exist or its value is not valid UTF-8. Use [`TTxtRecord::get()`]
+/// for fallible access.
+///
+/// [`TTxtRecord::get()`]: ../../txt_record/trait.TTxtRecord.html#tymethod.get
+impl Index<&str> for AvahiTxtRecord {
+    type Output = str;
+
+    fn index(&self, key: &str) -> &str {
+        let c_str = c_string!(key);
+
+        let text = unsafe { self.inner_mut().find(c_str.as_ptr() as *const c_char) }
+            .unwrap_or_else(|| panic!("TxtRecord has no key {:?}", key))
+            .text();
+
+        // a key without a value is stored without the `=`
+        let value = text.get(key.len() + 1..).unwrap_or_default();
+
+        str::from_utf8(value)
+            .unwrap_or_else(|_| panic!("TxtRecord value at key {:?} is not valid UTF-8", key))
     }
 }
 
 impl From<ManagedAvahiStringList> for AvahiTxtRecord {
     fn from(list: ManagedAvahiStringList) -> Self {
-        Self(UnsafeCell::new(list))
+        Self {
+            list: UnsafeCell::new(list),
+            duplicate_keys: Vec::new(),
+        }
     }
 }
 
 impl Clone for AvahiTxtRecord {
     fn clone(&self) -> Self {
-        Self::from(self.inner().clone())
+        Self {
+            list: UnsafeCell::new(self.inner().clone()),
+            duplicate_keys: self.duplicate_keys.clone(),
+        }
     }
 }
 
@@ -115,13 +231,33 @@ impl PartialEq for AvahiTxtRecord {
     }
 }
 
+/// An `Iterator` over the entries of an `AvahiStringList` in insertion order.
+///
+/// Avahi prepends new entries to the list, so the nodes are collected up front and visited from
+/// the tail.
 pub struct Iter<'a> {
-    node: Option<AvahiStringListNode<'a>>,
+    nodes: Vec<AvahiStringListNode<'a>>,
 }
 
 impl<'a> Iter<'a> {
-    pub fn new(node: AvahiStringListNode<'a>) -> Self {
-        Self { node: Some(node) }
+    pub fn new(head: AvahiStringListNode<'a>) -> Self {
+        let mut nodes = Vec::new();
+        let mut node = Some(head);
+
+        while let Some(n) = node {
+            if n.list().is_null() {
+                break;
+            }
+
+            node = n.next();
+            nodes.push(n);
+        }
+
+        Self { nodes }
+    }
+
+    fn next_node(&mut self) -> Option<AvahiStringListNode<'a>> {
+        self.nodes.pop()
     }
 }
 
@@ -129,14 +265,8 @@ impl Iterator for Iter<'_> {
     type Item = (String, String);
 
     fn next(&mut self) -> Option<Self::Item> {
-        let mut n = self.node.take()?;
-
-        if n.list().is_null() {
-            return None;
-        }
-
+        let mut n = self.next_node()?;
         let pair = n.get_pair();
-        self.node = n.next();
 
         Some((
             pair.key().as_str().unwrap().to_string(),
@@ -164,3 +294,61 @@ impl Iterator for Values<'_> {
         self.0.next().map(|e| e.1)
     }
 }
+
+pub struct BytesIter<'a>(Iter<'a>);
+
+impl<'a> Iterator for BytesIter<'a> {
+    type Item = (String, &'a [u8]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let n = self.0.next_node()?;
+        let (key, value) = split_entry(n.text());
+
+        Some((String::from_utf8_lossy(key).into_owned(), value))
+    }
+}
+
+/// Splits the raw text of an entry into its key and value.
+fn split_entry(text: &[u8]) -> (&[u8], &[u8]) {
+    // a key without a value is stored without the `=`
+    match text.iter().position(|b| *b == b'=') {
+        Some(i) => (&text[..i], &text[i + 1..]),
+        None => (text, &[][..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libc::c_void;
+
+    #[test]
+    fn serialize_emits_insertion_order() {
+        crate::tests::setup();
+
+        let mut record = AvahiTxtRecord::new();
+        record.insert("version", "1").unwrap();
+        record.insert("b", "2").unwrap();
+        record.insert("a", "3").unwrap();
+
+        assert_eq!(record.inner().serialize(), b"\x09version=1\x03b=2\x03a=3");
+        assert_eq!(
+            record.inner().to_string().as_str().unwrap(),
+            "\"version=1\" \"b=2\" \"a=3\""
+        );
+    }
+
+    #[test]
+    fn parsed_record_iterates_in_wire_order() {
+        crate::tests::setup();
+
+        let data = b"\x09version=1\x03b=2\x03a=3";
+        let list =
+            unsafe { ManagedAvahiStringList::parse(data.as_ptr() as *const c_void, data.len()) }
+                .unwrap();
+
+        let record = AvahiTxtRecord::from(list);
+
+        assert_eq!(record.keys().collect::<Vec<_>>(), vec!["version", "b", "a"]);
+    }
+}
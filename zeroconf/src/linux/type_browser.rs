@@ -0,0 +1,206 @@
+//! Avahi implementation for cross-platform service type browser
+
+use super::avahi_util;
+use super::client::{ManagedAvahiClient, ManagedAvahiClientParams};
+use super::flags::LookupFlags;
+use super::poll::ManagedAvahiSimplePoll;
+use super::raw_type_browser::{
+    ManagedAvahiServiceTypeBrowser, ManagedAvahiServiceTypeBrowserParams,
+};
+use crate::event_loop::TeardownGuard;
+use crate::ffi::{self, c_str, AsRaw, FromRaw};
+use crate::prelude::*;
+use crate::{
+    EventLoop, NetworkInterface, NetworkProtocol, Result, ServiceType,
+    ServiceTypeDiscoveredCallback,
+};
+use avahi_sys::{
+    AvahiBrowserEvent, AvahiClient, AvahiClientFlags, AvahiClientState, AvahiIfIndex,
+    AvahiLookupResultFlags, AvahiProtocol, AvahiServiceTypeBrowser,
+};
+use libc::{c_char, c_void};
+use std::any::Any;
+use std::collections::HashSet;
+use std::rc::Rc;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::{fmt, ptr};
+
+/// Browses for service types using Avahi's service type browser, which issues the DNS-SD
+/// meta-query on behalf of the client.
+#[derive(Debug)]
+pub struct AvahiMdnsServiceTypeBrowser {
+    client: Option<Rc<ManagedAvahiClient>>,
+    poll: Option<Rc<ManagedAvahiSimplePoll>>,
+    interface: NetworkInterface,
+    protocol: AvahiProtocol,
+    context: Box<AvahiServiceTypeBrowserContext>,
+    teardown: Option<TeardownGuard>,
+}
+
+impl TServiceTypeBrowser for AvahiMdnsServiceTypeBrowser {
+    type EventLoop<'a> = EventLoop<'a>;
+
+    fn new() -> Self {
+        Self {
+            client: None,
+            poll: None,
+            interface: NetworkInterface::Unspec,
+            protocol: avahi_sys::AVAHI_PROTO_UNSPEC,
+            context: Box::default(),
+            teardown: None,
+        }
+    }
+
+    fn set_network_interface(&mut self, interface: NetworkInterface) {
+        self.interface = interface;
+    }
+
+    fn network_interface(&self) -> NetworkInterface {
+        self.interface
+    }
+
+    fn set_network_protocol(&mut self, protocol: NetworkProtocol) {
+        self.protocol = avahi_util::protocol(protocol);
+    }
+
+    fn network_protocol(&self) -> NetworkProtocol {
+        avahi_util::protocol_from_raw(self.protocol)
+    }
+
+    fn set_service_type_discovered_callback(
+        &mut self,
+        service_type_discovered_callback: Box<ServiceTypeDiscoveredCallback>,
+    ) {
+        self.context.service_type_discovered_callback = Some(service_type_discovered_callback);
+    }
+
+    fn set_context(&mut self, context: Box<dyn Any>) {
+        self.context.user_context = Some(Arc::from(context));
+    }
+
+    fn context(&self) -> Option<&dyn Any> {
+        self.context.user_context.as_ref().map(|c| c.as_ref())
+    }
+
+    fn browse_types(&mut self) -> Result<EventLoop> {
+        debug!("Browsing service types: {:?}", self);
+
+        let interface_index = avahi_util::interface_index(self.interface)?;
+
+        self.poll = Some(Rc::new(ManagedAvahiSimplePoll::new()?));
+
+        self.client = Some(Rc::new(ManagedAvahiClient::new(
+            ManagedAvahiClientParams::builder()
+                .poll(Rc::clone(self.poll.as_ref().unwrap()))
+                .flags(AvahiClientFlags(0))
+                .callback(Some(client_callback))
+                .userdata(self.context.as_raw())
+                .build()?,
+        )?));
+
+        self.context.discovered.clear();
+
+        self.context.browser = Some(ManagedAvahiServiceTypeBrowser::new(
+            ManagedAvahiServiceTypeBrowserParams::builder()
+                .interface(interface_index)
+                .protocol(self.protocol)
+                .domain(ptr::null_mut())
+                .flags(LookupFlags::empty())
+                .callback(Some(browse_callback))
+                .userdata(self.context.as_raw())
+                .client(Rc::clone(self.client.as_ref().unwrap()))
+                .build()?,
+        )?);
+
+        let context: *mut AvahiServiceTypeBrowserContext = &mut *self.context;
+        let poll = self.poll.as_ref().unwrap();
+
+        // the context is boxed, so it stays at the same address until the browser is dropped,
+        // which unregisters the teardown
+        self.teardown = Some(
+            poll.loop_state()
+                .register_teardown(move || unsafe { (*context).browser = None }),
+        );
+
+        Ok(EventLoop::new(self.poll.as_ref().unwrap().clone()))
+    }
+}
+
+impl Drop for AvahiMdnsServiceTypeBrowser {
+    fn drop(&mut self) {
+        // browser must be freed first
+        self.context.browser = None;
+    }
+}
+
+#[derive(Default, FromRaw, AsRaw)]
+struct AvahiServiceTypeBrowserContext {
+    browser: Option<ManagedAvahiServiceTypeBrowser>,
+    discovered: HashSet<ServiceType>,
+    service_type_discovered_callback: Option<Box<ServiceTypeDiscoveredCallback>>,
+    user_context: Option<Arc<dyn Any>>,
+}
+
+impl AvahiServiceTypeBrowserContext {
+    fn invoke_callback(&self, result: Result<ServiceType>) {
+        if let Some(f) = &self.service_type_discovered_callback {
+            ffi::catch_callback_panic("service type discovered callback", || {
+                f(result, self.user_context.clone())
+            });
+        } else {
+            warn!("attempted to invoke service type browser callback but none was set");
+        }
+    }
+}
+
+impl fmt::Debug for AvahiServiceTypeBrowserContext {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("AvahiServiceTypeBrowserContext")
+            .field("browser", &self.browser)
+            .field("discovered", &self.discovered)
+            .finish()
+    }
+}
+
+unsafe extern "C" fn browse_callback(
+    _browser: *mut AvahiServiceTypeBrowser,
+    _interface: AvahiIfIndex,
+    _protocol: AvahiProtocol,
+    event: AvahiBrowserEvent,
+    kind: *const c_char,
+    _domain: *const c_char,
+    _flags: AvahiLookupResultFlags,
+    userdata: *mut c_void,
+) {
+    let context = AvahiServiceTypeBrowserContext::from_raw(userdata);
+
+    match event {
+        avahi_sys::AvahiBrowserEvent_AVAHI_BROWSER_NEW => {
+            match ServiceType::from_str(c_str::raw_to_str(kind)) {
+                Ok(service_type) => {
+                    if context.discovered.insert(service_type.clone()) {
+                        context.invoke_callback(Ok(service_type));
+                    }
+                }
+                Err(e) => context.invoke_callback(Err(e)),
+            }
+        }
+        avahi_sys::AvahiBrowserEvent_AVAHI_BROWSER_FAILURE => {
+            context.invoke_callback(Err("service type browser failure".into()))
+        }
+        _ => {}
+    };
+}
+
+unsafe extern "C" fn client_callback(
+    _client: *mut AvahiClient,
+    state: AvahiClientState,
+    userdata: *mut c_void,
+) {
+    if let avahi_sys::AvahiClientState_AVAHI_CLIENT_FAILURE = state {
+        warn!("Avahi client failure: the daemon has disconnected or restarted");
+        AvahiServiceTypeBrowserContext::from_raw(userdata)
+            .invoke_callback(Err("client failure".into()));
+    }
+}
@@ -0,0 +1,75 @@
+//! Rust friendly `AvahiWatch` wrappers/helpers
+
+use std::rc::Rc;
+
+use super::poll::ManagedAvahiSimplePoll;
+use crate::Result;
+use avahi_sys::{
+    avahi_simple_poll_get, AvahiPoll, AvahiWatch, AvahiWatchCallback, AvahiWatchEvent,
+};
+use libc::{c_int, c_void};
+
+/// Wraps the `AvahiWatch` type from the raw Avahi bindings.
+///
+/// This struct registers a new `*mut AvahiWatch` on the poll's `AvahiPoll` API when
+/// `ManagedAvahiWatch::new()` is invoked and calls the API's function responsible for freeing the
+/// watch on `trait Drop`. The callback is invoked while the poll is iterated.
+#[derive(Debug)]
+pub struct ManagedAvahiWatch {
+    inner: *mut AvahiWatch,
+    api: *const AvahiPoll,
+    _poll: Rc<ManagedAvahiSimplePoll>,
+}
+
+impl ManagedAvahiWatch {
+    /// Initializes the underlying `*mut AvahiWatch` and verifies it was created;
+    /// returning `Err(String)` if unsuccessful.
+    pub fn new(
+        ManagedAvahiWatchParams {
+            poll,
+            fd,
+            event,
+            callback,
+            userdata,
+        }: ManagedAvahiWatchParams,
+    ) -> Result<Self> {
+        let api = unsafe { avahi_simple_poll_get(poll.inner()) };
+
+        let inner = match unsafe { (*api).watch_new } {
+            Some(watch_new) => unsafe { watch_new(api, fd, event, callback, userdata) },
+            None => return Err("AvahiPoll does not support watches".into()),
+        };
+
+        if inner.is_null() {
+            Err("could not initialize Avahi watch".into())
+        } else {
+            Ok(Self {
+                inner,
+                api,
+                _poll: poll,
+            })
+        }
+    }
+}
+
+impl Drop for ManagedAvahiWatch {
+    fn drop(&mut self) {
+        if let Some(watch_free) = unsafe { (*self.api).watch_free } {
+            unsafe { watch_free(self.inner) };
+        }
+    }
+}
+
+/// Holds parameters for initializing a new `ManagedAvahiWatch` with `ManagedAvahiWatch::new()`.
+///
+/// See [`AvahiPoll`] for more information about these parameters.
+///
+/// [`AvahiPoll`]: https://avahi.org/doxygen/html/structAvahiPoll.html
+#[derive(Builder, BuilderDelegate)]
+pub struct ManagedAvahiWatchParams {
+    poll: Rc<ManagedAvahiSimplePoll>,
+    fd: c_int,
+    event: AvahiWatchEvent,
+    callback: AvahiWatchCallback,
+    userdata: *mut c_void,
+}
@@ -1,10 +1,14 @@
 //! Utilities related to Bonjour
 
+use std::net::IpAddr;
 use std::{ffi::CString, str::FromStr};
 
 use super::constants;
-use crate::{check_valid_characters, lstrip_underscore, NetworkInterface, Result, ServiceType};
-use bonjour_sys::DNSServiceErrorType;
+use crate::{
+    check_valid_characters, lstrip_underscore, DiscoveryMode, DomainBrowserKind, InterfaceIndex,
+    NetworkInterface, NetworkProtocol, Result, Scope, ServiceType,
+};
+use bonjour_sys::{DNSServiceErrorType, DNSServiceFlags, DNSServiceProtocol};
 
 /// Normalizes the specified domain `&str` to conform to a standard enforced by this crate.
 ///
@@ -18,21 +22,118 @@ pub fn normalize_domain(domain: &str) -> String {
     }
 }
 
+/// Returns the `DNSServiceEnumerateDomains()` flags that enumerate the specified kind of domains.
+pub fn domain_enumeration_flags(kind: DomainBrowserKind) -> DNSServiceFlags {
+    match kind {
+        DomainBrowserKind::Browse => bonjour_sys::kDNSServiceFlagsBrowseDomains,
+        DomainBrowserKind::Register => bonjour_sys::kDNSServiceFlagsRegistrationDomains,
+    }
+}
+
 /// Converts the specified [`NetworkInterface`] to the Bonjour expected value.
 ///
 /// [`NetworkInterface`]: ../../enum.NetworkInterface.html
 pub fn interface_index(interface: NetworkInterface) -> u32 {
-    match interface {
-        NetworkInterface::Unspec => constants::BONJOUR_IF_UNSPEC,
-        NetworkInterface::AtIndex(i) => i,
+    match InterfaceIndex::from(interface) {
+        InterfaceIndex::ANY => constants::BONJOUR_IF_UNSPEC,
+        InterfaceIndex(i) => i,
     }
 }
 
 /// Converts the specified Bonjour interface index to a [`NetworkInterface`].
+///
+/// Bonjour uses `0` to mean "any interface", which is reported as `NetworkInterface::Unspec`,
+/// the same as [`InterfaceIndex::ANY`].
+///
+/// [`NetworkInterface`]: ../../enum.NetworkInterface.html
+/// [`InterfaceIndex::ANY`]: ../../struct.InterfaceIndex.html#associatedconstant.ANY
 pub fn interface_from_index(index: u32) -> NetworkInterface {
     match index {
         constants::BONJOUR_IF_UNSPEC => NetworkInterface::Unspec,
-        _ => NetworkInterface::AtIndex(index),
+        _ => NetworkInterface::AtIndex(InterfaceIndex(index)),
+    }
+}
+
+/// Converts the specified [`NetworkProtocol`] to the Bonjour expected value.
+///
+/// [`NetworkProtocol`]: ../../enum.NetworkProtocol.html
+pub fn protocol(protocol: NetworkProtocol) -> DNSServiceProtocol {
+    match protocol {
+        NetworkProtocol::Unspec => constants::BONJOUR_PROTO_UNSPEC,
+        NetworkProtocol::Ipv4 => constants::BONJOUR_PROTO_IPV4,
+        NetworkProtocol::Ipv6 => constants::BONJOUR_PROTO_IPV6,
+    }
+}
+
+/// Converts the specified Bonjour protocol to a [`NetworkProtocol`]. Values requesting both
+/// address families, or any unknown value, are treated as unspecified.
+///
+/// [`NetworkProtocol`]: ../../enum.NetworkProtocol.html
+pub fn protocol_from_raw(protocol: DNSServiceProtocol) -> NetworkProtocol {
+    match protocol {
+        constants::BONJOUR_PROTO_IPV4 => NetworkProtocol::Ipv4,
+        constants::BONJOUR_PROTO_IPV6 => NetworkProtocol::Ipv6,
+        _ => NetworkProtocol::Unspec,
+    }
+}
+
+/// Converts the specified `*const sockaddr` returned by Bonjour to an `IpAddr`.
+///
+/// # Safety
+/// This function is unsafe because of raw pointer dereference.
+pub unsafe fn ip_address(address: *const bonjour_sys::sockaddr) -> IpAddr {
+    assert_not_null!(address);
+
+    if i32::from((*(address as *const libc::sockaddr)).sa_family) == libc::AF_INET6 {
+        let address = address as *const libc::sockaddr_in6;
+        IpAddr::from((*address).sin6_addr.s6_addr)
+    } else {
+        // on macOS the bytes are swapped for the ip
+        let address = address as *const libc::sockaddr_in;
+        IpAddr::from((*address).sin_addr.s_addr.to_le_bytes())
+    }
+}
+
+/// Returns the name of the Bonjour constant for the specified error code, as declared in
+/// `dns_sd.h` (e.g. `"kDNSServiceErr_NameConflict"`), or `"UNKNOWN"` if there is none.
+pub fn error_name(code: DNSServiceErrorType) -> &'static str {
+    match code {
+        0 => "kDNSServiceErr_NoError",
+        -65537 => "kDNSServiceErr_Unknown",
+        -65538 => "kDNSServiceErr_NoSuchName",
+        -65539 => "kDNSServiceErr_NoMemory",
+        -65540 => "kDNSServiceErr_BadParam",
+        -65541 => "kDNSServiceErr_BadReference",
+        -65542 => "kDNSServiceErr_BadState",
+        -65543 => "kDNSServiceErr_BadFlags",
+        -65544 => "kDNSServiceErr_Unsupported",
+        -65545 => "kDNSServiceErr_NotInitialized",
+        -65547 => "kDNSServiceErr_AlreadyRegistered",
+        -65548 => "kDNSServiceErr_NameConflict",
+        -65549 => "kDNSServiceErr_Invalid",
+        -65550 => "kDNSServiceErr_Firewall",
+        -65551 => "kDNSServiceErr_Incompatible",
+        -65552 => "kDNSServiceErr_BadInterfaceIndex",
+        -65553 => "kDNSServiceErr_Refused",
+        -65554 => "kDNSServiceErr_NoSuchRecord",
+        -65555 => "kDNSServiceErr_NoAuth",
+        -65556 => "kDNSServiceErr_NoSuchKey",
+        -65557 => "kDNSServiceErr_NATTraversal",
+        -65558 => "kDNSServiceErr_DoubleNAT",
+        -65559 => "kDNSServiceErr_BadTime",
+        -65560 => "kDNSServiceErr_BadSig",
+        -65561 => "kDNSServiceErr_BadKey",
+        -65562 => "kDNSServiceErr_Transient",
+        -65563 => "kDNSServiceErr_ServiceNotRunning",
+        -65564 => "kDNSServiceErr_NATPortMappingUnsupported",
+        -65565 => "kDNSServiceErr_NATPortMappingDisabled",
+        -65566 => "kDNSServiceErr_NoRouter",
+        -65567 => "kDNSServiceErr_PollingMode",
+        -65568 => "kDNSServiceErr_Timeout",
+        -65569 => "kDNSServiceErr_DefunctConnection",
+        -65570 => "kDNSServiceErr_PolicyDenied",
+        -65571 => "kDNSServiceErr_NotPermitted",
+        _ => "UNKNOWN",
     }
 }
 
@@ -41,7 +142,7 @@ pub fn sys_exec<F: FnOnce() -> DNSServiceErrorType>(func: F, message: &str) -> R
     let err = func();
 
     if err < 0 {
-        Err(format!("{} (code: {})", message, err).into())
+        Err(format!("{} (code: {}, {})", message, err, error_name(err)).into())
     } else {
         Ok(())
     }
@@ -78,11 +179,111 @@ pub fn parse_regtype(regtype: &str) -> Result<ServiceType> {
     ServiceType::with_sub_types(service_type.name(), service_type.protocol(), sub_types)
 }
 
+/// Returns the `DNSServiceFlags` used by browsers for the specified [`DiscoveryMode`] and
+/// [`Scope`].
+///
+/// [`DiscoveryMode`]: ../../enum.DiscoveryMode.html
+/// [`Scope`]: ../../enum.Scope.html
+pub fn discovery_flags(mode: DiscoveryMode, scope: Scope) -> DNSServiceFlags {
+    let flags = match mode {
+        DiscoveryMode::Fast => bonjour_sys::kDNSServiceFlagsForceMulticast,
+        DiscoveryMode::Balanced => 0,
+        DiscoveryMode::PowerSaving => constants::BONJOUR_BACKGROUND_TRAFFIC_CLASS_FLAGS,
+    };
+
+    match scope {
+        Scope::LinkLocal => flags | bonjour_sys::kDNSServiceFlagsForceMulticast,
+        Scope::WideArea => flags & !bonjour_sys::kDNSServiceFlagsForceMulticast,
+        Scope::Both => flags,
+    }
+}
+
+/// Returns the `DNSServiceFlags` used to resolve services and look up their addresses for the
+/// specified [`Scope`].
+///
+/// [`Scope`]: ../../enum.Scope.html
+pub fn lookup_flags(scope: Scope) -> DNSServiceFlags {
+    match scope {
+        Scope::LinkLocal | Scope::Both => bonjour_sys::kDNSServiceFlagsForceMulticast,
+        Scope::WideArea => 0,
+    }
+}
+
+/// Returns the domain to browse or register in for the specified [`Scope`], if it restricts the
+/// domain.
+///
+/// [`Scope`]: ../../enum.Scope.html
+pub fn scope_domain(scope: Scope) -> Option<CString> {
+    match scope {
+        Scope::LinkLocal => Some(c_string!("local.")),
+        Scope::WideArea | Scope::Both => None,
+    }
+}
+
+/// Parses a result of the DNS-SD meta-query into a `ServiceType`.
+///
+/// Bonjour reports each service type as an instance `name` such as `_http` with a `regtype` such
+/// as `_tcp.local.`, where the first label of `regtype` holds the protocol.
+pub fn parse_meta_query_result(name: &str, regtype: &str) -> Result<ServiceType> {
+    let protocol = regtype.split('.').next().unwrap_or_default();
+    ServiceType::from_str(&format!("{}.{}", name, protocol))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::ServiceType;
 
+    #[test]
+    fn error_name_returns_constant_name() {
+        assert_eq!(error_name(0), "kDNSServiceErr_NoError");
+        assert_eq!(error_name(-65548), "kDNSServiceErr_NameConflict");
+        assert_eq!(
+            error_name(constants::BONJOUR_ERR_SERVICE_NOT_RUNNING),
+            "kDNSServiceErr_ServiceNotRunning"
+        );
+        assert_eq!(error_name(-65546), "UNKNOWN");
+    }
+
+    #[test]
+    fn interface_index_round_trips() {
+        assert_eq!(interface_index(NetworkInterface::Unspec), 0);
+        assert_eq!(interface_from_index(0), NetworkInterface::Unspec);
+        assert_eq!(
+            interface_from_index(interface_index(NetworkInterface::AtIndex(InterfaceIndex(
+                u32::MAX
+            )))),
+            NetworkInterface::AtIndex(InterfaceIndex(u32::MAX))
+        );
+    }
+
+    #[test]
+    fn protocol_round_trips() {
+        for p in &[
+            NetworkProtocol::Unspec,
+            NetworkProtocol::Ipv4,
+            NetworkProtocol::Ipv6,
+        ] {
+            assert_eq!(protocol_from_raw(protocol(*p)), *p);
+        }
+    }
+
+    #[test]
+    fn protocol_from_raw_returns_unspec_for_both() {
+        assert_eq!(
+            protocol_from_raw(constants::BONJOUR_PROTO_IPV4 | constants::BONJOUR_PROTO_IPV6),
+            NetworkProtocol::Unspec
+        );
+    }
+
+    #[test]
+    fn parse_meta_query_result_success() {
+        assert_eq!(
+            parse_meta_query_result("_http", "_tcp.local.").unwrap(),
+            ServiceType::new("http", "tcp").unwrap()
+        );
+    }
+
     #[test]
     fn parse_regtype_success() {
         assert_eq!(
@@ -143,9 +344,32 @@ mod tests {
         assert_eq!(interface_index(NetworkInterface::Unspec), 0);
     }
 
+    #[test]
+    fn interface_index_any_maps_to_bonjour_if_unspec() {
+        assert_eq!(
+            interface_index(NetworkInterface::AtIndex(InterfaceIndex::ANY)),
+            constants::BONJOUR_IF_UNSPEC
+        );
+    }
+
     #[test]
     fn network_interface_at_index_maps_to_index() {
-        assert_eq!(interface_index(NetworkInterface::AtIndex(42)), 42);
+        assert_eq!(
+            interface_index(NetworkInterface::AtIndex(InterfaceIndex(42))),
+            42
+        );
+    }
+
+    #[test]
+    fn domain_enumeration_flags_maps_kinds() {
+        assert_eq!(
+            domain_enumeration_flags(DomainBrowserKind::Browse),
+            bonjour_sys::kDNSServiceFlagsBrowseDomains
+        );
+        assert_eq!(
+            domain_enumeration_flags(DomainBrowserKind::Register),
+            bonjour_sys::kDNSServiceFlagsRegistrationDomains
+        );
     }
 
     #[test]
@@ -160,4 +384,30 @@ mod tests {
     fn normalize_domain_does_not_remove_trailing_dot_if_not_present() {
         assert_eq!(normalize_domain("foo.bar.baz"), String::from("foo.bar.baz"));
     }
+
+    #[test]
+    fn discovery_flags_returns_flags_for_mode() {
+        assert_eq!(
+            discovery_flags(DiscoveryMode::Fast, Scope::Both),
+            bonjour_sys::kDNSServiceFlagsForceMulticast
+        );
+        assert_eq!(discovery_flags(DiscoveryMode::Balanced, Scope::Both), 0);
+        assert_eq!(
+            discovery_flags(DiscoveryMode::PowerSaving, Scope::Both),
+            constants::BONJOUR_BACKGROUND_TRAFFIC_CLASS_FLAGS
+        );
+    }
+
+    #[test]
+    fn discovery_flags_restricts_to_scope() {
+        assert_eq!(
+            discovery_flags(DiscoveryMode::Balanced, Scope::LinkLocal),
+            bonjour_sys::kDNSServiceFlagsForceMulticast
+        );
+        assert_eq!(discovery_flags(DiscoveryMode::Fast, Scope::WideArea), 0);
+        assert_eq!(
+            discovery_flags(DiscoveryMode::PowerSaving, Scope::WideArea),
+            constants::BONJOUR_BACKGROUND_TRAFFIC_CLASS_FLAGS
+        );
+    }
 }
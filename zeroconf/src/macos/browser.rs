@@ -1,38 +1,80 @@
 //! Bonjour implementation for cross-platform browser
 
 use super::service_ref::{
-    BrowseServicesParams, GetAddressInfoParams, ManagedDNSServiceRef, ServiceResolveParams,
+    BrowseServicesParams, GetAddressInfoParams, ManagedDNSServiceRef, QueryRecordParams,
+    ServiceResolveParams,
 };
-use super::txt_record_ref::ManagedTXTRecordRef;
 use super::{bonjour_util, constants};
-use crate::ffi::{c_str, AsRaw, FromRaw};
+use crate::browser::{self, CallbackThrottle, IgnoredServices, TxtRequirements};
+use crate::error::Error;
+use crate::event_loop::{ActiveGuard, ActiveKind, LoopState, TeardownGuard, TimerGuard};
+use crate::ffi::c_str::{self, AsCChars};
+use crate::ffi::{self, AsRaw, FromRaw, UnwrapOrNull};
 use crate::prelude::*;
-use crate::{EventLoop, NetworkInterface, Result, ServiceType, TxtRecord};
-use crate::{ServiceDiscoveredCallback, ServiceDiscovery};
-use bonjour_sys::{DNSServiceErrorType, DNSServiceFlags, DNSServiceRef};
-use libc::{c_char, c_uchar, c_void, sockaddr_in};
+use crate::service;
+use crate::{
+    BrowseEvent, CancellationToken, ClientState, ClientStateCallback, DiscoveryMode, EventLoop,
+    NetworkInterface, NetworkProtocol, OnInvalidUtf8, Result, Scope, ServiceAddress, ServiceType,
+    TxtRecord,
+};
+use crate::{ServiceBrowse, ServiceBrowsedCallback, ServiceDiscoveredCallback, ServiceDiscovery};
+use bonjour_sys::{DNSServiceErrorType, DNSServiceFlags, DNSServiceProtocol, DNSServiceRef};
+use libc::{c_char, c_uchar, c_void};
 use std::any::Any;
-use std::ffi::CString;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ffi::{CStr, CString};
 use std::fmt::{self, Formatter};
 use std::net::IpAddr;
 use std::ptr;
+use std::rc::Rc;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
+#[cfg(feature = "diagnostics")]
+use std::time::Instant;
 
 #[derive(Debug)]
 pub struct BonjourMdnsBrowser {
     service: Arc<Mutex<ManagedDNSServiceRef>>,
+    domain_services: Arc<Mutex<Vec<ManagedDNSServiceRef>>>,
+    service_type: ServiceType,
     kind: CString,
+    domains: Vec<String>,
     interface_index: u32,
+    discovery_mode: DiscoveryMode,
+    resolve_all_txt_records: bool,
+    ipv6_fallback: bool,
+    restart_on_network_change: bool,
+    long_lived: bool,
+    max_concurrent_resolves: usize,
     context: Box<BonjourBrowserContext>,
+    loop_state: Rc<LoopState>,
+    teardown: Option<TeardownGuard>,
+    // declared last so that it is dropped after the browser that shares it
+    connection: Option<Arc<Mutex<ManagedDNSServiceRef>>>,
 }
 
 impl TMdnsBrowser for BonjourMdnsBrowser {
+    type EventLoop<'a> = EventLoop<'a>;
+
     fn new(service_type: ServiceType) -> Self {
         Self {
             service: Arc::default(),
+            domain_services: Arc::default(),
             kind: bonjour_util::format_regtype(&service_type),
+            service_type,
+            domains: Vec::new(),
             interface_index: constants::BONJOUR_IF_UNSPEC,
+            discovery_mode: DiscoveryMode::default(),
+            resolve_all_txt_records: false,
+            ipv6_fallback: false,
+            restart_on_network_change: false,
+            long_lived: false,
+            max_concurrent_resolves: browser::DEFAULT_MAX_CONCURRENT_RESOLVES,
             context: Box::default(),
+            loop_state: Rc::default(),
+            teardown: None,
+            connection: None,
         }
     }
 
@@ -44,6 +86,14 @@ impl TMdnsBrowser for BonjourMdnsBrowser {
         bonjour_util::interface_from_index(self.interface_index)
     }
 
+    fn set_network_protocol(&mut self, protocol: NetworkProtocol) {
+        self.context.protocol = bonjour_util::protocol(protocol);
+    }
+
+    fn network_protocol(&self) -> NetworkProtocol {
+        bonjour_util::protocol_from_raw(self.context.protocol)
+    }
+
     fn set_service_discovered_callback(
         &mut self,
         service_discovered_callback: Box<ServiceDiscoveredCallback>,
@@ -51,6 +101,230 @@ impl TMdnsBrowser for BonjourMdnsBrowser {
         self.context.service_discovered_callback = Some(service_discovered_callback);
     }
 
+    fn set_service_browsed_callback(
+        &mut self,
+        service_browsed_callback: Box<ServiceBrowsedCallback>,
+    ) {
+        self.context.service_browsed_callback = Some(service_browsed_callback);
+    }
+
+    fn set_client_state_callback(&mut self, client_state_callback: Box<ClientStateCallback>) {
+        self.context.client_state_callback = Some(client_state_callback);
+    }
+
+    fn set_resolve_services(&mut self, resolve_services: bool) {
+        self.context.resolve_services = resolve_services;
+    }
+
+    fn resolve_services(&self) -> bool {
+        self.context.resolve_services
+    }
+
+    fn set_resolve_txt(&mut self, resolve_txt: bool) {
+        self.context.resolve_txt = resolve_txt;
+        self.context.required_txt.warn_if_txt_disabled(resolve_txt);
+    }
+
+    fn resolve_txt(&self) -> bool {
+        self.context.resolve_txt
+    }
+
+    fn set_resolve_all_txt_records(&mut self, resolve_all_txt_records: bool) {
+        // Bonjour only returns the first TXT record when resolving
+        self.resolve_all_txt_records = resolve_all_txt_records;
+    }
+
+    fn resolve_all_txt_records(&self) -> bool {
+        self.resolve_all_txt_records
+    }
+
+    fn set_resolve_all_addresses(&mut self, resolve_all_addresses: bool) {
+        self.context.resolve_all_addresses = resolve_all_addresses;
+    }
+
+    fn resolve_all_addresses(&self) -> bool {
+        self.context.resolve_all_addresses
+    }
+
+    fn set_coalesce_addresses(&mut self, coalesce_addresses: bool) {
+        self.context.coalesce_addresses = coalesce_addresses;
+    }
+
+    fn coalesce_addresses(&self) -> bool {
+        self.context.coalesce_addresses
+    }
+
+    fn set_ipv6_fallback(&mut self, ipv6_fallback: bool) {
+        // Bonjour does not report the protocol a resolution failed over
+        self.ipv6_fallback = ipv6_fallback;
+    }
+
+    fn ipv6_fallback(&self) -> bool {
+        self.ipv6_fallback
+    }
+
+    fn set_resolve_on_browse_interface(&mut self, resolve_on_browse_interface: bool) {
+        self.context.resolve_on_browse_interface = resolve_on_browse_interface;
+    }
+
+    fn resolve_on_browse_interface(&self) -> bool {
+        self.context.resolve_on_browse_interface
+    }
+
+    fn set_restart_on_network_change(&mut self, restart_on_network_change: bool) {
+        self.restart_on_network_change = restart_on_network_change;
+    }
+
+    fn restart_on_network_change(&self) -> bool {
+        self.restart_on_network_change
+    }
+
+    fn set_long_lived(&mut self, long_lived: bool) {
+        // `DNSServiceBrowse()` subscribes to wide-area domains whenever their server supports it
+        self.long_lived = long_lived;
+    }
+
+    fn long_lived(&self) -> bool {
+        self.long_lived
+    }
+
+    fn set_resolve_srv_priority(&mut self, resolve_srv_priority: bool) {
+        self.context.resolve_srv_priority = resolve_srv_priority;
+    }
+
+    fn resolve_srv_priority(&self) -> bool {
+        self.context.resolve_srv_priority
+    }
+
+    fn set_domains(&mut self, domains: Vec<String>) {
+        self.domains = browser::normalize_browse_domains(domains);
+    }
+
+    fn domains(&self) -> &[String] {
+        &self.domains
+    }
+
+    fn set_scope(&mut self, scope: Scope) {
+        self.context.scope = scope;
+    }
+
+    fn scope(&self) -> Scope {
+        self.context.scope
+    }
+
+    fn set_discovery_mode(&mut self, mode: DiscoveryMode) {
+        self.discovery_mode = mode;
+    }
+
+    fn discovery_mode(&self) -> DiscoveryMode {
+        self.discovery_mode
+    }
+
+    fn set_on_invalid_utf8(&mut self, on_invalid_utf8: OnInvalidUtf8) {
+        self.context.on_invalid_utf8 = on_invalid_utf8;
+    }
+
+    fn on_invalid_utf8(&self) -> OnInvalidUtf8 {
+        self.context.on_invalid_utf8
+    }
+
+    fn set_max_concurrent_resolves(&mut self, max_concurrent_resolves: usize) {
+        // Bonjour resolves browsed services one at a time
+        self.max_concurrent_resolves = max_concurrent_resolves.max(1);
+    }
+
+    fn max_concurrent_resolves(&self) -> usize {
+        self.max_concurrent_resolves
+    }
+
+    fn set_callback_rate_limit(&mut self, per_second: u32) {
+        self.context.throttle.get_mut().set_per_second(per_second);
+    }
+
+    fn callback_rate_limit(&self) -> u32 {
+        self.context.throttle.borrow().per_second()
+    }
+
+    fn ignore(&mut self, service_key: (String, String, String)) {
+        self.context.ignored.insert(service_key);
+    }
+
+    fn unignore(&mut self, service_key: &(String, String, String)) {
+        self.context.ignored.remove(service_key);
+    }
+
+    fn is_ignored(&self, service_key: &(String, String, String)) -> bool {
+        self.context.ignored.contains(service_key)
+    }
+
+    fn require_txt(&mut self, key: &str, value: &str) {
+        self.context.required_txt.insert(key, value);
+        self.context
+            .required_txt
+            .warn_if_txt_disabled(self.context.resolve_txt);
+    }
+
+    fn required_txt(&self) -> &[(String, String)] {
+        self.context.required_txt.entries()
+    }
+
+    fn resolve(&mut self, service: &ServiceBrowse) -> Result<EventLoop> {
+        self.start_resolve(service, None)?;
+
+        Ok(EventLoop::new(
+            self.event_loop_service(),
+            Rc::clone(&self.loop_state),
+        ))
+    }
+
+    /// Resolves the specified browsed service, blocking the calling thread until it has been
+    /// resolved or `timeout` elapses. Bonjour resolves services synchronously, so the browser does
+    /// not need to have been started first. The time taken to look up the address of a resolved
+    /// service is not limited by `timeout`.
+    fn resolve_timeout(
+        &mut self,
+        service: &ServiceBrowse,
+        timeout: Duration,
+    ) -> Result<ServiceDiscovery> {
+        let found: Arc<Mutex<Option<Result<ServiceDiscovery>>>> = Arc::default();
+        let found_callback = found.clone();
+
+        self.set_service_discovered_callback(Box::new(move |result, _| {
+            *found_callback.lock().unwrap() = Some(result);
+        }));
+
+        // the service ref is deallocated when resolving returns, including on timeout
+        self.start_resolve(service, Some(timeout))?;
+
+        let result = found.lock().unwrap().take();
+        result.unwrap_or_else(|| Err("service was not resolved".into()))
+    }
+
+    fn set_cancellation_token(&mut self, token: CancellationToken) {
+        self.context.cancellation_token = Some(token);
+    }
+
+    fn cancel(&mut self) {
+        debug!("Cancelling browser: {:?}", self);
+
+        self.service = Arc::default();
+        self.domain_services.lock().unwrap().clear();
+        self.teardown = None;
+        self.context.browsed.clear();
+        self.context.throttle_timer = None;
+        self.context.throttle.get_mut().clear();
+        self.context.cancellation_token = None;
+    }
+
+    fn close(self) -> Result<()> {
+        debug!("Closing browser: {:?}", self);
+
+        // `DNSServiceRefDeallocate()` does not report errors, so there is nothing to return
+        drop(self);
+
+        Ok(())
+    }
+
     fn set_context(&mut self, context: Box<dyn Any>) {
         self.context.user_context = Some(Arc::from(context));
     }
@@ -62,56 +336,340 @@ impl TMdnsBrowser for BonjourMdnsBrowser {
     fn browse_services(&mut self) -> Result<EventLoop> {
         debug!("Browsing services: {:?}", self);
 
-        self.service.lock().unwrap().browse_services(
+        if self.context.resolve_services && self.context.service_discovered_callback.is_none() {
+            return Err("a service discovered callback must be set to resolve services".into());
+        }
+
+        // `SCNetworkReachability` requires a `CFRunLoop`, which the event loop does not run
+        if self.restart_on_network_change {
+            return Err(Error::Unsupported(
+                "restarting on network changes".to_string(),
+            ));
+        }
+
+        let domains = if self.domains.is_empty() {
+            vec![bonjour_util::scope_domain(self.context.scope)]
+        } else {
+            self.domains
+                .iter()
+                .map(|d| Some(c_string!(d.as_str())))
+                .collect()
+        };
+
+        // the results of several browse operations can only be processed on one connection
+        if domains.len() > 1 && self.connection.is_none() {
+            let mut connection = ManagedDNSServiceRef::new();
+            connection.create_connection()?;
+            self.connection = Some(Arc::new(Mutex::new(connection)));
+        }
+
+        self.context.all_for_now_reported = false;
+        self.domain_services.lock().unwrap().clear();
+
+        let service = Arc::clone(&self.service);
+        self.browse_domain(&mut service.lock().unwrap(), domains[0].as_ref())?;
+
+        for domain in &domains[1..] {
+            let mut service = ManagedDNSServiceRef::new();
+            self.browse_domain(&mut service, domain.as_ref())?;
+            self.domain_services.lock().unwrap().push(service);
+        }
+
+        // Bonjour has no client state, but the daemon has accepted the browse request
+        self.context
+            .invoke_client_state_callback(ClientState::Running);
+
+        let active = ActiveGuard::new(&self.loop_state, ActiveKind::Browser);
+        let service = Arc::clone(&self.service);
+        let domain_services = Arc::clone(&self.domain_services);
+
+        self.teardown = Some(self.loop_state.register_teardown(move || {
+            domain_services.lock().unwrap().clear();
+            *service.lock().unwrap() = ManagedDNSServiceRef::default();
+            drop(active);
+        }));
+
+        let context: *const BonjourBrowserContext = &*self.context;
+
+        // the context is boxed, so it stays at the same address until the browser is dropped,
+        // which unregisters the timer
+        let throttle_timer = self.context.throttle.get_mut().interval().map(|interval| {
+            self.loop_state
+                .register_timer(interval, move || unsafe { (*context).deliver_throttled() })
+        });
+
+        self.context.throttle_timer = throttle_timer;
+
+        Ok(EventLoop::new(
+            self.event_loop_service(),
+            Rc::clone(&self.loop_state),
+        ))
+    }
+}
+
+impl BonjourMdnsBrowser {
+    fn browse_domain(
+        &mut self,
+        service: &mut ManagedDNSServiceRef,
+        domain: Option<&CString>,
+    ) -> Result<()> {
+        let mut flags = bonjour_util::discovery_flags(self.discovery_mode, self.context.scope);
+
+        if let Some(connection) = &self.connection {
+            flags |= constants::BONJOUR_SHARE_CONNECTION_FLAGS;
+            service.share_connection(&connection.lock().unwrap());
+        }
+
+        service.browse_services(
             BrowseServicesParams::builder()
-                .flags(0)
+                .flags(flags)
                 .interface_index(self.interface_index)
                 .regtype(self.kind.as_ptr())
-                .domain(ptr::null_mut())
+                .domain(domain.as_c_chars().unwrap_or_null())
                 .callback(Some(browse_callback))
                 .context(self.context.as_raw())
                 .build()?,
+        )
+    }
+
+    fn start_resolve(&mut self, service: &ServiceBrowse, timeout: Option<Duration>) -> Result<()> {
+        browser::check_resolved_type(&self.service_type, service)?;
+
+        if self.context.is_cancelled() {
+            return Err("browser has been cancelled".into());
+        }
+
+        let service_type = ServiceType::new(
+            service.service_type().name(),
+            service.service_type().protocol(),
         )?;
 
-        Ok(EventLoop::new(self.service.clone()))
+        let name = c_string!(service.raw_name_bytes());
+        let regtype = bonjour_util::format_regtype(&service_type);
+        let domain = c_string!(service.domain().as_str());
+
+        #[cfg(feature = "diagnostics")]
+        {
+            self.context.browsed_at = None;
+        }
+
+        unsafe {
+            resolve_service(
+                &mut self.context,
+                bonjour_util::interface_index(*service.interface()),
+                name.as_ptr(),
+                regtype.as_ptr(),
+                domain.as_ptr(),
+                timeout,
+            )
+        }
+    }
+
+    /// Creates a new browser that browses using the specified shared connection instead of
+    /// creating its own.
+    pub(crate) fn with_connection(
+        service_type: ServiceType,
+        connection: Arc<Mutex<ManagedDNSServiceRef>>,
+        loop_state: Rc<LoopState>,
+    ) -> Self {
+        let mut browser = Self::new(service_type);
+        browser.loop_state = loop_state;
+        browser.connection = Some(connection);
+        browser
+    }
+
+    // events for a shared connection are only delivered through the connection itself
+    fn event_loop_service(&self) -> Arc<Mutex<ManagedDNSServiceRef>> {
+        Arc::clone(self.connection.as_ref().unwrap_or(&self.service))
     }
 }
 
-#[derive(Default, FromRaw, AsRaw)]
+#[derive(FromRaw, AsRaw)]
 struct BonjourBrowserContext {
+    resolve_services: bool,
+    resolve_txt: bool,
+    resolve_on_browse_interface: bool,
+    all_for_now_reported: bool,
     service_discovered_callback: Option<Box<ServiceDiscoveredCallback>>,
-    resolved_name: Option<String>,
-    resolved_kind: Option<String>,
-    resolved_domain: Option<String>,
-    resolved_port: u16,
-    resolved_txt: Option<TxtRecord>,
+    service_browsed_callback: Option<Box<ServiceBrowsedCallback>>,
+    client_state_callback: Option<Box<ClientStateCallback>>,
+    cancellation_token: Option<CancellationToken>,
+    resolve_all_addresses: bool,
+    coalesce_addresses: bool,
+    browsed: HashMap<(String, String, String), usize>,
+    resolve_srv_priority: bool,
+    // when the browse event currently being handled was received
+    #[cfg(feature = "diagnostics")]
+    browsed_at: Option<Instant>,
+    protocol: DNSServiceProtocol,
+    scope: Scope,
+    on_invalid_utf8: OnInvalidUtf8,
+    ignored: IgnoredServices,
+    required_txt: TxtRequirements,
+    throttle: RefCell<CallbackThrottle>,
+    throttle_timer: Option<TimerGuard>,
     user_context: Option<Arc<dyn Any>>,
 }
 
 impl BonjourBrowserContext {
+    fn is_cancelled(&self) -> bool {
+        self.cancellation_token
+            .as_ref()
+            .map_or(false, CancellationToken::is_cancelled)
+    }
+
+    /// Returns `true` if TXT records are resolved, which they are to compare them if TXT
+    /// entries are required.
+    fn resolves_txt(&self) -> bool {
+        self.resolve_txt || !self.required_txt.is_empty()
+    }
+
     fn invoke_callback(&self, result: Result<ServiceDiscovery>) {
+        if self.ignored.suppresses_discovery(&result)
+            || self.required_txt.suppresses_discovery(&result)
+        {
+            return;
+        }
+
+        if self.throttle_timer.is_some() {
+            self.throttle.borrow_mut().push(result);
+        } else {
+            self.deliver(result);
+        }
+    }
+
+    /// Delivers the discovery that has been held back the longest by the callback rate limit.
+    fn deliver_throttled(&self) {
+        let result = self.throttle.borrow_mut().pop();
+
+        if let Some(result) = result {
+            self.deliver(result);
+        }
+    }
+
+    fn deliver(&self, result: Result<ServiceDiscovery>) {
         if let Some(f) = &self.service_discovered_callback {
-            f(result, self.user_context.clone());
+            ffi::catch_callback_panic("service discovered callback", || {
+                f(result, self.user_context.clone())
+            });
         } else {
             warn!("attempted to invoke callback but none was set");
         }
     }
+
+    fn invoke_browsed_callback(&self, result: Result<BrowseEvent>) {
+        if self.ignored.suppresses_event(&result) {
+            return;
+        }
+
+        if let Some(f) = &self.service_browsed_callback {
+            ffi::catch_callback_panic("service browsed callback", || {
+                f(result, self.user_context.clone())
+            });
+        }
+    }
+
+    fn invoke_client_state_callback(&self, state: ClientState) {
+        if let Some(f) = &self.client_state_callback {
+            ffi::catch_callback_panic("client state callback", || {
+                f(state, self.user_context.clone())
+            });
+        }
+    }
+
+    /// Counts an added or removed instance of the specified service, returning `true` if it was
+    /// added on its first interface.
+    fn count_instance(&mut self, key: (String, String, String), added: bool) -> bool {
+        if added {
+            let instances = self.browsed.entry(key).or_insert(0);
+            *instances += 1;
+            return *instances == 1;
+        }
+
+        if let Some(instances) = self.browsed.get_mut(&key) {
+            *instances -= 1;
+
+            if *instances == 0 {
+                self.browsed.remove(&key);
+            }
+        }
+
+        false
+    }
+}
+
+impl Default for BonjourBrowserContext {
+    fn default() -> Self {
+        Self {
+            resolve_services: true,
+            resolve_txt: true,
+            resolve_on_browse_interface: true,
+            all_for_now_reported: false,
+            service_discovered_callback: None,
+            service_browsed_callback: None,
+            client_state_callback: None,
+            cancellation_token: None,
+            resolve_all_addresses: false,
+            coalesce_addresses: true,
+            browsed: HashMap::new(),
+            resolve_srv_priority: false,
+            #[cfg(feature = "diagnostics")]
+            browsed_at: None,
+            protocol: constants::BONJOUR_PROTO_UNSPEC,
+            scope: Scope::default(),
+            on_invalid_utf8: OnInvalidUtf8::default(),
+            ignored: IgnoredServices::default(),
+            required_txt: TxtRequirements::default(),
+            throttle: RefCell::default(),
+            throttle_timer: None,
+            user_context: None,
+        }
+    }
 }
 
 impl fmt::Debug for BonjourBrowserContext {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         f.debug_struct("BonjourResolverContext")
-            .field("resolved_name", &self.resolved_name)
-            .field("resolved_kind", &self.resolved_kind)
-            .field("resolved_domain", &self.resolved_domain)
-            .field("resolved_port", &self.resolved_port)
+            .field("resolve_services", &self.resolve_services)
+            .field("resolve_txt", &self.resolve_txt)
+            .field(
+                "resolve_on_browse_interface",
+                &self.resolve_on_browse_interface,
+            )
+            .field("scope", &self.scope)
+            .field("on_invalid_utf8", &self.on_invalid_utf8)
+            .field("ignored", &self.ignored)
+            .field("required_txt", &self.required_txt)
+            .field("throttle", &self.throttle)
             .finish()
     }
 }
 
+/// The state of resolving a single service. Each resolve has its own, so that resolving a service
+/// while another is being resolved does not overwrite the results of the other.
+#[derive(FromRaw, AsRaw)]
+struct BonjourResolveContext<'a> {
+    browser: &'a BonjourBrowserContext,
+    name: Option<String>,
+    raw_name: Option<Vec<u8>>,
+    kind: String,
+    domain: String,
+    port: u16,
+    txt: Option<TxtRecord>,
+    addresses: Vec<ServiceAddress>,
+    priority: u16,
+    weight: u16,
+    // when the service was browsed and resolving it was started
+    #[cfg(feature = "diagnostics")]
+    timing: Option<(Instant, Instant)>,
+    // the target host of the service, reported if it resolves to no address
+    #[cfg(feature = "diagnostics")]
+    host: Option<String>,
+}
+
 unsafe extern "C" fn browse_callback(
     _sd_ref: DNSServiceRef,
-    _flags: DNSServiceFlags,
+    flags: DNSServiceFlags,
     interface_index: u32,
     error: DNSServiceErrorType,
     name: *const c_char,
@@ -120,11 +678,110 @@ unsafe extern "C" fn browse_callback(
     context: *mut c_void,
 ) {
     let ctx = BonjourBrowserContext::from_raw(context);
+
+    #[cfg(feature = "diagnostics")]
+    {
+        ctx.browsed_at = Some(Instant::now());
+    }
+
+    if ctx.is_cancelled() {
+        return;
+    }
+    let added = flags & bonjour_sys::kDNSServiceFlagsAdd != 0;
+
+    if error == constants::BONJOUR_ERR_SERVICE_NOT_RUNNING {
+        ctx.invoke_client_state_callback(ClientState::Failure);
+    }
+
+    if error == 0 {
+        let raw_name = CStr::from_ptr(name).to_bytes();
+
+        match browser::decode_instance_name(raw_name, ctx.on_invalid_utf8) {
+            Ok(Some(_)) => {}
+            Ok(None) => return,
+            // services that were reported as errors when added are not reported again
+            Err(_) if !added => return,
+            Err(e) => {
+                if ctx.service_browsed_callback.is_some() {
+                    ctx.invoke_browsed_callback(Err(e));
+                } else {
+                    ctx.invoke_callback(Err(e));
+                }
+                return;
+            }
+        }
+    }
+
+    if ctx.service_browsed_callback.is_some() {
+        if error != 0 {
+            ctx.invoke_browsed_callback(Err(format!(
+                "browse_callback() reported error (code: {})",
+                error
+            )
+            .into()));
+            return;
+        }
+
+        let result = browse_event(name, regtype, domain, interface_index).map(|service| {
+            if added {
+                BrowseEvent::New(service)
+            } else {
+                BrowseEvent::Removed(service)
+            }
+        });
+
+        ctx.invoke_browsed_callback(result);
+
+        if flags & bonjour_sys::kDNSServiceFlagsMoreComing == 0 && !ctx.all_for_now_reported {
+            ctx.all_for_now_reported = true;
+            ctx.invoke_browsed_callback(Ok(BrowseEvent::AllForNow));
+        }
+    }
+
+    if !ctx.resolve_services {
+        return;
+    }
+
+    if error == 0 && ctx.coalesce_addresses {
+        // the service is browsed once per interface, but only resolved on the first one
+        let key = (
+            CStr::from_ptr(name).to_string_lossy().to_ascii_lowercase(),
+            c_str::raw_to_str(regtype).to_ascii_lowercase(),
+            bonjour_util::normalize_domain(c_str::raw_to_str(domain)).to_ascii_lowercase(),
+        );
+
+        if !ctx.count_instance(key, added) {
+            return;
+        }
+    } else if error == 0 && !added {
+        return;
+    }
+
     if let Err(e) = handle_browse(ctx, error, name, regtype, domain, interface_index) {
         ctx.invoke_callback(Err(e));
     }
 }
 
+unsafe fn browse_event(
+    name: *const c_char,
+    regtype: *const c_char,
+    domain: *const c_char,
+    interface_index: u32,
+) -> Result<ServiceBrowse> {
+    let kind = bonjour_util::normalize_domain(c_str::raw_to_str(regtype));
+    let raw_name = CStr::from_ptr(name).to_bytes();
+
+    // names that are not valid UTF-8 have been skipped unless they are to be decoded lossily
+    Ok(ServiceBrowse::builder()
+        .name(String::from_utf8_lossy(raw_name).into_owned())
+        .raw_name(browser::invalid_utf8_name(raw_name))
+        .service_type(bonjour_util::parse_regtype(&kind)?)
+        .domain(bonjour_util::normalize_domain(c_str::raw_to_str(domain)))
+        .interface(bonjour_util::interface_from_index(interface_index))
+        .build()
+        .expect("could not build ServiceBrowse"))
+}
+
 unsafe fn handle_browse(
     ctx: &mut BonjourBrowserContext,
     error: DNSServiceErrorType,
@@ -137,19 +794,61 @@ unsafe fn handle_browse(
         return Err(format!("browse_callback() reported error (code: {})", error).into());
     }
 
-    ctx.resolved_name = Some(c_str::copy_raw(name));
-    ctx.resolved_kind = Some(c_str::copy_raw(regtype));
-    ctx.resolved_domain = Some(c_str::copy_raw(domain));
+    resolve_service(ctx, interface_index, name, regtype, domain, None)
+}
+
+unsafe fn resolve_service(
+    ctx: &mut BonjourBrowserContext,
+    interface_index: u32,
+    name: *const c_char,
+    regtype: *const c_char,
+    domain: *const c_char,
+    timeout: Option<Duration>,
+) -> Result<()> {
+    let interface_index = if ctx.resolve_on_browse_interface {
+        interface_index
+    } else {
+        constants::BONJOUR_IF_UNSPEC
+    };
+
+    #[cfg(feature = "diagnostics")]
+    let timing = {
+        let resolve_started_at = Instant::now();
+        let browsed_at = ctx.browsed_at.take().unwrap_or(resolve_started_at);
+        Some((browsed_at, resolve_started_at))
+    };
+
+    let raw_name = CStr::from_ptr(name).to_bytes();
+
+    // resolving returns once the service has been resolved or has failed to, so the context
+    // outlives every callback that it is passed to
+    let mut resolve_ctx = BonjourResolveContext {
+        browser: ctx,
+        name: Some(String::from_utf8_lossy(raw_name).into_owned()),
+        raw_name: browser::invalid_utf8_name(raw_name),
+        kind: c_str::copy_raw(regtype),
+        domain: c_str::copy_raw(domain),
+        port: 0,
+        txt: None,
+        addresses: Vec::new(),
+        priority: 0,
+        weight: 0,
+        #[cfg(feature = "diagnostics")]
+        timing,
+        #[cfg(feature = "diagnostics")]
+        host: None,
+    };
 
     ManagedDNSServiceRef::default().resolve_service(
         ServiceResolveParams::builder()
-            .flags(bonjour_sys::kDNSServiceFlagsForceMulticast)
+            .flags(bonjour_util::lookup_flags(resolve_ctx.browser.scope))
             .interface_index(interface_index)
             .name(name)
             .regtype(regtype)
             .domain(domain)
             .callback(Some(resolve_callback))
-            .context(ctx.as_raw())
+            .context(resolve_ctx.as_raw())
+            .timeout(timeout)
             .build()?,
     )
 }
@@ -159,58 +858,95 @@ unsafe extern "C" fn resolve_callback(
     _flags: DNSServiceFlags,
     interface_index: u32,
     error: DNSServiceErrorType,
-    _fullname: *const c_char,
+    fullname: *const c_char,
     host_target: *const c_char,
     port: u16,
     txt_len: u16,
     txt_record: *const c_uchar,
     context: *mut c_void,
 ) {
-    let ctx = BonjourBrowserContext::from_raw(context);
+    let ctx = BonjourResolveContext::from_raw(context);
+
+    if ctx.browser.is_cancelled() {
+        return;
+    }
 
     let result = handle_resolve(
         ctx,
         error,
         port,
         interface_index,
+        fullname,
         host_target,
         txt_len,
         txt_record,
     );
 
     if let Err(e) = result {
-        ctx.invoke_callback(Err(e));
+        ctx.browser.invoke_callback(Err(e));
     }
 }
 
 unsafe fn handle_resolve(
-    ctx: &mut BonjourBrowserContext,
+    ctx: &mut BonjourResolveContext,
     error: DNSServiceErrorType,
     port: u16,
     interface_index: u32,
+    fullname: *const c_char,
     host_target: *const c_char,
     txt_len: u16,
     txt_record: *const c_uchar,
 ) -> Result<()> {
     if error != 0 {
-        return Err(format!("error reported by resolve_callback: (code: {})", error).into());
+        let failed = browser::resolve_failed(format!(
+            "error reported by resolve_callback: (code: {})",
+            error
+        ));
+        #[cfg(feature = "diagnostics")]
+        let failed = failed.with_diagnostics(browser::ResolveDiagnostics::new(
+            bonjour_util::interface_from_index(interface_index),
+            NetworkProtocol::Unspec,
+            bonjour_util::protocol_from_raw(ctx.browser.protocol),
+            None,
+            bonjour_util::error_name(error).to_string(),
+        ));
+
+        return Err(failed);
     }
 
-    ctx.resolved_port = port;
+    ctx.port = port;
+
+    #[cfg(feature = "diagnostics")]
+    {
+        ctx.host = Some(c_str::copy_raw(host_target));
+    }
 
-    ctx.resolved_txt = if txt_len > 1 {
-        Some(TxtRecord::from(ManagedTXTRecordRef::clone_raw(
-            txt_record, txt_len,
-        )?))
+    ctx.txt = if txt_len > 1 && ctx.browser.resolves_txt() {
+        let data = std::slice::from_raw_parts(txt_record, txt_len as usize);
+        Some(TxtRecord::from_bytes(data)?)
     } else {
         None
     };
 
+    if ctx.browser.resolve_srv_priority {
+        ManagedDNSServiceRef::default().query_record(
+            QueryRecordParams::builder()
+                .flags(bonjour_util::lookup_flags(ctx.browser.scope))
+                .interface_index(interface_index)
+                .fullname(fullname)
+                .rrtype(bonjour_sys::kDNSServiceType_SRV as u16)
+                .rrclass(bonjour_sys::kDNSServiceClass_IN as u16)
+                .callback(Some(query_record_callback))
+                .context(ctx.as_raw())
+                .build()?,
+        )?;
+    }
+
     ManagedDNSServiceRef::default().get_address_info(
         GetAddressInfoParams::builder()
-            .flags(bonjour_sys::kDNSServiceFlagsForceMulticast)
+            .flags(bonjour_util::lookup_flags(ctx.browser.scope))
             .interface_index(interface_index)
-            .protocol(0)
+            .protocol(ctx.browser.protocol)
             .hostname(host_target)
             .callback(Some(get_address_info_callback))
             .context(ctx.as_raw())
@@ -218,68 +954,156 @@ unsafe fn handle_resolve(
     )
 }
 
-unsafe extern "C" fn get_address_info_callback(
+#[allow(clippy::too_many_arguments)]
+unsafe extern "C" fn query_record_callback(
     _sd_ref: DNSServiceRef,
     _flags: DNSServiceFlags,
     _interface_index: u32,
     error: DNSServiceErrorType,
+    _fullname: *const c_char,
+    _rrtype: u16,
+    _rrclass: u16,
+    rdlen: u16,
+    rdata: *const c_void,
+    _ttl: u32,
+    context: *mut c_void,
+) {
+    let ctx = BonjourResolveContext::from_raw(context);
+
+    if error != 0 {
+        warn!("query_record_callback() reported error (code: {})", error);
+        return;
+    }
+
+    let rdata = std::slice::from_raw_parts(rdata as *const u8, rdlen as usize);
+
+    match browser::srv_priority_weight(rdata) {
+        Ok((priority, weight)) => {
+            ctx.priority = priority;
+            ctx.weight = weight;
+        }
+        Err(e) => warn!("{}", e),
+    }
+}
+
+unsafe extern "C" fn get_address_info_callback(
+    _sd_ref: DNSServiceRef,
+    flags: DNSServiceFlags,
+    interface_index: u32,
+    error: DNSServiceErrorType,
     hostname: *const c_char,
     address: *const bonjour_sys::sockaddr,
     _ttl: u32,
     context: *mut c_void,
 ) {
-    let ctx = BonjourBrowserContext::from_raw(context);
-    if let Err(e) = handle_get_address_info(ctx, error, address, hostname) {
-        ctx.invoke_callback(Err(e));
+    let ctx = BonjourResolveContext::from_raw(context);
+
+    if ctx.browser.is_cancelled() {
+        return;
+    }
+    if let Err(e) = handle_get_address_info(ctx, flags, interface_index, error, address, hostname) {
+        ctx.browser.invoke_callback(Err(e));
     }
 }
 
 unsafe fn handle_get_address_info(
-    ctx: &mut BonjourBrowserContext,
+    ctx: &mut BonjourResolveContext,
+    flags: DNSServiceFlags,
+    interface_index: u32,
     error: DNSServiceErrorType,
     address: *const bonjour_sys::sockaddr,
     hostname: *const c_char,
 ) -> Result<()> {
     // this callback runs multiple times for some reason
-    if ctx.resolved_name.is_none() {
+    if ctx.name.is_none() {
         return Ok(());
     }
 
     if error != 0 {
-        return Err(format!(
+        let failed = browser::resolve_failed(format!(
             "get_address_info_callback() reported error (code: {})",
             error
-        )
-        .into());
+        ));
+        #[cfg(feature = "diagnostics")]
+        let failed = failed.with_diagnostics(browser::ResolveDiagnostics::new(
+            bonjour_util::interface_from_index(interface_index),
+            bonjour_util::protocol_from_raw(ctx.browser.protocol),
+            bonjour_util::protocol_from_raw(ctx.browser.protocol),
+            ctx.host.take(),
+            bonjour_util::error_name(error).to_string(),
+        ));
+
+        return Err(failed);
     }
 
-    // on macOS the bytes are swapped for the port
-    let port: u16 = ctx.resolved_port.to_be();
-
-    // on macOS the bytes are swapped for the ip
-    let ip = {
-        let address = address as *const sockaddr_in;
-        assert_not_null!(address);
-        let s_addr = (*address).sin_addr.s_addr.to_le_bytes();
-        IpAddr::from(s_addr).to_string()
+    let ip = bonjour_util::ip_address(address);
+
+    let protocol = match ip {
+        IpAddr::V4(_) => NetworkProtocol::Ipv4,
+        IpAddr::V6(_) => NetworkProtocol::Ipv6,
     };
 
+    let address = ServiceAddress::builder()
+        .address(ip)
+        .interface(bonjour_util::interface_from_index(interface_index))
+        .protocol(protocol)
+        .build()?;
+
+    if !ctx.addresses.contains(&address) {
+        ctx.addresses.push(address);
+    }
+
+    let all_addresses = ctx.browser.resolve_all_addresses || ctx.browser.coalesce_addresses;
+
+    if all_addresses && flags & bonjour_sys::kDNSServiceFlagsMoreComing != 0 {
+        return Ok(());
+    }
+
+    // on macOS the bytes are swapped for the port
+    let port: u16 = ctx.port.to_be();
+
+    let addresses = std::mem::take(&mut ctx.addresses);
+    let ip = addresses[0].address().to_string();
+
     let hostname = c_str::copy_raw(hostname);
-    let domain = bonjour_util::normalize_domain(&ctx.resolved_domain.take().unwrap());
-    let kind = bonjour_util::normalize_domain(&ctx.resolved_kind.take().unwrap());
+    let domain = bonjour_util::normalize_domain(&ctx.domain);
+    let kind = bonjour_util::normalize_domain(&ctx.kind);
 
-    let result = ServiceDiscovery::builder()
-        .name(ctx.resolved_name.take().unwrap())
-        .service_type(bonjour_util::parse_regtype(&kind)?)
+    let txt = ctx.txt.take();
+
+    let name = ctx.name.take().unwrap();
+    let service_type = bonjour_util::parse_regtype(&kind)?;
+    let our_own = service::is_own_service(&browser::service_key(&name, &service_type, &domain));
+
+    let mut result = ServiceDiscovery::builder();
+
+    #[cfg(feature = "diagnostics")]
+    if let Some((browsed_at, resolve_started_at)) = ctx.timing.take() {
+        result.timing(Some(browser::ResolveTiming::new(
+            browsed_at,
+            resolve_started_at,
+            Instant::now(),
+        )));
+    }
+
+    let result = result
+        .name(name)
+        .raw_name(ctx.raw_name.take())
+        .service_type(service_type)
         .domain(domain)
         .host_name(hostname)
         .address(ip)
         .port(port)
-        .txt(ctx.resolved_txt.take())
+        .txt_records(txt.iter().cloned().collect())
+        .txt(txt)
+        .addresses(addresses)
+        .priority(ctx.priority)
+        .weight(ctx.weight)
+        .our_own(our_own)
         .build()
         .expect("could not build ServiceResolution");
 
-    ctx.invoke_callback(Ok(result));
+    ctx.browser.invoke_callback(Ok(result));
 
     Ok(())
 }
@@ -1,4 +1,11 @@
-use bonjour_sys::DNSServiceFlags;
+use bonjour_sys::{DNSServiceErrorType, DNSServiceFlags, DNSServiceProtocol};
 
 pub const BONJOUR_IF_UNSPEC: u32 = 0;
 pub const BONJOUR_RENAME_FLAGS: DNSServiceFlags = 0;
+pub const BONJOUR_SHARE_CONNECTION_FLAGS: DNSServiceFlags = 0x4000;
+pub const BONJOUR_BACKGROUND_TRAFFIC_CLASS_FLAGS: DNSServiceFlags = 0x80000;
+pub const BONJOUR_KNOWN_UNIQUE_FLAGS: DNSServiceFlags = 0x800000;
+pub const BONJOUR_PROTO_UNSPEC: DNSServiceProtocol = 0;
+pub const BONJOUR_PROTO_IPV4: DNSServiceProtocol = 1;
+pub const BONJOUR_PROTO_IPV6: DNSServiceProtocol = 2;
+pub const BONJOUR_ERR_SERVICE_NOT_RUNNING: DNSServiceErrorType = -65563;
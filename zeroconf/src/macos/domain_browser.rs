@@ -0,0 +1,158 @@
+//! Bonjour implementation for cross-platform domain browser
+
+use super::service_ref::{EnumerateDomainsParams, ManagedDNSServiceRef};
+use super::{bonjour_util, constants};
+use crate::event_loop::{ActiveGuard, ActiveKind, LoopState, TeardownGuard};
+use crate::ffi::{self, c_str, AsRaw, FromRaw};
+use crate::prelude::*;
+use crate::{DomainBrowserKind, DomainDiscoveredCallback, EventLoop, NetworkInterface, Result};
+use bonjour_sys::{DNSServiceErrorType, DNSServiceFlags, DNSServiceRef};
+use libc::{c_char, c_void};
+use std::any::Any;
+use std::collections::HashSet;
+use std::fmt::{self, Formatter};
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+
+/// Enumerates browse or registration domains with `DNSServiceEnumerateDomains()`.
+#[derive(Debug)]
+pub struct BonjourMdnsDomainBrowser {
+    service: Arc<Mutex<ManagedDNSServiceRef>>,
+    kind: DomainBrowserKind,
+    interface_index: u32,
+    context: Box<BonjourDomainBrowserContext>,
+    loop_state: Rc<LoopState>,
+    teardown: Option<TeardownGuard>,
+}
+
+impl TDomainBrowser for BonjourMdnsDomainBrowser {
+    type EventLoop<'a> = EventLoop<'a>;
+
+    fn new() -> Self {
+        Self {
+            service: Arc::default(),
+            kind: DomainBrowserKind::default(),
+            interface_index: constants::BONJOUR_IF_UNSPEC,
+            context: Box::default(),
+            loop_state: Rc::default(),
+            teardown: None,
+        }
+    }
+
+    fn set_kind(&mut self, kind: DomainBrowserKind) {
+        self.kind = kind;
+    }
+
+    fn kind(&self) -> DomainBrowserKind {
+        self.kind
+    }
+
+    fn set_network_interface(&mut self, interface: NetworkInterface) {
+        self.interface_index = bonjour_util::interface_index(interface);
+    }
+
+    fn network_interface(&self) -> NetworkInterface {
+        bonjour_util::interface_from_index(self.interface_index)
+    }
+
+    fn set_domain_discovered_callback(
+        &mut self,
+        domain_discovered_callback: Box<DomainDiscoveredCallback>,
+    ) {
+        self.context.domain_discovered_callback = Some(domain_discovered_callback);
+    }
+
+    fn set_context(&mut self, context: Box<dyn Any>) {
+        self.context.user_context = Some(Arc::from(context));
+    }
+
+    fn context(&self) -> Option<&dyn Any> {
+        self.context.user_context.as_ref().map(|c| c.as_ref())
+    }
+
+    fn browse_domains(&mut self) -> Result<EventLoop> {
+        debug!("Browsing domains: {:?}", self);
+
+        self.context.discovered.clear();
+
+        self.service.lock().unwrap().enumerate_domains(
+            EnumerateDomainsParams::builder()
+                .flags(bonjour_util::domain_enumeration_flags(self.kind))
+                .interface_index(self.interface_index)
+                .callback(Some(enumerate_callback))
+                .context(self.context.as_raw())
+                .build()?,
+        )?;
+
+        let active = ActiveGuard::new(&self.loop_state, ActiveKind::Browser);
+        let service = Arc::clone(&self.service);
+
+        self.teardown = Some(self.loop_state.register_teardown(move || {
+            *service.lock().unwrap() = ManagedDNSServiceRef::default();
+            drop(active);
+        }));
+
+        Ok(EventLoop::new(
+            self.service.clone(),
+            Rc::clone(&self.loop_state),
+        ))
+    }
+}
+
+#[derive(Default, FromRaw, AsRaw)]
+struct BonjourDomainBrowserContext {
+    discovered: HashSet<String>,
+    domain_discovered_callback: Option<Box<DomainDiscoveredCallback>>,
+    user_context: Option<Arc<dyn Any>>,
+}
+
+impl BonjourDomainBrowserContext {
+    fn invoke_callback(&self, result: Result<String>) {
+        if let Some(f) = &self.domain_discovered_callback {
+            ffi::catch_callback_panic("domain discovered callback", || {
+                f(result, self.user_context.clone())
+            });
+        } else {
+            warn!("attempted to invoke callback but none was set");
+        }
+    }
+}
+
+impl fmt::Debug for BonjourDomainBrowserContext {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BonjourDomainBrowserContext")
+            .field("discovered", &self.discovered)
+            .finish()
+    }
+}
+
+unsafe extern "C" fn enumerate_callback(
+    _sd_ref: DNSServiceRef,
+    flags: DNSServiceFlags,
+    _interface_index: u32,
+    error: DNSServiceErrorType,
+    reply_domain: *const c_char,
+    context: *mut c_void,
+) {
+    let ctx = BonjourDomainBrowserContext::from_raw(context);
+
+    if error != 0 {
+        ctx.invoke_callback(Err(format!(
+            "enumerate_callback() reported error (code: {}, {})",
+            error,
+            bonjour_util::error_name(error)
+        )
+        .into()));
+        return;
+    }
+
+    if flags & bonjour_sys::kDNSServiceFlagsAdd == 0 {
+        return;
+    }
+
+    let domain = bonjour_util::normalize_domain(c_str::raw_to_str(reply_domain));
+
+    if ctx.discovered.insert(domain.to_ascii_lowercase()) {
+        ctx.invoke_callback(Ok(domain));
+    }
+}
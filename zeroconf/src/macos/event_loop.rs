@@ -1,15 +1,20 @@
 //! Event loop for running a `MdnsService` or `MdnsBrowser`.
 
 use super::service_ref::ManagedDNSServiceRef;
-use crate::event_loop::TEventLoop;
+use crate::event_loop::{self, LoopState, SuspendPolicy, TEventLoop};
 use crate::{ffi, Result};
+use std::cell::Cell;
 use std::marker::PhantomData;
+use std::rc::Rc;
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
 #[derive(new)]
 pub struct BonjourEventLoop<'a> {
     service: Arc<Mutex<ManagedDNSServiceRef>>,
+    loop_state: Rc<LoopState>,
+    #[new(default)]
+    suspension: Cell<Option<SuspendPolicy>>,
     phantom: PhantomData<&'a ManagedDNSServiceRef>,
 }
 
@@ -20,12 +25,46 @@ impl<'a> TEventLoop for BonjourEventLoop<'a> {
     /// `select()` on the underlying socket with the specified timeout. If the socket contains no
     /// new data, the blocking call is not made.
     fn poll(&self, timeout: Duration) -> Result<()> {
-        let service = self.service.lock().unwrap();
-        let select = unsafe { ffi::macos::read_select(service.sock_fd(), timeout)? };
-        if select > 0 {
-            service.process_result()
-        } else {
-            Ok(())
-        }
+        event_loop::poll_with_timers(
+            &self.loop_state,
+            self.suspension.get(),
+            timeout,
+            |timeout| {
+                let service = self.service.lock().unwrap();
+                let select = unsafe { ffi::macos::read_select(service.sock_fd(), timeout)? };
+                if select > 0 {
+                    service.process_result()
+                } else {
+                    Ok(())
+                }
+            },
+        )
+    }
+
+    fn suspend(&self, policy: SuspendPolicy) {
+        debug!("Suspending event loop: {:?}", policy);
+        self.suspension.set(Some(policy));
+    }
+
+    fn resume(&self) {
+        debug!("Resuming event loop");
+        self.suspension.set(None);
+    }
+
+    fn suspension(&self) -> Option<SuspendPolicy> {
+        self.suspension.get()
+    }
+
+    fn active_resolvers(&self) -> usize {
+        self.loop_state.resolvers()
+    }
+
+    fn active_browsers(&self) -> usize {
+        self.loop_state.browsers()
+    }
+
+    fn cancel_all(&self) {
+        debug!("Cancelling all operations");
+        self.loop_state.cancel_all();
     }
 }
@@ -0,0 +1,51 @@
+//! Bonjour implementation for cross-platform facade
+
+use super::browser::BonjourMdnsBrowser;
+use super::service::BonjourMdnsService;
+use super::service_ref::ManagedDNSServiceRef;
+use crate::event_loop::LoopState;
+use crate::prelude::*;
+use crate::{EventLoop, Result, ServiceType};
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+
+/// Shares a single connection to the Bonjour daemon, created with `DNSServiceCreateConnection`,
+/// between every service and browser it creates.
+#[derive(Debug)]
+pub struct BonjourZeroconf {
+    connection: Arc<Mutex<ManagedDNSServiceRef>>,
+    loop_state: Rc<LoopState>,
+}
+
+impl TZeroconf for BonjourZeroconf {
+    fn new() -> Result<Self> {
+        let mut connection = ManagedDNSServiceRef::new();
+        connection.create_connection()?;
+
+        Ok(Self {
+            connection: Arc::new(Mutex::new(connection)),
+            loop_state: Rc::default(),
+        })
+    }
+
+    fn new_service(&self, service_type: ServiceType, port: u16) -> BonjourMdnsService {
+        BonjourMdnsService::with_connection(
+            service_type,
+            port,
+            Arc::clone(&self.connection),
+            Rc::clone(&self.loop_state),
+        )
+    }
+
+    fn new_browser(&self, service_type: ServiceType) -> BonjourMdnsBrowser {
+        BonjourMdnsBrowser::with_connection(
+            service_type,
+            Arc::clone(&self.connection),
+            Rc::clone(&self.loop_state),
+        )
+    }
+
+    fn event_loop(&self) -> EventLoop {
+        EventLoop::new(Arc::clone(&self.connection), Rc::clone(&self.loop_state))
+    }
+}
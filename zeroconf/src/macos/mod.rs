@@ -8,8 +8,11 @@ pub(crate) mod constants;
 
 pub mod bonjour_util;
 pub mod browser;
+pub mod domain_browser;
 pub mod event_loop;
+pub mod facade;
 pub mod service;
 pub mod service_ref;
 pub mod txt_record;
 pub mod txt_record_ref;
+pub mod type_browser;
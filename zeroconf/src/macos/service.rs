@@ -1,19 +1,30 @@
 //! Bonjour implementation for cross-platform service.
 
-use super::service_ref::{ManagedDNSServiceRef, RegisterServiceParams};
+use super::service_ref::{ManagedDNSServiceRef, RegisterRecordParams, RegisterServiceParams};
 use super::{bonjour_util, constants};
+use crate::error::Error;
+use crate::event_loop::{LoopState, TeardownGuard, TimerGuard};
 use crate::ffi::c_str::{self, AsCChars};
-use crate::ffi::{AsRaw, FromRaw, UnwrapOrNull};
+use crate::ffi::{self, AsRaw, FromRaw, UnwrapOrNull};
 use crate::prelude::*;
+use crate::service::{self, OwnService, RegistrationStates};
 use crate::{
-    EventLoop, NetworkInterface, Result, ServiceRegisteredCallback, ServiceRegistration,
-    ServiceType, TxtRecord,
+    ClientFlags, ClientState, ClientStateCallback, EventLoop, HostNameChangedCallback,
+    NetworkInterface, NetworkProtocol, RegistrationState, Result, Scope, ServiceHandle,
+    ServiceRegisteredCallback, ServiceRegistration, ServiceType, TxtProvider, TxtRecord,
+    MIN_TXT_PROVIDER_INTERVAL,
 };
-use bonjour_sys::{DNSServiceErrorType, DNSServiceFlags, DNSServiceRef};
+use bonjour_sys::{DNSRecordRef, DNSServiceErrorType, DNSServiceFlags, DNSServiceRef};
 use libc::{c_char, c_void};
 use std::any::Any;
+use std::cell::RefCell;
 use std::ffi::CString;
+use std::net::IpAddr;
+use std::ptr;
+use std::rc::{Rc, Weak};
+use std::sync::mpsc::Receiver;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 #[derive(Debug)]
 pub struct BonjourMdnsService {
@@ -22,13 +33,38 @@ pub struct BonjourMdnsService {
     port: u16,
     name: Option<CString>,
     domain: Option<CString>,
+    scope: Scope,
     host: Option<CString>,
     interface_index: u32,
+    protocol: NetworkProtocol,
     txt_record: Option<TxtRecord>,
+    publish_empty_txt: bool,
+    txt_provider: Option<(Duration, Rc<TxtProvider>)>,
+    addresses: Vec<IpAddr>,
+    publish_reverse_ptr: bool,
+    client_flags: ClientFlags,
+    no_probe: bool,
+    srv_priority: u16,
+    srv_weight: u16,
+    ptr_ttl: Option<u32>,
+    srv_ttl: Option<u32>,
+    txt_ttl: Option<u32>,
+    address_ttl: Option<u32>,
+    track_interface_changes: bool,
+    follow_interfaces: bool,
+    address_records: Arc<Mutex<Option<ManagedDNSServiceRef>>>,
     context: Box<BonjourServiceContext>,
+    loop_state: Rc<LoopState>,
+    txt_timer: Rc<RefCell<Option<TimerGuard>>>,
+    teardown: Option<TeardownGuard>,
+    // declared last so that it is dropped after the service that shares it
+    connection: Option<Arc<Mutex<ManagedDNSServiceRef>>>,
 }
 
 impl TMdnsService for BonjourMdnsService {
+    type EventLoop<'a> = EventLoop<'a>;
+    type Handle = ServiceHandle;
+
     fn new(service_type: ServiceType, port: u16) -> Self {
         Self {
             service: Arc::default(),
@@ -36,10 +72,31 @@ impl TMdnsService for BonjourMdnsService {
             port,
             name: None,
             domain: None,
+            scope: Scope::default(),
             host: None,
             interface_index: constants::BONJOUR_IF_UNSPEC,
+            protocol: NetworkProtocol::Unspec,
             txt_record: None,
+            publish_empty_txt: true,
+            txt_provider: None,
+            addresses: Vec::new(),
+            publish_reverse_ptr: false,
+            client_flags: ClientFlags::empty(),
+            no_probe: false,
+            srv_priority: 0,
+            srv_weight: 0,
+            ptr_ttl: None,
+            srv_ttl: None,
+            txt_ttl: None,
+            address_ttl: None,
+            track_interface_changes: false,
+            follow_interfaces: false,
+            address_records: Arc::default(),
             context: Box::default(),
+            loop_state: Rc::default(),
+            txt_timer: Rc::default(),
+            teardown: None,
+            connection: None,
         }
     }
 
@@ -61,6 +118,16 @@ impl TMdnsService for BonjourMdnsService {
         bonjour_util::interface_from_index(self.interface_index)
     }
 
+    /// Bonjour does not support restricting the protocol of a registration, so the protocol is
+    /// only stored.
+    fn set_network_protocol(&mut self, protocol: NetworkProtocol) {
+        self.protocol = protocol;
+    }
+
+    fn network_protocol(&self) -> NetworkProtocol {
+        self.protocol
+    }
+
     fn set_domain(&mut self, domain: &str) {
         self.domain = Some(c_string!(domain));
     }
@@ -69,6 +136,19 @@ impl TMdnsService for BonjourMdnsService {
         self.domain.as_ref().map(|d| d.to_str().unwrap())
     }
 
+    /// Sets the [`Scope`] on which to advertise the service. Bonjour cannot exclude the local
+    /// link when registering, so `Scope::WideArea` registers in the default registration domains
+    /// like `Scope::Both`.
+    ///
+    /// [`Scope`]: ../../enum.Scope.html
+    fn set_scope(&mut self, scope: Scope) {
+        self.scope = scope;
+    }
+
+    fn scope(&self) -> Scope {
+        self.scope
+    }
+
     fn set_host(&mut self, host: &str) {
         self.host = Some(c_string!(host));
     }
@@ -85,10 +165,153 @@ impl TMdnsService for BonjourMdnsService {
         self.txt_record.as_ref()
     }
 
+    fn current_txt(&self) -> Option<TxtRecord> {
+        let published = self.context.published.try_borrow().ok()?;
+
+        if published.sd_ref.is_null() {
+            return None;
+        }
+
+        published.txt_record.clone()
+    }
+
+    fn set_publish_empty_txt(&mut self, publish_empty_txt: bool) {
+        self.publish_empty_txt = publish_empty_txt;
+    }
+
+    fn publish_empty_txt(&self) -> bool {
+        self.publish_empty_txt
+    }
+
+    fn set_txt_provider(&mut self, interval: Duration, txt_provider: Box<TxtProvider>) {
+        self.txt_provider = Some((
+            interval.max(MIN_TXT_PROVIDER_INTERVAL),
+            Rc::from(txt_provider),
+        ));
+    }
+
+    fn txt_provider_interval(&self) -> Option<Duration> {
+        self.txt_provider.as_ref().map(|(interval, _)| *interval)
+    }
+
+    fn set_addresses(&mut self, addresses: Vec<IpAddr>) {
+        self.addresses = addresses;
+    }
+
+    fn addresses(&self) -> &[IpAddr] {
+        &self.addresses
+    }
+
+    fn set_client_flags(&mut self, flags: ClientFlags) {
+        self.client_flags = flags;
+    }
+
+    fn client_flags(&self) -> ClientFlags {
+        self.client_flags
+    }
+
+    fn set_publish_reverse_ptr(&mut self, publish_reverse_ptr: bool) {
+        self.publish_reverse_ptr = publish_reverse_ptr;
+    }
+
+    fn publish_reverse_ptr(&self) -> bool {
+        self.publish_reverse_ptr
+    }
+
+    fn set_no_probe(&mut self, no_probe: bool) {
+        self.no_probe = no_probe;
+    }
+
+    fn no_probe(&self) -> bool {
+        self.no_probe
+    }
+
+    fn set_srv_priority(&mut self, priority: u16) {
+        self.srv_priority = priority;
+    }
+
+    fn srv_priority(&self) -> u16 {
+        self.srv_priority
+    }
+
+    fn set_srv_weight(&mut self, weight: u16) {
+        self.srv_weight = weight;
+    }
+
+    fn srv_weight(&self) -> u16 {
+        self.srv_weight
+    }
+
+    /// Not supported by Bonjour, which publishes services with its own TTLs. The same applies to
+    /// the SRV and TXT TTLs; only the address TTL is honored.
+    fn set_ptr_ttl(&mut self, ttl: u32) {
+        self.ptr_ttl = Some(ttl);
+    }
+
+    fn ptr_ttl(&self) -> Option<u32> {
+        self.ptr_ttl
+    }
+
+    fn set_srv_ttl(&mut self, ttl: u32) {
+        self.srv_ttl = Some(ttl);
+    }
+
+    fn srv_ttl(&self) -> Option<u32> {
+        self.srv_ttl
+    }
+
+    fn set_txt_ttl(&mut self, ttl: u32) {
+        self.txt_ttl = Some(ttl);
+    }
+
+    fn txt_ttl(&self) -> Option<u32> {
+        self.txt_ttl
+    }
+
+    fn set_address_ttl(&mut self, ttl: u32) {
+        self.address_ttl = Some(ttl);
+    }
+
+    fn address_ttl(&self) -> Option<u32> {
+        self.address_ttl
+    }
+
+    /// Not supported by Bonjour, since monitoring the interface with `SCNetworkReachability`
+    /// requires a `CFRunLoop`, so registering a service with addresses fails while this is
+    /// enabled.
+    fn set_track_interface_changes(&mut self, track_interface_changes: bool) {
+        self.track_interface_changes = track_interface_changes;
+    }
+
+    fn track_interface_changes(&self) -> bool {
+        self.track_interface_changes
+    }
+
+    /// Not supported by Bonjour, for the same reason as `set_track_interface_changes()`, so
+    /// registering fails while this is enabled.
+    fn set_follow_interfaces(&mut self, follow_interfaces: bool) {
+        self.follow_interfaces = follow_interfaces;
+    }
+
+    fn follow_interfaces(&self) -> bool {
+        self.follow_interfaces
+    }
+
     fn set_registered_callback(&mut self, registered_callback: Box<ServiceRegisteredCallback>) {
         self.context.registered_callback = Some(registered_callback);
     }
 
+    fn set_client_state_callback(&mut self, client_state_callback: Box<ClientStateCallback>) {
+        self.context.client_state_callback = Some(client_state_callback);
+    }
+
+    fn set_host_name_changed_callback(
+        &mut self,
+        host_name_changed_callback: Box<HostNameChangedCallback>,
+    ) {
+        self.context.host_name_changed_callback = Some(host_name_changed_callback);
+    }
+
     fn set_context(&mut self, context: Box<dyn Any>) {
         self.context.user_context = Some(Arc::from(context));
     }
@@ -97,9 +320,80 @@ impl TMdnsService for BonjourMdnsService {
         self.context.user_context.as_ref().map(|c| c.as_ref())
     }
 
+    fn handle(&self) -> ServiceHandle {
+        BonjourServiceHandle {
+            published: Rc::downgrade(&self.context.published),
+        }
+    }
+
+    fn state_stream(&self) -> Receiver<RegistrationState> {
+        self.context.states.borrow_mut().subscribe()
+    }
+
+    fn is_registered(&self) -> bool {
+        let established =
+            self.context.states.borrow().current() == Some(&RegistrationState::Established);
+
+        // events for a shared connection are only delivered through the connection itself
+        let service = self.connection.as_ref().unwrap_or(&self.service);
+        let sock_fd = service.lock().unwrap().sock_fd();
+
+        established && !unsafe { ffi::macos::is_closed(sock_fd) }
+    }
+
     fn register(&mut self) -> Result<EventLoop> {
         debug!("Registering service: {:?}", self);
 
+        if self.context.registered_callback.is_none() {
+            return Err("a registered callback must be set before registering the service".into());
+        }
+
+        if self.srv_priority != 0 || self.srv_weight != 0 {
+            return Err(
+                "Bonjour does not support publishing a service with an SRV priority or weight"
+                    .into(),
+            );
+        }
+
+        if let Some((_, txt_provider)) = &self.txt_provider {
+            if let Some(txt_record) = service::provide_txt(&**txt_provider) {
+                self.txt_record = Some(txt_record);
+            }
+        }
+
+        if service::omits_txt(self.publish_empty_txt, self.txt_record.as_ref()) {
+            return Err(Error::Unsupported(
+                "publishing a service without a TXT record".to_string(),
+            ));
+        }
+
+        service::check_addresses(self.network_interface(), self.host(), &self.addresses)?;
+
+        if !self.client_flags.is_empty() {
+            return Err(Error::Unsupported(format!(
+                "client flags {:?}",
+                self.client_flags
+            )));
+        }
+
+        if self.publish_reverse_ptr && !self.addresses.is_empty() {
+            return Err(Error::Unsupported(
+                "publishing reverse PTR records for addresses".to_string(),
+            ));
+        }
+
+        if self.track_interface_changes && !self.addresses.is_empty() {
+            return Err(Error::Unsupported(
+                "tracking interface changes for addresses".to_string(),
+            ));
+        }
+
+        if self.follow_interfaces {
+            return Err(Error::Unsupported("following interfaces".to_string()));
+        }
+
+        self.register_addresses()?;
+
         let txt_len = self
             .txt_record
             .as_ref()
@@ -112,13 +406,34 @@ impl TMdnsService for BonjourMdnsService {
             .map(|t| t.inner().get_bytes_ptr())
             .unwrap_or_null();
 
-        self.service.lock().unwrap().register_service(
+        let domain = self
+            .domain
+            .clone()
+            .or_else(|| bonjour_util::scope_domain(self.scope));
+
+        let mut flags = constants::BONJOUR_RENAME_FLAGS;
+
+        if self.no_probe {
+            flags |= constants::BONJOUR_KNOWN_UNIQUE_FLAGS;
+        }
+
+        let mut service = self.service.lock().unwrap();
+
+        // frees any earlier registration, such as one lost when the daemon restarted
+        *service = ManagedDNSServiceRef::default();
+
+        if let Some(connection) = &self.connection {
+            flags |= constants::BONJOUR_SHARE_CONNECTION_FLAGS;
+            service.share_connection(&connection.lock().unwrap());
+        }
+
+        service.register_service(
             RegisterServiceParams::builder()
-                .flags(constants::BONJOUR_RENAME_FLAGS)
+                .flags(flags)
                 .interface_index(self.interface_index)
                 .name(self.name.as_ref().as_c_chars().unwrap_or_null())
                 .regtype(self.kind.as_ptr())
-                .domain(self.domain.as_ref().as_c_chars().unwrap_or_null())
+                .domain(domain.as_ref().as_c_chars().unwrap_or_null())
                 .host(self.host.as_ref().as_c_chars().unwrap_or_null())
                 .port(self.port)
                 .txt_len(txt_len)
@@ -128,36 +443,309 @@ impl TMdnsService for BonjourMdnsService {
                 .build()?,
         )?;
 
-        Ok(EventLoop::new(self.service.clone()))
+        drop(service);
+
+        // Bonjour renames the service itself on collisions, which is only noticed once it
+        // reports the name it was registered under
+        self.context.requested_name = self
+            .name
+            .as_ref()
+            .map(|name| name.to_string_lossy().into_owned());
+
+        self.context.set_state(RegistrationState::Registering);
+
+        self.context
+            .published
+            .try_borrow_mut()
+            .map_err(|_| Error::Reentrant)?
+            .txt_record = self.txt_record.clone();
+
+        // Bonjour has no client state, but the daemon has accepted the registration
+        self.context
+            .invoke_client_state_callback(ClientState::Running);
+
+        *self.txt_timer.borrow_mut() =
+            self.txt_provider.as_ref().map(|(interval, txt_provider)| {
+                self.loop_state.register_timer(
+                    *interval,
+                    service::txt_provider_tick(
+                        self.handle(),
+                        Rc::clone(txt_provider),
+                        self.txt_record.clone(),
+                    ),
+                )
+            });
+
+        let service = Arc::clone(&self.service);
+        let address_records = Arc::clone(&self.address_records);
+        let txt_timer = Rc::clone(&self.txt_timer);
+
+        self.teardown = Some(self.loop_state.register_teardown(move || {
+            debug!("Withdrawing service");
+            txt_timer.borrow_mut().take();
+            *service.lock().unwrap() = ManagedDNSServiceRef::default();
+            *address_records.lock().unwrap() = None;
+        }));
+
+        // events for a shared connection are only delivered through the connection itself
+        let event_loop_service = self.connection.as_ref().unwrap_or(&self.service);
+
+        Ok(EventLoop::new(
+            event_loop_service.clone(),
+            Rc::clone(&self.loop_state),
+        ))
+    }
+
+    fn unregister(&mut self) -> Result<()> {
+        debug!("Unregistering service: {:?}", self);
+
+        // `DNSServiceRefDeallocate()` does not report errors, so withdrawing always succeeds
+        *self
+            .context
+            .published
+            .try_borrow_mut()
+            .map_err(|_| Error::Reentrant)? = BonjourPublishedService::default();
+
+        self.teardown = None;
+        self.txt_timer.borrow_mut().take();
+        *self.service.lock().unwrap() = ManagedDNSServiceRef::default();
+        *self.address_records.lock().unwrap() = None;
+
+        Ok(())
+    }
+}
+
+impl BonjourMdnsService {
+    fn register_addresses(&mut self) -> Result<()> {
+        *self.address_records.lock().unwrap() = None;
+
+        if self.addresses.is_empty() {
+            return Ok(());
+        }
+
+        // the records are removed when the connection is deallocated
+        let mut records = ManagedDNSServiceRef::new();
+        records.create_connection()?;
+
+        let mut flags = bonjour_sys::kDNSServiceFlagsUnique;
+
+        if self.no_probe {
+            flags |= constants::BONJOUR_KNOWN_UNIQUE_FLAGS;
+        }
+
+        for address in &self.addresses {
+            debug!("Adding address: {}", address);
+
+            let (rrtype, rdata) = match address {
+                IpAddr::V4(v4) => (bonjour_sys::kDNSServiceType_A, v4.octets().to_vec()),
+                IpAddr::V6(v6) => (bonjour_sys::kDNSServiceType_AAAA, v6.octets().to_vec()),
+            };
+
+            records.register_record(
+                RegisterRecordParams::builder()
+                    .flags(flags)
+                    .interface_index(self.interface_index)
+                    .fullname(self.host.as_ref().unwrap().as_ptr())
+                    .rrtype(rrtype as u16)
+                    .rrclass(bonjour_sys::kDNSServiceClass_IN as u16)
+                    .rdlen(rdata.len() as u16)
+                    .rdata(rdata.as_ptr() as *const c_void)
+                    .ttl(self.address_ttl.unwrap_or(0))
+                    .callback(Some(register_record_callback))
+                    .context(ptr::null_mut())
+                    .build()?,
+            )?;
+        }
+
+        *self.address_records.lock().unwrap() = Some(records);
+
+        Ok(())
+    }
+
+    /// Creates a new service that registers using the specified shared connection instead of
+    /// creating its own.
+    pub(crate) fn with_connection(
+        service_type: ServiceType,
+        port: u16,
+        connection: Arc<Mutex<ManagedDNSServiceRef>>,
+        loop_state: Rc<LoopState>,
+    ) -> Self {
+        let mut service = Self::new(service_type, port);
+        service.loop_state = loop_state;
+        service.connection = Some(connection);
+        service
+    }
+}
+
+/// Bonjour implementation of [`TServiceHandle`].
+///
+/// [`TServiceHandle`]: ../../service/trait.TServiceHandle.html
+#[derive(Debug, Clone)]
+pub struct BonjourServiceHandle {
+    published: Weak<RefCell<BonjourPublishedService>>,
+}
+
+impl TServiceHandle for BonjourServiceHandle {
+    fn update_txt_record(&self, txt_record: TxtRecord) -> Result<()> {
+        let published = self
+            .published
+            .upgrade()
+            .ok_or_else(|| Error::from("service has been dropped"))?;
+
+        let mut published = published.try_borrow_mut().map_err(|_| Error::Reentrant)?;
+
+        published.update_txt_record(txt_record)
+    }
+
+    fn set_txt_key(&self, key: &str, value: &str) -> Result<()> {
+        self.update_txt_entries(|txt_record| txt_record.insert(key, value))
+    }
+
+    fn remove_txt_key(&self, key: &str) -> Result<()> {
+        self.update_txt_entries(|txt_record| {
+            txt_record.remove(key);
+            Ok(())
+        })
+    }
+
+    /// Bonjour cannot change the SRV record of a registration, so this always fails once the
+    /// service has been registered.
+    fn update_port(&self, _port: u16) -> Result<()> {
+        let published = self
+            .published
+            .upgrade()
+            .ok_or_else(|| Error::from("service has been dropped"))?;
+
+        if published
+            .try_borrow()
+            .map_err(|_| Error::Reentrant)?
+            .sd_ref
+            .is_null()
+        {
+            return Err("service has not been registered".into());
+        }
+
+        Err("Bonjour does not support changing the port of a registered service".into())
+    }
+}
+
+impl BonjourServiceHandle {
+    fn update_txt_entries<F>(&self, f: F) -> Result<()>
+    where
+        F: FnOnce(&mut TxtRecord) -> Result<()>,
+    {
+        let published = self
+            .published
+            .upgrade()
+            .ok_or_else(|| Error::from("service has been dropped"))?;
+
+        let mut published = published.try_borrow_mut().map_err(|_| Error::Reentrant)?;
+
+        if published.sd_ref.is_null() {
+            return Err("service has not been registered".into());
+        }
+
+        let mut txt_record = published.txt_record.clone().unwrap_or_default();
+        f(&mut txt_record)?;
+
+        published.update_txt_record(txt_record)
+    }
+}
+
+/// State of a registered service that is shared with its `BonjourServiceHandle`s.
+#[derive(Debug)]
+struct BonjourPublishedService {
+    sd_ref: DNSServiceRef,
+    txt_record: Option<TxtRecord>,
+}
+
+impl Default for BonjourPublishedService {
+    fn default() -> Self {
+        Self {
+            sd_ref: ptr::null_mut(),
+            txt_record: None,
+        }
+    }
+}
+
+impl BonjourPublishedService {
+    fn update_txt_record(&mut self, txt_record: TxtRecord) -> Result<()> {
+        if self.sd_ref.is_null() {
+            return Err("service has not been registered".into());
+        }
+
+        bonjour_util::sys_exec(
+            || unsafe {
+                bonjour_sys::DNSServiceUpdateRecord(
+                    self.sd_ref,
+                    ptr::null_mut(),
+                    0,
+                    txt_record.inner().get_length(),
+                    txt_record.inner().get_bytes_ptr(),
+                    0,
+                )
+            },
+            "could not update service TXT record",
+        )?;
+
+        self.txt_record = Some(txt_record);
+
+        Ok(())
     }
 }
 
 #[derive(Default, FromRaw, AsRaw)]
 struct BonjourServiceContext {
     registered_callback: Option<Box<ServiceRegisteredCallback>>,
+    client_state_callback: Option<Box<ClientStateCallback>>,
+    // only stored, Bonjour does not report host name changes
+    #[allow(dead_code)]
+    host_name_changed_callback: Option<Box<HostNameChangedCallback>>,
+    published: Rc<RefCell<BonjourPublishedService>>,
+    own_service: RefCell<OwnService>,
+    requested_name: Option<String>,
+    states: RefCell<RegistrationStates>,
     user_context: Option<Arc<dyn Any>>,
 }
 // Necessary for BonjourMdnsService, cant be `derive`d because of registered_callback
 impl std::fmt::Debug for BonjourServiceContext {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("BonjourServiceContext")
+            .field("published", &self.published)
             .field("user_context", &self.user_context)
             .finish()
     }
 }
 
 impl BonjourServiceContext {
-    fn invoke_callback(&self, result: Result<ServiceRegistration>) {
-        if let Some(f) = &self.registered_callback {
-            f(result, self.user_context.clone());
-        } else {
-            warn!("attempted to invoke callback but none was set");
+    fn set_state(&self, state: RegistrationState) {
+        self.states.borrow_mut().set(state);
+    }
+
+    /// Invokes the registered callback, returning `false` if it panicked.
+    fn invoke_callback(&self, result: Result<ServiceRegistration>) -> bool {
+        match &self.registered_callback {
+            Some(f) => ffi::catch_callback_panic("service registered callback", || {
+                f(result, self.user_context.clone())
+            }),
+            None => {
+                warn!("attempted to invoke callback but none was set");
+                true
+            }
+        }
+    }
+
+    fn invoke_client_state_callback(&self, state: ClientState) {
+        if let Some(f) = &self.client_state_callback {
+            ffi::catch_callback_panic("client state callback", || {
+                f(state, self.user_context.clone())
+            });
         }
     }
 }
 
 unsafe extern "C" fn register_callback(
-    _sd_ref: DNSServiceRef,
+    sd_ref: DNSServiceRef,
     _flags: DNSServiceFlags,
     error: DNSServiceErrorType,
     name: *const c_char,
@@ -166,11 +754,37 @@ unsafe extern "C" fn register_callback(
     context: *mut c_void,
 ) {
     let context = BonjourServiceContext::from_raw(context);
+
+    match context.published.try_borrow_mut() {
+        Ok(mut published) => published.sd_ref = sd_ref,
+        Err(_) => {
+            context.invoke_callback(Err(Error::Reentrant));
+            return;
+        }
+    }
+
+    if error == constants::BONJOUR_ERR_SERVICE_NOT_RUNNING {
+        context.invoke_client_state_callback(ClientState::Failure);
+    }
+
     if let Err(e) = handle_register(context, error, domain, name, regtype) {
+        context.set_state(RegistrationState::Failure(e.clone()));
         context.invoke_callback(Err(e));
     }
 }
 
+unsafe extern "C" fn register_record_callback(
+    _sd_ref: DNSServiceRef,
+    _record_ref: DNSRecordRef,
+    _flags: DNSServiceFlags,
+    error: DNSServiceErrorType,
+    _context: *mut c_void,
+) {
+    if error != 0 {
+        warn!("could not register address record (code: {})", error);
+    }
+}
+
 unsafe fn handle_register(
     context: &BonjourServiceContext,
     error: DNSServiceErrorType,
@@ -192,7 +806,21 @@ unsafe fn handle_register(
         .build()
         .expect("could not build ServiceRegistration");
 
-    context.invoke_callback(Ok(result));
+    context.own_service.borrow_mut().set(&result);
+
+    if let Some(requested_name) = &context.requested_name {
+        if result.name() != requested_name {
+            context.set_state(RegistrationState::Collision(result.name().clone()));
+        }
+    }
+
+    context.set_state(RegistrationState::Established);
+
+    if !context.invoke_callback(Ok(result)) {
+        context.set_state(RegistrationState::Failure(
+            "service registered callback panicked".into(),
+        ));
+    }
 
     Ok(())
 }
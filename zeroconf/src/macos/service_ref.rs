@@ -1,14 +1,17 @@
 //! Low level interface for interacting with `DNSserviceRef`
 
-use crate::{macos::bonjour_util, Result};
+use crate::error::Error;
+use crate::{ffi, macos::bonjour_util, macos::constants, Result};
 use bonjour_sys::{
-    DNSServiceBrowse, DNSServiceBrowseReply, DNSServiceFlags, DNSServiceGetAddrInfo,
-    DNSServiceGetAddrInfoReply, DNSServiceProcessResult, DNSServiceProtocol, DNSServiceRef,
-    DNSServiceRefDeallocate, DNSServiceRefSockFD, DNSServiceRegister, DNSServiceRegisterReply,
-    DNSServiceResolve, DNSServiceResolveReply,
+    DNSServiceBrowse, DNSServiceBrowseReply, DNSServiceCreateConnection, DNSServiceDomainEnumReply,
+    DNSServiceEnumerateDomains, DNSServiceFlags, DNSServiceGetAddrInfo, DNSServiceGetAddrInfoReply,
+    DNSServiceProcessResult, DNSServiceProtocol, DNSServiceQueryRecord, DNSServiceQueryRecordReply,
+    DNSServiceRef, DNSServiceRefDeallocate, DNSServiceRefSockFD, DNSServiceRegister,
+    DNSServiceRegisterReply, DNSServiceResolve, DNSServiceResolveReply,
 };
 use libc::{c_char, c_void};
 use std::ptr;
+use std::time::Duration;
 
 /// Wraps the `DNSServiceRef` type from the raw Bonjour bindings.
 ///
@@ -28,6 +31,31 @@ impl ManagedDNSServiceRef {
         Self(ptr::null_mut())
     }
 
+    /// Delegate function for [`DNSServiceCreateConnection`].
+    ///
+    /// The resulting connection may be shared by other instances through
+    /// [`share_connection()`].
+    ///
+    /// [`DNSServiceCreateConnection`]: https://developer.apple.com/documentation/dnssd/1804724-dnsservicecreateconnection?language=objc
+    /// [`share_connection()`]: #method.share_connection
+    pub fn create_connection(&mut self) -> Result<()> {
+        bonjour_util::sys_exec(
+            || unsafe { DNSServiceCreateConnection(&mut self.0 as *mut DNSServiceRef) },
+            "could not create connection",
+        )
+    }
+
+    /// Prepares this instance to share the connection of the specified instance, created with
+    /// [`create_connection()`]. The next delegate function called with `kDNSServiceFlagsShareConnection`
+    /// replaces the copied reference with a subordinate one.
+    ///
+    /// The connection must outlive this instance.
+    ///
+    /// [`create_connection()`]: #method.create_connection
+    pub(crate) fn share_connection(&mut self, connection: &ManagedDNSServiceRef) {
+        self.0 = connection.0;
+    }
+
     /// Delegate function for [`DNSServiceRegister`].
     ///
     /// [`DNSServiceRegister`]: https://developer.apple.com/documentation/dnssd/1804733-dnsserviceregister?language=objc
@@ -66,6 +94,52 @@ impl ManagedDNSServiceRef {
             },
             "could not register service",
         )
+        .map_err(|e| self.unshare_on_error(flags, e))
+    }
+
+    /// Delegate function for [`DNSServiceRegisterRecord`].
+    ///
+    /// Must be called on a connection created with [`create_connection()`]. The record remains
+    /// registered until the connection is deallocated.
+    ///
+    /// [`DNSServiceRegisterRecord`]: https://developer.apple.com/documentation/dnssd/1804727-dnsserviceregisterrecord?language=objc
+    /// [`create_connection()`]: #method.create_connection
+    pub fn register_record(
+        &mut self,
+        RegisterRecordParams {
+            flags,
+            interface_index,
+            fullname,
+            rrtype,
+            rrclass,
+            rdlen,
+            rdata,
+            ttl,
+            callback,
+            context,
+        }: RegisterRecordParams,
+    ) -> Result<()> {
+        let mut record: DNSRecordRef = ptr::null_mut();
+
+        bonjour_util::sys_exec(
+            || unsafe {
+                DNSServiceRegisterRecord(
+                    self.0,
+                    &mut record as *mut DNSRecordRef,
+                    flags,
+                    interface_index,
+                    fullname,
+                    rrtype,
+                    rrclass,
+                    rdlen,
+                    rdata,
+                    ttl,
+                    callback,
+                    context,
+                )
+            },
+            "could not register record",
+        )
     }
 
     /// Delegate function for [`DNSServiceBrowse`].
@@ -96,10 +170,37 @@ impl ManagedDNSServiceRef {
             },
             "could not browse services",
         )
+        .map_err(|e| self.unshare_on_error(flags, e))
     }
 
-    /// Delegate function fro [`DNSServiceResolve`].
+    /// Delegate function for `DNSServiceEnumerateDomains`.
+    pub fn enumerate_domains(
+        &mut self,
+        EnumerateDomainsParams {
+            flags,
+            interface_index,
+            callback,
+            context,
+        }: EnumerateDomainsParams,
+    ) -> Result<()> {
+        bonjour_util::sys_exec(
+            || unsafe {
+                DNSServiceEnumerateDomains(
+                    &mut self.0 as *mut DNSServiceRef,
+                    flags,
+                    interface_index,
+                    callback,
+                    context,
+                )
+            },
+            "could not enumerate domains",
+        )
+    }
+
+    /// Delegate function fro [`DNSServiceResolve`]. Blocks until the service has been resolved, or
+    /// returns [`Error::Timeout`] if a `timeout` was set and it elapses first.
     ///
+    /// [`Error::Timeout`]: ../../error/enum.Error.html#variant.Timeout
     /// [`DNSServiceResolve`]: https://developer.apple.com/documentation/dnssd/1804744-dnsserviceresolve?language=objc
     pub fn resolve_service(
         &mut self,
@@ -111,6 +212,7 @@ impl ManagedDNSServiceRef {
             domain,
             callback,
             context,
+            timeout,
         }: ServiceResolveParams,
     ) -> Result<()> {
         bonjour_util::sys_exec(
@@ -129,6 +231,12 @@ impl ManagedDNSServiceRef {
             "DNSServiceResolve() reported error",
         )?;
 
+        if let Some(timeout) = timeout {
+            if unsafe { ffi::macos::read_select(self.sock_fd(), timeout)? } == 0 {
+                return Err(Error::Timeout);
+            }
+        }
+
         self.process_result()
     }
 
@@ -164,6 +272,41 @@ impl ManagedDNSServiceRef {
         self.process_result()
     }
 
+    /// Delegate function for [`DNSServiceQueryRecord`]. Blocks until the first record has been
+    /// received.
+    ///
+    /// [`DNSServiceQueryRecord`]: https://developer.apple.com/documentation/dnssd/1804747-dnsservicequeryrecord?language=objc
+    pub fn query_record(
+        &mut self,
+        QueryRecordParams {
+            flags,
+            interface_index,
+            fullname,
+            rrtype,
+            rrclass,
+            callback,
+            context,
+        }: QueryRecordParams,
+    ) -> Result<()> {
+        bonjour_util::sys_exec(
+            || unsafe {
+                DNSServiceQueryRecord(
+                    &mut self.0 as *mut DNSServiceRef,
+                    flags,
+                    interface_index,
+                    fullname,
+                    rrtype,
+                    rrclass,
+                    callback,
+                    context,
+                )
+            },
+            "DNSServiceQueryRecord() reported error",
+        )?;
+
+        self.process_result()
+    }
+
     /// Delegate function for [`DNSServiceProcessResult`].
     ///
     /// [`DNSServiceProcessResult`]: https://developer.apple.com/documentation/dnssd/1804696-dnsserviceprocessresult?language=objc
@@ -180,6 +323,15 @@ impl ManagedDNSServiceRef {
     pub fn sock_fd(&self) -> i32 {
         unsafe { DNSServiceRefSockFD(self.0) }
     }
+
+    fn unshare_on_error(&mut self, flags: DNSServiceFlags, error: Error) -> Error {
+        // a failed call leaves the copied reference to the shared connection in place, which
+        // must not be deallocated by this instance
+        if flags & constants::BONJOUR_SHARE_CONNECTION_FLAGS != 0 {
+            self.0 = ptr::null_mut();
+        }
+        error
+    }
 }
 
 impl Default for ManagedDNSServiceRef {
@@ -214,6 +366,21 @@ pub struct RegisterServiceParams {
     context: *mut c_void,
 }
 
+/// Holds parameters for `ManagedDNSServiceRef::register_record()`.
+#[derive(Builder, BuilderDelegate)]
+pub struct RegisterRecordParams {
+    flags: DNSServiceFlags,
+    interface_index: u32,
+    fullname: *const c_char,
+    rrtype: u16,
+    rrclass: u16,
+    rdlen: u16,
+    rdata: *const c_void,
+    ttl: u32,
+    callback: DNSServiceRegisterRecordReply,
+    context: *mut c_void,
+}
+
 /// Holds parameters for `ManagedDNSServiceRef::browse_services()`.
 #[derive(Builder, BuilderDelegate)]
 pub struct BrowseServicesParams {
@@ -225,6 +392,15 @@ pub struct BrowseServicesParams {
     context: *mut c_void,
 }
 
+/// Holds parameters for `ManagedDNSServiceRef::enumerate_domains()`.
+#[derive(Builder, BuilderDelegate)]
+pub struct EnumerateDomainsParams {
+    flags: DNSServiceFlags,
+    interface_index: u32,
+    callback: DNSServiceDomainEnumReply,
+    context: *mut c_void,
+}
+
 /// Holds parameters for `ManagedDNSServiceRef::resolve_service()`.
 #[derive(Builder, BuilderDelegate)]
 pub struct ServiceResolveParams {
@@ -235,6 +411,8 @@ pub struct ServiceResolveParams {
     domain: *const c_char,
     callback: DNSServiceResolveReply,
     context: *mut c_void,
+    #[builder(default)]
+    timeout: Option<Duration>,
 }
 
 /// Holds parameters for `ManagedDNSServiceRef::get_address_info()`.
@@ -247,3 +425,15 @@ pub struct GetAddressInfoParams {
     callback: DNSServiceGetAddrInfoReply,
     context: *mut c_void,
 }
+
+/// Holds parameters for `ManagedDNSServiceRef::query_record()`.
+#[derive(Builder, BuilderDelegate)]
+pub struct QueryRecordParams {
+    flags: DNSServiceFlags,
+    interface_index: u32,
+    fullname: *const c_char,
+    rrtype: u16,
+    rrclass: u16,
+    callback: DNSServiceQueryRecordReply,
+    context: *mut c_void,
+}
@@ -1,28 +1,75 @@
 //! Bonjour implementation for cross-platform TXT record.
 
 use super::txt_record_ref::ManagedTXTRecordRef;
-use crate::txt_record::TTxtRecord;
+use crate::txt_record::{self, TTxtRecord};
 use crate::Result;
 use libc::{c_char, c_void};
 use std::ffi::CString;
-use std::{ptr, slice};
+use std::ops::Index;
+use std::{ptr, slice, str};
 
 /// Interface for interfacing with Bonjour's TXT record capabilities.
 #[derive(Clone)]
-pub struct BonjourTxtRecord(ManagedTXTRecordRef);
+pub struct BonjourTxtRecord {
+    txt: ManagedTXTRecordRef,
+    duplicate_keys: Vec<String>,
+}
 
 impl TTxtRecord for BonjourTxtRecord {
     fn new() -> Self {
-        Self(ManagedTXTRecordRef::new())
+        Self::from(ManagedTXTRecordRef::new())
+    }
+
+    /// Reserves [`ESTIMATED_ENTRY_LEN`] bytes per entry in the buffer the record is built in.
+    /// Without it, Bonjour reallocates the buffer on every insertion to fit exactly.
+    ///
+    /// [`ESTIMATED_ENTRY_LEN`]: ../../txt_record/constant.ESTIMATED_ENTRY_LEN.html
+    fn with_capacity(capacity: usize) -> Self {
+        let len = capacity.saturating_mul(txt_record::ESTIMATED_ENTRY_LEN);
+        let len = len.min(u16::MAX as usize) as u16;
+
+        Self::from(ManagedTXTRecordRef::with_buffer_len(len))
+    }
+
+    fn from_bytes(data: &[u8]) -> Result<Self> {
+        let (entries, duplicate_keys) = txt_record::parse_entries(data)?;
+        let mut txt = ManagedTXTRecordRef::new();
+
+        for entry in entries {
+            let key = txt_record::entry_key(entry);
+            let c_key = c_string!(String::from_utf8_lossy(key).into_owned());
+
+            // a key without a value is set with a null value pointer
+            let value = entry.get(key.len() + 1..);
+
+            unsafe {
+                txt.set_value(
+                    c_key.as_ptr() as *const c_char,
+                    value.map_or(0, |v| v.len() as u8),
+                    value.map_or(ptr::null(), |v| v.as_ptr() as *const c_void),
+                )?;
+            }
+        }
+
+        Ok(Self {
+            txt,
+            duplicate_keys,
+        })
+    }
+
+    fn duplicate_keys(&self) -> Vec<String> {
+        self.duplicate_keys.clone()
     }
 
     fn insert(&mut self, key: &str, value: &str) -> Result<()> {
+        txt_record::check_entry_length(key, value.as_bytes())?;
+
         let key = c_string!(key);
         let value = c_string!(value);
         let value_size = value.as_bytes().len();
 
         unsafe {
-            self.0.set_value(
+            self.txt.set_value(
                 key.as_ptr() as *const c_char,
                 value_size as u8,
                 value.as_ptr() as *const c_void,
@@ -30,13 +77,27 @@ impl TTxtRecord for BonjourTxtRecord {
         }
     }
 
+    fn insert_bytes(&mut self, key: &str, value: &[u8]) -> Result<()> {
+        txt_record::check_entry_length(key, value)?;
+
+        let key = c_string!(key);
+
+        unsafe {
+            self.txt.set_value(
+                key.as_ptr() as *const c_char,
+                value.len() as u8,
+                value.as_ptr() as *const c_void,
+            )
+        }
+    }
+
     fn get(&self, key: &str) -> Option<String> {
         let mut value_len: u8 = 0;
 
         let c_str = c_string!(key);
 
         let value_raw = unsafe {
-            self.0
+            self.txt
                 .get_value_ptr(c_str.as_ptr() as *const c_char, &mut value_len)
         };
 
@@ -52,7 +113,7 @@ impl TTxtRecord for BonjourTxtRecord {
         let prev = self.get(key)?;
 
         unsafe {
-            self.0
+            self.txt
                 .remove_value(c_str.as_ptr() as *const c_char)
                 .unwrap()
         };
@@ -62,11 +123,11 @@ impl TTxtRecord for BonjourTxtRecord {
 
     fn contains_key(&self, key: &str) -> bool {
         let c_str = c_string!(key);
-        unsafe { self.0.contains_key(c_str.as_ptr() as *const c_char) }
+        unsafe { self.txt.contains_key(c_str.as_ptr() as *const c_char) }
     }
 
     fn len(&self) -> usize {
-        self.0.get_count() as usize
+        self.txt.get_count() as usize
     }
 
     fn iter<'a>(&'a self) -> Box<dyn Iterator<Item = (String, String)> + 'a> {
@@ -80,17 +141,60 @@ impl TTxtRecord for BonjourTxtRecord {
     fn values<'a>(&'a self) -> Box<dyn Iterator<Item = String> + 'a> {
         Box::new(Values(Iter::new(self)))
     }
+
+    fn iter_bytes<'a>(&'a self) -> Box<dyn Iterator<Item = (String, &'a [u8])> + 'a> {
+        Box::new(BytesIter::new(self))
+    }
 }
 
 impl BonjourTxtRecord {
     pub(super) fn inner(&self) -> &ManagedTXTRecordRef {
-        &self.0
+        &self.txt
+    }
+}
+
+/// Returns the value at the specified key.
+///
+/// # Panics
+///
+/// Panics if the key does not exist or its value is not valid UTF-8. Use [`TTxtRecord::get()`]
+/// for fallible access.
+///
+/// [`TTxtRecord::get()`]: ../../txt_record/trait.TTxtRecord.html#tymethod.get
+impl Index<&str> for BonjourTxtRecord {
+    type Output = str;
+
+    fn index(&self, key: &str) -> &str {
+        let mut value_len: u8 = 0;
+        let c_str = c_string!(key);
+
+        let value_raw = unsafe {
+            self.txt
+                .get_value_ptr(c_str.as_ptr() as *const c_char, &mut value_len)
+        };
+
+        if value_raw.is_null() {
+            // a key without a value has no value pointer
+            if self.contains_key(key) {
+                return "";
+            }
+
+            panic!("TxtRecord has no key {:?}", key);
+        }
+
+        let value = unsafe { slice::from_raw_parts(value_raw as *const u8, value_len as usize) };
+
+        str::from_utf8(value)
+            .unwrap_or_else(|_| panic!("TxtRecord value at key {:?} is not valid UTF-8", key))
     }
 }
 
 impl From<ManagedTXTRecordRef> for BonjourTxtRecord {
     fn from(txt: ManagedTXTRecordRef) -> Self {
-        Self(txt)
+        Self {
+            txt,
+            duplicate_keys: Vec::new(),
+        }
     }
 }
 
@@ -101,35 +205,54 @@ impl PartialEq for BonjourTxtRecord {
 }
 
 /// An `Iterator` that allows iteration over a [`BonjourTxtRecord`] similar to a `HashMap`.
+pub struct Iter<'a>(BytesIter<'a>);
+
+impl<'a> Iter<'a> {
+    pub fn new(record: &'a BonjourTxtRecord) -> Self {
+        Self(BytesIter::new(record))
+    }
+}
+
+impl Iterator for Iter<'_> {
+    type Item = (String, String);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0
+            .next()
+            .map(|(key, value)| (key, String::from_utf8(value.to_vec()).unwrap()))
+    }
+}
+
+/// An `Iterator` that allows iteration over a [`BonjourTxtRecord`]'s keys and raw values.
 #[derive(new)]
-pub struct Iter<'a> {
+pub struct BytesIter<'a> {
     record: &'a BonjourTxtRecord,
     #[new(default)]
     index: usize,
 }
 
-impl Iter<'_> {
+impl BytesIter<'_> {
     const KEY_LEN: u16 = 256;
 }
 
-impl Iterator for Iter<'_> {
-    type Item = (String, String);
+impl<'a> Iterator for BytesIter<'a> {
+    type Item = (String, &'a [u8]);
 
     fn next(&mut self) -> Option<Self::Item> {
         if self.index == self.record.len() {
             return None;
         }
 
-        let raw_key: CString = unsafe { c_string!(alloc(Iter::KEY_LEN as usize)) };
+        let raw_key: CString = unsafe { c_string!(alloc(BytesIter::KEY_LEN as usize)) };
         let mut value_len: u8 = 0;
         let mut value: *const c_void = ptr::null_mut();
 
         unsafe {
             self.record
-                .0
+                .txt
                 .get_item_at_index(
                     self.index as u16,
-                    Iter::KEY_LEN,
+                    BytesIter::KEY_LEN,
                     raw_key.as_ptr() as *mut c_char,
                     &mut value_len,
                     &mut value,
@@ -137,13 +260,16 @@ impl Iterator for Iter<'_> {
                 .unwrap();
         }
 
-        assert_not_null!(value);
-
         let key = String::from(raw_key.to_str().unwrap())
             .trim_matches(char::from(0))
             .to_string();
 
-        let value = unsafe { read_value(value, value_len) };
+        // the value points into the record's buffer, which outlives the iterator
+        let value = if value.is_null() {
+            &[][..]
+        } else {
+            unsafe { slice::from_raw_parts(value as *const u8, value_len as usize) }
+        };
 
         self.index += 1;
 
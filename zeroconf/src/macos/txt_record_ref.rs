@@ -15,7 +15,7 @@ use super::bonjour_util;
 /// Wraps the `ManagedTXTRecordRef` type from the raw Bonjour bindings.
 ///
 /// `zeroconf::TxtRecord` provides the cross-platform bindings for this functionality.
-pub struct ManagedTXTRecordRef(TXTRecordRef);
+pub struct ManagedTXTRecordRef(TXTRecordRef, Option<Box<[u8]>>);
 
 impl ManagedTXTRecordRef {
     /// Creates a new empty TXT record
@@ -26,7 +26,22 @@ impl ManagedTXTRecordRef {
             record
         };
 
-        Self(record)
+        Self(record, None)
+    }
+
+    /// Creates a new empty TXT record backed by a buffer of `len` bytes, which Bonjour only
+    /// replaces with one it allocates itself once the record outgrows it.
+    pub fn with_buffer_len(len: u16) -> Self {
+        // the buffer is boxed, so it stays in place when the record is moved
+        let mut buffer = vec![0; len as usize].into_boxed_slice();
+
+        let record = unsafe {
+            let mut record: TXTRecordRef = mem::zeroed();
+            TXTRecordCreate(&mut record, len, buffer.as_mut_ptr() as *mut c_void);
+            record
+        };
+
+        Self(record, Some(buffer))
     }
 
     /// Delegate function for [`TXTRecordGetBytes()`].
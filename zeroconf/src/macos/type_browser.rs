@@ -0,0 +1,173 @@
+//! Bonjour implementation for cross-platform service type browser
+
+use super::service_ref::{BrowseServicesParams, ManagedDNSServiceRef};
+use super::{bonjour_util, constants};
+use crate::event_loop::{ActiveGuard, ActiveKind, LoopState, TeardownGuard};
+use crate::ffi::{self, c_str, AsRaw, FromRaw};
+use crate::prelude::*;
+use crate::service_type_browser::META_QUERY_SERVICE_TYPE;
+use crate::{
+    EventLoop, NetworkInterface, NetworkProtocol, Result, ServiceType,
+    ServiceTypeDiscoveredCallback,
+};
+use bonjour_sys::{DNSServiceErrorType, DNSServiceFlags, DNSServiceRef};
+use libc::{c_char, c_void};
+use std::any::Any;
+use std::collections::HashSet;
+use std::ffi::CString;
+use std::fmt::{self, Formatter};
+use std::ptr;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+
+/// Browses for service types by issuing the DNS-SD meta-query with `DNSServiceBrowse()`.
+#[derive(Debug)]
+pub struct BonjourMdnsServiceTypeBrowser {
+    service: Arc<Mutex<ManagedDNSServiceRef>>,
+    kind: CString,
+    interface_index: u32,
+    protocol: NetworkProtocol,
+    context: Box<BonjourServiceTypeBrowserContext>,
+    loop_state: Rc<LoopState>,
+    teardown: Option<TeardownGuard>,
+}
+
+impl TServiceTypeBrowser for BonjourMdnsServiceTypeBrowser {
+    type EventLoop<'a> = EventLoop<'a>;
+
+    fn new() -> Self {
+        Self {
+            service: Arc::default(),
+            kind: c_string!(META_QUERY_SERVICE_TYPE),
+            interface_index: constants::BONJOUR_IF_UNSPEC,
+            protocol: NetworkProtocol::Unspec,
+            context: Box::default(),
+            loop_state: Rc::default(),
+            teardown: None,
+        }
+    }
+
+    fn set_network_interface(&mut self, interface: NetworkInterface) {
+        self.interface_index = bonjour_util::interface_index(interface);
+    }
+
+    fn network_interface(&self) -> NetworkInterface {
+        bonjour_util::interface_from_index(self.interface_index)
+    }
+
+    fn set_network_protocol(&mut self, protocol: NetworkProtocol) {
+        self.protocol = protocol;
+    }
+
+    fn network_protocol(&self) -> NetworkProtocol {
+        self.protocol
+    }
+
+    fn set_service_type_discovered_callback(
+        &mut self,
+        service_type_discovered_callback: Box<ServiceTypeDiscoveredCallback>,
+    ) {
+        self.context.service_type_discovered_callback = Some(service_type_discovered_callback);
+    }
+
+    fn set_context(&mut self, context: Box<dyn Any>) {
+        self.context.user_context = Some(Arc::from(context));
+    }
+
+    fn context(&self) -> Option<&dyn Any> {
+        self.context.user_context.as_ref().map(|c| c.as_ref())
+    }
+
+    fn browse_types(&mut self) -> Result<EventLoop> {
+        debug!("Browsing service types: {:?}", self);
+
+        self.context.discovered.clear();
+
+        self.service.lock().unwrap().browse_services(
+            BrowseServicesParams::builder()
+                .flags(0)
+                .interface_index(self.interface_index)
+                .regtype(self.kind.as_ptr())
+                .domain(ptr::null_mut())
+                .callback(Some(browse_callback))
+                .context(self.context.as_raw())
+                .build()?,
+        )?;
+
+        let active = ActiveGuard::new(&self.loop_state, ActiveKind::Browser);
+        let service = Arc::clone(&self.service);
+
+        self.teardown = Some(self.loop_state.register_teardown(move || {
+            *service.lock().unwrap() = ManagedDNSServiceRef::default();
+            drop(active);
+        }));
+
+        Ok(EventLoop::new(
+            self.service.clone(),
+            Rc::clone(&self.loop_state),
+        ))
+    }
+}
+
+#[derive(Default, FromRaw, AsRaw)]
+struct BonjourServiceTypeBrowserContext {
+    discovered: HashSet<ServiceType>,
+    service_type_discovered_callback: Option<Box<ServiceTypeDiscoveredCallback>>,
+    user_context: Option<Arc<dyn Any>>,
+}
+
+impl BonjourServiceTypeBrowserContext {
+    fn invoke_callback(&self, result: Result<ServiceType>) {
+        if let Some(f) = &self.service_type_discovered_callback {
+            ffi::catch_callback_panic("service type discovered callback", || {
+                f(result, self.user_context.clone())
+            });
+        } else {
+            warn!("attempted to invoke callback but none was set");
+        }
+    }
+}
+
+impl fmt::Debug for BonjourServiceTypeBrowserContext {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BonjourServiceTypeBrowserContext")
+            .field("discovered", &self.discovered)
+            .finish()
+    }
+}
+
+unsafe extern "C" fn browse_callback(
+    _sd_ref: DNSServiceRef,
+    flags: DNSServiceFlags,
+    _interface_index: u32,
+    error: DNSServiceErrorType,
+    name: *const c_char,
+    regtype: *const c_char,
+    _domain: *const c_char,
+    context: *mut c_void,
+) {
+    let ctx = BonjourServiceTypeBrowserContext::from_raw(context);
+
+    if error != 0 {
+        ctx.invoke_callback(Err(format!(
+            "browse_callback() reported error (code: {})",
+            error
+        )
+        .into()));
+        return;
+    }
+
+    if flags & bonjour_sys::kDNSServiceFlagsAdd == 0 {
+        return;
+    }
+
+    match bonjour_util::parse_meta_query_result(c_str::raw_to_str(name), c_str::raw_to_str(regtype))
+    {
+        Ok(service_type) => {
+            if ctx.discovered.insert(service_type.clone()) {
+                ctx.invoke_callback(Ok(service_type));
+            }
+        }
+        Err(e) => ctx.invoke_callback(Err(e)),
+    }
+}
@@ -13,6 +13,20 @@ macro_rules! c_string {
     };
 }
 
+/// Implements a public `build()` for a builder derived with
+/// `#[builder(build_fn(private, name = "try_build"))]`, which returns a crate `Error` naming the
+/// missing field instead of the builder's `String` error.
+macro_rules! builder_build_fn {
+    ($builder:ident, $target:ident) => {
+        impl $builder {
+            /// Builds the value, returning `Error::MissingField` if a required field was not set.
+            pub fn build(&self) -> crate::Result<$target> {
+                self.try_build().map_err(crate::error::Error::from_builder)
+            }
+        }
+    };
+}
+
 #[cfg(test)]
 mod tests {
     use libc::c_char;
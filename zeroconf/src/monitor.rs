@@ -0,0 +1,280 @@
+//! Watchdog that reports whether a specific service is still present on the network
+
+use crate::browser::BLOCKING_POLL_INTERVAL;
+use crate::error::Error;
+use crate::ffi;
+use crate::prelude::*;
+use crate::{
+    CancellationToken, MdnsBrowser, NetworkInterface, Result, ServiceBrowse, ServiceDiscovery,
+    ServiceType,
+};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// Reported to the callback of a [`MdnsMonitor`] when the watched service appears or goes
+/// missing.
+///
+/// [`MdnsMonitor`]: struct.MdnsMonitor.html
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PresenceEvent {
+    /// The service was resolved, either for the first time or after it was reported missing
+    Present(Box<ServiceDiscovery>),
+    /// The service has not been resolved for at least the `missing_after` threshold, which is
+    /// how long it has been since it was last resolved or since watching began
+    Missing(Duration),
+}
+
+/// Periodically re-resolves a specific service on a background thread and reports when it goes
+/// missing for longer than a threshold, or comes back.
+///
+/// The service is re-resolved every third of `missing_after`, but no more often than every 100
+/// milliseconds, with each attempt given that same interval to complete. A service that
+/// disappears is thus reported missing between `missing_after` and `missing_after` plus one
+/// interval after it was last resolved. Only changes are reported: [`PresenceEvent::Present`]
+/// once the service is first resolved and whenever it returns, and [`PresenceEvent::Missing`]
+/// once each time it goes missing.
+///
+/// ```no_run
+/// use std::time::Duration;
+/// use zeroconf::{MdnsMonitor, PresenceEvent, ServiceType};
+///
+/// let monitor = MdnsMonitor::watch(
+///     "Database",
+///     ServiceType::new("postgresql", "tcp").unwrap(),
+///     "local",
+///     Duration::from_secs(30),
+///     |event| match event {
+///         PresenceEvent::Present(service) => println!("up at {}", service.address()),
+///         PresenceEvent::Missing(for_how_long) => println!("down for {:?}", for_how_long),
+///     },
+/// );
+///
+/// std::thread::sleep(Duration::from_secs(300));
+/// monitor.stop().unwrap();
+/// ```
+///
+/// The callback is invoked from the background thread. Dropping the monitor also stops it.
+///
+/// [`PresenceEvent::Present`]: enum.PresenceEvent.html#variant.Present
+/// [`PresenceEvent::Missing`]: enum.PresenceEvent.html#variant.Missing
+#[derive(Debug)]
+pub struct MdnsMonitor {
+    token: CancellationToken,
+    thread: Option<JoinHandle<Result<()>>>,
+}
+
+impl MdnsMonitor {
+    /// Starts watching the service instance `name` of type `kind` in `domain`, invoking
+    /// `callback` whenever it appears or has been missing for `missing_after`.
+    pub fn watch<F>(
+        name: &str,
+        kind: ServiceType,
+        domain: &str,
+        missing_after: Duration,
+        callback: F,
+    ) -> Self
+    where
+        F: Fn(PresenceEvent) + Send + 'static,
+    {
+        let token = CancellationToken::new();
+        let thread_token = token.clone();
+        let name = name.to_string();
+        let domain = domain.to_string();
+
+        let thread = thread::spawn(move || {
+            let service = ServiceBrowse::builder()
+                .name(name)
+                .service_type(kind.clone())
+                .domain(domain)
+                .interface(NetworkInterface::Unspec)
+                .build()?;
+
+            let mut browser = MdnsBrowser::new(kind);
+            browser.set_resolve_services(false);
+            browser.browse_services()?;
+
+            let interval = resolve_interval(missing_after);
+            let mut presence = Presence::new(missing_after, Instant::now());
+
+            while !thread_token.is_cancelled() {
+                let started = Instant::now();
+
+                let resolved = match browser.resolve_timeout(&service, interval) {
+                    Ok(discovery) => Some(discovery),
+                    Err(Error::Timeout) => None,
+                    Err(e) => {
+                        warn!("could not resolve watched service: {}", e);
+                        None
+                    }
+                };
+
+                if let Some(event) = presence.update(Instant::now(), resolved) {
+                    ffi::catch_callback_panic("presence callback", || callback(event));
+                }
+
+                while started.elapsed() < interval && !thread_token.is_cancelled() {
+                    thread::sleep((interval - started.elapsed()).min(BLOCKING_POLL_INTERVAL));
+                }
+            }
+
+            browser.cancel();
+            Ok(())
+        });
+
+        Self {
+            token,
+            thread: Some(thread),
+        }
+    }
+
+    /// Returns `true` if the monitor is still running. It stops early if browsing could not be
+    /// started, in which case the error is returned from [`stop()`].
+    ///
+    /// [`stop()`]: #method.stop
+    pub fn is_running(&self) -> bool {
+        self.thread.as_ref().map_or(false, |t| !t.is_finished())
+    }
+
+    /// Stops watching and waits for the background thread to finish. Returns the error that
+    /// stopped it early, if any.
+    pub fn stop(mut self) -> Result<()> {
+        self.join()
+    }
+
+    fn join(&mut self) -> Result<()> {
+        self.token.cancel();
+
+        match self.thread.take() {
+            Some(thread) => thread
+                .join()
+                .unwrap_or_else(|_| Err(Error::from("monitor thread panicked"))),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for MdnsMonitor {
+    fn drop(&mut self) {
+        if let Err(e) = self.join() {
+            warn!("monitor stopped with error: {}", e);
+        }
+    }
+}
+
+fn resolve_interval(missing_after: Duration) -> Duration {
+    (missing_after / 3).max(BLOCKING_POLL_INTERVAL)
+}
+
+/// Tracks whether the watched service was last reported present or missing.
+#[derive(Debug)]
+struct Presence {
+    missing_after: Duration,
+    last_seen: Instant,
+    present: Option<bool>,
+}
+
+impl Presence {
+    fn new(missing_after: Duration, now: Instant) -> Self {
+        Self {
+            missing_after,
+            last_seen: now,
+            present: None,
+        }
+    }
+
+    /// Records the outcome of a resolution, returning the event to report if the service's
+    /// presence changed.
+    fn update(
+        &mut self,
+        now: Instant,
+        resolved: Option<ServiceDiscovery>,
+    ) -> Option<PresenceEvent> {
+        match resolved {
+            Some(discovery) => {
+                self.last_seen = now;
+
+                if self.present == Some(true) {
+                    return None;
+                }
+
+                self.present = Some(true);
+                Some(PresenceEvent::Present(Box::new(discovery)))
+            }
+            None => {
+                let missing_for = now.duration_since(self.last_seen);
+
+                if self.present == Some(false) || missing_for < self.missing_after {
+                    return None;
+                }
+
+                self.present = Some(false);
+                Some(PresenceEvent::Missing(missing_for))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MISSING_AFTER: Duration = Duration::from_secs(30);
+
+    fn discovery() -> ServiceDiscovery {
+        ServiceDiscovery::builder()
+            .name("Database".to_string())
+            .service_type(ServiceType::new("postgresql", "tcp").unwrap())
+            .domain("local".to_string())
+            .host_name("db.local".to_string())
+            .address("192.168.1.20".to_string())
+            .port(5432)
+            .txt(None)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn presence_reports_only_changes() {
+        let start = Instant::now();
+        let mut presence = Presence::new(MISSING_AFTER, start);
+
+        assert_eq!(
+            presence.update(start, Some(discovery())),
+            Some(PresenceEvent::Present(Box::new(discovery())))
+        );
+        assert_eq!(presence.update(start, Some(discovery())), None);
+
+        let gone = start + MISSING_AFTER;
+
+        assert_eq!(
+            presence.update(gone, None),
+            Some(PresenceEvent::Missing(MISSING_AFTER))
+        );
+        assert_eq!(presence.update(gone + MISSING_AFTER, None), None);
+        assert_eq!(
+            presence.update(gone + MISSING_AFTER, Some(discovery())),
+            Some(PresenceEvent::Present(Box::new(discovery())))
+        );
+    }
+
+    #[test]
+    fn presence_waits_for_threshold() {
+        let start = Instant::now();
+        let mut presence = Presence::new(MISSING_AFTER, start);
+
+        assert_eq!(presence.update(start + MISSING_AFTER / 2, None), None);
+        assert_eq!(
+            presence.update(start + MISSING_AFTER, None),
+            Some(PresenceEvent::Missing(MISSING_AFTER))
+        );
+    }
+
+    #[test]
+    fn resolve_interval_is_a_third_of_threshold() {
+        assert_eq!(resolve_interval(MISSING_AFTER), Duration::from_secs(10));
+        assert_eq!(
+            resolve_interval(Duration::from_millis(30)),
+            BLOCKING_POLL_INTERVAL
+        );
+    }
+}
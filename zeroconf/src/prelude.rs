@@ -1,8 +1,12 @@
 //! Crate prelude
 
 pub use crate::browser::TMdnsBrowser;
+pub use crate::domain_browser::TDomainBrowser;
 pub use crate::event_loop::TEventLoop;
-pub use crate::service::TMdnsService;
+pub use crate::facade::TZeroconf;
+pub use crate::run_loop::TRunnable;
+pub use crate::service::{TMdnsService, TServiceHandle};
+pub use crate::service_type_browser::TServiceTypeBrowser;
 pub use crate::txt_record::TTxtRecord;
 
 /// Implements a `builder()` function for the specified type
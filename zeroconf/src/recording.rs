@@ -0,0 +1,388 @@
+//! Recording browse results to a file and replaying them without an mDNS daemon
+
+use crate::ffi;
+use crate::prelude::*;
+use crate::{Result, ServiceDiscoveredCallback, ServiceDiscovery};
+use std::any::Any;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// A single result reported to a [`RecordingBrowser`]'s callback, along with when it was reported.
+///
+/// [`RecordingBrowser`]: struct.RecordingBrowser.html
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct RecordedEvent {
+    elapsed_ms: u64,
+    result: std::result::Result<ServiceDiscovery, String>,
+}
+
+impl RecordedEvent {
+    /// Returns how long after browsing started the result was reported.
+    pub fn elapsed(&self) -> Duration {
+        Duration::from_millis(self.elapsed_ms)
+    }
+
+    /// Returns the recorded result. Errors are recorded by their description only, so replayed
+    /// errors are always `Error::Generic`.
+    pub fn result(&self) -> Result<ServiceDiscovery> {
+        self.result.clone().map_err(Into::into)
+    }
+}
+
+/// Wraps a browser to record every result reported to its callback, with timing, as it is
+/// reported.
+///
+/// The recording is written as one JSON-serialized [`RecordedEvent`] per line and is flushed
+/// after each event, so a session that ends abruptly still leaves a usable recording. It can be
+/// played back with a [`ReplayBrowser`]. Only available with the `recording` feature.
+///
+/// ```no_run
+/// use zeroconf::prelude::*;
+/// use zeroconf::recording::RecordingBrowser;
+/// use zeroconf::{MdnsBrowser, ServiceType};
+///
+/// let browser = MdnsBrowser::new(ServiceType::new("http", "tcp").unwrap());
+/// let mut recorder = RecordingBrowser::create(browser, "http.jsonl").unwrap();
+///
+/// recorder.set_service_discovered_callback(Box::new(|result, _| println!("{:?}", result)));
+///
+/// let event_loop = recorder.browse_services().unwrap();
+/// event_loop.poll(std::time::Duration::from_secs(5)).unwrap();
+/// ```
+///
+/// The wrapped browser can be configured through [`browser_mut()`], but its callback must be set
+/// on the `RecordingBrowser` for results to be recorded.
+///
+/// [`RecordedEvent`]: struct.RecordedEvent.html
+/// [`ReplayBrowser`]: struct.ReplayBrowser.html
+/// [`browser_mut()`]: #method.browser_mut
+#[derive(Debug)]
+pub struct RecordingBrowser<B> {
+    browser: B,
+    recorder: Arc<Mutex<Recorder>>,
+}
+
+impl<B: TMdnsBrowser> RecordingBrowser<B> {
+    /// Wraps `browser` to record its results to a new file at `path`, replacing any existing file.
+    pub fn create<P: AsRef<Path>>(browser: B, path: P) -> Result<Self> {
+        let file = File::create(path.as_ref()).map_err(|e| {
+            format!(
+                "could not create recording {}: {}",
+                path.as_ref().display(),
+                e
+            )
+        })?;
+
+        Ok(Self::with_writer(browser, file))
+    }
+
+    /// Wraps `browser` to record its results to `writer`.
+    pub fn with_writer<W: Write + Send + 'static>(browser: B, writer: W) -> Self {
+        let mut recording = Self {
+            browser,
+            recorder: Arc::new(Mutex::new(Recorder::new(Box::new(writer)))),
+        };
+
+        recording.install_callback(None);
+        recording
+    }
+
+    /// Sets the [`ServiceDiscoveredCallback`] that is invoked with each result after it has been
+    /// recorded.
+    ///
+    /// [`ServiceDiscoveredCallback`]: ../type.ServiceDiscoveredCallback.html
+    pub fn set_service_discovered_callback(
+        &mut self,
+        service_discovered_callback: Box<ServiceDiscoveredCallback>,
+    ) {
+        self.install_callback(Some(service_discovered_callback));
+    }
+
+    /// Starts browsing with the wrapped browser. Recorded timings are relative to this call.
+    pub fn browse_services(&mut self) -> Result<B::EventLoop<'_>> {
+        self.recorder.lock().unwrap().started = Instant::now();
+        self.browser.browse_services()
+    }
+
+    /// Returns the wrapped browser.
+    pub fn browser(&self) -> &B {
+        &self.browser
+    }
+
+    /// Returns the wrapped browser for configuration.
+    pub fn browser_mut(&mut self) -> &mut B {
+        &mut self.browser
+    }
+
+    /// Returns the wrapped browser, which keeps recording to the same file if it browses again.
+    pub fn into_inner(self) -> B {
+        self.browser
+    }
+
+    fn install_callback(&mut self, callback: Option<Box<ServiceDiscoveredCallback>>) {
+        let recorder = Arc::clone(&self.recorder);
+
+        self.browser
+            .set_service_discovered_callback(Box::new(move |result, context| {
+                recorder.lock().unwrap().record(&result);
+
+                if let Some(f) = &callback {
+                    f(result, context);
+                }
+            }));
+    }
+}
+
+struct Recorder {
+    writer: Box<dyn Write + Send>,
+    started: Instant,
+}
+
+impl Recorder {
+    fn new(writer: Box<dyn Write + Send>) -> Self {
+        Self {
+            writer,
+            started: Instant::now(),
+        }
+    }
+
+    fn record(&mut self, result: &Result<ServiceDiscovery>) {
+        let event = RecordedEvent {
+            elapsed_ms: self.started.elapsed().as_millis() as u64,
+            result: result.clone().map_err(|e| e.to_string()),
+        };
+
+        if let Err(e) = self.write(&event) {
+            warn!("could not record browse event: {}", e);
+        }
+    }
+
+    fn write(&mut self, event: &RecordedEvent) -> std::result::Result<(), String> {
+        let line = serde_json::to_string(event).map_err(|e| e.to_string())?;
+
+        writeln!(self.writer, "{}", line)
+            .and_then(|_| self.writer.flush())
+            .map_err(|e| e.to_string())
+    }
+}
+
+impl std::fmt::Debug for Recorder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Recorder")
+            .field("started", &self.started)
+            .finish()
+    }
+}
+
+/// Plays back a recording made by a [`RecordingBrowser`], invoking a callback with each recorded
+/// result as if it were reported by a live browser.
+///
+/// This allows regression tests against captured real-world network conditions to run without
+/// an mDNS daemon. Only available with the `recording` feature.
+///
+/// ```no_run
+/// use zeroconf::recording::ReplayBrowser;
+///
+/// let mut replay = ReplayBrowser::open("http.jsonl").unwrap();
+///
+/// replay.set_preserve_timing(false);
+/// replay.set_service_discovered_callback(Box::new(|result, _| println!("{:?}", result)));
+/// replay.replay();
+/// ```
+///
+/// [`RecordingBrowser`]: struct.RecordingBrowser.html
+pub struct ReplayBrowser {
+    events: Vec<RecordedEvent>,
+    preserve_timing: bool,
+    service_discovered_callback: Option<Box<ServiceDiscoveredCallback>>,
+    user_context: Option<Arc<dyn Any>>,
+}
+
+impl ReplayBrowser {
+    /// Reads the recording at `path`.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let file = File::open(path.as_ref()).map_err(|e| {
+            format!(
+                "could not open recording {}: {}",
+                path.as_ref().display(),
+                e
+            )
+        })?;
+
+        Self::from_reader(BufReader::new(file))
+    }
+
+    /// Reads a recording from `reader`, returning an error naming the first line that is not a
+    /// valid event. Blank lines are skipped.
+    pub fn from_reader<R: BufRead>(reader: R) -> Result<Self> {
+        let mut events = Vec::new();
+
+        for (i, line) in reader.lines().enumerate() {
+            let line = line.map_err(|e| format!("could not read recording: {}", e))?;
+
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let event = serde_json::from_str(&line)
+                .map_err(|e| format!("invalid event on line {} of recording: {}", i + 1, e))?;
+
+            events.push(event);
+        }
+
+        Ok(Self {
+            events,
+            preserve_timing: true,
+            service_discovered_callback: None,
+            user_context: None,
+        })
+    }
+
+    /// Returns the recorded events, in the order they were reported.
+    pub fn events(&self) -> &[RecordedEvent] {
+        &self.events
+    }
+
+    /// Sets whether [`replay()`] waits between events as long as the recorded session did.
+    /// Defaults to `true`; tests will usually want to disable it.
+    ///
+    /// [`replay()`]: #method.replay
+    pub fn set_preserve_timing(&mut self, preserve_timing: bool) {
+        self.preserve_timing = preserve_timing;
+    }
+
+    /// Sets the [`ServiceDiscoveredCallback`] that is invoked with each recorded result.
+    ///
+    /// [`ServiceDiscoveredCallback`]: ../type.ServiceDiscoveredCallback.html
+    pub fn set_service_discovered_callback(
+        &mut self,
+        service_discovered_callback: Box<ServiceDiscoveredCallback>,
+    ) {
+        self.service_discovered_callback = Some(service_discovered_callback);
+    }
+
+    /// Sets the optional user context to pass through to the callback.
+    pub fn set_context(&mut self, context: Box<dyn Any>) {
+        self.user_context = Some(Arc::from(context));
+    }
+
+    /// Returns the optional user context to pass through to the callback.
+    pub fn context(&self) -> Option<&dyn Any> {
+        self.user_context.as_deref()
+    }
+
+    /// Invokes the callback with every recorded result in order, blocking until all of them have
+    /// been replayed.
+    pub fn replay(&self) {
+        let callback = match &self.service_discovered_callback {
+            Some(f) => f,
+            None => {
+                warn!("attempted to replay recording but no callback was set");
+                return;
+            }
+        };
+
+        let started = Instant::now();
+
+        for event in &self.events {
+            if self.preserve_timing {
+                if let Some(wait) = event.elapsed().checked_sub(started.elapsed()) {
+                    thread::sleep(wait);
+                }
+            }
+
+            ffi::catch_callback_panic("service discovered callback", || {
+                callback(event.result(), self.user_context.clone())
+            });
+        }
+    }
+}
+
+impl std::fmt::Debug for ReplayBrowser {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ReplayBrowser")
+            .field("events", &self.events)
+            .field("preserve_timing", &self.preserve_timing)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Error;
+    use crate::ServiceType;
+    use std::cell::RefCell;
+    use std::io::Cursor;
+    use std::rc::Rc;
+
+    #[derive(Clone, Default)]
+    struct SharedWriter(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedWriter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn discovery(name: &str) -> ServiceDiscovery {
+        ServiceDiscovery::builder()
+            .name(name.to_string())
+            .service_type(ServiceType::new("http", "tcp").unwrap())
+            .domain("local".to_string())
+            .host_name("host.local".to_string())
+            .address("192.168.1.10".to_string())
+            .port(8080)
+            .txt(None)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn recording_replays_results_in_order() {
+        let writer = SharedWriter::default();
+        let mut recorder = Recorder::new(Box::new(writer.clone()));
+
+        recorder.record(&Ok(discovery("first")));
+        recorder.record(&Err("resolve failed".into()));
+        recorder.record(&Ok(discovery("second")));
+
+        let recording = writer.0.lock().unwrap().clone();
+        let mut replay = ReplayBrowser::from_reader(Cursor::new(recording)).unwrap();
+        let replayed = Rc::new(RefCell::new(Vec::new()));
+        let replayed_clone = Rc::clone(&replayed);
+
+        replay.set_preserve_timing(false);
+        replay.set_service_discovered_callback(Box::new(move |result, _| {
+            replayed_clone.borrow_mut().push(result);
+        }));
+        replay.replay();
+
+        assert_eq!(
+            *replayed.borrow(),
+            vec![
+                Ok(discovery("first")),
+                Err(Error::Generic("resolve failed".to_string())),
+                Ok(discovery("second")),
+            ]
+        );
+    }
+
+    #[test]
+    fn replay_rejects_invalid_line() {
+        let recording = "\n{ \"elapsed_ms\": 0 }\n";
+
+        assert!(ReplayBrowser::from_reader(Cursor::new(recording))
+            .unwrap_err()
+            .to_string()
+            .contains("line 2"));
+    }
+}
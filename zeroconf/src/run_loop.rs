@@ -0,0 +1,222 @@
+//! Managed background run loop for services and browsers
+
+use crate::error::Error;
+use crate::prelude::*;
+use crate::{CancellationToken, EventLoop, MdnsBrowser, MdnsService, Result, ServiceTypeBrowser};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// An object that can be started to produce an `EventLoop`, such as a `MdnsService` or a
+/// `MdnsBrowser`.
+pub trait TRunnable {
+    /// Starts the object, as with `register()`, `browse_services()` or `browse_types()`.
+    fn start(&mut self) -> Result<EventLoop>;
+}
+
+impl TRunnable for MdnsService {
+    fn start(&mut self) -> Result<EventLoop> {
+        self.register()
+    }
+}
+
+impl TRunnable for MdnsBrowser {
+    fn start(&mut self) -> Result<EventLoop> {
+        self.browse_services()
+    }
+}
+
+impl TRunnable for ServiceTypeBrowser {
+    fn start(&mut self) -> Result<EventLoop> {
+        self.browse_types()
+    }
+}
+
+/// Tunables for the adaptive poll interval of a [`RunHandle`].
+///
+/// Each poll waits for events for up to the current interval. Whenever a poll returns early
+/// because events arrived, the interval drops back to `min_interval`; whenever a poll waits out
+/// the full interval, the interval doubles up to `max_interval`.
+///
+/// Waiting on the underlying socket does not consume CPU, so the interval mostly bounds how long
+/// [`RunHandle::stop()`] takes to be noticed by the thread. A smaller `max_interval` shortens that
+/// delay at the cost of waking up more often while idle. A smaller `min_interval` wakes up more
+/// often while events are flowing. On systems where the underlying poll does not respect its
+/// timeout, every poll returns immediately and the thread spins at `min_interval`.
+///
+/// [`RunHandle`]: struct.RunHandle.html
+/// [`RunHandle::stop()`]: struct.RunHandle.html#method.stop
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RunOptions {
+    /// The interval used while events are flowing. Defaults to 10 milliseconds.
+    pub min_interval: Duration,
+    /// The interval backed off to while idle. Defaults to 500 milliseconds. If it is less than
+    /// `min_interval`, `min_interval` is used throughout.
+    pub max_interval: Duration,
+}
+
+impl RunOptions {
+    fn next_interval(&self, interval: Duration, elapsed: Duration) -> Duration {
+        if elapsed < interval {
+            self.min_interval
+        } else {
+            // not `clamp()`, which panics if `max_interval` is less than `min_interval`
+            (interval * 2).min(self.max_interval).max(self.min_interval)
+        }
+    }
+}
+
+impl Default for RunOptions {
+    fn default() -> Self {
+        Self {
+            min_interval: Duration::from_millis(10),
+            max_interval: Duration::from_millis(500),
+        }
+    }
+}
+
+/// A handle on a background thread that drives a service or browser.
+///
+/// The event loop of a service or browser borrows it and is tied to the thread it was created on,
+/// so rather than a running `EventLoop`, a `RunHandle` is given a function that creates the
+/// service or browser. The function is invoked on the new thread and the result is started and
+/// polled there until [`stop()`] is called, invoking the registered callbacks from that thread.
+///
+/// Dropping the handle also stops the thread.
+///
+/// [`stop()`]: #method.stop
+#[derive(Debug)]
+pub struct RunHandle {
+    token: CancellationToken,
+    thread: Option<JoinHandle<Result<()>>>,
+}
+
+impl RunHandle {
+    /// Spawns a thread which creates a service or browser with `create` and drives it with the
+    /// default [`RunOptions`].
+    ///
+    /// [`RunOptions`]: struct.RunOptions.html
+    pub fn spawn<R, F>(create: F) -> Self
+    where
+        R: TRunnable,
+        F: FnOnce() -> R + Send + 'static,
+    {
+        Self::spawn_with_options(RunOptions::default(), create)
+    }
+
+    /// Spawns a thread which creates a service or browser with `create` and drives it with the
+    /// specified [`RunOptions`].
+    ///
+    /// [`RunOptions`]: struct.RunOptions.html
+    pub fn spawn_with_options<R, F>(options: RunOptions, create: F) -> Self
+    where
+        R: TRunnable,
+        F: FnOnce() -> R + Send + 'static,
+    {
+        let token = CancellationToken::new();
+        let thread_token = token.clone();
+
+        let thread = thread::spawn(move || {
+            let mut runnable = create();
+            let event_loop = runnable.start()?;
+            let mut interval = options.min_interval;
+
+            while !thread_token.is_cancelled() {
+                let start = Instant::now();
+                event_loop.poll(interval)?;
+                interval = options.next_interval(interval, start.elapsed());
+            }
+
+            Ok(())
+        });
+
+        Self {
+            token,
+            thread: Some(thread),
+        }
+    }
+
+    /// Returns `true` if the thread is still running. The thread stops early if starting or
+    /// polling fails, in which case the error is returned from [`stop()`].
+    ///
+    /// [`stop()`]: #method.stop
+    pub fn is_running(&self) -> bool {
+        self.thread.as_ref().map_or(false, |t| !t.is_finished())
+    }
+
+    /// Stops the thread and waits for it to finish, tearing down the service or browser. Returns
+    /// the error that stopped the thread early, if any.
+    pub fn stop(mut self) -> Result<()> {
+        self.join()
+    }
+
+    fn join(&mut self) -> Result<()> {
+        self.token.cancel();
+
+        match self.thread.take() {
+            Some(thread) => thread
+                .join()
+                .unwrap_or_else(|_| Err(Error::from("run loop thread panicked"))),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for RunHandle {
+    fn drop(&mut self) {
+        if let Err(e) = self.join() {
+            warn!("run loop stopped with error: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OPTIONS: RunOptions = RunOptions {
+        min_interval: Duration::from_millis(10),
+        max_interval: Duration::from_millis(100),
+    };
+
+    #[test]
+    fn next_interval_backs_off_while_idle() {
+        let interval = Duration::from_millis(10);
+        assert_eq!(
+            OPTIONS.next_interval(interval, interval),
+            Duration::from_millis(20)
+        );
+    }
+
+    #[test]
+    fn next_interval_is_capped_at_max_interval() {
+        let interval = Duration::from_millis(80);
+        assert_eq!(
+            OPTIONS.next_interval(interval, interval),
+            Duration::from_millis(100)
+        );
+    }
+
+    #[test]
+    fn next_interval_uses_min_interval_if_above_max_interval() {
+        let options = RunOptions {
+            min_interval: Duration::from_millis(100),
+            max_interval: Duration::from_millis(10),
+        };
+        let interval = options.min_interval;
+
+        assert_eq!(options.next_interval(interval, interval), interval);
+        assert_eq!(
+            options.next_interval(interval, Duration::from_millis(1)),
+            interval
+        );
+    }
+
+    #[test]
+    fn next_interval_resets_when_events_arrive() {
+        let interval = Duration::from_millis(100);
+        assert_eq!(
+            OPTIONS.next_interval(interval, Duration::from_millis(1)),
+            Duration::from_millis(10)
+        );
+    }
+}
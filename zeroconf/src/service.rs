@@ -1,15 +1,97 @@
 //! Trait definition for cross-platform service.
 
-use crate::{EventLoop, NetworkInterface, Result, ServiceType, TxtRecord};
+use crate::browser;
+use crate::error::{Error, NameError};
+use crate::event_loop::TEventLoop;
+use crate::txt_record::TTxtRecord;
+use crate::{
+    ClientFlags, ClientStateCallback, HostNameChangedCallback, NetworkInterface, NetworkProtocol,
+    Result, Scope, ServiceConfig, ServiceType, TxtRecord,
+};
 use std::any::Any;
-use std::sync::Arc;
+use std::cell::RefCell;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::net::IpAddr;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 /// Interface for interacting with underlying mDNS service implementation registration
 /// capabilities.
+///
+/// # Re-entrancy
+/// The `EventLoop` returned by [`register()`] borrows the service, so the service itself cannot
+/// be modified while it is running. To update a running service, including from within its own
+/// [`ServiceRegisteredCallback`], use a [`ServiceHandle`] obtained with [`handle()`].
+///
+/// Callbacks may freely create and run other services and browsers. Operations that would
+/// re-enter the library while it is already operating on the same service return
+/// [`Error::Reentrant`] instead of being performed.
+///
+/// [`register()`]: #tymethod.register
+/// [`handle()`]: #tymethod.handle
+/// [`ServiceRegisteredCallback`]: ../type.ServiceRegisteredCallback.html
+/// [`ServiceHandle`]: ../type.ServiceHandle.html
+/// [`Error::Reentrant`]: ../error/enum.Error.html#variant.Reentrant
 pub trait TMdnsService {
+    /// The event loop returned when the service is registered, which borrows the service.
+    type EventLoop<'a>: TEventLoop
+    where
+        Self: 'a;
+
+    /// The handle returned by [`handle()`].
+    ///
+    /// [`handle()`]: #tymethod.handle
+    type Handle: TServiceHandle;
+
     /// Creates a new `MdnsService` with the specified `ServiceType` (e.g. `_http._tcp`) and `port`.
+    ///
+    /// A service without a connectable endpoint that only advertises metadata through its TXT
+    /// record may use port `0`, which is published in the SRV record as is and reported as
+    /// `0` when the service is resolved. On macOS, Bonjour treats a port of `0` as a placeholder
+    /// that reserves the name but is not browsable, so such services are only discoverable on
+    /// Linux.
     fn new(service_type: ServiceType, port: u16) -> Self;
 
+    /// Creates a new `MdnsService` described by the specified [`ServiceConfig`], such as one read
+    /// from a configuration file.
+    ///
+    /// Every field is validated first with [`ServiceConfig::validate()`], returning
+    /// [`Error::InvalidConfig`] listing all fields that are not valid.
+    ///
+    /// [`ServiceConfig`]: ../struct.ServiceConfig.html
+    /// [`ServiceConfig::validate()`]: ../struct.ServiceConfig.html#method.validate
+    /// [`Error::InvalidConfig`]: ../error/enum.Error.html#variant.InvalidConfig
+    fn from_config(config: ServiceConfig) -> Result<Self>
+    where
+        Self: Sized,
+    {
+        config.validate()?;
+
+        let mut service = Self::new(config.to_service_type()?, *config.port());
+
+        if let Some(name) = config.name() {
+            service.set_name(name);
+        }
+
+        if let Some(domain) = config.domain() {
+            service.set_domain(domain);
+        }
+
+        if let Some(host) = config.host() {
+            service.set_host(host);
+        }
+
+        if let Some(txt_record) = config.to_txt_record()? {
+            service.set_txt_record(txt_record);
+        }
+
+        Ok(service)
+    }
+
     /// Sets the name to register this service under.
     fn set_name(&mut self, name: &str);
 
@@ -17,15 +99,52 @@ pub trait TMdnsService {
     /// may be auto-assigned, in which case in may not be available until after registration.
     fn name(&self) -> Option<&str>;
 
+    /// Sets the name to register this service under to `prefix` followed by a hyphen and a
+    /// random suffix, such as `worker-3f9a0c1be27d`, for short-lived instances whose names are
+    /// disposable.
+    ///
+    /// The suffix is [`UNIQUE_NAME_SUFFIX_LEN`] lowercase hexadecimal digits, or 48 bits, derived
+    /// from the randomly keyed hasher of the standard library along with the process id, the
+    /// current time and a counter, so it differs between calls and processes. It is not suitable
+    /// for anything security related. Such a name is unlikely enough to collide that probing can
+    /// be skipped with [`set_no_probe()`]. The name the service was registered under is reported
+    /// in the [`ServiceRegistration`].
+    ///
+    /// Returns an error if the resulting name is not a valid instance name, such as when the
+    /// prefix is too long to fit the suffix.
+    ///
+    /// [`UNIQUE_NAME_SUFFIX_LEN`]: ../constant.UNIQUE_NAME_SUFFIX_LEN.html
+    /// [`set_no_probe()`]: #tymethod.set_no_probe
+    /// [`ServiceRegistration`]: ../struct.ServiceRegistration.html
+    fn set_unique_name(&mut self, prefix: &str) -> Result<()> {
+        let name = format!("{}-{}", prefix, random_suffix());
+        validate_instance_name(&name)?;
+        self.set_name(&name);
+        Ok(())
+    }
+
     /// Sets the network interface to bind this service to.
     ///
     /// Most applications will want to use the default value `NetworkInterface::Unspec` to bind to
-    /// all available interfaces.
+    /// all available interfaces. On Linux, [`register()`] returns an error if Avahi cannot represent
+    /// the index of the interface.
+    ///
+    /// [`register()`]: #tymethod.register
     fn set_network_interface(&mut self, interface: NetworkInterface);
 
     /// Returns the network interface to bind this service to.
     fn network_interface(&self) -> NetworkInterface;
 
+    /// Sets the network protocol over which to publish this service. Defaults to
+    /// `NetworkProtocol::Unspec`, which publishes over both IPv4 and IPv6.
+    ///
+    /// Bonjour does not support restricting the protocol of a registration, so this has no
+    /// effect on macOS.
+    fn set_network_protocol(&mut self, protocol: NetworkProtocol);
+
+    /// Returns the network protocol over which to publish this service.
+    fn network_protocol(&self) -> NetworkProtocol;
+
     /// Sets the domain on which to advertise the service.
     ///
     /// Most applications will want to use the default value of `ptr::null()` to register to the
@@ -35,6 +154,16 @@ pub trait TMdnsService {
     /// Returns the domain on which to advertise the service.
     fn domain(&self) -> Option<&str>;
 
+    /// Sets the [`Scope`] on which to advertise the service. Defaults to `Scope::LinkLocal`.
+    ///
+    /// [`Scope`]: enum.Scope.html
+    fn set_scope(&mut self, scope: Scope);
+
+    /// Returns the [`Scope`] on which to advertise the service.
+    ///
+    /// [`Scope`]: enum.Scope.html
+    fn scope(&self) -> Scope;
+
     /// Sets the SRV target host name.
     ///
     /// Most applications will want to use the default value of `ptr::null()` to use the machine's
@@ -50,12 +179,368 @@ pub trait TMdnsService {
     /// Returns the optional `TxtRecord` to register this service with.
     fn txt_record(&self) -> Option<&TxtRecord>;
 
+    /// Returns the `TxtRecord` the service is currently advertised with, or `None` if it is not
+    /// registered or was registered without one.
+    ///
+    /// Unlike [`txt_record()`], this reflects the updates made since registering through a
+    /// [`ServiceHandle`] or by the provider set with [`set_txt_provider()`], as the copy of the
+    /// record kept by the crate is replaced once the daemon accepts each update.
+    ///
+    /// [`txt_record()`]: #tymethod.txt_record
+    /// [`ServiceHandle`]: ../type.ServiceHandle.html
+    /// [`set_txt_provider()`]: #tymethod.set_txt_provider
+    fn current_txt(&self) -> Option<TxtRecord>;
+
+    /// Sets whether a service without TXT keys still publishes a TXT record. Defaults to `true`.
+    ///
+    /// When enabled, a service with no `TxtRecord`, or an empty one, publishes a TXT record
+    /// holding a single zero-length string, which is one `0x00` byte of RDATA, as [RFC 6763]
+    /// requires. Some strict clients fail to resolve a service that has no TXT record at all.
+    /// When disabled, no TXT record is published for such a service, and clients that query for
+    /// one receive no answer. Services with TXT keys always publish them.
+    ///
+    /// On Linux, Avahi always publishes a TXT record for services, so when disabled the service's
+    /// SRV and PTR records are published individually as raw records instead, and updating the
+    /// TXT record through a [`ServiceHandle`] fails. On macOS, Bonjour always publishes a
+    /// TXT record for services and [`register()`] fails if this is disabled for a service without
+    /// TXT keys.
+    ///
+    /// [RFC 6763]: https://www.rfc-editor.org/rfc/rfc6763#section-6.1
+    /// [`ServiceHandle`]: ../type.ServiceHandle.html
+    /// [`register()`]: #tymethod.register
+    fn set_publish_empty_txt(&mut self, publish_empty_txt: bool);
+
+    /// Returns whether a service without TXT keys still publishes an empty TXT record.
+    fn publish_empty_txt(&self) -> bool;
+
+    /// Sets a `txt_provider` that generates the service's TXT record, for metadata that changes
+    /// while the service is running, such as its current load or status.
+    ///
+    /// Once registered, the service publishes the record returned by the provider and calls it
+    /// again every `interval`, which is at least [`MIN_TXT_PROVIDER_INTERVAL`], while its event
+    /// loop is polled. The record is updated in place, as with
+    /// [`TServiceHandle::update_txt_record()`], whenever its entries differ from the one last
+    /// published as compared by [`TTxtRecord::diff()`], so a record whose entries only moved is
+    /// not published again. This replaces any record set with [`set_txt_record()`].
+    ///
+    /// mDNS does not query the advertiser for each request, so clients see the record as it was
+    /// last announced, which is at most one interval old. Each change is announced to the whole
+    /// link with a multicast packet and flushes the record from every cache, so on a busy network
+    /// the interval should be tens of seconds or more, and values that change on every call should
+    /// be rounded so that the record only changes when it matters. Unchanged records cost
+    /// nothing on the network.
+    ///
+    /// The provider is invoked from `poll()` and not while the event loop is suspended.
+    ///
+    /// [`MIN_TXT_PROVIDER_INTERVAL`]: ../constant.MIN_TXT_PROVIDER_INTERVAL.html
+    /// [`TServiceHandle::update_txt_record()`]: trait.TServiceHandle.html#tymethod.update_txt_record
+    /// [`TTxtRecord::diff()`]: ../txt_record/trait.TTxtRecord.html#method.diff
+    /// [`set_txt_record()`]: #tymethod.set_txt_record
+    fn set_txt_provider(&mut self, interval: Duration, txt_provider: Box<TxtProvider>);
+
+    /// Returns the interval at which the TXT provider is called, if one is set.
+    fn txt_provider_interval(&self) -> Option<Duration>;
+
+    /// Adds the conventional DNS-SD metadata keys to the service's `TxtRecord`, creating one if
+    /// none is set:
+    ///
+    /// * `txtvers=1` - the version of the TXT record's format, as recommended by [RFC 6763]
+    /// * `version=<version>` - the version of the application advertising the service
+    /// * `id=<id>` - an identifier for the instance that stays the same across restarts, such as
+    ///   a UUID
+    ///
+    /// The keys are placed first, with `txtvers` at the start of the record. A key already set by
+    /// the application is kept as is and not added again. Setting the `TxtRecord` with
+    /// [`set_txt_record()`] afterwards replaces these keys.
+    ///
+    /// [RFC 6763]: https://www.rfc-editor.org/rfc/rfc6763#section-6.7
+    /// [`set_txt_record()`]: #tymethod.set_txt_record
+    fn set_standard_txt(&mut self, version: &str, id: &str) -> Result<()> {
+        let mut txt_record = TxtRecord::new();
+        let current = self.txt_record();
+
+        for (key, value) in &[("txtvers", "1"), ("version", version), ("id", id)] {
+            if !current.map_or(false, |t| t.contains_key(key)) {
+                txt_record.insert(key, value)?;
+            }
+        }
+
+        if let Some(current) = current {
+            for (key, value) in current.iter_bytes() {
+                txt_record.insert_bytes(&key, value)?;
+            }
+        }
+
+        self.set_txt_record(txt_record);
+
+        Ok(())
+    }
+
+    /// Sets the addresses to advertise for the service's host, which must be set with
+    /// [`set_host()`]. On macOS, the host must be fully qualified, such as `myhost.local.`.
+    ///
+    /// By default, no addresses are published by the service and the host is resolved to
+    /// whichever addresses the daemon already advertises for it, which for the machine's own host
+    /// name are chosen automatically. When addresses are set, an A or AAAA record is published for
+    /// each of them under the host, so the host must not be the machine's own host name. The
+    /// daemon's records for the machine's own host name are left untouched.
+    ///
+    /// [`register()`] fails if addresses are set without a host, or if an address is of a family
+    /// that the service's network interface has no address of.
+    ///
+    /// [`set_host()`]: #tymethod.set_host
+    /// [`register()`]: #tymethod.register
+    fn set_addresses(&mut self, addresses: Vec<IpAddr>);
+
+    /// Returns the addresses to advertise for the service's host.
+    fn addresses(&self) -> &[IpAddr];
+
+    /// Sets whether a reverse PTR record, mapping the address back to the service's host, is
+    /// published alongside the A or AAAA record of each address set with [`set_addresses()`].
+    /// Defaults to `false`.
+    ///
+    /// Reverse lookups in `.local` are multicast queries for names under `in-addr.arpa` or
+    /// `ip6.arpa`, as described in RFC 6762. Only one host can answer for an address, and the
+    /// daemon already publishes the reverse records of the machine's own addresses, so enabling
+    /// this for an address of the machine, or one another host claims, makes the records
+    /// conflict and the registration fail. It is meant for hosts the service advertises on
+    /// behalf of, such as a device behind a proxy, for tools like `ping` or `ssh` that show
+    /// the name of the address they connect to. Many clients never look up reverse records, so
+    /// they are noise on networks that do not need them.
+    ///
+    /// On Linux, this clears `AVAHI_PUBLISH_NO_REVERSE` from the address records, and with an
+    /// [`address_ttl()`] publishes the PTR records individually. On macOS, Bonjour does not
+    /// publish reverse records for registered addresses, so [`register()`] returns
+    /// [`Error::Unsupported`] if this is enabled and addresses are set.
+    ///
+    /// [`set_addresses()`]: #tymethod.set_addresses
+    /// [`address_ttl()`]: #tymethod.address_ttl
+    /// [`register()`]: #tymethod.register
+    /// [`Error::Unsupported`]: ../error/enum.Error.html#variant.Unsupported
+    fn set_publish_reverse_ptr(&mut self, publish_reverse_ptr: bool);
+
+    /// Returns whether reverse PTR records are published for the service's addresses.
+    fn publish_reverse_ptr(&self) -> bool;
+
+    /// Sets whether the daemon should skip probing the network for conflicts with the service's
+    /// name before announcing it. Defaults to `false`.
+    ///
+    /// Probing takes roughly 750 milliseconds, which can be skipped for services whose name is
+    /// guaranteed to be unique, such as one derived from a serial number. If another service is
+    /// published under the same name, the conflict goes unnoticed by the daemon and both
+    /// services are advertised on the network under the same name. This is for advanced use only.
+    ///
+    /// On Linux, this sets `AVAHI_PUBLISH_NO_PROBE` on the service's records, which Avahi only
+    /// accepts for individual records, so the service's SRV, TXT and PTR records are published
+    /// individually rather than as a service. On macOS, this sets `kDNSServiceFlagsKnownUnique`,
+    /// which Bonjour documents for individual records; it may not skip probing for the service
+    /// itself on every version of the daemon.
+    fn set_no_probe(&mut self, no_probe: bool);
+
+    /// Returns whether the daemon skips probing for conflicts with the service's name.
+    fn no_probe(&self) -> bool;
+
+    /// Sets the priority of the service's SRV record, which clients use to choose between
+    /// instances of a service as described in RFC 2782. Lower values are preferred. Defaults to
+    /// `0`.
+    ///
+    /// On Linux, Avahi always publishes services with a priority and weight of `0`, so when
+    /// either is set the service's SRV, TXT and PTR records are published individually as raw
+    /// records instead. On macOS, Bonjour does not support this and [`register()`] fails if
+    /// either is set.
+    ///
+    /// [`register()`]: #tymethod.register
+    fn set_srv_priority(&mut self, priority: u16);
+
+    /// Returns the priority of the service's SRV record.
+    fn srv_priority(&self) -> u16;
+
+    /// Sets the weight of the service's SRV record, which clients use to distribute load between
+    /// instances of the same priority as described in RFC 2782. Defaults to `0`.
+    ///
+    /// See [`set_srv_priority()`] for how this is published.
+    ///
+    /// [`set_srv_priority()`]: #tymethod.set_srv_priority
+    fn set_srv_weight(&mut self, weight: u16);
+
+    /// Returns the weight of the service's SRV record.
+    fn srv_weight(&self) -> u16;
+
+    /// Sets the time-to-live in seconds of the service's PTR records, which point browsers of
+    /// the service type and its subtypes to the service. Defaults to the daemon's default of 75
+    /// minutes.
+    ///
+    /// Shorter TTLs make caches forget the service sooner after it disappears without saying
+    /// goodbye, at the cost of more queries on the network.
+    ///
+    /// On Linux, Avahi does not accept TTLs for services, so when the PTR, SRV or TXT TTL is set
+    /// the service's SRV, TXT and PTR records are published individually as raw records instead.
+    /// On macOS, Bonjour does not support TTLs for services and this has no effect. Both honor
+    /// [`set_address_ttl()`].
+    ///
+    /// [`set_address_ttl()`]: #tymethod.set_address_ttl
+    fn set_ptr_ttl(&mut self, ttl: u32);
+
+    /// Returns the time-to-live of the service's PTR records, if set.
+    fn ptr_ttl(&self) -> Option<u32>;
+
+    /// Sets the time-to-live in seconds of the service's SRV record. Defaults to the daemon's
+    /// default of 2 minutes.
+    ///
+    /// See [`set_ptr_ttl()`] for which platforms honor this.
+    ///
+    /// [`set_ptr_ttl()`]: #tymethod.set_ptr_ttl
+    fn set_srv_ttl(&mut self, ttl: u32);
+
+    /// Returns the time-to-live of the service's SRV record, if set.
+    fn srv_ttl(&self) -> Option<u32>;
+
+    /// Sets the time-to-live in seconds of the service's TXT record. Defaults to the daemon's
+    /// default of 75 minutes.
+    ///
+    /// See [`set_ptr_ttl()`] for which platforms honor this.
+    ///
+    /// [`set_ptr_ttl()`]: #tymethod.set_ptr_ttl
+    fn set_txt_ttl(&mut self, ttl: u32);
+
+    /// Returns the time-to-live of the service's TXT record, if set.
+    fn txt_ttl(&self) -> Option<u32>;
+
+    /// Sets the time-to-live in seconds of the address records published for the addresses set
+    /// with [`set_addresses()`]. Defaults to the daemon's default of 2 minutes.
+    ///
+    /// This has no effect without explicit addresses, in which case the daemon publishes the
+    /// records of the machine's own host name.
+    ///
+    /// [`set_addresses()`]: #tymethod.set_addresses
+    fn set_address_ttl(&mut self, ttl: u32);
+
+    /// Returns the time-to-live of the service's address records, if set.
+    fn address_ttl(&self) -> Option<u32>;
+
+    /// Sets whether the addresses set with [`set_addresses()`] should follow the addresses of the
+    /// service's network interface. Defaults to `false`.
+    ///
+    /// When enabled, the published address records are replaced with the interface's current
+    /// addresses whenever an address is added to or removed from the machine, such as after a
+    /// DHCP renewal or an interface going up or down. Only addresses of the families present in
+    /// the addresses set initially are published, and loopback addresses are skipped. The
+    /// registered callback is invoked again once the updated records are established.
+    ///
+    /// This has no effect without explicit addresses, in which case the daemon already keeps the
+    /// records of the machine's own host name up to date.
+    ///
+    /// On Linux, changes are noticed through a netlink socket subscribed to the kernel's address
+    /// notifications and watched on the service's event loop. On macOS, this is not supported:
+    /// `SCNetworkReachability` requires a `CFRunLoop`, which the event loop does not run, so
+    /// [`register()`] returns [`Error::Unsupported`] if this is enabled and addresses are set.
+    ///
+    /// [`set_addresses()`]: #tymethod.set_addresses
+    /// [`register()`]: #tymethod.register
+    /// [`Error::Unsupported`]: ../error/enum.Error.html#variant.Unsupported
+    fn set_track_interface_changes(&mut self, track_interface_changes: bool);
+
+    /// Returns whether the published addresses follow the addresses of the network interface.
+    fn track_interface_changes(&self) -> bool;
+
+    /// Sets whether the service should be published separately on each of the machine's
+    /// interfaces, following them as they come and go. Defaults to `false`.
+    ///
+    /// When enabled, the service is published on every interface that is up, is not a loopback
+    /// interface and has an address of the service's [`NetworkProtocol`], such as a USB Ethernet
+    /// adapter or a tethered phone. It is published on interfaces that appear later and withdrawn
+    /// from interfaces that go away, and the registered callback is invoked again once the
+    /// updated registration is established. Registering fails unless the service's
+    /// [`NetworkInterface`] is `NetworkInterface::Unspec`.
+    ///
+    /// On Linux, the service is added to its Avahi entry group once per interface, and changes
+    /// are noticed through the same netlink socket as for [`set_track_interface_changes()`],
+    /// which reports an interface gaining or losing its addresses. On macOS, the interfaces cannot
+    /// be monitored, since `SCNetworkReachability` requires a `CFRunLoop`, which the event loop
+    /// does not run, so [`register()`] returns [`Error::Unsupported`] if this is enabled. A
+    /// service registered on all interfaces is kept up to date by `mDNSResponder` as interfaces
+    /// come and go.
+    ///
+    /// [`NetworkProtocol`]: ../enum.NetworkProtocol.html
+    /// [`NetworkInterface`]: ../enum.NetworkInterface.html
+    /// [`set_track_interface_changes()`]: #tymethod.set_track_interface_changes
+    /// [`register()`]: #tymethod.register
+    /// [`Error::Unsupported`]: ../error/enum.Error.html#variant.Unsupported
+    fn set_follow_interfaces(&mut self, follow_interfaces: bool);
+
+    /// Returns whether the service is published separately on each interface, following them as
+    /// they come and go.
+    fn follow_interfaces(&self) -> bool;
+
     /// Sets the [`ServiceRegisteredCallback`] that is invoked when the service has been
-    /// registered.
+    /// registered. It must be set before [`register()`] is called.
     ///
     /// [`ServiceRegisteredCallback`]: ../type.ServiceRegisteredCallback.html
+    /// [`register()`]: #tymethod.register
     fn set_registered_callback(&mut self, registered_callback: Box<ServiceRegisteredCallback>);
 
+    /// Sets the [`ClientStateCallback`] that is invoked when the state of the service's
+    /// connection to the mDNS daemon changes. Takes effect the next time the service is
+    /// registered.
+    ///
+    /// The callback is invoked for the connection the service creates itself when it is first
+    /// registered, and for one shared through a [`Zeroconf`] facade with the states it goes
+    /// through once the service has been registered. On Linux, the initial state of a connection
+    /// of its own is reported while the connection is being created, before [`register()`]
+    /// returns.
+    ///
+    /// [`ClientStateCallback`]: ../type.ClientStateCallback.html
+    /// [`Zeroconf`]: ../type.Zeroconf.html
+    /// [`register()`]: #tymethod.register
+    fn set_client_state_callback(&mut self, client_state_callback: Box<ClientStateCallback>);
+
+    /// Sets the [`ClientFlags`] that the service's connection to the mDNS daemon is created with.
+    /// Defaults to no flags.
+    ///
+    /// The flags of a connection cannot be changed once it is created, so this only takes effect
+    /// when [`register()`] creates one, which it does the first time the service is registered
+    /// and after the connection has failed. A service whose connection is shared through a
+    /// [`Zeroconf`] facade uses the facade's connection and ignores this.
+    ///
+    /// On macOS, Bonjour supports no flags and [`register()`] returns [`Error::Unsupported`] if
+    /// any are set.
+    ///
+    /// [`ClientFlags`]: ../struct.ClientFlags.html
+    /// [`register()`]: #tymethod.register
+    /// [`Zeroconf`]: ../type.Zeroconf.html
+    /// [`Error::Unsupported`]: ../error/enum.Error.html#variant.Unsupported
+    fn set_client_flags(&mut self, flags: ClientFlags);
+
+    /// Returns the flags of the service's connection to the mDNS daemon once it has been created,
+    /// or those it will be created with before then.
+    ///
+    /// This lets code layered on top tell whether the connection survives the daemon restarting,
+    /// which it does with [`ClientFlags::NO_FAIL`], or fails and has to be re-registered.
+    ///
+    /// [`ClientFlags::NO_FAIL`]: ../struct.ClientFlags.html#associatedconstant.NO_FAIL
+    fn client_flags(&self) -> ClientFlags;
+
+    /// Sets the [`HostNameChangedCallback`] that is invoked when the fully qualified host name of
+    /// the machine changes while the service is registered, so that anything derived from it,
+    /// such as URLs, can be updated.
+    ///
+    /// On Linux, Avahi renames the host when its name collides with another host on the network
+    /// (`host.local` becoming `host-2.local`), after which the connection to the daemon reports
+    /// `ClientState::Running` again. The host name is compared each time it does, and the
+    /// callback is invoked if it differs from the one the service was registered under. As with
+    /// the [`ClientStateCallback`], only the connection the service creates itself is watched.
+    ///
+    /// On macOS, Bonjour does not report host name changes to its clients; they surface through
+    /// the System Configuration framework instead, which this crate does not use. The callback is
+    /// only stored and never invoked.
+    ///
+    /// [`HostNameChangedCallback`]: ../type.HostNameChangedCallback.html
+    /// [`ClientStateCallback`]: ../type.ClientStateCallback.html
+    fn set_host_name_changed_callback(
+        &mut self,
+        host_name_changed_callback: Box<HostNameChangedCallback>,
+    );
+
     /// Sets the optional user context to pass through to the callback. This is useful if you need
     /// to share state between pre and post-callback. The context type must implement `Any`.
     fn set_context(&mut self, context: Box<dyn Any>);
@@ -63,9 +548,501 @@ pub trait TMdnsService {
     /// Returns the optional user context.
     fn context(&self) -> Option<&dyn Any>;
 
+    /// Returns a [`ServiceHandle`] which can be used to update this service once it has been
+    /// registered. Handles remain valid for as long as the service exists.
+    ///
+    /// [`ServiceHandle`]: ../type.ServiceHandle.html
+    fn handle(&self) -> Self::Handle;
+
+    /// Subscribes to the [`RegistrationState`] of the service, returning a receiver that is sent
+    /// each state the registration goes through, starting with the current one if the service
+    /// has been registered.
+    ///
+    /// States are sent from the callbacks of the daemon while the event loop is polled. As the
+    /// event loop borrows the service, subscribe before calling [`register()`]; the receiver can
+    /// be moved to another thread, such as one supervising the service, and keeps receiving
+    /// states for as long as the service exists. Dropped receivers are unsubscribed.
+    ///
+    /// The receiver is a blocking `std::sync::mpsc::Receiver` rather than an async `Stream`, as
+    /// the crate does not depend on an async runtime. An async application can forward the states
+    /// from a thread blocked on [`Receiver::recv()`] to a channel of its runtime.
+    ///
+    /// [`RegistrationState`]: enum.RegistrationState.html
+    /// [`register()`]: #tymethod.register
+    /// [`Receiver::recv()`]: https://doc.rust-lang.org/std/sync/mpsc/struct.Receiver.html#method.recv
+    fn state_stream(&self) -> Receiver<RegistrationState>;
+
+    /// Returns `true` if the service is currently registered with the daemon, according to the
+    /// live state of its registration rather than the last [`RegistrationState`] reported.
+    ///
+    /// A registration can be lost without the service noticing, most notably when the daemon
+    /// restarts and forgets every service registered with it. Such a service can be detected with
+    /// this and published again with [`register()`], which reconnects to the daemon if needed.
+    ///
+    /// On Linux, this checks that the Avahi client is running and that the service's entry group
+    /// is established. Avahi learns about the daemon going away over D-Bus, which is only read
+    /// when the event loop is polled, so this can only be `false` after a restart once the event
+    /// loop has been polled since. On macOS, this checks that the registration was established and
+    /// that the connection to `mDNSResponder` is still open, which is closed when it restarts.
+    ///
+    /// To reproduce a restart manually, register a service and poll its event loop, restart the
+    /// daemon with `sudo systemctl restart avahi-daemon` on Linux or
+    /// `sudo killall mDNSResponder` on macOS, which launchd starts again, poll again and check that
+    /// this returns `false`.
+    ///
+    /// [`RegistrationState`]: enum.RegistrationState.html
+    /// [`register()`]: #tymethod.register
+    fn is_registered(&self) -> bool;
+
     /// Registers and start's the service. Returns an `EventLoop` which can be called to keep
     /// the service alive.
-    fn register(&mut self) -> Result<EventLoop>;
+    ///
+    /// Returns an error if no [`ServiceRegisteredCallback`] has been set, as the result of the
+    /// registration would otherwise go unreported.
+    ///
+    /// [`ServiceRegisteredCallback`]: ../type.ServiceRegisteredCallback.html
+    fn register(&mut self) -> Result<Self::EventLoop<'_>>;
+
+    /// Withdraws the registered service from the network, returning the error reported by the
+    /// daemon if its records could not be removed. Does nothing if the service is not registered.
+    ///
+    /// Withdrawing sends goodbye packets, so that peers notice the service is gone without
+    /// waiting for its records to expire. Dropping a registered service withdraws it as well, but
+    /// cannot report errors. On Linux, the service's Avahi entry group is reset and then freed. On
+    /// macOS, its Bonjour registration is deallocated, which does not report errors.
+    fn unregister(&mut self) -> Result<()>;
+
+    /// Registers the service and returns a [`ServiceGuard`] that keeps it advertised until the
+    /// guard is dropped, such as at the end of the enclosing block.
+    ///
+    /// The event loop returned by [`register()`] is dropped, as it borrows the service that the
+    /// guard takes ownership of, and nothing polls the daemon afterwards. So the
+    /// [`ServiceRegisteredCallback`], the client state callback and [`state_stream()`] never
+    /// report anything, and a name collision or failure after the service has been submitted goes
+    /// unnoticed, while the daemon keeps the service advertised. Use [`register()`] and poll its
+    /// event loop where these matter. As it is never invoked, the [`ServiceRegisteredCallback`]
+    /// need not be set, and is replaced if it is.
+    ///
+    /// [`ServiceGuard`]: struct.ServiceGuard.html
+    /// [`register()`]: #tymethod.register
+    /// [`ServiceRegisteredCallback`]: ../type.ServiceRegisteredCallback.html
+    /// [`state_stream()`]: #tymethod.state_stream
+    fn register_scoped(mut self) -> Result<ServiceGuard<Self>>
+    where
+        Self: Sized,
+    {
+        self.set_registered_callback(Box::new(|_, _| {}));
+        self.register()?;
+        Ok(ServiceGuard { service: self })
+    }
+}
+
+/// Keeps a [`MdnsService`] registered with [`TMdnsService::register_scoped()`] advertised for as
+/// long as the guard exists.
+///
+/// Dropping the guard drops the service, which withdraws it from the network: on Linux, its Avahi
+/// entry group is freed and on macOS its Bonjour registration is deallocated, both of which send
+/// goodbye packets so that peers notice the service is gone without waiting for its records to
+/// expire. Use [`shutdown()`] instead to learn whether withdrawing the service succeeded.
+///
+/// The service's event loop is not polled while the guard exists, so its callbacks are not
+/// invoked, as described for [`TMdnsService::register_scoped()`].
+///
+/// [`shutdown()`]: #method.shutdown
+/// [`MdnsService`]: ../type.MdnsService.html
+/// [`TMdnsService::register_scoped()`]: trait.TMdnsService.html#method.register_scoped
+#[derive(Debug)]
+pub struct ServiceGuard<S: TMdnsService> {
+    service: S,
+}
+
+impl<S: TMdnsService> ServiceGuard<S> {
+    /// Returns the advertised service.
+    pub fn service(&self) -> &S {
+        &self.service
+    }
+
+    /// Returns a [`ServiceHandle`] which can be used to update the advertised service.
+    ///
+    /// [`ServiceHandle`]: ../type.ServiceHandle.html
+    pub fn handle(&self) -> S::Handle {
+        self.service.handle()
+    }
+
+    /// Withdraws the service with [`TMdnsService::unregister()`], as dropping the guard does, but
+    /// returns the error reported by the daemon if the service could not be withdrawn.
+    ///
+    /// [`TMdnsService::unregister()`]: trait.TMdnsService.html#tymethod.unregister
+    pub fn shutdown(mut self) -> Result<()> {
+        debug!("Shutting down scoped service");
+        self.service.unregister()
+    }
+}
+
+/// Publishes a TCP service named `name` of `service_type` (e.g. `http` for `_http._tcp`) on
+/// `port`, returning a [`ServiceGuard`] that keeps it advertised until the guard is dropped.
+///
+/// This is the quickest way to get started:
+///
+/// ```no_run
+/// let guard = zeroconf::publish_tcp("My Web Server", "http", 8080).unwrap();
+///
+/// // ... serve requests, the service is advertised for as long as `guard` exists
+///
+/// drop(guard);
+/// ```
+///
+/// The service is published on all interfaces and in the default domain, without a TXT record.
+/// As with [`TMdnsService::register_scoped()`], no callbacks are invoked, so errors reported by
+/// the daemon after the service has been submitted are not seen. Use a [`MdnsService`] directly
+/// to configure anything else.
+///
+/// [`ServiceGuard`]: struct.ServiceGuard.html
+/// [`TMdnsService::register_scoped()`]: trait.TMdnsService.html#method.register_scoped
+/// [`MdnsService`]: ../type.MdnsService.html
+pub fn publish_tcp(
+    name: &str,
+    service_type: &str,
+    port: u16,
+) -> Result<ServiceGuard<crate::MdnsService>> {
+    validate_instance_name(name)?;
+
+    let mut service = crate::MdnsService::new(ServiceType::new(service_type, "tcp")?, port);
+    service.set_name(name);
+    service.register_scoped()
+}
+
+/// The state of the registration of a [`MdnsService`], as sent to the receivers returned by
+/// [`TMdnsService::state_stream()`].
+///
+/// [`MdnsService`]: ../type.MdnsService.html
+/// [`TMdnsService::state_stream()`]: trait.TMdnsService.html#tymethod.state_stream
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RegistrationState {
+    /// The service is being registered, which includes probing the network for services with the
+    /// same name
+    Registering,
+    /// The service has been registered and is being announced
+    Established,
+    /// The name of the service collided with another service on the network, and the service is
+    /// being registered again under the contained name
+    Collision(String),
+    /// The service could not be registered
+    Failure(Error),
+}
+
+/// The subscribers of [`TMdnsService::state_stream()`] along with the state last sent to them.
+///
+/// [`TMdnsService::state_stream()`]: trait.TMdnsService.html#tymethod.state_stream
+#[derive(Debug, Default)]
+pub(crate) struct RegistrationStates {
+    current: Option<RegistrationState>,
+    senders: Vec<Sender<RegistrationState>>,
+}
+
+impl RegistrationStates {
+    /// Returns the state last sent to the subscribers, if any.
+    #[cfg_attr(not(target_vendor = "apple"), allow(dead_code))]
+    pub(crate) fn current(&self) -> Option<&RegistrationState> {
+        self.current.as_ref()
+    }
+
+    pub(crate) fn subscribe(&mut self) -> Receiver<RegistrationState> {
+        let (sender, receiver) = mpsc::channel();
+
+        if let Some(state) = &self.current {
+            // the receiver is still in scope
+            sender.send(state.clone()).unwrap();
+        }
+
+        self.senders.push(sender);
+        receiver
+    }
+
+    /// Sends `state` to every subscriber unless it is already the current state, which it can be
+    /// when the daemon reports it again.
+    pub(crate) fn set(&mut self, state: RegistrationState) {
+        if self.current.as_ref() == Some(&state) {
+            return;
+        }
+
+        debug!("Registration state changed to {:?}", state);
+
+        self.senders
+            .retain(|sender| sender.send(state.clone()).is_ok());
+
+        self.current = Some(state);
+    }
+}
+
+/// Returns the name to register a service under after `name` collided with another service, as
+/// Avahi's `avahi_alternative_service_name()` does: `name #2` for `name`, `name #3` for
+/// `name #2` and so on, shortening `name` to keep within [`INSTANCE_NAME_MAX_LEN`].
+///
+/// [`INSTANCE_NAME_MAX_LEN`]: constant.INSTANCE_NAME_MAX_LEN.html
+#[cfg_attr(target_vendor = "apple", allow(dead_code))]
+pub(crate) fn alternative_service_name(name: &str) -> String {
+    let (base, number) = match name.rsplit_once(" #") {
+        Some((base, n)) if !n.starts_with('0') => match n.parse::<u64>() {
+            Ok(n) => (base, n.saturating_add(1)),
+            Err(_) => (name, 2),
+        },
+        _ => (name, 2),
+    };
+
+    let suffix = format!(" #{}", number);
+    let mut end = base
+        .len()
+        .min(INSTANCE_NAME_MAX_LEN.saturating_sub(suffix.len()));
+
+    while !base.is_char_boundary(end) {
+        end -= 1;
+    }
+
+    format!("{}{}", &base[..end], suffix)
+}
+
+/// Maximum length in bytes of a service instance name, the maximum length of a DNS label.
+pub const INSTANCE_NAME_MAX_LEN: usize = 63;
+
+/// Length of the random suffix appended by [`TMdnsService::set_unique_name()`].
+///
+/// [`TMdnsService::set_unique_name()`]: trait.TMdnsService.html#method.set_unique_name
+pub const UNIQUE_NAME_SUFFIX_LEN: usize = 12;
+
+/// Returns [`UNIQUE_NAME_SUFFIX_LEN`] random lowercase hexadecimal digits.
+///
+/// [`UNIQUE_NAME_SUFFIX_LEN`]: constant.UNIQUE_NAME_SUFFIX_LEN.html
+fn random_suffix() -> String {
+    let suffix = format!("{:016x}", random_u64());
+    suffix[..UNIQUE_NAME_SUFFIX_LEN].to_string()
+}
+
+/// Returns a random number derived from the randomly keyed hasher of the standard library, the
+/// process id, a counter and the current time. Not suitable for cryptographic use.
+pub(crate) fn random_u64() -> u64 {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u32(std::process::id());
+    hasher.write_u64(COUNTER.fetch_add(1, Ordering::Relaxed));
+
+    if let Ok(elapsed) = SystemTime::now().duration_since(UNIX_EPOCH) {
+        hasher.write_u128(elapsed.as_nanos());
+    }
+
+    hasher.finish()
+}
+
+/// Validates a service instance name such as `Living Room Printer`, as set with
+/// [`TMdnsService::set_name()`].
+///
+/// Instance names may contain any Unicode characters, including spaces and dots, but must be 1 to
+/// [`INSTANCE_NAME_MAX_LEN`] bytes of UTF-8 long and must not contain control characters, as
+/// required by [RFC 6763].
+///
+/// [`TMdnsService::set_name()`]: trait.TMdnsService.html#tymethod.set_name
+/// [`INSTANCE_NAME_MAX_LEN`]: constant.INSTANCE_NAME_MAX_LEN.html
+/// [RFC 6763]: https://www.rfc-editor.org/rfc/rfc6763#section-4.1.1
+pub fn validate_instance_name(name: &str) -> Result<()> {
+    if name.is_empty() {
+        return Err(NameError::Empty.into());
+    }
+
+    if name.len() > INSTANCE_NAME_MAX_LEN {
+        return Err(NameError::TooLong {
+            len: name.len(),
+            max: INSTANCE_NAME_MAX_LEN,
+        }
+        .into());
+    }
+
+    if let Some(c) = name.chars().find(|c| c.is_ascii_control()) {
+        return Err(NameError::IllegalCharacter(c).into());
+    }
+
+    Ok(())
+}
+
+/// Checks that the specified addresses can be published for `host` on `interface`.
+pub(crate) fn check_addresses(
+    interface: NetworkInterface,
+    host: Option<&str>,
+    addresses: &[IpAddr],
+) -> Result<()> {
+    if addresses.is_empty() {
+        return Ok(());
+    }
+
+    if host.is_none() {
+        return Err("a host must be set to publish addresses".into());
+    }
+
+    for address in addresses {
+        if address.is_unspecified() || address.is_multicast() {
+            return Err(format!("address `{}` cannot be published", address).into());
+        }
+
+        if !interface.supports_address(address) {
+            return Err(format!(
+                "address `{}` is of a family not supported by interface {:?}",
+                address, interface
+            )
+            .into());
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the addresses to publish in place of `explicit` once the service's interface has the
+/// `current` addresses; that is, the current addresses of the families present in `explicit`,
+/// excluding loopback addresses.
+pub(crate) fn tracked_addresses(explicit: &[IpAddr], current: &[IpAddr]) -> Vec<IpAddr> {
+    let has_v4 = explicit.iter().any(|a| a.is_ipv4());
+    let has_v6 = explicit.iter().any(|a| a.is_ipv6());
+
+    current
+        .iter()
+        .filter(|a| !a.is_loopback() && !a.is_unspecified())
+        .filter(|a| if a.is_ipv4() { has_v4 } else { has_v6 })
+        .copied()
+        .collect()
+}
+
+/// Returns the name of the reverse PTR record of the specified address: the dotted octets of an
+/// IPv4 address in reverse order under `in-addr.arpa`, or the nibbles of an IPv6 address in
+/// reverse order under `ip6.arpa`.
+#[cfg_attr(target_vendor = "apple", allow(dead_code))]
+pub(crate) fn reverse_lookup_name(address: &IpAddr) -> String {
+    match address {
+        IpAddr::V4(v4) => {
+            let octets = v4.octets();
+            format!(
+                "{}.{}.{}.{}.in-addr.arpa",
+                octets[3], octets[2], octets[1], octets[0]
+            )
+        }
+        IpAddr::V6(v6) => {
+            let mut name = String::with_capacity(72);
+
+            for octet in v6.octets().iter().rev() {
+                name.push_str(&format!("{:x}.{:x}.", octet & 0xf, octet >> 4));
+            }
+
+            name.push_str("ip6.arpa");
+            name
+        }
+    }
+}
+
+/// Returns `true` if the TXT record of a service is not published at all, which is the case when
+/// it has no TXT keys and [`TMdnsService::set_publish_empty_txt()`] is disabled.
+///
+/// [`TMdnsService::set_publish_empty_txt()`]: trait.TMdnsService.html#tymethod.set_publish_empty_txt
+pub(crate) fn omits_txt(publish_empty_txt: bool, txt_record: Option<&TxtRecord>) -> bool {
+    !publish_empty_txt && txt_record.map_or(true, |t| t.is_empty())
+}
+
+/// Keys of the services currently registered by this process, as returned by
+/// [`ServiceDiscovery::service_key()`].
+///
+/// [`ServiceDiscovery::service_key()`]: ../struct.ServiceDiscovery.html#method.service_key
+static OWN_SERVICES: Mutex<Vec<(String, String, String)>> = Mutex::new(Vec::new());
+
+/// Returns `true` if a service with the specified key is currently registered by this process.
+pub(crate) fn is_own_service(service_key: &(String, String, String)) -> bool {
+    OWN_SERVICES.lock().unwrap().contains(service_key)
+}
+
+/// Records the name a service of this process was registered under for as long as it remains
+/// registered, which is until it is registered under another name or this is dropped.
+#[derive(Debug, Default)]
+pub(crate) struct OwnService {
+    key: Option<(String, String, String)>,
+}
+
+impl OwnService {
+    /// Records `registration` in place of the previous registration, if any.
+    pub(crate) fn set(&mut self, registration: &ServiceRegistration) {
+        self.clear();
+
+        let key = browser::service_key(
+            &registration.name,
+            &registration.service_type,
+            &registration.domain,
+        );
+
+        OWN_SERVICES.lock().unwrap().push(key.clone());
+        self.key = Some(key);
+    }
+
+    /// Forgets the registration, if any.
+    pub(crate) fn clear(&mut self) {
+        if let Some(key) = self.key.take() {
+            let mut own_services = OWN_SERVICES.lock().unwrap();
+
+            // services of other instances may have the same key, only remove this one
+            if let Some(i) = own_services.iter().position(|k| *k == key) {
+                own_services.remove(i);
+            }
+        }
+    }
+}
+
+impl Drop for OwnService {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+/// Interface for updating a registered [`MdnsService`] without borrowing it.
+///
+/// [`MdnsService`]: ../type.MdnsService.html
+pub trait TServiceHandle: Clone {
+    /// Replaces the TXT record of the registered service.
+    ///
+    /// Returns an error if the service has not been registered yet or has been dropped, and
+    /// [`Error::Reentrant`] if the library is already operating on the service.
+    ///
+    /// [`Error::Reentrant`]: ../error/enum.Error.html#variant.Reentrant
+    fn update_txt_record(&self, txt_record: TxtRecord) -> Result<()>;
+
+    /// Sets the value of `key` in the TXT record of the registered service and updates the record
+    /// in place, as with [`update_txt_record()`], keeping its other entries. The key is moved to
+    /// the end of the record if it was already present.
+    ///
+    /// Returns an error if the service has not been registered yet or has been dropped, or if the
+    /// entry is not valid.
+    ///
+    /// [`update_txt_record()`]: #tymethod.update_txt_record
+    fn set_txt_key(&self, key: &str, value: &str) -> Result<()>;
+
+    /// Removes `key` from the TXT record of the registered service and updates the record in
+    /// place, as with [`update_txt_record()`], keeping its other entries. Removing a key that is
+    /// not present still updates the record.
+    ///
+    /// Returns an error if the service has not been registered yet or has been dropped.
+    ///
+    /// [`update_txt_record()`]: #tymethod.update_txt_record
+    fn remove_txt_key(&self, key: &str) -> Result<()>;
+
+    /// Changes the port advertised in the SRV record of the registered service, such as after
+    /// the server behind it restarted on a different port.
+    ///
+    /// The SRV record cannot be updated in place like the TXT record, so on Linux the Avahi entry
+    /// group is reset and the service is added again with the new port and committed. The service
+    /// is briefly withdrawn while this happens: peers may see it removed and then discovered
+    /// again, and the [`ServiceRegisteredCallback`] is invoked again once it has been
+    /// re-established. On macOS, Bonjour does not support this and an error is returned, so the
+    /// service has to be registered again instead.
+    ///
+    /// Returns an error if the service has not been registered yet or has been dropped, and
+    /// [`Error::Reentrant`] if the library is already operating on the service.
+    ///
+    /// [`ServiceRegisteredCallback`]: ../type.ServiceRegisteredCallback.html
+    /// [`Error::Reentrant`]: ../error/enum.Error.html#variant.Reentrant
+    fn update_port(&self, port: u16) -> Result<()>;
 }
 
 /// Callback invoked from [`MdnsService`] once it has successfully registered.
@@ -77,12 +1054,473 @@ pub trait TMdnsService {
 /// [`MdnsService`]: type.MdnsService.html
 pub type ServiceRegisteredCallback = dyn Fn(Result<ServiceRegistration>, Option<Arc<dyn Any>>);
 
+/// Generates the TXT record of a [`MdnsService`] each time it is called, as set with
+/// [`TMdnsService::set_txt_provider()`].
+///
+/// [`MdnsService`]: type.MdnsService.html
+/// [`TMdnsService::set_txt_provider()`]: trait.TMdnsService.html#tymethod.set_txt_provider
+pub type TxtProvider = dyn Fn() -> TxtRecord;
+
+/// The shortest interval at which a [`TxtProvider`] is called.
+///
+/// [`TxtProvider`]: type.TxtProvider.html
+pub const MIN_TXT_PROVIDER_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Calls `txt_provider`, returning `None` if it panicked.
+pub(crate) fn provide_txt(txt_provider: &TxtProvider) -> Option<TxtRecord> {
+    let mut txt_record = None;
+    crate::ffi::catch_callback_panic("TXT provider", || txt_record = Some(txt_provider()));
+    txt_record
+}
+
+/// Returns the timer tick that regenerates the TXT record of the service behind `handle` with
+/// `txt_provider`, and updates it if it differs from `published`, the record last published.
+pub(crate) fn txt_provider_tick<H>(
+    handle: H,
+    txt_provider: Rc<TxtProvider>,
+    published: Option<TxtRecord>,
+) -> impl Fn()
+where
+    H: TServiceHandle + 'static,
+{
+    let published = RefCell::new(published);
+
+    move || {
+        let txt_record = match provide_txt(&*txt_provider) {
+            Some(txt_record) => txt_record,
+            None => return,
+        };
+
+        if let Some(published) = &*published.borrow() {
+            let diff = published.diff(&txt_record);
+
+            if diff.is_empty() {
+                return;
+            }
+
+            debug!("TXT record from provider changed: {}", diff);
+        }
+
+        match handle.update_txt_record(txt_record.clone()) {
+            Ok(()) => *published.borrow_mut() = Some(txt_record),
+            Err(e) => warn!("could not update TXT record from provider: {}", e),
+        }
+    }
+}
+
 /// Represents a registration event for a [`MdnsService`].
 ///
 /// [`MdnsService`]: type.MdnsService.html
 #[derive(Builder, BuilderDelegate, Debug, Getters, Clone, Default, PartialEq, Eq)]
+#[builder(build_fn(private, name = "try_build"))]
 pub struct ServiceRegistration {
     name: String,
     service_type: ServiceType,
     domain: String,
 }
+
+builder_build_fn!(ServiceRegistrationBuilder, ServiceRegistration);
+
+impl ServiceRegistration {
+    /// Returns the fully qualified domain name of the registered service, e.g.
+    /// `My\032Service._http._tcp.local.`.
+    ///
+    /// The instance name is the one the service was actually registered under, which may differ
+    /// from the requested name if it was renamed to resolve a conflict. Dots, backslashes, spaces
+    /// and control characters in the instance name are escaped as in DNS presentation format, so
+    /// the result can be passed to other DNS-SD tools as is.
+    pub fn full_name(&self) -> String {
+        format!(
+            "{}._{}._{}.{}.",
+            escape_label(&self.name),
+            self.service_type.name(),
+            self.service_type.protocol(),
+            self.domain.trim_end_matches('.')
+        )
+    }
+}
+
+/// Escapes `label` for use as a single label of a domain name in presentation format.
+pub(crate) fn escape_label(label: &str) -> String {
+    let mut escaped = String::with_capacity(label.len());
+
+    for c in label.chars() {
+        match c {
+            '.' | '\\' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            c if c <= ' ' || c == '\x7f' => escaped.push_str(&format!("\\{:03}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Error;
+    use crate::prelude::*;
+    use crate::InterfaceIndex;
+    use std::collections::HashSet;
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    fn registration(name: &str) -> ServiceRegistration {
+        ServiceRegistration::builder()
+            .name(name.to_string())
+            .service_type(ServiceType::new("http", "tcp").unwrap())
+            .domain("local".to_string())
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn registration_states_send_current_state_on_subscription() {
+        let mut states = RegistrationStates::default();
+        let early = states.subscribe();
+
+        assert!(early.try_recv().is_err());
+
+        states.set(RegistrationState::Registering);
+        states.set(RegistrationState::Registering);
+        states.set(RegistrationState::Established);
+
+        let late = states.subscribe();
+
+        assert_eq!(
+            early.try_iter().collect::<Vec<_>>(),
+            vec![
+                RegistrationState::Registering,
+                RegistrationState::Established
+            ]
+        );
+        assert_eq!(
+            late.try_iter().collect::<Vec<_>>(),
+            vec![RegistrationState::Established]
+        );
+    }
+
+    #[test]
+    fn registration_states_unsubscribe_dropped_receivers() {
+        let mut states = RegistrationStates::default();
+        drop(states.subscribe());
+
+        states.set(RegistrationState::Registering);
+
+        assert!(states.senders.is_empty());
+    }
+
+    #[test]
+    fn alternative_service_name_increments_number() {
+        assert_eq!(alternative_service_name("Printer"), "Printer #2");
+        assert_eq!(alternative_service_name("Printer #2"), "Printer #3");
+        assert_eq!(alternative_service_name("Printer #9"), "Printer #10");
+        assert_eq!(alternative_service_name("Printer #x"), "Printer #x #2");
+        assert_eq!(alternative_service_name("Printer #02"), "Printer #02 #2");
+    }
+
+    #[test]
+    fn alternative_service_name_stays_within_max_len() {
+        let name = "é".repeat(INSTANCE_NAME_MAX_LEN / 2);
+        let alternative = alternative_service_name(&name);
+
+        assert!(alternative.len() <= INSTANCE_NAME_MAX_LEN);
+        assert!(alternative.ends_with(" #2"));
+        assert_eq!(
+            alternative_service_name(&alternative).len(),
+            alternative.len()
+        );
+    }
+
+    #[test]
+    fn random_suffix_is_hexadecimal() {
+        let suffix = random_suffix();
+
+        assert_eq!(suffix.len(), UNIQUE_NAME_SUFFIX_LEN);
+        assert!(suffix
+            .chars()
+            .all(|c| c.is_ascii_digit() || ('a'..='f').contains(&c)));
+    }
+
+    #[test]
+    fn random_suffix_differs_between_calls() {
+        let suffixes = (0..100).map(|_| random_suffix()).collect::<HashSet<_>>();
+        assert_eq!(suffixes.len(), 100);
+    }
+
+    #[test]
+    fn own_service_is_tracked_until_dropped() {
+        let first = registration("own_service_is_tracked_until_dropped");
+        let second = registration("own_service_is_tracked_until_dropped (2)");
+        let key =
+            |r: &ServiceRegistration| browser::service_key(r.name(), r.service_type(), r.domain());
+
+        let mut own_service = OwnService::default();
+        own_service.set(&first);
+        assert!(is_own_service(&key(&first)));
+
+        own_service.set(&second);
+        assert!(!is_own_service(&key(&first)));
+        assert!(is_own_service(&key(&second)));
+
+        drop(own_service);
+        assert!(!is_own_service(&key(&second)));
+    }
+
+    #[test]
+    fn own_service_keeps_other_instances_with_same_key() {
+        let registration = registration("own_service_keeps_other_instances_with_same_key");
+        let key = browser::service_key(
+            registration.name(),
+            registration.service_type(),
+            registration.domain(),
+        );
+
+        let mut first = OwnService::default();
+        let mut second = OwnService::default();
+        first.set(&registration);
+        second.set(&registration);
+
+        first.clear();
+        assert!(is_own_service(&key));
+
+        second.clear();
+        assert!(!is_own_service(&key));
+    }
+
+    #[test]
+    fn service_registration_builder_names_missing_field() {
+        assert_eq!(
+            ServiceRegistration::builder()
+                .name("My Service".to_string())
+                .domain("local".to_string())
+                .build(),
+            Err(Error::MissingField("service_type".to_string()))
+        );
+
+        assert_eq!(
+            ServiceRegistration::builder()
+                .service_type(ServiceType::new("http", "tcp").unwrap())
+                .domain("local".to_string())
+                .build(),
+            Err(Error::MissingField("name".to_string()))
+        );
+
+        assert_eq!(
+            ServiceRegistration::builder()
+                .name("My Service".to_string())
+                .service_type(ServiceType::new("http", "tcp").unwrap())
+                .build(),
+            Err(Error::MissingField("domain".to_string()))
+        );
+    }
+
+    #[test]
+    fn full_name_escapes_instance_name() {
+        assert_eq!(
+            registration("My Service").full_name(),
+            "My\\032Service._http._tcp.local."
+        );
+        assert_eq!(
+            registration("a.b\\c\td").full_name(),
+            "a\\.b\\\\c\\009d._http._tcp.local."
+        );
+    }
+
+    #[test]
+    fn full_name_keeps_non_ascii_characters() {
+        assert_eq!(registration("Café").full_name(), "Café._http._tcp.local.");
+    }
+
+    #[test]
+    fn validate_instance_name_success() {
+        assert_eq!(validate_instance_name("Living Room Printer"), Ok(()));
+        assert_eq!(validate_instance_name("Drucker (Küche) v1.2"), Ok(()));
+        assert_eq!(validate_instance_name(&"a".repeat(63)), Ok(()));
+    }
+
+    #[test]
+    fn validate_instance_name_rejects_empty() {
+        assert_eq!(
+            validate_instance_name(""),
+            Err(Error::InvalidName(NameError::Empty))
+        );
+    }
+
+    #[test]
+    fn validate_instance_name_rejects_long_name() {
+        // counts bytes rather than characters
+        assert_eq!(
+            validate_instance_name(&"ü".repeat(32)),
+            Err(Error::InvalidName(NameError::TooLong { len: 64, max: 63 }))
+        );
+    }
+
+    #[test]
+    fn validate_instance_name_rejects_control_characters() {
+        assert_eq!(
+            validate_instance_name("foo\nbar"),
+            Err(Error::InvalidName(NameError::IllegalCharacter('\n')))
+        );
+        assert_eq!(
+            validate_instance_name("foo\0"),
+            Err(Error::InvalidName(NameError::IllegalCharacter('\0')))
+        );
+    }
+
+    #[test]
+    fn check_addresses_allows_no_addresses_without_host() {
+        assert_eq!(check_addresses(NetworkInterface::Unspec, None, &[]), Ok(()));
+    }
+
+    #[test]
+    fn check_addresses_requires_host() {
+        assert_eq!(
+            check_addresses(
+                NetworkInterface::Unspec,
+                None,
+                &[IpAddr::V4(Ipv4Addr::LOCALHOST)]
+            ),
+            Err("a host must be set to publish addresses".into())
+        );
+    }
+
+    #[test]
+    fn check_addresses_rejects_unspecified_address() {
+        assert_eq!(
+            check_addresses(
+                NetworkInterface::Unspec,
+                Some("myhost.local"),
+                &[IpAddr::V6(Ipv6Addr::UNSPECIFIED)]
+            ),
+            Err("address `::` cannot be published".into())
+        );
+    }
+
+    #[test]
+    fn check_addresses_rejects_unknown_interface() {
+        let address = IpAddr::V4(Ipv4Addr::new(192, 168, 1, 2));
+
+        assert_eq!(
+            check_addresses(
+                NetworkInterface::AtIndex(InterfaceIndex(u32::MAX)),
+                Some("myhost.local"),
+                &[address]
+            ),
+            Err(
+                "address `192.168.1.2` is of a family not supported by interface AtIndex(InterfaceIndex(4294967295))"
+                    .into()
+            )
+        );
+    }
+
+    #[test]
+    fn tracked_addresses_keeps_families_of_explicit_addresses() {
+        let v4 = IpAddr::V4(Ipv4Addr::new(192, 168, 1, 3));
+        let v6 = IpAddr::V6(Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, 3));
+
+        assert_eq!(
+            tracked_addresses(
+                &[IpAddr::V4(Ipv4Addr::new(192, 168, 1, 2))],
+                &[v6, v4, IpAddr::V4(Ipv4Addr::LOCALHOST)]
+            ),
+            vec![v4]
+        );
+    }
+
+    #[test]
+    fn tracked_addresses_is_empty_without_explicit_addresses() {
+        assert_eq!(
+            tracked_addresses(&[], &[IpAddr::V4(Ipv4Addr::new(192, 168, 1, 3))]),
+            Vec::<IpAddr>::new()
+        );
+    }
+
+    #[test]
+    fn reverse_lookup_name_reverses_address() {
+        assert_eq!(
+            reverse_lookup_name(&IpAddr::V4(Ipv4Addr::new(192, 168, 1, 20))),
+            "20.1.168.192.in-addr.arpa"
+        );
+        assert_eq!(
+            reverse_lookup_name(&IpAddr::V6(Ipv6Addr::new(
+                0x2001, 0xdb8, 0, 0, 0, 0, 0, 0x1ab
+            ))),
+            "b.a.1.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.8.b.d.0.1.0.0.2.ip6.arpa"
+        );
+    }
+
+    #[test]
+    fn omits_txt_only_without_keys_when_disabled() {
+        let mut txt_record = TxtRecord::new();
+
+        assert!(!omits_txt(true, None));
+        assert!(!omits_txt(true, Some(&txt_record)));
+        assert!(omits_txt(false, None));
+        assert!(omits_txt(false, Some(&txt_record)));
+
+        txt_record.insert("foo", "bar").unwrap();
+        assert!(!omits_txt(false, Some(&txt_record)));
+    }
+
+    /// Records the TXT records the provider tick replaces, which is the only update it makes.
+    #[derive(Debug, Clone, Default)]
+    struct RecordingHandle(Rc<RefCell<Vec<TxtRecord>>>);
+
+    impl TServiceHandle for RecordingHandle {
+        fn update_txt_record(&self, txt_record: TxtRecord) -> Result<()> {
+            self.0.borrow_mut().push(txt_record);
+            Ok(())
+        }
+
+        fn set_txt_key(&self, _key: &str, _value: &str) -> Result<()> {
+            Err("only TXT records are recorded".into())
+        }
+
+        fn remove_txt_key(&self, _key: &str) -> Result<()> {
+            Err("only TXT records are recorded".into())
+        }
+
+        fn update_port(&self, _port: u16) -> Result<()> {
+            Err("only TXT records are recorded".into())
+        }
+    }
+
+    fn status_txt(status: &str) -> TxtRecord {
+        TxtRecord::from_pairs(&[("status", status)]).unwrap()
+    }
+
+    #[test]
+    fn txt_provider_tick_updates_only_changed_records() {
+        let handle = RecordingHandle::default();
+        let status = Rc::new(RefCell::new("idle"));
+
+        let provider_status = Rc::clone(&status);
+        let tick = txt_provider_tick(
+            handle.clone(),
+            Rc::new(move || status_txt(*provider_status.borrow())),
+            Some(status_txt("idle")),
+        );
+
+        tick();
+        assert!(handle.0.borrow().is_empty());
+
+        *status.borrow_mut() = "busy";
+        tick();
+        tick();
+
+        assert_eq!(*handle.0.borrow(), vec![status_txt("busy")]);
+    }
+
+    #[test]
+    fn txt_provider_tick_skips_panicking_provider() {
+        let handle = RecordingHandle::default();
+        let tick = txt_provider_tick(handle.clone(), Rc::new(|| panic!("no status")), None);
+
+        tick();
+
+        assert!(handle.0.borrow().is_empty());
+    }
+}
@@ -0,0 +1,227 @@
+//! Data type for describing a service in a configuration file
+
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use crate::error::Error;
+use crate::prelude::*;
+use crate::{service, txt_record, Result, ServiceType, TxtRecord};
+
+/// Describes a service to publish, such as one read from a TOML or JSON configuration file, which
+/// can be turned into an [`MdnsService`] with [`TMdnsService::from_config()`].
+///
+/// In a configuration file, only `type` and `port` are required:
+///
+/// ```json
+/// {
+///     "name": "Living Room Printer",
+///     "type": "_ipp._tcp",
+///     "port": 631,
+///     "sub_types": ["printer"],
+///     "txt": { "txtvers": "1", "rp": "printers/living-room" }
+/// }
+/// ```
+///
+/// * `name` - the instance name, defaulting to the machine's host name
+/// * `type` - the service type as it appears on the network, e.g. `_http._tcp`
+/// * `port` - the port the service is reachable on
+/// * `domain` - the domain to register in, defaulting to the daemon's default domain
+/// * `host` - the SRV target host name, defaulting to the machine's host name
+/// * `sub_types` - the sub-types to register the service under, without the leading underscore
+/// * `txt` - the entries of the TXT record, which is only registered if there are any
+///
+/// [`MdnsService`]: type.MdnsService.html
+/// [`TMdnsService::from_config()`]: service/trait.TMdnsService.html#method.from_config
+#[derive(
+    Debug, Getters, Builder, BuilderDelegate, Serialize, Deserialize, Clone, PartialEq, Eq,
+)]
+#[builder(build_fn(private, name = "try_build"))]
+pub struct ServiceConfig {
+    #[builder(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    #[serde(rename = "type")]
+    service_type: String,
+    port: u16,
+    #[builder(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    domain: Option<String>,
+    #[builder(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    host: Option<String>,
+    #[builder(default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    sub_types: Vec<String>,
+    #[builder(default)]
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    txt: BTreeMap<String, String>,
+}
+
+builder_build_fn!(ServiceConfigBuilder, ServiceConfig);
+
+impl ServiceConfig {
+    /// Validates every field of the configuration, returning [`Error::InvalidConfig`] listing
+    /// each field that is not valid along with why.
+    ///
+    /// [`Error::InvalidConfig`]: error/enum.Error.html#variant.InvalidConfig
+    pub fn validate(&self) -> Result<()> {
+        let mut errors = Vec::new();
+
+        if let Some(name) = &self.name {
+            if let Err(e) = service::validate_instance_name(name) {
+                errors.push(("name".to_string(), e));
+            }
+        }
+
+        if let Err(e) = crate::validate_service_type(&self.service_type) {
+            errors.push(("type".to_string(), e));
+        }
+
+        if self.domain.as_deref() == Some("") {
+            errors.push(("domain".to_string(), "cannot be empty".into()));
+        }
+
+        if self.host.as_deref() == Some("") {
+            errors.push(("host".to_string(), "cannot be empty".into()));
+        }
+
+        for sub_type in &self.sub_types {
+            if let Err(e) = crate::check_valid_characters(sub_type) {
+                errors.push((format!("sub_types.{}", sub_type), e));
+            }
+        }
+
+        for (key, value) in &self.txt {
+            if let Err(e) = txt_record::check_entry_length(key, value.as_bytes()) {
+                errors.push((format!("txt.{}", key), e));
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::InvalidConfig(errors))
+        }
+    }
+
+    /// Returns the [`ServiceType`] described by the configuration, including its sub-types.
+    ///
+    /// [`ServiceType`]: struct.ServiceType.html
+    pub fn to_service_type(&self) -> Result<ServiceType> {
+        let service_type = ServiceType::from_str(&self.service_type)?;
+
+        ServiceType::with_sub_types(
+            service_type.name(),
+            service_type.protocol(),
+            self.sub_types.iter().map(String::as_str).collect(),
+        )
+    }
+
+    /// Returns the TXT record described by the configuration, or `None` if it has no entries.
+    pub fn to_txt_record(&self) -> Result<Option<TxtRecord>> {
+        if self.txt.is_empty() {
+            return Ok(None);
+        }
+
+        let mut txt_record = TxtRecord::new();
+
+        for (key, value) in &self.txt {
+            txt_record.insert(key, value)?;
+        }
+
+        Ok(Some(txt_record))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::NameError;
+
+    #[test]
+    fn service_config_round_trips_through_json() {
+        let json = r#"{
+            "name": "Living Room Printer",
+            "type": "_ipp._tcp",
+            "port": 631,
+            "domain": "local",
+            "sub_types": ["printer"],
+            "txt": { "txtvers": "1", "rp": "printers/living-room" }
+        }"#;
+
+        let config: ServiceConfig = serde_json::from_str(json).unwrap();
+
+        assert_eq!(config.name().as_deref(), Some("Living Room Printer"));
+        assert_eq!(config.service_type(), "_ipp._tcp");
+        assert_eq!(*config.port(), 631);
+        assert_eq!(config.host(), &None);
+        assert_eq!(config.txt().get("rp").unwrap(), "printers/living-room");
+
+        let reparsed: ServiceConfig =
+            serde_json::from_str(&serde_json::to_string(&config).unwrap()).unwrap();
+
+        assert_eq!(reparsed, config);
+    }
+
+    #[test]
+    fn service_config_requires_type_and_port() {
+        assert!(serde_json::from_str::<ServiceConfig>(r#"{ "port": 80 }"#).is_err());
+        assert!(serde_json::from_str::<ServiceConfig>(r#"{ "type": "_http._tcp" }"#).is_err());
+    }
+
+    #[test]
+    fn service_config_builder_names_missing_field() {
+        assert_eq!(
+            ServiceConfig::builder().port(80).build(),
+            Err(Error::MissingField("service_type".to_string()))
+        );
+    }
+
+    #[test]
+    fn validate_accepts_valid_config() {
+        let config: ServiceConfig =
+            serde_json::from_str(r#"{ "type": "_http._tcp", "port": 80 }"#).unwrap();
+
+        assert_eq!(config.validate(), Ok(()));
+    }
+
+    #[test]
+    fn validate_aggregates_errors() {
+        let config = ServiceConfig::builder()
+            .name(Some(String::new()))
+            .service_type("http".to_string())
+            .port(80)
+            .sub_types(vec!["a.b".to_string()])
+            .build()
+            .unwrap();
+
+        let errors = match config.validate() {
+            Err(Error::InvalidConfig(errors)) => errors,
+            result => panic!("unexpected result: {:?}", result),
+        };
+
+        assert_eq!(
+            errors,
+            vec![
+                ("name".to_string(), NameError::Empty.into()),
+                ("type".to_string(), NameError::InvalidFormat.into()),
+                ("sub_types.a.b".to_string(), "invalid character: .".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn to_service_type_includes_sub_types() {
+        let config = ServiceConfig::builder()
+            .service_type("_ipp._tcp".to_string())
+            .port(631)
+            .sub_types(vec!["printer".to_string()])
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            config.to_service_type().unwrap(),
+            ServiceType::with_sub_types("ipp", "tcp", vec!["printer"]).unwrap()
+        );
+    }
+}
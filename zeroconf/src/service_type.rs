@@ -2,10 +2,16 @@
 
 use std::str::FromStr;
 
-use crate::{error::Error, Result};
+use crate::error::{Error, NameError};
+use crate::Result;
+
+/// Maximum length in bytes of the name of a service type, without the leading underscore.
+///
+/// See [RFC 6335](https://www.rfc-editor.org/rfc/rfc6335#section-5.1).
+pub const SERVICE_NAME_MAX_LEN: usize = 15;
 
 /// Data type for constructing a service type to register as an mDNS service.
-#[derive(Default, Debug, Getters, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[derive(Default, Debug, Getters, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
 pub struct ServiceType {
     name: String,
     protocol: String,
@@ -53,6 +59,60 @@ impl FromStr for ServiceType {
     }
 }
 
+/// Validates a service type such as `_http._tcp` as it appears on the network.
+///
+/// The name must be at most [`SERVICE_NAME_MAX_LEN`] bytes of letters, digits and hyphens,
+/// contain at least one letter and must not start or end with a hyphen or contain two in a row,
+/// as required by [RFC 6335]. The protocol must be `_tcp` or `_udp`.
+///
+/// [`ServiceType::new()`] is more lenient, since daemons accept service types that do not follow
+/// these rules, but types that do are the most interoperable.
+///
+/// [`SERVICE_NAME_MAX_LEN`]: constant.SERVICE_NAME_MAX_LEN.html
+/// [RFC 6335]: https://www.rfc-editor.org/rfc/rfc6335#section-5.1
+/// [`ServiceType::new()`]: struct.ServiceType.html#method.new
+pub fn validate_service_type(service_type: &str) -> Result<()> {
+    let (name, protocol) = match service_type.split('.').collect::<Vec<_>>()[..] {
+        [name, protocol] => (name, protocol),
+        _ => return Err(NameError::InvalidFormat.into()),
+    };
+
+    let name = name.strip_prefix('_').ok_or(NameError::InvalidFormat)?;
+
+    if !protocol.eq_ignore_ascii_case("_tcp") && !protocol.eq_ignore_ascii_case("_udp") {
+        return Err(NameError::InvalidFormat.into());
+    }
+
+    if name.is_empty() {
+        return Err(NameError::Empty.into());
+    }
+
+    if name.len() > SERVICE_NAME_MAX_LEN {
+        return Err(NameError::TooLong {
+            len: name.len(),
+            max: SERVICE_NAME_MAX_LEN,
+        }
+        .into());
+    }
+
+    if let Some(c) = name
+        .chars()
+        .find(|c| !c.is_ascii_alphanumeric() && *c != '-')
+    {
+        return Err(NameError::IllegalCharacter(c).into());
+    }
+
+    if !name.chars().any(|c| c.is_ascii_alphabetic()) {
+        return Err(NameError::MissingLetter.into());
+    }
+
+    if name.starts_with('-') || name.ends_with('-') || name.contains("--") {
+        return Err(NameError::MisplacedHyphen.into());
+    }
+
+    Ok(())
+}
+
 pub fn check_valid_characters(part: &str) -> Result<&str> {
     if part.contains('.') {
         Err("invalid character: .".into())
@@ -121,6 +181,55 @@ mod tests {
         assert_eq!(check_valid_characters("foo").unwrap(), "foo");
     }
 
+    #[test]
+    fn validate_service_type_success() {
+        assert_eq!(validate_service_type("_http._tcp"), Ok(()));
+        assert_eq!(validate_service_type("_zeroconf-rs._udp"), Ok(()));
+        assert_eq!(validate_service_type("_IPP._TCP"), Ok(()));
+    }
+
+    #[test]
+    fn validate_service_type_rejects_invalid_format() {
+        for service_type in &["http._tcp", "_http", "_http._tcp.local", "_http._sctp"] {
+            assert_eq!(
+                validate_service_type(service_type),
+                Err(Error::InvalidName(NameError::InvalidFormat))
+            );
+        }
+    }
+
+    #[test]
+    fn validate_service_type_rejects_long_name() {
+        assert_eq!(
+            validate_service_type("_zeroconftypetest._tcp"),
+            Err(Error::InvalidName(NameError::TooLong { len: 16, max: 15 }))
+        );
+    }
+
+    #[test]
+    fn validate_service_type_rejects_illegal_name() {
+        assert_eq!(
+            validate_service_type("_._tcp"),
+            Err(Error::InvalidName(NameError::Empty))
+        );
+        assert_eq!(
+            validate_service_type("_foo_bar._tcp"),
+            Err(Error::InvalidName(NameError::IllegalCharacter('_')))
+        );
+        assert_eq!(
+            validate_service_type("_123._tcp"),
+            Err(Error::InvalidName(NameError::MissingLetter))
+        );
+        assert_eq!(
+            validate_service_type("_foo--bar._tcp"),
+            Err(Error::InvalidName(NameError::MisplacedHyphen))
+        );
+        assert_eq!(
+            validate_service_type("_-foo._tcp"),
+            Err(Error::InvalidName(NameError::MisplacedHyphen))
+        );
+    }
+
     #[test]
     fn lstrip_underscore_returns_stripped() {
         assert_eq!(lstrip_underscore("_foo"), "foo");
@@ -0,0 +1,78 @@
+//! Trait definition for cross-platform service type browser
+
+use crate::event_loop::TEventLoop;
+use crate::{NetworkInterface, NetworkProtocol, Result, ServiceType};
+use std::any::Any;
+use std::sync::Arc;
+
+/// The DNS-SD meta-query type used to enumerate the service types present on the network.
+pub const META_QUERY_SERVICE_TYPE: &str = "_services._dns-sd._udp";
+
+/// Interface for interacting with underlying mDNS implementation service type browsing
+/// capabilities.
+///
+/// A service type browser issues the DNS-SD meta-query ([`META_QUERY_SERVICE_TYPE`]) to discover
+/// which kinds of services exist on the network, without knowing them in advance.
+///
+/// [`META_QUERY_SERVICE_TYPE`]: constant.META_QUERY_SERVICE_TYPE.html
+pub trait TServiceTypeBrowser {
+    /// The event loop returned when the browser is started, which borrows the browser.
+    type EventLoop<'a>: TEventLoop
+    where
+        Self: 'a;
+
+    /// Creates a new `ServiceTypeBrowser`
+    fn new() -> Self;
+
+    /// Sets the network interface on which to browse for service types on.
+    ///
+    /// Most applications will want to use the default value `NetworkInterface::Unspec` to browse
+    /// on all available interfaces.
+    fn set_network_interface(&mut self, interface: NetworkInterface);
+
+    /// Returns the network interface on which to browse for service types on.
+    fn network_interface(&self) -> NetworkInterface;
+
+    /// Sets the network protocol over which to issue the meta-query. Defaults to
+    /// `NetworkProtocol::Unspec`, which browses over both IPv4 and IPv6.
+    ///
+    /// The meta-query is still a multicast query, and its answers are PTR records naming the
+    /// service types that have been advertised over the chosen protocol. A service type is
+    /// reported regardless of whether any of its instances are reachable on that address family.
+    ///
+    /// Bonjour does not support restricting the protocol of a browser, so this has no effect on
+    /// macOS.
+    fn set_network_protocol(&mut self, protocol: NetworkProtocol);
+
+    /// Returns the network protocol over which to issue the meta-query.
+    fn network_protocol(&self) -> NetworkProtocol;
+
+    /// Sets the [`ServiceTypeDiscoveredCallback`] that is invoked when the browser has discovered
+    /// a service type. Each service type is reported at most once, even if it is advertised in
+    /// more than one domain or on more than one interface.
+    ///
+    /// [`ServiceTypeDiscoveredCallback`]: ../type.ServiceTypeDiscoveredCallback.html
+    fn set_service_type_discovered_callback(
+        &mut self,
+        service_type_discovered_callback: Box<ServiceTypeDiscoveredCallback>,
+    );
+
+    /// Sets the optional user context to pass through to the callback. This is useful if you need
+    /// to share state between pre and post-callback. The context type must implement `Any`.
+    fn set_context(&mut self, context: Box<dyn Any>);
+
+    /// Returns the optional user context to pass through to the callback.
+    fn context(&self) -> Option<&dyn Any>;
+
+    /// Starts the browser. Returns an `EventLoop` which can be called to keep the browser alive.
+    fn browse_types(&mut self) -> Result<Self::EventLoop<'_>>;
+}
+
+/// Callback invoked from [`ServiceTypeBrowser`] once a service type has been discovered.
+///
+/// # Arguments
+/// * `service_type` - The service type that was discovered
+/// * `context` - The optional user context passed through
+///
+/// [`ServiceTypeBrowser`]: type.ServiceTypeBrowser.html
+pub type ServiceTypeDiscoveredCallback = dyn Fn(Result<ServiceType>, Option<Arc<dyn Any>>);
@@ -0,0 +1,1203 @@
+use crate::error::Error;
+use crate::prelude::*;
+use crate::{
+    BrowseEvent, CancellationToken, MdnsBrowser, MdnsService, NetworkInterface, NetworkProtocol,
+    Scope, ServiceBrowse, ServiceDiscovery, ServiceType, TxtRecord,
+};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+const TOTAL_TEST_TIME: Duration = Duration::from_secs(30);
+
+#[derive(Default, Debug)]
+struct Context {
+    browsed: Vec<ServiceBrowse>,
+    resolved: Option<String>,
+}
+
+#[test]
+fn browser_resolves_lazily() {
+    super::setup();
+
+    static SERVICE_NAME: &str = "browser_resolves_lazily";
+
+    let mut service = MdnsService::new(ServiceType::new("http", "tcp").unwrap(), 8080);
+    service.set_name(SERVICE_NAME);
+    service.set_registered_callback(Box::new(|_, _| {
+        debug!("Service published");
+    }));
+
+    let service_loop = service.register().unwrap();
+
+    let context: Arc<Mutex<Context>> = Arc::default();
+    let mut browser = MdnsBrowser::new(ServiceType::new("http", "tcp").unwrap());
+
+    browser.set_resolve_services(false);
+    browser.set_context(Box::new(context.clone()));
+
+    browser.set_service_browsed_callback(Box::new(|event, context| {
+        if let BrowseEvent::New(service) = event.unwrap() {
+            context
+                .as_ref()
+                .unwrap()
+                .downcast_ref::<Arc<Mutex<Context>>>()
+                .unwrap()
+                .lock()
+                .unwrap()
+                .browsed
+                .push(service);
+        }
+    }));
+
+    browser.set_service_discovered_callback(Box::new(|service, context| {
+        let service = service.unwrap();
+
+        context
+            .as_ref()
+            .unwrap()
+            .downcast_ref::<Arc<Mutex<Context>>>()
+            .unwrap()
+            .lock()
+            .unwrap()
+            .resolved = Some(service.name().clone());
+    }));
+
+    let mut browser_loop = browser.browse_services().unwrap();
+    let start = Instant::now();
+    let mut requested = false;
+
+    while start.elapsed() < TOTAL_TEST_TIME {
+        service_loop.poll(Duration::from_secs(0)).unwrap();
+        browser_loop.poll(Duration::from_secs(0)).unwrap();
+
+        let mtx = context.lock().unwrap();
+
+        if mtx.resolved.is_some() {
+            break;
+        }
+
+        if requested {
+            continue;
+        }
+
+        // nothing must be resolved until explicitly requested
+        assert!(mtx.resolved.is_none());
+
+        if let Some(browsed) = mtx
+            .browsed
+            .iter()
+            .find(|s| s.name() == SERVICE_NAME)
+            .cloned()
+        {
+            drop(mtx);
+            drop(browser_loop);
+            browser_loop = browser.resolve(&browsed).unwrap();
+            requested = true;
+        }
+    }
+
+    assert!(requested);
+    assert_eq!(
+        context.lock().unwrap().resolved.as_deref(),
+        Some(SERVICE_NAME)
+    );
+}
+
+#[test]
+fn browser_cancel_stops_callbacks() {
+    super::setup();
+
+    const QUIET_TIME: Duration = Duration::from_secs(2);
+
+    let services: Vec<_> = [
+        "browser_cancel_stops_callbacks_1",
+        "browser_cancel_stops_callbacks_2",
+    ]
+    .iter()
+    .map(|name| {
+        let mut service = MdnsService::new(ServiceType::new("http", "tcp").unwrap(), 8080);
+        service.set_name(name);
+        service.set_registered_callback(Box::new(|_, _| {
+            debug!("Service published");
+        }));
+        service
+    })
+    .collect();
+
+    let mut services = services;
+    let service_loops = services
+        .iter_mut()
+        .map(|s| s.register().unwrap())
+        .collect::<Vec<_>>();
+
+    let events: Arc<Mutex<usize>> = Arc::default();
+    let token = CancellationToken::new();
+    let mut browser = MdnsBrowser::new(ServiceType::new("http", "tcp").unwrap());
+
+    browser.set_cancellation_token(token.clone());
+    browser.set_context(Box::new(events.clone()));
+
+    let callback_token = token.clone();
+    browser.set_service_discovered_callback(Box::new(move |_, context| {
+        *context
+            .as_ref()
+            .unwrap()
+            .downcast_ref::<Arc<Mutex<usize>>>()
+            .unwrap()
+            .lock()
+            .unwrap() += 1;
+
+        callback_token.cancel();
+    }));
+
+    let browser_loop = browser.browse_services().unwrap();
+    let start = Instant::now();
+    let mut cancelled_at = None;
+
+    while start.elapsed() < TOTAL_TEST_TIME {
+        for service_loop in &service_loops {
+            service_loop.poll(Duration::from_secs(0)).unwrap();
+        }
+
+        browser_loop.poll(Duration::from_secs(0)).unwrap();
+
+        match cancelled_at {
+            None if token.is_cancelled() => cancelled_at = Some(Instant::now()),
+            Some(t) if t.elapsed() > QUIET_TIME => break,
+            _ => {}
+        }
+    }
+
+    assert!(token.is_cancelled());
+    assert_eq!(*events.lock().unwrap(), 1);
+
+    drop(browser_loop);
+    browser.cancel();
+}
+
+#[test]
+fn browser_wait_for_finds_service() {
+    super::setup();
+
+    static SERVICE_NAME: &str = "browser_wait_for_finds_service";
+
+    let stop: Arc<Mutex<bool>> = Arc::default();
+    let stop_service = stop.clone();
+
+    let service_thread = std::thread::spawn(move || {
+        let mut service = MdnsService::new(ServiceType::new("http", "tcp").unwrap(), 8080);
+        service.set_name(SERVICE_NAME);
+        service.set_registered_callback(Box::new(|_, _| {
+            debug!("Service published");
+        }));
+
+        let event_loop = service.register().unwrap();
+
+        while !*stop_service.lock().unwrap() {
+            event_loop.poll(Duration::from_millis(100)).unwrap();
+        }
+    });
+
+    let mut browser = MdnsBrowser::new(ServiceType::new("http", "tcp").unwrap());
+    let result = browser.wait_for(SERVICE_NAME, TOTAL_TEST_TIME);
+
+    *stop.lock().unwrap() = true;
+    service_thread.join().unwrap();
+
+    assert_eq!(result.unwrap().name(), SERVICE_NAME);
+}
+
+#[test]
+fn browser_find_first_finds_service() {
+    super::setup();
+
+    static SERVICE_NAME: &str = "browser_find_first_finds_service";
+
+    let stop: Arc<Mutex<bool>> = Arc::default();
+    let stop_service = stop.clone();
+
+    let service_thread = std::thread::spawn(move || {
+        let mut service = MdnsService::new(ServiceType::new("zeroconffirst", "tcp").unwrap(), 8080);
+        service.set_registered_callback(Box::new(|_, _| {}));
+        service.set_name(SERVICE_NAME);
+
+        let event_loop = service.register().unwrap();
+
+        while !*stop_service.lock().unwrap() {
+            event_loop.poll(Duration::from_millis(100)).unwrap();
+        }
+    });
+
+    let mut browser = MdnsBrowser::new(ServiceType::new("zeroconffirst", "tcp").unwrap());
+    let result = browser.find_first(TOTAL_TEST_TIME);
+
+    *stop.lock().unwrap() = true;
+    service_thread.join().unwrap();
+
+    assert_eq!(result.unwrap().name(), SERVICE_NAME);
+}
+
+#[test]
+fn browser_requires_discovered_callback_to_resolve() {
+    super::setup();
+
+    let mut browser = MdnsBrowser::new(ServiceType::new("http", "tcp").unwrap());
+
+    assert_eq!(
+        browser.browse_services().err(),
+        Some("a service discovered callback must be set to resolve services".into())
+    );
+
+    browser.set_resolve_services(false);
+    assert!(browser.browse_services().is_ok());
+}
+
+#[test]
+fn browser_reports_panicking_discovered_callback() {
+    super::setup();
+
+    static SERVICE_NAME: &str = "browser_reports_panicking_discovered_callback";
+
+    let mut service = MdnsService::new(ServiceType::new("zeroconfpanic", "tcp").unwrap(), 8080);
+    service.set_registered_callback(Box::new(|_, _| {}));
+    service.set_name(SERVICE_NAME);
+
+    let service_loop = service.register().unwrap();
+
+    let mut browser = MdnsBrowser::new(ServiceType::new("zeroconfpanic", "tcp").unwrap());
+    browser.set_service_discovered_callback(Box::new(|_, _| {
+        panic!("discovered callback panicked");
+    }));
+
+    let browser_loop = browser.browse_services().unwrap();
+    let start = Instant::now();
+
+    let error = loop {
+        assert!(start.elapsed() < TOTAL_TEST_TIME, "service not discovered");
+
+        service_loop.poll(Duration::from_millis(0)).unwrap();
+
+        if let Err(e) = browser_loop.poll(Duration::from_millis(0)) {
+            break e;
+        }
+    };
+
+    assert_eq!(
+        error,
+        Error::CallbackPanicked {
+            callback: "service discovered callback".to_string(),
+            message: "discovered callback panicked".to_string(),
+        }
+    );
+
+    // the process is still alive and the event loop is still usable
+    browser_loop.poll(Duration::from_millis(100)).unwrap();
+}
+
+#[test]
+fn browser_reports_our_own_service() {
+    super::setup();
+
+    static SERVICE_NAME: &str = "browser_reports_our_own_service";
+
+    let mut service = MdnsService::new(ServiceType::new("zeroconfown", "tcp").unwrap(), 8080);
+    service.set_registered_callback(Box::new(|_, _| {}));
+    service.set_name(SERVICE_NAME);
+
+    let service_loop = service.register().unwrap();
+
+    let found: Arc<Mutex<Option<ServiceDiscovery>>> = Arc::default();
+    let found_callback = found.clone();
+
+    let mut browser = MdnsBrowser::new(ServiceType::new("zeroconfown", "tcp").unwrap());
+    browser.set_service_discovered_callback(Box::new(move |result, _| {
+        found_callback
+            .lock()
+            .unwrap()
+            .get_or_insert(result.unwrap());
+    }));
+
+    let browser_loop = browser.browse_services().unwrap();
+    let start = Instant::now();
+
+    while found.lock().unwrap().is_none() && start.elapsed() < TOTAL_TEST_TIME {
+        service_loop.poll(Duration::from_millis(0)).unwrap();
+        browser_loop.poll(Duration::from_millis(0)).unwrap();
+    }
+
+    let discovery = found.lock().unwrap().take().unwrap();
+
+    assert_eq!(discovery.name(), SERVICE_NAME);
+    assert!(discovery.is_our_own());
+}
+
+#[cfg(feature = "diagnostics")]
+#[test]
+fn browser_reports_resolve_timing() {
+    super::setup();
+
+    static SERVICE_NAME: &str = "browser_reports_resolve_timing";
+
+    let mut service = MdnsService::new(ServiceType::new("zeroconftiming", "tcp").unwrap(), 8080);
+    service.set_registered_callback(Box::new(|_, _| {}));
+    service.set_name(SERVICE_NAME);
+
+    let service_loop = service.register().unwrap();
+
+    let found: Arc<Mutex<Option<ServiceDiscovery>>> = Arc::default();
+    let found_callback = found.clone();
+
+    let mut browser = MdnsBrowser::new(ServiceType::new("zeroconftiming", "tcp").unwrap());
+    browser.set_service_discovered_callback(Box::new(move |result, _| {
+        found_callback
+            .lock()
+            .unwrap()
+            .get_or_insert(result.unwrap());
+    }));
+
+    let start = Instant::now();
+    let browser_loop = browser.browse_services().unwrap();
+
+    while found.lock().unwrap().is_none() && start.elapsed() < TOTAL_TEST_TIME {
+        service_loop.poll(Duration::from_millis(0)).unwrap();
+        browser_loop.poll(Duration::from_millis(0)).unwrap();
+    }
+
+    let discovery = found.lock().unwrap().take().unwrap();
+    let timing = discovery.timing().unwrap();
+
+    assert!(start <= *timing.browsed_at());
+    assert!(timing.browsed_at() <= timing.resolve_started_at());
+    assert!(timing.resolve_started_at() <= timing.resolved_at());
+}
+
+#[test]
+fn browser_find_first_times_out() {
+    super::setup();
+
+    let timeout = Duration::from_secs(1);
+    let mut browser = MdnsBrowser::new(ServiceType::new("zeroconfnone", "tcp").unwrap());
+
+    let start = Instant::now();
+    let result = browser.find_first(timeout);
+
+    assert_eq!(result, Err(Error::Timeout));
+    assert!(start.elapsed() < timeout * 2);
+}
+
+#[test]
+fn is_port_advertised_finds_registered_port() {
+    super::setup();
+
+    let stop: Arc<Mutex<bool>> = Arc::default();
+    let stop_service = stop.clone();
+
+    let service_thread = std::thread::spawn(move || {
+        let mut service = MdnsService::new(ServiceType::new("zeroconfport", "tcp").unwrap(), 8123);
+        service.set_registered_callback(Box::new(|_, _| {}));
+        service.set_name("is_port_advertised_finds_registered_port");
+
+        let event_loop = service.register().unwrap();
+
+        while !*stop_service.lock().unwrap() {
+            event_loop.poll(Duration::from_millis(100)).unwrap();
+        }
+    });
+
+    let service_type = ServiceType::new("zeroconfport", "tcp").unwrap();
+    let result = crate::is_port_advertised(service_type, 8123, TOTAL_TEST_TIME);
+
+    *stop.lock().unwrap() = true;
+    service_thread.join().unwrap();
+
+    assert_eq!(result, Ok(true));
+}
+
+#[test]
+fn is_port_advertised_returns_false_after_timeout() {
+    super::setup();
+
+    let timeout = Duration::from_secs(1);
+    let service_type = ServiceType::new("zeroconfnone", "tcp").unwrap();
+
+    let start = Instant::now();
+    let result = crate::is_port_advertised(service_type, 8123, timeout);
+
+    assert_eq!(result, Ok(false));
+    assert!(start.elapsed() < timeout * 2);
+}
+
+#[test]
+fn browser_set_multicast_endpoint_rejects_custom_endpoint() {
+    super::setup();
+
+    let mut browser = MdnsBrowser::new(ServiceType::new("http", "tcp").unwrap());
+
+    assert_eq!(
+        browser.set_multicast_endpoint("224.0.0.251:5353".parse().unwrap()),
+        Ok(())
+    );
+    assert_eq!(
+        browser.set_multicast_endpoint("239.255.0.1:5353".parse().unwrap()),
+        Err(Error::Unsupported(
+            "browsing on the multicast endpoint 239.255.0.1:5353".to_string()
+        ))
+    );
+}
+
+#[test]
+fn browser_max_concurrent_resolves_is_at_least_one() {
+    super::setup();
+
+    let mut browser = MdnsBrowser::new(ServiceType::new("http", "tcp").unwrap());
+    browser.set_max_concurrent_resolves(0);
+    assert_eq!(browser.max_concurrent_resolves(), 1);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn browser_restarting_on_network_change_finds_service() {
+    super::setup();
+
+    static SERVICE_NAME: &str = "browser_restarting_on_network_change_finds_service";
+
+    let stop: Arc<Mutex<bool>> = Arc::default();
+    let stop_service = stop.clone();
+
+    let service_thread = std::thread::spawn(move || {
+        let mut service = MdnsService::new(ServiceType::new("http", "tcp").unwrap(), 8080);
+        service.set_registered_callback(Box::new(|_, _| {}));
+        service.set_name(SERVICE_NAME);
+
+        let event_loop = service.register().unwrap();
+
+        while !*stop_service.lock().unwrap() {
+            event_loop.poll(Duration::from_millis(100)).unwrap();
+        }
+    });
+
+    let mut browser = MdnsBrowser::new(ServiceType::new("http", "tcp").unwrap());
+    browser.set_restart_on_network_change(true);
+
+    let result = browser.wait_for(SERVICE_NAME, TOTAL_TEST_TIME);
+
+    *stop.lock().unwrap() = true;
+    service_thread.join().unwrap();
+
+    assert_eq!(result.unwrap().name(), SERVICE_NAME);
+}
+
+#[test]
+fn browser_wait_for_times_out() {
+    super::setup();
+
+    let timeout = Duration::from_secs(1);
+    let mut browser = MdnsBrowser::new(ServiceType::new("http", "tcp").unwrap());
+
+    let start = Instant::now();
+    let result = browser.wait_for("browser_wait_for_times_out", timeout);
+
+    assert_eq!(result, Err(Error::Timeout));
+    assert!(start.elapsed() < timeout * 2);
+}
+
+fn configure_browser<B: TMdnsBrowser>(browser: &mut B) {
+    browser.set_network_protocol(NetworkProtocol::Ipv4);
+    browser.set_resolve_txt(false);
+}
+
+#[test]
+fn browser_can_be_configured_generically() {
+    super::setup();
+
+    let mut browser = MdnsBrowser::new(ServiceType::new("http", "tcp").unwrap());
+    configure_browser(&mut browser);
+
+    assert_eq!(browser.network_protocol(), NetworkProtocol::Ipv4);
+    assert!(!browser.resolve_txt());
+}
+
+#[test]
+fn browser_resolve_timeout_times_out() {
+    super::setup();
+
+    let timeout = Duration::from_secs(1);
+    let mut browser = MdnsBrowser::new(ServiceType::new("http", "tcp").unwrap());
+
+    let service = ServiceBrowse::builder()
+        .name("browser_resolve_timeout_times_out".to_string())
+        .service_type(ServiceType::new("http", "tcp").unwrap())
+        .domain("local".to_string())
+        .interface(NetworkInterface::Unspec)
+        .build()
+        .unwrap();
+
+    browser.set_resolve_services(false);
+    browser.browse_services().unwrap();
+
+    let start = Instant::now();
+    let result = browser.resolve_timeout(&service, timeout);
+
+    assert_eq!(result, Err(Error::Timeout));
+    assert!(start.elapsed() < timeout * 2);
+}
+
+#[test]
+fn browser_refuses_to_resolve_service_of_other_type() {
+    super::setup();
+
+    let mut browser = MdnsBrowser::new(ServiceType::new("http", "tcp").unwrap());
+
+    let service = ServiceBrowse::builder()
+        .name("browser_refuses_to_resolve_service_of_other_type".to_string())
+        .service_type(ServiceType::new("ipp", "tcp").unwrap())
+        .domain("local".to_string())
+        .interface(NetworkInterface::Unspec)
+        .build()
+        .unwrap();
+
+    assert_eq!(
+        browser.resolve_timeout(&service, Duration::from_secs(1)),
+        Err(Error::ServiceTypeMismatch {
+            browsed: "_http._tcp".to_string(),
+            resolved: "_ipp._tcp".to_string(),
+        })
+    );
+}
+
+#[test]
+fn browser_skips_txt_when_disabled() {
+    super::setup();
+
+    static SERVICE_NAME: &str = "browser_skips_txt_when_disabled";
+
+    let stop: Arc<Mutex<bool>> = Arc::default();
+    let stop_service = stop.clone();
+
+    let service_thread = std::thread::spawn(move || {
+        let mut txt = TxtRecord::new();
+        txt.insert("foo", "bar").unwrap();
+
+        let mut service = MdnsService::new(ServiceType::new("http", "tcp").unwrap(), 8080);
+        service.set_registered_callback(Box::new(|_, _| {}));
+        service.set_name(SERVICE_NAME);
+        service.set_txt_record(txt);
+
+        let event_loop = service.register().unwrap();
+
+        while !*stop_service.lock().unwrap() {
+            event_loop.poll(Duration::from_millis(100)).unwrap();
+        }
+    });
+
+    let mut browser = MdnsBrowser::new(ServiceType::new("http", "tcp").unwrap());
+    browser.set_resolve_txt(false);
+
+    let result = browser.wait_for(SERVICE_NAME, TOTAL_TEST_TIME);
+
+    *stop.lock().unwrap() = true;
+    service_thread.join().unwrap();
+
+    assert!(result.unwrap().txt().is_none());
+}
+
+#[test]
+fn browser_resolves_srv_priority() {
+    super::setup();
+
+    static SERVICE_NAME: &str = "browser_resolves_srv_priority";
+
+    let stop: Arc<Mutex<bool>> = Arc::default();
+    let stop_service = stop.clone();
+
+    let service_thread = std::thread::spawn(move || {
+        let mut service = MdnsService::new(ServiceType::new("http", "tcp").unwrap(), 8080);
+        service.set_registered_callback(Box::new(|_, _| {}));
+        service.set_name(SERVICE_NAME);
+
+        let event_loop = service.register().unwrap();
+
+        while !*stop_service.lock().unwrap() {
+            event_loop.poll(Duration::from_millis(100)).unwrap();
+        }
+    });
+
+    let mut browser = MdnsBrowser::new(ServiceType::new("http", "tcp").unwrap());
+    browser.set_resolve_srv_priority(true);
+
+    let result = browser.wait_for(SERVICE_NAME, TOTAL_TEST_TIME);
+
+    *stop.lock().unwrap() = true;
+    service_thread.join().unwrap();
+
+    let service = result.unwrap();
+
+    assert_eq!(*service.port(), 8080);
+    assert_eq!(*service.priority(), 0);
+    assert_eq!(*service.weight(), 0);
+}
+
+#[test]
+fn browser_resolves_target_host_and_addresses() {
+    super::setup();
+
+    static SERVICE_NAME: &str = "browser_resolves_target_host_and_addresses";
+
+    let stop: Arc<Mutex<bool>> = Arc::default();
+    let stop_service = stop.clone();
+
+    let service_thread = std::thread::spawn(move || {
+        let mut service = MdnsService::new(ServiceType::new("http", "tcp").unwrap(), 8080);
+        service.set_registered_callback(Box::new(|_, _| {}));
+        service.set_name(SERVICE_NAME);
+
+        let event_loop = service.register().unwrap();
+
+        while !*stop_service.lock().unwrap() {
+            event_loop.poll(Duration::from_millis(100)).unwrap();
+        }
+    });
+
+    let mut browser = MdnsBrowser::new(ServiceType::new("http", "tcp").unwrap());
+    let result = browser.wait_for(SERVICE_NAME, TOTAL_TEST_TIME);
+
+    *stop.lock().unwrap() = true;
+    service_thread.join().unwrap();
+
+    let service = result.unwrap();
+
+    assert!(service.target_host().ends_with(".local"));
+    assert!(!service.ip_addresses().is_empty());
+    assert_eq!(service.ip_addresses()[0].to_string(), *service.address());
+}
+
+#[test]
+fn browser_discover_sorted_orders_by_key() {
+    super::setup();
+
+    static SERVICE_NAMES: [&str; 2] = ["browser_discover_sorted_b", "browser_discover_sorted_a"];
+
+    let stop: Arc<Mutex<bool>> = Arc::default();
+    let stop_service = stop.clone();
+
+    let service_thread = std::thread::spawn(move || {
+        let mut services = SERVICE_NAMES
+            .iter()
+            .map(|name| {
+                let mut service = MdnsService::new(ServiceType::new("http", "tcp").unwrap(), 8080);
+                service.set_name(name);
+                service.set_registered_callback(Box::new(|_, _| {}));
+                service
+            })
+            .collect::<Vec<_>>();
+
+        let event_loops = services
+            .iter_mut()
+            .map(|s| s.register().unwrap())
+            .collect::<Vec<_>>();
+
+        while !*stop_service.lock().unwrap() {
+            for event_loop in &event_loops {
+                event_loop.poll(Duration::from_millis(10)).unwrap();
+            }
+        }
+    });
+
+    let mut browser = MdnsBrowser::new(ServiceType::new("http", "tcp").unwrap());
+    let result = browser.discover_sorted(Duration::from_secs(5), |s| s.name().clone());
+
+    *stop.lock().unwrap() = true;
+    service_thread.join().unwrap();
+
+    let names = result
+        .unwrap()
+        .into_iter()
+        .map(|s| s.name().clone())
+        .filter(|n| SERVICE_NAMES.contains(&n.as_str()))
+        .collect::<Vec<_>>();
+
+    assert_eq!(names.first().map(String::as_str), Some(SERVICE_NAMES[1]));
+    assert_eq!(names.last().map(String::as_str), Some(SERVICE_NAMES[0]));
+}
+
+#[test]
+fn browser_list_instances_lists_without_resolving() {
+    super::setup();
+
+    static SERVICE_NAMES: [&str; 2] = ["browser_list_instances_1", "browser_list_instances_2"];
+
+    let stop: Arc<Mutex<bool>> = Arc::default();
+    let stop_service = stop.clone();
+
+    let service_thread = std::thread::spawn(move || {
+        let mut services = SERVICE_NAMES
+            .iter()
+            .map(|name| {
+                let mut service = MdnsService::new(ServiceType::new("http", "tcp").unwrap(), 8080);
+                service.set_name(name);
+                service.set_registered_callback(Box::new(|_, _| {}));
+                service
+            })
+            .collect::<Vec<_>>();
+
+        let event_loops = services
+            .iter_mut()
+            .map(|s| s.register().unwrap())
+            .collect::<Vec<_>>();
+
+        while !*stop_service.lock().unwrap() {
+            for event_loop in &event_loops {
+                event_loop.poll(Duration::from_millis(10)).unwrap();
+            }
+        }
+    });
+
+    let resolved: Arc<Mutex<bool>> = Arc::default();
+    let resolved_callback = resolved.clone();
+
+    let mut browser = MdnsBrowser::new(ServiceType::new("http", "tcp").unwrap());
+
+    browser.set_service_discovered_callback(Box::new(move |_, _| {
+        *resolved_callback.lock().unwrap() = true;
+    }));
+
+    let result = browser.list_instances(Duration::from_secs(5));
+
+    *stop.lock().unwrap() = true;
+    service_thread.join().unwrap();
+
+    let instances = result.unwrap();
+
+    for name in &SERVICE_NAMES {
+        assert!(instances.iter().any(|i| i.name() == name));
+    }
+
+    assert!(browser.resolve_services());
+    assert!(!*resolved.lock().unwrap());
+}
+
+/// Publishes records through the raw Avahi record API, bypassing `MdnsService`.
+#[cfg(target_os = "linux")]
+struct RawRecords {
+    poll: std::rc::Rc<crate::linux::poll::ManagedAvahiSimplePoll>,
+    _group: crate::linux::entry_group::ManagedAvahiEntryGroup,
+}
+
+#[cfg(target_os = "linux")]
+impl RawRecords {
+    /// Publishes each `(name, kind, rdata)` record in class `IN`.
+    fn publish(records: &[(&str, u32, &[u8])]) -> Self {
+        use crate::linux::client::{ManagedAvahiClient, ManagedAvahiClientParams};
+        use crate::linux::entry_group::{
+            AddRecordParams, ManagedAvahiEntryGroup, ManagedAvahiEntryGroupParams,
+        };
+        use crate::linux::flags::PublishFlags;
+        use crate::linux::poll::ManagedAvahiSimplePoll;
+        use avahi_sys::AvahiClientFlags;
+        use libc::c_void;
+        use std::ptr;
+        use std::rc::Rc;
+
+        let poll = Rc::new(ManagedAvahiSimplePoll::new().unwrap());
+
+        let client = Rc::new(
+            ManagedAvahiClient::new(
+                ManagedAvahiClientParams::builder()
+                    .poll(Rc::clone(&poll))
+                    .flags(AvahiClientFlags(0))
+                    .callback(None)
+                    .userdata(ptr::null_mut())
+                    .build()
+                    .unwrap(),
+            )
+            .unwrap(),
+        );
+
+        let mut group = ManagedAvahiEntryGroup::new(
+            ManagedAvahiEntryGroupParams::builder()
+                .client(client)
+                .callback(None)
+                .userdata(ptr::null_mut())
+                .build()
+                .unwrap(),
+        )
+        .unwrap();
+
+        for (name, kind, rdata) in records {
+            let name = c_string!(*name);
+
+            group
+                .add_record(
+                    AddRecordParams::builder()
+                        .interface(avahi_sys::AVAHI_IF_UNSPEC)
+                        .protocol(avahi_sys::AVAHI_PROTO_UNSPEC)
+                        .flags(PublishFlags::empty())
+                        .name(name.as_ptr())
+                        .clazz(avahi_sys::AVAHI_DNS_CLASS_IN as u16)
+                        .kind(*kind as u16)
+                        .ttl(avahi_sys::AVAHI_DEFAULT_TTL)
+                        .rdata(rdata.as_ptr() as *const c_void)
+                        .size(rdata.len())
+                        .build()
+                        .unwrap(),
+                )
+                .unwrap();
+        }
+
+        group.commit().unwrap();
+
+        Self {
+            poll,
+            _group: group,
+        }
+    }
+
+    fn poll(&self, timeout: Duration) {
+        self.poll.iterate(timeout).unwrap();
+    }
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn browser_resolves_all_txt_records() {
+    use crate::linux::avahi_util;
+
+    super::setup();
+
+    static SERVICE_NAME: &str = "browser_resolves_all_txt_records";
+
+    let stop = Arc::new(Mutex::new(false));
+    let stop_service = stop.clone();
+
+    let service_thread = std::thread::spawn(move || {
+        let mut txt = TxtRecord::new();
+        txt.insert("foo", "bar").unwrap();
+
+        let mut service = MdnsService::new(ServiceType::new("http", "tcp").unwrap(), 8080);
+        service.set_registered_callback(Box::new(|_, _| {}));
+        service.set_name(SERVICE_NAME);
+        service.set_txt_record(txt);
+
+        let event_loop = service.register().unwrap();
+
+        // publish a second TXT record for the same service
+        let full_name = avahi_util::service_name_join(SERVICE_NAME, "_http._tcp", "local").unwrap();
+
+        let records = RawRecords::publish(&[(
+            full_name.to_str().unwrap(),
+            avahi_sys::AVAHI_DNS_TYPE_TXT,
+            b"\x0bhello=world",
+        )]);
+
+        while !*stop_service.lock().unwrap() {
+            event_loop.poll(Duration::from_millis(50)).unwrap();
+            records.poll(Duration::from_millis(50));
+        }
+    });
+
+    let mut browser = MdnsBrowser::new(ServiceType::new("http", "tcp").unwrap());
+    browser.set_resolve_all_txt_records(true);
+
+    let result = browser.wait_for(SERVICE_NAME, TOTAL_TEST_TIME);
+
+    *stop.lock().unwrap() = true;
+    service_thread.join().unwrap();
+
+    let service = result.unwrap();
+    let records = service.txt_records();
+
+    assert_eq!(records.len(), 2);
+    assert_eq!(service.txt().as_ref(), records.first());
+    assert!(records
+        .iter()
+        .any(|t| t.get("foo").as_deref() == Some("bar")));
+    assert!(records
+        .iter()
+        .any(|t| t.get("hello").as_deref() == Some("world")));
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn browser_resolves_all_addresses() {
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+    super::setup();
+
+    static SERVICE_NAME: &str = "browser_resolves_all_addresses";
+    static HOST_NAME: &str = "zeroconf-rs-dual-stack.local";
+
+    let v4 = Ipv4Addr::new(192, 0, 2, 1);
+    let v6 = Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1);
+
+    let stop = Arc::new(Mutex::new(false));
+    let stop_service = stop.clone();
+
+    let service_thread = std::thread::spawn(move || {
+        let mut service = MdnsService::new(ServiceType::new("http", "tcp").unwrap(), 8080);
+        service.set_registered_callback(Box::new(|_, _| {}));
+        service.set_name(SERVICE_NAME);
+        service.set_host(HOST_NAME);
+
+        let event_loop = service.register().unwrap();
+
+        let records = RawRecords::publish(&[
+            (HOST_NAME, avahi_sys::AVAHI_DNS_TYPE_A, &v4.octets()),
+            (HOST_NAME, avahi_sys::AVAHI_DNS_TYPE_AAAA, &v6.octets()),
+        ]);
+
+        while !*stop_service.lock().unwrap() {
+            event_loop.poll(Duration::from_millis(50)).unwrap();
+            records.poll(Duration::from_millis(50));
+        }
+    });
+
+    let mut browser = MdnsBrowser::new(ServiceType::new("http", "tcp").unwrap());
+    browser.set_resolve_all_addresses(true);
+
+    let result = browser.wait_for(SERVICE_NAME, TOTAL_TEST_TIME);
+
+    *stop.lock().unwrap() = true;
+    service_thread.join().unwrap();
+
+    let service = result.unwrap();
+    let addresses = service
+        .addresses()
+        .iter()
+        .map(|a| *a.address())
+        .collect::<Vec<_>>();
+
+    assert!(addresses.contains(&IpAddr::V4(v4)));
+    assert!(addresses.contains(&IpAddr::V6(v6)));
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn browser_coalesces_dual_stack_resolutions() {
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+    super::setup();
+
+    static SERVICE_NAME: &str = "browser_coalesces_dual_stack_resolutions";
+    static HOST_NAME: &str = "zeroconf-rs-coalesced.local";
+
+    let v4 = Ipv4Addr::new(192, 0, 2, 2);
+    let v6 = Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 2);
+
+    let stop = Arc::new(Mutex::new(false));
+    let stop_service = stop.clone();
+
+    let service_thread = std::thread::spawn(move || {
+        let mut service = MdnsService::new(ServiceType::new("http", "tcp").unwrap(), 8080);
+        service.set_registered_callback(Box::new(|_, _| {}));
+        service.set_name(SERVICE_NAME);
+        service.set_host(HOST_NAME);
+
+        let event_loop = service.register().unwrap();
+
+        let records = RawRecords::publish(&[
+            (HOST_NAME, avahi_sys::AVAHI_DNS_TYPE_A, &v4.octets()),
+            (HOST_NAME, avahi_sys::AVAHI_DNS_TYPE_AAAA, &v6.octets()),
+        ]);
+
+        while !*stop_service.lock().unwrap() {
+            event_loop.poll(Duration::from_millis(50)).unwrap();
+            records.poll(Duration::from_millis(50));
+        }
+    });
+
+    let discovered: Arc<Mutex<Vec<ServiceDiscovery>>> = Arc::default();
+    let discovered_clone = discovered.clone();
+
+    let mut browser = MdnsBrowser::new(ServiceType::new("http", "tcp").unwrap());
+    browser.set_resolve_all_addresses(true);
+
+    browser.set_service_discovered_callback(Box::new(move |result, _| {
+        if let Ok(service) = result {
+            if service.name() == SERVICE_NAME {
+                discovered_clone.lock().unwrap().push(service);
+            }
+        }
+    }));
+
+    let event_loop = browser.browse_services().unwrap();
+    let start = Instant::now();
+
+    while discovered.lock().unwrap().is_empty() {
+        assert!(start.elapsed() < TOTAL_TEST_TIME, "service not discovered");
+        event_loop.poll(Duration::from_millis(50)).unwrap();
+    }
+
+    // resolutions over the other protocol are not reported separately
+    let settle = Instant::now();
+
+    while settle.elapsed() < Duration::from_secs(3) {
+        event_loop.poll(Duration::from_millis(50)).unwrap();
+    }
+
+    *stop.lock().unwrap() = true;
+    service_thread.join().unwrap();
+
+    let discovered = discovered.lock().unwrap();
+
+    assert_eq!(discovered.len(), 1);
+
+    let addresses = discovered[0]
+        .addresses()
+        .iter()
+        .map(|a| *a.address())
+        .collect::<Vec<_>>();
+
+    assert!(addresses.contains(&IpAddr::V4(v4)));
+    assert!(addresses.contains(&IpAddr::V6(v6)));
+}
+
+#[test]
+fn browser_ignores_service_until_unignored() {
+    let mut browser = MdnsBrowser::new(ServiceType::new("http", "tcp").unwrap());
+    let key = (
+        "My Service".to_string(),
+        "_http._tcp".to_string(),
+        "local".to_string(),
+    );
+
+    browser.ignore(key.clone());
+
+    assert!(browser.is_ignored(&key));
+    assert!(browser.is_ignored(&("my service".to_string(), key.1.clone(), key.2.clone())));
+
+    browser.unignore(&key);
+
+    assert!(!browser.is_ignored(&key));
+}
+
+#[test]
+fn browser_domains_strip_leading_dots() {
+    let mut browser = MdnsBrowser::new(ServiceType::new("http", "tcp").unwrap());
+    browser.set_domains(vec![".local".to_string(), "example.com".to_string()]);
+
+    assert_eq!(
+        browser.domains(),
+        &["local".to_string(), "example.com".to_string()]
+    );
+}
+
+#[test]
+fn browser_with_several_domains_finds_local_service() {
+    super::setup();
+
+    static SERVICE_NAME: &str = "browser_with_several_domains_finds_local_service";
+
+    let stop: Arc<Mutex<bool>> = Arc::default();
+    let stop_service = stop.clone();
+
+    let service_thread = std::thread::spawn(move || {
+        let mut service = MdnsService::new(ServiceType::new("http", "tcp").unwrap(), 8080);
+        service.set_registered_callback(Box::new(|_, _| {}));
+        service.set_name(SERVICE_NAME);
+
+        let event_loop = service.register().unwrap();
+
+        while !*stop_service.lock().unwrap() {
+            event_loop.poll(Duration::from_millis(100)).unwrap();
+        }
+    });
+
+    let mut browser = MdnsBrowser::new(ServiceType::new("http", "tcp").unwrap());
+    browser.set_scope(Scope::Both);
+    browser.set_domains(vec!["example.invalid".to_string(), ".local".to_string()]);
+
+    let result = browser.wait_for(SERVICE_NAME, TOTAL_TEST_TIME);
+
+    *stop.lock().unwrap() = true;
+    service_thread.join().unwrap();
+
+    let service = result.unwrap();
+
+    assert_eq!(service.name(), SERVICE_NAME);
+    assert_eq!(service.domain().trim_end_matches('.'), "local");
+}
+
+#[test]
+fn browser_reports_all_for_now_once() {
+    super::setup();
+
+    let mut service = MdnsService::new(ServiceType::new("http", "tcp").unwrap(), 8080);
+    service.set_registered_callback(Box::new(|_, _| {}));
+    service.set_name("browser_reports_all_for_now_once");
+
+    let service_loop = service.register().unwrap();
+
+    let all_for_now: Arc<Mutex<usize>> = Arc::default();
+    let all_for_now_clone = all_for_now.clone();
+    let mut browser = MdnsBrowser::new(ServiceType::new("http", "tcp").unwrap());
+
+    browser.set_resolve_services(false);
+
+    browser.set_service_browsed_callback(Box::new(move |event, _| {
+        if let BrowseEvent::AllForNow = event.unwrap() {
+            *all_for_now_clone.lock().unwrap() += 1;
+        }
+    }));
+
+    let browser_loop = browser.browse_services().unwrap();
+    let start = Instant::now();
+
+    while *all_for_now.lock().unwrap() == 0 {
+        assert!(start.elapsed() < TOTAL_TEST_TIME, "no AllForNow event");
+        service_loop.poll(Duration::from_secs(0)).unwrap();
+        browser_loop.poll(Duration::from_millis(10)).unwrap();
+    }
+
+    // later results do not report the event again
+    let settle = Instant::now();
+
+    while settle.elapsed() < Duration::from_secs(1) {
+        service_loop.poll(Duration::from_secs(0)).unwrap();
+        browser_loop.poll(Duration::from_millis(10)).unwrap();
+    }
+
+    assert_eq!(*all_for_now.lock().unwrap(), 1);
+}
+
+#[test]
+fn browser_require_txt_replaces_key_case_insensitively() {
+    super::setup();
+
+    let mut browser = MdnsBrowser::new(ServiceType::new("http", "tcp").unwrap());
+    assert!(browser.required_txt().is_empty());
+
+    browser.set_resolve_txt(false);
+    browser.require_txt("model", "X");
+    browser.require_txt("Model", "Y");
+
+    assert_eq!(
+        browser.required_txt(),
+        &[("Model".to_string(), "Y".to_string())]
+    );
+    assert!(!browser.resolve_txt());
+}
+
+#[test]
+fn browser_close_without_browsing_succeeds() {
+    super::setup();
+
+    let browser = MdnsBrowser::new(ServiceType::new("http", "tcp").unwrap());
+    assert_eq!(browser.close(), Ok(()));
+}
+
+#[test]
+fn browser_close_many_browsers() {
+    super::setup();
+
+    for _ in 0..100 {
+        let mut browser = MdnsBrowser::new(ServiceType::new("http", "tcp").unwrap());
+        browser.set_service_discovered_callback(Box::new(|_, _| {}));
+
+        let event_loop = browser.browse_services().unwrap();
+        event_loop.poll(Duration::from_millis(10)).unwrap();
+        assert_eq!(event_loop.active_browsers(), 1);
+        drop(event_loop);
+
+        assert_eq!(browser.close(), Ok(()));
+    }
+}
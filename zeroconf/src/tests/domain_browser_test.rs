@@ -0,0 +1,59 @@
+use crate::prelude::*;
+use crate::{DomainBrowser, DomainBrowserKind};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+const TOTAL_TEST_TIME: Duration = Duration::from_secs(5);
+
+fn enumerate_domains(kind: DomainBrowserKind) -> Vec<String> {
+    let discovered: Arc<Mutex<Vec<String>>> = Arc::default();
+    let mut browser = DomainBrowser::new();
+
+    browser.set_kind(kind);
+    browser.set_context(Box::new(discovered.clone()));
+    browser.set_domain_discovered_callback(Box::new(|domain, context| {
+        context
+            .as_ref()
+            .unwrap()
+            .downcast_ref::<Arc<Mutex<Vec<String>>>>()
+            .unwrap()
+            .lock()
+            .unwrap()
+            .push(domain.unwrap());
+    }));
+
+    let browser_loop = browser.browse_domains().unwrap();
+    let start = Instant::now();
+
+    while start.elapsed() < TOTAL_TEST_TIME {
+        browser_loop.poll(Duration::from_millis(100)).unwrap();
+    }
+
+    let discovered = discovered.lock().unwrap().clone();
+    discovered
+}
+
+#[test]
+fn domain_browser_enumerates_browse_and_registration_domains() {
+    super::setup();
+
+    for kind in [DomainBrowserKind::Browse, DomainBrowserKind::Register] {
+        let domains = enumerate_domains(kind);
+
+        assert!(domains.iter().all(|d| !d.is_empty() && !d.ends_with('.')));
+
+        // Avahi only reports domains configured beyond the local link, Bonjour reports `local`
+        #[cfg(target_vendor = "apple")]
+        assert!(domains.iter().any(|d| d == "local"), "{:?}", kind);
+    }
+}
+
+#[test]
+fn configured_browse_domains_start_with_local() {
+    super::setup();
+
+    let domains = crate::configured_browse_domains().unwrap();
+
+    assert_eq!(domains.first().map(String::as_str), Some("local"));
+    assert!(domains.iter().all(|d| !d.is_empty() && !d.ends_with('.')));
+}
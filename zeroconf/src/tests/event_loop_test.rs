@@ -1,5 +1,7 @@
 use crate::prelude::*;
-use crate::{MdnsService, ServiceType, TxtRecord};
+use crate::{MdnsBrowser, MdnsService, ServiceType, SuspendPolicy, TxtRecord};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 const TEST_DURATION: Duration = Duration::from_secs(1);
@@ -83,3 +85,56 @@ fn event_loop_long_polls() {
 
     assert!(LONG_POLL_MAX_ITERS > iterations);
 }
+
+#[test]
+fn event_loop_counts_active_browsers() {
+    super::setup();
+
+    let mut browser = MdnsBrowser::new(ServiceType::new("http", "tcp").unwrap());
+    browser.set_service_discovered_callback(Box::new(|_, _| {}));
+
+    let event_loop = browser.browse_services().unwrap();
+    event_loop.poll(Duration::from_millis(100)).unwrap();
+
+    assert_eq!(event_loop.active_browsers(), 1);
+
+    drop(event_loop);
+    browser.cancel();
+
+    let event_loop = browser.browse_services().unwrap();
+    assert_eq!(event_loop.active_browsers(), 1);
+}
+
+#[test]
+fn event_loop_queues_callbacks_while_suspended() {
+    super::setup();
+
+    let registered = Arc::new(AtomicBool::new(false));
+    let registered_clone = registered.clone();
+
+    let mut service = MdnsService::new(ServiceType::new("http", "tcp").unwrap(), 8080);
+    service.set_name("event_loop_queues_callbacks_while_suspended");
+    service.set_registered_callback(Box::new(move |_, _| {
+        registered_clone.store(true, Ordering::SeqCst);
+    }));
+
+    let event_loop = service.register().unwrap();
+    event_loop.suspend(SuspendPolicy::Queue);
+
+    assert_eq!(event_loop.suspension(), Some(SuspendPolicy::Queue));
+
+    let start = Instant::now();
+
+    while start.elapsed() < TEST_DURATION {
+        event_loop.poll(Duration::from_millis(100)).unwrap();
+    }
+
+    assert!(!registered.load(Ordering::SeqCst));
+
+    event_loop.resume();
+    assert_eq!(event_loop.suspension(), None);
+
+    while !registered.load(Ordering::SeqCst) {
+        event_loop.poll(Duration::from_millis(100)).unwrap();
+    }
+}
@@ -0,0 +1,110 @@
+use crate::prelude::*;
+use crate::{ServiceType, Zeroconf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+#[test]
+fn zeroconf_registers_and_browses_on_one_connection() {
+    super::setup();
+
+    const TOTAL_TEST_TIME_S: u64 = 30;
+    static SERVICE_NAME: &str = "zeroconf_registers_and_browses_on_one_connection";
+
+    let zeroconf = Zeroconf::new().unwrap();
+
+    let mut service = zeroconf.new_service(ServiceType::new("http", "tcp").unwrap(), 8080);
+    service.set_registered_callback(Box::new(|_, _| {}));
+    service.set_name(SERVICE_NAME);
+    service.register().unwrap();
+
+    let discovered = Arc::new(AtomicBool::new(false));
+    let discovered_clone = discovered.clone();
+
+    let mut browser = zeroconf.new_browser(ServiceType::new("http", "tcp").unwrap());
+
+    browser.set_service_discovered_callback(Box::new(move |service, _| {
+        if service.unwrap().name() == SERVICE_NAME {
+            discovered_clone.store(true, Ordering::SeqCst);
+        }
+    }));
+
+    browser.browse_services().unwrap();
+
+    let event_loop = zeroconf.event_loop();
+    let start = Instant::now();
+
+    while !discovered.load(Ordering::SeqCst) {
+        event_loop.poll(Duration::from_secs(0)).unwrap();
+        assert!(
+            start.elapsed().as_secs() < TOTAL_TEST_TIME_S,
+            "service not discovered"
+        );
+    }
+}
+
+#[test]
+fn zeroconf_event_loop_cancels_all_operations() {
+    super::setup();
+
+    const TOTAL_TEST_TIME_S: u64 = 30;
+    static SERVICE_NAME: &str = "zeroconf_event_loop_cancels_all_operations";
+
+    let zeroconf = Zeroconf::new().unwrap();
+
+    let mut service = zeroconf.new_service(ServiceType::new("http", "tcp").unwrap(), 8080);
+    service.set_registered_callback(Box::new(|_, _| {}));
+    service.set_name(SERVICE_NAME);
+    service.register().unwrap();
+
+    let mut other_service = zeroconf.new_service(ServiceType::new("ipp", "tcp").unwrap(), 631);
+    other_service.set_registered_callback(Box::new(|_, _| {}));
+    other_service.set_name(SERVICE_NAME);
+    other_service.register().unwrap();
+
+    let discovered = Arc::new(AtomicBool::new(false));
+    let discovered_clone = discovered.clone();
+
+    let mut browser = zeroconf.new_browser(ServiceType::new("http", "tcp").unwrap());
+
+    browser.set_service_discovered_callback(Box::new(move |service, _| {
+        if service.unwrap().name() == SERVICE_NAME {
+            discovered_clone.store(true, Ordering::SeqCst);
+        }
+    }));
+
+    browser.browse_services().unwrap();
+
+    let mut other_browser = zeroconf.new_browser(ServiceType::new("ipp", "tcp").unwrap());
+    other_browser.set_service_discovered_callback(Box::new(|_, _| {}));
+    other_browser.browse_services().unwrap();
+
+    let event_loop = zeroconf.event_loop();
+    let start = Instant::now();
+
+    while !discovered.load(Ordering::SeqCst) {
+        event_loop.poll(Duration::from_secs(0)).unwrap();
+        assert!(
+            start.elapsed().as_secs() < TOTAL_TEST_TIME_S,
+            "service not discovered"
+        );
+    }
+
+    assert_eq!(event_loop.active_browsers(), 2);
+
+    event_loop.cancel_all();
+
+    assert_eq!(event_loop.active_browsers(), 0);
+    assert_eq!(event_loop.active_resolvers(), 0);
+
+    discovered.store(false, Ordering::SeqCst);
+    service.register().unwrap();
+
+    let start = Instant::now();
+
+    while start.elapsed() < Duration::from_secs(1) {
+        event_loop.poll(Duration::from_millis(100)).unwrap();
+    }
+
+    assert!(!discovered.load(Ordering::SeqCst));
+}
@@ -6,5 +6,12 @@ pub(crate) fn setup() {
     INIT.call_once(env_logger::init);
 }
 
+mod browser_test;
+mod domain_browser_test;
 mod event_loop_test;
+mod facade_test;
+mod monitor_test;
+mod round_trip_test;
+mod run_loop_test;
 mod service_test;
+mod type_browser_test;
@@ -0,0 +1,72 @@
+use crate::prelude::*;
+use crate::{MdnsMonitor, MdnsService, PresenceEvent, ServiceType};
+use std::sync::mpsc;
+use std::time::Duration;
+
+#[test]
+fn monitor_reports_present_then_missing() {
+    super::setup();
+
+    static SERVICE_NAME: &str = "monitor_reports_present_then_missing";
+
+    let mut service = MdnsService::new(ServiceType::new("zeroconfmonitor", "tcp").unwrap(), 8080);
+    service.set_name(SERVICE_NAME);
+
+    let guard = service.register_scoped().unwrap();
+    let (sender, receiver) = mpsc::channel();
+
+    let monitor = MdnsMonitor::watch(
+        SERVICE_NAME,
+        ServiceType::new("zeroconfmonitor", "tcp").unwrap(),
+        "local",
+        Duration::from_secs(3),
+        move |event| {
+            sender.send(event).ok();
+        },
+    );
+
+    let present = receiver.recv_timeout(Duration::from_secs(30));
+    guard.shutdown().unwrap();
+    let missing = receiver.recv_timeout(Duration::from_secs(30));
+
+    monitor.stop().unwrap();
+
+    match present {
+        Ok(PresenceEvent::Present(service)) => assert_eq!(service.name(), SERVICE_NAME),
+        event => panic!("unexpected event: {:?}", event),
+    }
+
+    assert!(
+        matches!(missing, Ok(PresenceEvent::Missing(_))),
+        "unexpected event: {:?}",
+        missing
+    );
+}
+
+#[test]
+fn monitor_reports_missing_service() {
+    super::setup();
+
+    let (sender, receiver) = mpsc::channel();
+
+    let monitor = MdnsMonitor::watch(
+        "monitor_reports_missing_service",
+        ServiceType::new("zeroconfmonitor", "tcp").unwrap(),
+        "local",
+        Duration::from_millis(500),
+        move |event| {
+            sender.send(event).ok();
+        },
+    );
+
+    let event = receiver.recv_timeout(Duration::from_secs(10));
+
+    monitor.stop().unwrap();
+
+    match event {
+        Ok(PresenceEvent::Missing(missing_for)) => {
+            assert!(missing_for >= Duration::from_millis(500))
+        }
+        event => panic!("unexpected event: {:?}", event),
+    }
+}
@@ -0,0 +1,88 @@
+use crate::prelude::*;
+use crate::{MdnsBrowser, MdnsService, ServiceDiscovery, ServiceType, TxtRecord, Zeroconf};
+use std::sync::mpsc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+const TIMEOUT: Duration = Duration::from_secs(30);
+const PORT: u16 = 8123;
+
+/// Returns `false` if no mDNS daemon can be reached, in which case the round trip is skipped.
+fn daemon_available() -> bool {
+    match Zeroconf::new() {
+        Ok(_) => true,
+        Err(e) => {
+            warn!("skipping round trip, mDNS daemon not available: {}", e);
+            false
+        }
+    }
+}
+
+fn unique_name() -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .subsec_nanos();
+
+    format!("round_trip_{}_{}", std::process::id(), nanos)
+}
+
+#[test]
+fn published_service_is_discovered_with_matching_details() {
+    super::setup();
+
+    if !daemon_available() {
+        return;
+    }
+
+    let name = unique_name();
+    let service_type = ServiceType::new("zeroconf-rs", "tcp").unwrap();
+
+    let mut txt = TxtRecord::new();
+    txt.insert("round", "trip").unwrap();
+
+    let mut service = MdnsService::new(service_type.clone(), PORT);
+    service.set_name(&name);
+    service.set_txt_record(txt.clone());
+    service.set_registered_callback(Box::new(|result, _| {
+        result.expect("service registration failed");
+    }));
+
+    let (sender, receiver) = mpsc::channel::<ServiceDiscovery>();
+    let expected_name = name.clone();
+
+    let mut browser = MdnsBrowser::new(service_type.clone());
+    browser.set_service_discovered_callback(Box::new(move |result, _| {
+        if let Ok(discovery) = result {
+            if *discovery.name() == expected_name {
+                sender.send(discovery).ok();
+            }
+        }
+    }));
+
+    let service_loop = service.register().unwrap();
+    let browser_loop = browser.browse_services().unwrap();
+    let start = Instant::now();
+
+    let discovery = loop {
+        service_loop.poll(Duration::from_millis(10)).unwrap();
+        browser_loop.poll(Duration::from_millis(10)).unwrap();
+
+        if let Ok(discovery) = receiver.try_recv() {
+            break discovery;
+        }
+
+        assert!(
+            start.elapsed() < TIMEOUT,
+            "service `{}` not discovered",
+            name
+        );
+    };
+
+    assert_eq!(*discovery.name(), name);
+    assert_eq!(*discovery.service_type(), service_type);
+    assert_eq!(*discovery.port(), PORT);
+    assert_eq!(
+        discovery.txt().as_ref().unwrap().get("round"),
+        Some("trip".into())
+    );
+}
@@ -0,0 +1,41 @@
+use crate::prelude::*;
+use crate::{MdnsBrowser, MdnsService, RunHandle, ServiceType};
+use std::sync::mpsc;
+use std::time::Duration;
+
+#[test]
+fn run_handle_drives_service_and_browser() {
+    super::setup();
+
+    static SERVICE_NAME: &str = "run_handle_drives_service_and_browser";
+
+    let service = RunHandle::spawn(|| {
+        let mut service = MdnsService::new(ServiceType::new("http", "tcp").unwrap(), 8080);
+        service.set_name(SERVICE_NAME);
+        service.set_registered_callback(Box::new(|_, _| {
+            debug!("Service published");
+        }));
+        service
+    });
+
+    let (sender, receiver) = mpsc::channel();
+
+    let browser = RunHandle::spawn(move || {
+        let mut browser = MdnsBrowser::new(ServiceType::new("http", "tcp").unwrap());
+        browser.set_service_discovered_callback(Box::new(move |service, _| {
+            if let Ok(service) = service {
+                if service.name() == SERVICE_NAME {
+                    sender.send(()).ok();
+                }
+            }
+        }));
+        browser
+    });
+
+    let result = receiver.recv_timeout(Duration::from_secs(30));
+
+    browser.stop().unwrap();
+    service.stop().unwrap();
+
+    assert!(result.is_ok(), "service not discovered");
+}
@@ -1,5 +1,11 @@
+use crate::error::Error;
 use crate::prelude::*;
-use crate::{MdnsBrowser, MdnsService, ServiceType, TxtRecord};
+use crate::{
+    ClientFlags, ClientState, InterfaceIndex, MdnsBrowser, MdnsService, NetworkInterface,
+    RegistrationState, Scope, ServiceConfig, ServiceType, TxtRecord, MIN_TXT_PROVIDER_INTERVAL,
+    UNIQUE_NAME_SUFFIX_LEN,
+};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
@@ -101,3 +107,991 @@ fn service_register_is_browsable() {
 
     assert!(!context.lock().unwrap().timed_out);
 }
+
+#[test]
+fn service_txt_update_from_registered_callback() {
+    super::setup();
+
+    const TOTAL_TEST_TIME: Duration = Duration::from_secs(30);
+    static SERVICE_NAME: &str = "service_txt_update_from_registered_callback";
+
+    let mut service = MdnsService::new(ServiceType::new("http", "tcp").unwrap(), 8080);
+
+    let mut txt = TxtRecord::new();
+    txt.insert("foo", "bar").unwrap();
+
+    let mut updated_txt = TxtRecord::new();
+    updated_txt.insert("foo", "baz").unwrap();
+
+    let update_result: Arc<Mutex<Option<crate::Result<()>>>> = Arc::default();
+    let handle = service.handle();
+    let callback_txt = updated_txt.clone();
+
+    service.set_name(SERVICE_NAME);
+    service.set_txt_record(txt);
+    service.set_context(Box::new(update_result.clone()));
+
+    service.set_registered_callback(Box::new(move |_, context| {
+        let result = handle.update_txt_record(callback_txt.clone());
+
+        *context
+            .as_ref()
+            .unwrap()
+            .downcast_ref::<Arc<Mutex<Option<crate::Result<()>>>>>()
+            .unwrap()
+            .lock()
+            .unwrap() = Some(result);
+    }));
+
+    let service_loop = service.register().unwrap();
+
+    let discovered: Arc<Mutex<Option<TxtRecord>>> = Arc::default();
+    let mut browser = MdnsBrowser::new(ServiceType::new("http", "tcp").unwrap());
+
+    browser.set_context(Box::new(discovered.clone()));
+    browser.set_service_discovered_callback(Box::new(|service, context| {
+        let service = service.unwrap();
+
+        if service.name() == SERVICE_NAME {
+            *context
+                .as_ref()
+                .unwrap()
+                .downcast_ref::<Arc<Mutex<Option<TxtRecord>>>>()
+                .unwrap()
+                .lock()
+                .unwrap() = service.txt().clone();
+        }
+    }));
+
+    let browser_loop = browser.browse_services().unwrap();
+    let start = std::time::Instant::now();
+
+    while start.elapsed() < TOTAL_TEST_TIME {
+        service_loop.poll(Duration::from_secs(0)).unwrap();
+        browser_loop.poll(Duration::from_secs(0)).unwrap();
+
+        if discovered.lock().unwrap().as_ref() == Some(&updated_txt) {
+            break;
+        }
+    }
+
+    assert_eq!(*update_result.lock().unwrap(), Some(Ok(())));
+    assert_eq!(discovered.lock().unwrap().as_ref(), Some(&updated_txt));
+}
+
+#[test]
+fn service_txt_key_update_keeps_other_entries() {
+    super::setup();
+
+    const TOTAL_TEST_TIME: Duration = Duration::from_secs(30);
+    static SERVICE_NAME: &str = "service_txt_key_update_keeps_other_entries";
+
+    let mut service = MdnsService::new(ServiceType::new("http", "tcp").unwrap(), 8080);
+
+    let mut txt = TxtRecord::new();
+    txt.insert("foo", "bar").unwrap();
+    txt.insert("state", "idle").unwrap();
+    txt.insert("old", "1").unwrap();
+
+    let mut updated_txt = TxtRecord::new();
+    updated_txt.insert("foo", "bar").unwrap();
+    updated_txt.insert("state", "busy").unwrap();
+
+    let handle = service.handle();
+
+    service.set_name(SERVICE_NAME);
+    service.set_txt_record(txt);
+    service.set_registered_callback(Box::new(move |_, _| {
+        handle.set_txt_key("state", "busy").unwrap();
+        handle.remove_txt_key("old").unwrap();
+    }));
+
+    let service_loop = service.register().unwrap();
+
+    let discovered: Arc<Mutex<Option<TxtRecord>>> = Arc::default();
+    let mut browser = MdnsBrowser::new(ServiceType::new("http", "tcp").unwrap());
+
+    browser.set_context(Box::new(discovered.clone()));
+    browser.set_service_discovered_callback(Box::new(|service, context| {
+        let service = service.unwrap();
+
+        if service.name() == SERVICE_NAME {
+            *context
+                .as_ref()
+                .unwrap()
+                .downcast_ref::<Arc<Mutex<Option<TxtRecord>>>>()
+                .unwrap()
+                .lock()
+                .unwrap() = service.txt().clone();
+        }
+    }));
+
+    let browser_loop = browser.browse_services().unwrap();
+    let start = std::time::Instant::now();
+
+    while start.elapsed() < TOTAL_TEST_TIME {
+        service_loop.poll(Duration::from_secs(0)).unwrap();
+        browser_loop.poll(Duration::from_secs(0)).unwrap();
+
+        if discovered.lock().unwrap().as_ref() == Some(&updated_txt) {
+            break;
+        }
+    }
+
+    assert_eq!(discovered.lock().unwrap().as_ref(), Some(&updated_txt));
+}
+
+#[test]
+fn service_handle_requires_registered_service() {
+    super::setup();
+
+    let service = MdnsService::new(ServiceType::new("http", "tcp").unwrap(), 8080);
+    let handle = service.handle();
+
+    assert_eq!(
+        handle.update_txt_record(TxtRecord::new()),
+        Err("service has not been registered".into())
+    );
+
+    assert_eq!(
+        handle.update_port(9090),
+        Err("service has not been registered".into())
+    );
+
+    assert_eq!(
+        handle.set_txt_key("state", "idle"),
+        Err("service has not been registered".into())
+    );
+    assert_eq!(
+        handle.remove_txt_key("state"),
+        Err("service has not been registered".into())
+    );
+
+    drop(service);
+
+    assert_eq!(
+        handle.update_txt_record(TxtRecord::new()),
+        Err("service has been dropped".into())
+    );
+    assert_eq!(
+        handle.update_port(9090),
+        Err("service has been dropped".into())
+    );
+}
+
+#[test]
+fn service_unregister_withdraws_registration() {
+    super::setup();
+
+    let mut service = MdnsService::new(ServiceType::new("http", "tcp").unwrap(), 8080);
+    service.set_registered_callback(Box::new(|_, _| {}));
+    assert_eq!(service.unregister(), Ok(()));
+
+    service.register().unwrap();
+    assert_eq!(service.unregister(), Ok(()));
+    assert!(!service.is_registered());
+    assert_eq!(service.unregister(), Ok(()));
+    assert_eq!(
+        service.handle().update_port(9090),
+        Err("service has not been registered".into())
+    );
+}
+
+#[test]
+fn service_update_port_is_resolved() {
+    super::setup();
+
+    const TOTAL_TEST_TIME: Duration = Duration::from_secs(30);
+    static SERVICE_NAME: &str = "service_update_port_is_resolved";
+
+    let mut service = MdnsService::new(ServiceType::new("http", "tcp").unwrap(), 8080);
+    let registered: Arc<Mutex<bool>> = Arc::default();
+    let handle = service.handle();
+
+    service.set_name(SERVICE_NAME);
+    service.set_context(Box::new(registered.clone()));
+    service.set_registered_callback(Box::new(|result, context| {
+        result.unwrap();
+
+        *context
+            .as_ref()
+            .unwrap()
+            .downcast_ref::<Arc<Mutex<bool>>>()
+            .unwrap()
+            .lock()
+            .unwrap() = true;
+    }));
+
+    let service_loop = service.register().unwrap();
+    let start = std::time::Instant::now();
+
+    while !*registered.lock().unwrap() && start.elapsed() < TOTAL_TEST_TIME {
+        service_loop.poll(Duration::from_millis(100)).unwrap();
+    }
+
+    handle.update_port(9090).unwrap();
+
+    let port: Arc<Mutex<Option<u16>>> = Arc::default();
+    let mut browser = MdnsBrowser::new(ServiceType::new("http", "tcp").unwrap());
+
+    browser.set_context(Box::new(port.clone()));
+    browser.set_service_discovered_callback(Box::new(|service, context| {
+        let service = service.unwrap();
+
+        if service.name() == SERVICE_NAME {
+            *context
+                .as_ref()
+                .unwrap()
+                .downcast_ref::<Arc<Mutex<Option<u16>>>>()
+                .unwrap()
+                .lock()
+                .unwrap() = Some(*service.port());
+        }
+    }));
+
+    let browser_loop = browser.browse_services().unwrap();
+
+    while start.elapsed() < TOTAL_TEST_TIME {
+        service_loop.poll(Duration::from_secs(0)).unwrap();
+        browser_loop.poll(Duration::from_secs(0)).unwrap();
+
+        if *port.lock().unwrap() == Some(9090) {
+            break;
+        }
+    }
+
+    assert_eq!(*port.lock().unwrap(), Some(9090));
+}
+
+#[test]
+fn service_survives_panicking_registered_callback() {
+    super::setup();
+
+    const TOTAL_TEST_TIME_S: u64 = 30;
+
+    let mut service = MdnsService::new(ServiceType::new("http", "tcp").unwrap(), 8080);
+    let registered: Arc<Mutex<bool>> = Arc::default();
+    let registered_clone = registered.clone();
+
+    service.set_name("service_survives_panicking_registered_callback");
+
+    service.set_registered_callback(Box::new(move |_, _| {
+        *registered_clone.lock().unwrap() = true;
+        panic!("registered callback panicked");
+    }));
+
+    let event_loop = service.register().unwrap();
+    let start = std::time::Instant::now();
+
+    let error = loop {
+        if let Err(e) = event_loop.poll(Duration::from_secs(0)) {
+            break e;
+        }
+        assert!(
+            start.elapsed().as_secs() < TOTAL_TEST_TIME_S,
+            "service not registered"
+        );
+    };
+
+    assert!(*registered.lock().unwrap());
+    assert_eq!(
+        error,
+        Error::CallbackPanicked {
+            callback: "service registered callback".to_string(),
+            message: "registered callback panicked".to_string(),
+        }
+    );
+
+    // the process is still alive and the event loop is still usable
+    event_loop.poll(Duration::from_millis(100)).unwrap();
+}
+
+#[test]
+fn service_requires_registered_callback() {
+    super::setup();
+
+    let mut service = MdnsService::new(ServiceType::new("http", "tcp").unwrap(), 8080);
+
+    assert_eq!(
+        service.register().err(),
+        Some("a registered callback must be set before registering the service".into())
+    );
+}
+
+#[test]
+fn service_registration_full_name_is_escaped() {
+    super::setup();
+
+    const TOTAL_TEST_TIME_S: u64 = 30;
+
+    let mut service = MdnsService::new(ServiceType::new("http", "tcp").unwrap(), 8080);
+    let full_name: Arc<Mutex<Option<String>>> = Arc::default();
+    let full_name_clone = full_name.clone();
+
+    service.set_name("service registration full name");
+
+    service.set_registered_callback(Box::new(move |registration, _| {
+        *full_name_clone.lock().unwrap() = Some(registration.unwrap().full_name());
+    }));
+
+    let event_loop = service.register().unwrap();
+    let start = std::time::Instant::now();
+
+    while full_name.lock().unwrap().is_none() {
+        event_loop.poll(Duration::from_secs(0)).unwrap();
+        assert!(
+            start.elapsed().as_secs() < TOTAL_TEST_TIME_S,
+            "service not registered"
+        );
+    }
+
+    assert_eq!(
+        full_name.lock().unwrap().as_deref(),
+        Some("service\\032registration\\032full\\032name._http._tcp.local.")
+    );
+}
+
+#[test]
+fn service_no_probe_registers_faster() {
+    super::setup();
+
+    fn time_to_register(name: &str, no_probe: bool) -> Duration {
+        const TOTAL_TEST_TIME_S: u64 = 30;
+
+        let mut service = MdnsService::new(ServiceType::new("http", "tcp").unwrap(), 8080);
+        let registered: Arc<Mutex<bool>> = Arc::default();
+        let registered_clone = registered.clone();
+
+        service.set_name(name);
+        service.set_no_probe(no_probe);
+
+        service.set_registered_callback(Box::new(move |registration, _| {
+            registration.unwrap();
+            *registered_clone.lock().unwrap() = true;
+        }));
+
+        let start = std::time::Instant::now();
+        let event_loop = service.register().unwrap();
+
+        while !*registered.lock().unwrap() {
+            event_loop.poll(Duration::from_millis(1)).unwrap();
+            assert!(
+                start.elapsed().as_secs() < TOTAL_TEST_TIME_S,
+                "service not registered"
+            );
+        }
+
+        start.elapsed()
+    }
+
+    let probed = time_to_register("service_no_probe_registers_faster_probed", false);
+    let unprobed = time_to_register("service_no_probe_registers_faster_unprobed", true);
+
+    info!(
+        "time to register: {:?} with probing, {:?} without",
+        probed, unprobed
+    );
+
+    assert!(unprobed < probed);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn service_wide_area_scope_is_unsupported() {
+    super::setup();
+
+    let mut service = MdnsService::new(ServiceType::new("http", "tcp").unwrap(), 8080);
+    service.set_registered_callback(Box::new(|_, _| {}));
+    service.set_scope(Scope::WideArea);
+
+    assert_eq!(
+        service.register().err(),
+        Some("Avahi does not support publishing services on a wide-area scope".into())
+    );
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn service_with_zero_port_round_trips() {
+    super::setup();
+
+    static SERVICE_NAME: &str = "service_with_zero_port_round_trips";
+
+    let stop: Arc<Mutex<bool>> = Arc::default();
+    let stop_service = stop.clone();
+
+    let mut txt = TxtRecord::new();
+    txt.insert("foo", "bar").unwrap();
+    let service_txt = txt.clone();
+
+    let service_thread = std::thread::spawn(move || {
+        let mut service = MdnsService::new(ServiceType::new("http", "tcp").unwrap(), 0);
+        service.set_registered_callback(Box::new(|_, _| {}));
+        service.set_name(SERVICE_NAME);
+        service.set_txt_record(service_txt);
+
+        let event_loop = service.register().unwrap();
+
+        while !*stop_service.lock().unwrap() {
+            event_loop.poll(Duration::from_millis(100)).unwrap();
+        }
+    });
+
+    let mut browser = MdnsBrowser::new(ServiceType::new("http", "tcp").unwrap());
+    let result = browser.wait_for(SERVICE_NAME, Duration::from_secs(30));
+
+    *stop.lock().unwrap() = true;
+    service_thread.join().unwrap();
+
+    let service = result.unwrap();
+    assert_eq!(*service.port(), 0);
+    assert_eq!(service.txt().as_ref(), Some(&txt));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn service_srv_priority_and_weight_round_trip() {
+    super::setup();
+
+    static SERVICE_NAME: &str = "service_srv_priority_and_weight_round_trip";
+
+    let stop: Arc<Mutex<bool>> = Arc::default();
+    let stop_service = stop.clone();
+
+    let service_thread = std::thread::spawn(move || {
+        let mut service = MdnsService::new(ServiceType::new("http", "tcp").unwrap(), 8080);
+        service.set_registered_callback(Box::new(|_, _| {}));
+        service.set_name(SERVICE_NAME);
+        service.set_srv_priority(10);
+        service.set_srv_weight(300);
+
+        let event_loop = service.register().unwrap();
+
+        while !*stop_service.lock().unwrap() {
+            event_loop.poll(Duration::from_millis(100)).unwrap();
+        }
+    });
+
+    let mut browser = MdnsBrowser::new(ServiceType::new("http", "tcp").unwrap());
+    browser.set_resolve_srv_priority(true);
+
+    let result = browser.wait_for(SERVICE_NAME, Duration::from_secs(30));
+
+    *stop.lock().unwrap() = true;
+    service_thread.join().unwrap();
+
+    let service = result.unwrap();
+
+    assert_eq!(*service.port(), 8080);
+    assert_eq!(*service.priority(), 10);
+    assert_eq!(*service.weight(), 300);
+}
+
+#[test]
+fn service_reports_running_client_state() {
+    super::setup();
+
+    let mut service = MdnsService::new(ServiceType::new("http", "tcp").unwrap(), 8080);
+    service.set_name("service_reports_running_client_state");
+    service.set_registered_callback(Box::new(|_, _| {}));
+
+    let states: Arc<Mutex<Vec<ClientState>>> = Arc::default();
+    let states_clone = states.clone();
+
+    service.set_client_state_callback(Box::new(move |state, _| {
+        states_clone.lock().unwrap().push(state);
+    }));
+
+    let event_loop = service.register().unwrap();
+    event_loop.poll(Duration::from_millis(100)).unwrap();
+
+    assert!(states.lock().unwrap().contains(&ClientState::Running));
+}
+
+#[test]
+fn service_state_stream_is_empty_before_registration() {
+    super::setup();
+
+    let service = MdnsService::new(ServiceType::new("http", "tcp").unwrap(), 8080);
+    let states = service.state_stream();
+
+    assert!(states.try_recv().is_err());
+}
+
+#[test]
+fn service_state_stream_reports_established() {
+    super::setup();
+
+    let mut service = MdnsService::new(ServiceType::new("http", "tcp").unwrap(), 8080);
+    service.set_name("service_state_stream_reports_established");
+    service.set_registered_callback(Box::new(|_, _| {}));
+
+    let states = service.state_stream();
+    let event_loop = service.register().unwrap();
+
+    let mut received = Vec::new();
+
+    while !received.contains(&RegistrationState::Established) {
+        event_loop.poll(Duration::from_millis(100)).unwrap();
+        received.extend(states.try_iter());
+    }
+
+    assert_eq!(received.last(), Some(&RegistrationState::Established));
+}
+
+#[test]
+fn service_standard_txt_is_added_before_user_entries() {
+    super::setup();
+
+    let mut service = MdnsService::new(ServiceType::new("http", "tcp").unwrap(), 8080);
+    service.set_txt_record(TxtRecord::from_pairs(&[("path", "/"), ("version", "2.0")]).unwrap());
+    service.set_standard_txt("1.4.2", "c0ffee").unwrap();
+
+    assert_eq!(
+        service.txt_record().unwrap().iter().collect::<Vec<_>>(),
+        vec![
+            ("txtvers".to_string(), "1".to_string()),
+            ("id".to_string(), "c0ffee".to_string()),
+            ("path".to_string(), "/".to_string()),
+            ("version".to_string(), "2.0".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn service_standard_txt_creates_record() {
+    super::setup();
+
+    let mut service = MdnsService::new(ServiceType::new("http", "tcp").unwrap(), 8080);
+    service.set_standard_txt("1.4.2", "c0ffee").unwrap();
+
+    let txt_record = service.txt_record().unwrap();
+
+    assert_eq!(txt_record.get("txtvers").as_deref(), Some("1"));
+    assert_eq!(txt_record.get("version").as_deref(), Some("1.4.2"));
+    assert_eq!(txt_record.get("id").as_deref(), Some("c0ffee"));
+}
+
+#[test]
+fn service_from_config_read_from_json() {
+    super::setup();
+
+    let config: ServiceConfig = serde_json::from_str(
+        r#"{
+            "name": "service_from_config_read_from_json",
+            "type": "_http._tcp",
+            "port": 8080,
+            "domain": "local",
+            "host": "myhost.local",
+            "txt": { "path": "/" }
+        }"#,
+    )
+    .unwrap();
+
+    let service = MdnsService::from_config(config).unwrap();
+
+    assert_eq!(service.name(), Some("service_from_config_read_from_json"));
+    assert_eq!(service.domain(), Some("local"));
+    assert_eq!(service.host(), Some("myhost.local"));
+    assert_eq!(
+        service.txt_record().unwrap().get("path").as_deref(),
+        Some("/")
+    );
+}
+
+#[test]
+fn service_from_config_rejects_invalid_config() {
+    super::setup();
+
+    let config: ServiceConfig =
+        serde_json::from_str(r#"{ "name": "", "type": "http", "port": 8080 }"#).unwrap();
+
+    match MdnsService::from_config(config) {
+        Err(Error::InvalidConfig(errors)) => assert_eq!(errors.len(), 2),
+        result => panic!("unexpected result: {:?}", result.map(|_| ())),
+    }
+}
+
+#[test]
+fn service_register_scoped_is_browsable() {
+    super::setup();
+
+    static SERVICE_NAME: &str = "service_register_scoped_is_browsable";
+
+    let mut service = MdnsService::new(ServiceType::new("zeroconfscoped", "tcp").unwrap(), 8080);
+    service.set_name(SERVICE_NAME);
+
+    let guard = service.register_scoped().unwrap();
+    assert_eq!(guard.service().name(), Some(SERVICE_NAME));
+
+    let mut browser = MdnsBrowser::new(ServiceType::new("zeroconfscoped", "tcp").unwrap());
+    let result = browser.wait_for(SERVICE_NAME, Duration::from_secs(30));
+
+    guard.shutdown().unwrap();
+
+    assert_eq!(result.unwrap().name(), SERVICE_NAME);
+}
+
+#[test]
+fn publish_tcp_is_browsable() {
+    super::setup();
+
+    static SERVICE_NAME: &str = "publish_tcp_is_browsable";
+
+    let guard = crate::publish_tcp(SERVICE_NAME, "zeroconfpublish", 8080).unwrap();
+    assert_eq!(guard.service().name(), Some(SERVICE_NAME));
+
+    let mut browser = MdnsBrowser::new(ServiceType::new("zeroconfpublish", "tcp").unwrap());
+    let result = browser.wait_for(SERVICE_NAME, Duration::from_secs(30));
+
+    guard.shutdown().unwrap();
+
+    assert_eq!(result.unwrap().name(), SERVICE_NAME);
+}
+
+#[test]
+fn publish_tcp_rejects_invalid_name_and_type() {
+    super::setup();
+
+    assert!(crate::publish_tcp("", "http", 8080).is_err());
+    assert!(crate::publish_tcp("name", "a.b", 8080).is_err());
+}
+
+#[test]
+fn service_unique_name_has_prefix_and_suffix() {
+    super::setup();
+
+    let mut service = MdnsService::new(ServiceType::new("http", "tcp").unwrap(), 8080);
+    service.set_unique_name("worker").unwrap();
+
+    let name = service.name().unwrap().to_string();
+    let suffix = name.strip_prefix("worker-").unwrap();
+    assert_eq!(suffix.len(), UNIQUE_NAME_SUFFIX_LEN);
+
+    service.set_unique_name("worker").unwrap();
+    assert_ne!(service.name().unwrap(), name);
+}
+
+#[test]
+fn service_unique_name_rejects_long_prefix() {
+    super::setup();
+
+    let mut service = MdnsService::new(ServiceType::new("http", "tcp").unwrap(), 8080);
+    assert!(service.set_unique_name(&"a".repeat(60)).is_err());
+    assert_eq!(service.name(), None);
+}
+
+#[test]
+fn service_with_record_ttls_is_browsable() {
+    super::setup();
+
+    static SERVICE_NAME: &str = "service_with_record_ttls_is_browsable";
+
+    let stop: Arc<Mutex<bool>> = Arc::default();
+    let stop_service = stop.clone();
+
+    let service_thread = std::thread::spawn(move || {
+        let mut txt = TxtRecord::new();
+        txt.insert("foo", "bar").unwrap();
+
+        let mut service = MdnsService::new(ServiceType::new("http", "tcp").unwrap(), 8080);
+        service.set_registered_callback(Box::new(|_, _| {}));
+        service.set_name(SERVICE_NAME);
+        service.set_txt_record(txt);
+        service.set_ptr_ttl(60);
+        service.set_srv_ttl(30);
+        service.set_txt_ttl(90);
+
+        let event_loop = service.register().unwrap();
+
+        while !*stop_service.lock().unwrap() {
+            event_loop.poll(Duration::from_millis(100)).unwrap();
+        }
+    });
+
+    let mut browser = MdnsBrowser::new(ServiceType::new("http", "tcp").unwrap());
+    let result = browser.wait_for(SERVICE_NAME, Duration::from_secs(30));
+
+    *stop.lock().unwrap() = true;
+    service_thread.join().unwrap();
+
+    let service = result.unwrap();
+
+    assert_eq!(*service.port(), 8080);
+    assert_eq!(service.txt().as_ref().unwrap().get("foo").unwrap(), "bar");
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn service_client_flags_reflect_created_client() {
+    super::setup();
+
+    let mut service = MdnsService::new(ServiceType::new("http", "tcp").unwrap(), 8080);
+    service.set_client_flags(ClientFlags::IGNORE_USER_CONFIG);
+    service.set_registered_callback(Box::new(|_, _| {}));
+
+    let _event_loop = service.register().unwrap();
+
+    assert_eq!(service.client_flags(), ClientFlags::IGNORE_USER_CONFIG);
+
+    // the flags of the existing client cannot change
+    service.set_client_flags(ClientFlags::NO_FAIL);
+    assert_eq!(service.client_flags(), ClientFlags::IGNORE_USER_CONFIG);
+}
+
+/// Registers a service without TXT keys that publishes an empty TXT record if
+/// `publish_empty_txt`, and resolves it with a browser that resolves TXT records if `resolve_txt`.
+fn resolve_service_without_txt_keys(
+    name: &'static str,
+    publish_empty_txt: bool,
+    resolve_txt: bool,
+) -> crate::Result<crate::ServiceDiscovery> {
+    let stop: Arc<Mutex<bool>> = Arc::default();
+    let stop_service = stop.clone();
+
+    let service_thread = std::thread::spawn(move || {
+        let mut service = MdnsService::new(ServiceType::new("http", "tcp").unwrap(), 8080);
+        service.set_registered_callback(Box::new(|_, _| {}));
+        service.set_name(name);
+        service.set_publish_empty_txt(publish_empty_txt);
+
+        let event_loop = service.register().unwrap();
+
+        while !*stop_service.lock().unwrap() {
+            event_loop.poll(Duration::from_millis(100)).unwrap();
+        }
+    });
+
+    let mut browser = MdnsBrowser::new(ServiceType::new("http", "tcp").unwrap());
+    browser.set_resolve_txt(resolve_txt);
+
+    let result = browser.wait_for(name, Duration::from_secs(30));
+
+    *stop.lock().unwrap() = true;
+    service_thread.join().unwrap();
+
+    result
+}
+
+#[test]
+fn service_publishes_empty_txt_by_default() {
+    super::setup();
+
+    let service =
+        resolve_service_without_txt_keys("service_publishes_empty_txt_by_default", true, true)
+            .unwrap();
+
+    assert!(service.txt().as_ref().map_or(true, |t| t.is_empty()));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn service_without_empty_txt_is_browsable() {
+    super::setup();
+
+    let service =
+        resolve_service_without_txt_keys("service_without_empty_txt_is_browsable", false, false)
+            .unwrap();
+
+    assert_eq!(*service.port(), 8080);
+    assert!(service.txt().is_none());
+}
+
+#[cfg(target_os = "macos")]
+#[test]
+fn service_without_empty_txt_is_unsupported() {
+    super::setup();
+
+    let mut service = MdnsService::new(ServiceType::new("http", "tcp").unwrap(), 8080);
+    service.set_registered_callback(Box::new(|_, _| {}));
+    service.set_publish_empty_txt(false);
+
+    assert!(matches!(service.register(), Err(Error::Unsupported(_))));
+}
+
+#[test]
+fn service_txt_provider_interval_has_minimum() {
+    super::setup();
+
+    let mut service = MdnsService::new(ServiceType::new("http", "tcp").unwrap(), 8080);
+    assert_eq!(service.txt_provider_interval(), None);
+
+    service.set_txt_provider(Duration::ZERO, Box::new(TxtRecord::new));
+    assert_eq!(
+        service.txt_provider_interval(),
+        Some(MIN_TXT_PROVIDER_INTERVAL)
+    );
+}
+
+#[test]
+fn service_publishes_txt_from_provider() {
+    super::setup();
+
+    static SERVICE_NAME: &str = "service_publishes_txt_from_provider";
+
+    let calls = Arc::new(AtomicUsize::new(0));
+    let stop: Arc<Mutex<bool>> = Arc::default();
+    let stop_service = stop.clone();
+    let service_calls = calls.clone();
+
+    let service_thread = std::thread::spawn(move || {
+        let mut service = MdnsService::new(ServiceType::new("http", "tcp").unwrap(), 8080);
+        service.set_registered_callback(Box::new(|_, _| {}));
+        service.set_name(SERVICE_NAME);
+        service.set_txt_provider(
+            MIN_TXT_PROVIDER_INTERVAL,
+            Box::new(move || {
+                let call = service_calls.fetch_add(1, Ordering::SeqCst);
+                TxtRecord::from_pairs(&[("call", &call.to_string())]).unwrap()
+            }),
+        );
+
+        let event_loop = service.register().unwrap();
+
+        while !*stop_service.lock().unwrap() {
+            event_loop.poll(Duration::from_millis(100)).unwrap();
+        }
+    });
+
+    let mut browser = MdnsBrowser::new(ServiceType::new("http", "tcp").unwrap());
+    let result = browser.wait_for(SERVICE_NAME, Duration::from_secs(30));
+
+    std::thread::sleep(MIN_TXT_PROVIDER_INTERVAL * 2);
+
+    *stop.lock().unwrap() = true;
+    service_thread.join().unwrap();
+
+    let service = result.unwrap();
+
+    assert!(service.txt().as_ref().unwrap().contains_key("call"));
+    assert!(calls.load(Ordering::SeqCst) >= 2);
+}
+
+#[test]
+fn service_is_registered_once_established() {
+    super::setup();
+
+    let mut service = MdnsService::new(ServiceType::new("http", "tcp").unwrap(), 8080);
+    let registered = Arc::new(AtomicUsize::new(0));
+
+    service.set_name("service_is_registered_once_established");
+    service.set_context(Box::new(registered.clone()));
+
+    service.set_registered_callback(Box::new(|result, context| {
+        result.unwrap();
+
+        context
+            .as_ref()
+            .unwrap()
+            .downcast_ref::<Arc<AtomicUsize>>()
+            .unwrap()
+            .fetch_add(1, Ordering::SeqCst);
+    }));
+
+    assert!(!service.is_registered());
+
+    {
+        let event_loop = service.register().unwrap();
+        let start = std::time::Instant::now();
+
+        while registered.load(Ordering::SeqCst) == 0 {
+            assert!(start.elapsed() < Duration::from_secs(10));
+            event_loop.poll(Duration::from_millis(100)).unwrap();
+        }
+    }
+
+    assert!(service.is_registered());
+}
+
+#[test]
+fn service_current_txt_reflects_updates() {
+    super::setup();
+
+    let mut service = MdnsService::new(ServiceType::new("http", "tcp").unwrap(), 8080);
+    let updated = Arc::new(AtomicUsize::new(0));
+    let handle = service.handle();
+
+    service.set_name("service_current_txt_reflects_updates");
+    service.set_txt_record(TxtRecord::from_pairs(&[("state", "idle")]).unwrap());
+    service.set_context(Box::new(updated.clone()));
+
+    service.set_registered_callback(Box::new(move |result, context| {
+        result.unwrap();
+        handle.set_txt_key("state", "busy").unwrap();
+
+        context
+            .as_ref()
+            .unwrap()
+            .downcast_ref::<Arc<AtomicUsize>>()
+            .unwrap()
+            .fetch_add(1, Ordering::SeqCst);
+    }));
+
+    assert_eq!(service.current_txt(), None);
+
+    {
+        let event_loop = service.register().unwrap();
+        let start = std::time::Instant::now();
+
+        while updated.load(Ordering::SeqCst) == 0 {
+            assert!(start.elapsed() < Duration::from_secs(10));
+            event_loop.poll(Duration::from_millis(100)).unwrap();
+        }
+    }
+
+    assert_eq!(
+        service.current_txt(),
+        Some(TxtRecord::from_pairs(&[("state", "busy")]).unwrap())
+    );
+    assert_eq!(
+        service.txt_record(),
+        Some(&TxtRecord::from_pairs(&[("state", "idle")]).unwrap())
+    );
+}
+
+#[test]
+fn service_follow_interfaces_requires_all_interfaces() {
+    super::setup();
+
+    let mut service = MdnsService::new(ServiceType::new("http", "tcp").unwrap(), 8080);
+    service.set_registered_callback(Box::new(|_, _| {}));
+    assert!(!service.follow_interfaces());
+
+    service.set_follow_interfaces(true);
+    service.set_network_interface(NetworkInterface::AtIndex(InterfaceIndex(1)));
+
+    assert!(service.follow_interfaces());
+    assert!(service.register().is_err());
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn service_follow_interfaces_is_browsable() {
+    super::setup();
+
+    static SERVICE_NAME: &str = "service_follow_interfaces_is_browsable";
+
+    let stop: Arc<Mutex<bool>> = Arc::default();
+    let stop_service = stop.clone();
+
+    let service_thread = std::thread::spawn(move || {
+        let mut service = MdnsService::new(ServiceType::new("http", "tcp").unwrap(), 8080);
+        service.set_registered_callback(Box::new(|_, _| {}));
+        service.set_name(SERVICE_NAME);
+        service.set_follow_interfaces(true);
+
+        let event_loop = service.register().unwrap();
+
+        while !*stop_service.lock().unwrap() {
+            event_loop.poll(Duration::from_millis(100)).unwrap();
+        }
+    });
+
+    let mut browser = MdnsBrowser::new(ServiceType::new("http", "tcp").unwrap());
+    let result = browser.wait_for(SERVICE_NAME, Duration::from_secs(30));
+
+    *stop.lock().unwrap() = true;
+    service_thread.join().unwrap();
+
+    assert_eq!(result.unwrap().name(), SERVICE_NAME);
+}
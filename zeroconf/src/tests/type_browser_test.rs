@@ -0,0 +1,53 @@
+use crate::prelude::*;
+use crate::{MdnsService, ServiceType, ServiceTypeBrowser};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+const TOTAL_TEST_TIME: Duration = Duration::from_secs(30);
+
+#[test]
+fn type_browser_discovers_registered_type() {
+    super::setup();
+
+    let service_type = ServiceType::new("zeroconftypetest", "tcp").unwrap();
+
+    let mut service = MdnsService::new(service_type.clone(), 8080);
+    service.set_name("type_browser_discovers_registered_type");
+    service.set_registered_callback(Box::new(|_, _| {
+        debug!("Service published");
+    }));
+
+    let service_loop = service.register().unwrap();
+
+    let discovered: Arc<Mutex<Vec<ServiceType>>> = Arc::default();
+    let mut browser = ServiceTypeBrowser::new();
+
+    browser.set_context(Box::new(discovered.clone()));
+    browser.set_service_type_discovered_callback(Box::new(|service_type, context| {
+        context
+            .as_ref()
+            .unwrap()
+            .downcast_ref::<Arc<Mutex<Vec<ServiceType>>>>()
+            .unwrap()
+            .lock()
+            .unwrap()
+            .push(service_type.unwrap());
+    }));
+
+    let browser_loop = browser.browse_types().unwrap();
+    let start = Instant::now();
+
+    while start.elapsed() < TOTAL_TEST_TIME {
+        service_loop.poll(Duration::from_secs(0)).unwrap();
+        browser_loop.poll(Duration::from_secs(0)).unwrap();
+
+        if discovered.lock().unwrap().contains(&service_type) {
+            break;
+        }
+    }
+
+    let discovered = discovered.lock().unwrap();
+    let matching = discovered.iter().filter(|t| **t == service_type).count();
+
+    assert_eq!(matching, 1);
+}
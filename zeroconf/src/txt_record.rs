@@ -1,21 +1,140 @@
 //! TxtRecord utilities common to all platforms
 
+use crate::error::Error;
 use crate::{Result, TxtRecord};
-use serde::de::{MapAccess, Visitor};
+use serde::de::value::{self, MapDeserializer, StringDeserializer};
+use serde::de::{self, DeserializeOwned, IntoDeserializer, MapAccess, Unexpected, Visitor};
 use serde::ser::SerializeMap;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::collections::HashMap;
 use std::fmt::{self, Debug};
 use std::marker::PhantomData;
 
+/// Maximum length in bytes of a single `key=value` entry in a TXT record.
+///
+/// Each entry is encoded as one length-prefixed string and DNS-SD does not allow an entry to be
+/// split across strings.
+pub const TXT_ENTRY_MAX_LEN: usize = 255;
+
+/// Recommended maximum encoded size in bytes of a whole TXT record.
+///
+/// [RFC 6763] recommends keeping TXT records below 200 bytes where possible and below 400 bytes
+/// in general. Larger records are legal, but must share a single multicast packet with the rest of
+/// the response; records beyond [`TXT_SINGLE_PACKET_MAX_LEN`] require multi-packet responses,
+/// which are slower and less reliable on lossy or constrained networks.
+///
+/// [RFC 6763]: https://www.rfc-editor.org/rfc/rfc6763#section-6.2
+/// [`TXT_SINGLE_PACKET_MAX_LEN`]: constant.TXT_SINGLE_PACKET_MAX_LEN.html
+pub const TXT_RECOMMENDED_MAX_LEN: usize = 400;
+
+/// Approximate maximum encoded size in bytes of a TXT record that still fits, together with the
+/// other records of a typical response, in a single Ethernet-sized multicast packet.
+pub const TXT_SINGLE_PACKET_MAX_LEN: usize = 1300;
+
+/// Recommended maximum length in bytes of a TXT record key, as [RFC 6763] advises keys to be no
+/// more than nine characters long.
+///
+/// [RFC 6763]: https://www.rfc-editor.org/rfc/rfc6763#section-6.4
+pub const TXT_KEY_RECOMMENDED_MAX_LEN: usize = 9;
+
+/// Number of bytes reserved per entry by [`TTxtRecord::with_capacity()`] on platforms that build
+/// the record in a single buffer.
+///
+/// [`TTxtRecord::with_capacity()`]: trait.TTxtRecord.html#method.with_capacity
+pub const ESTIMATED_ENTRY_LEN: usize = 32;
+
 /// Interface for interacting with underlying mDNS implementation TXT record capabilities
+///
+/// Entries are kept in insertion order, which is the order they are iterated in and published
+/// on the wire. DNS-SD does not assign any meaning to the order, but some consumers expect certain
+/// keys, such as a version, to come first. Inserting a key that already exists replaces its value
+/// and moves it to the end.
 pub trait TTxtRecord: Clone + PartialEq + Eq + Debug {
     /// Constructs a new TXT record
     fn new() -> Self;
 
+    /// Constructs a new TXT record with room for about `capacity` entries, to avoid reallocating
+    /// while building a record with many entries.
+    ///
+    /// The capacity is advisory: the record still grows past it and may not reserve anything. On
+    /// macOS, the Bonjour record is built in a buffer sized for `capacity` entries of
+    /// [`ESTIMATED_ENTRY_LEN`] bytes. On Linux, the Avahi string list allocates each entry on its
+    /// own and cannot be pre-sized, so this is the same as [`new()`].
+    ///
+    /// [`ESTIMATED_ENTRY_LEN`]: constant.ESTIMATED_ENTRY_LEN.html
+    /// [`new()`]: #tymethod.new
+    fn with_capacity(_capacity: usize) -> Self {
+        Self::new()
+    }
+
+    /// Constructs a TXT record from the specified key-value pairs, in order.
+    ///
+    /// This is equivalent to calling [`insert()`] for each pair, but the implementation builds
+    /// the record in a single call where possible. Returns an error if any entry exceeds the
+    /// length limit.
+    ///
+    /// [`insert()`]: #tymethod.insert
+    fn from_pairs(pairs: &[(&str, &str)]) -> Result<Self> {
+        let mut record = Self::new();
+
+        for (key, value) in pairs {
+            record.insert(key, value)?;
+        }
+
+        Ok(record)
+    }
+
+    /// Parses the wire format of a TXT record, a sequence of length-prefixed `key=value` strings.
+    ///
+    /// Keys are compared case-insensitively. If a key occurs more than once, the first value wins
+    /// as required by [RFC 6763] and the key is reported by [`duplicate_keys()`]. Empty strings
+    /// and strings without a key are ignored.
+    ///
+    /// [RFC 6763]: https://www.rfc-editor.org/rfc/rfc6763#section-6.4
+    /// [`duplicate_keys()`]: #tymethod.duplicate_keys
+    fn from_bytes(data: &[u8]) -> Result<Self>;
+
+    /// Like [`from_bytes()`], but returns [`Error::DuplicateKeys`] if a key occurs more than
+    /// once.
+    ///
+    /// [`from_bytes()`]: #tymethod.from_bytes
+    /// [`Error::DuplicateKeys`]: ../error/enum.Error.html#variant.DuplicateKeys
+    fn from_bytes_strict(data: &[u8]) -> Result<Self> {
+        let record = Self::from_bytes(data)?;
+        let duplicate_keys = record.duplicate_keys();
+
+        if duplicate_keys.is_empty() {
+            Ok(record)
+        } else {
+            Err(Error::DuplicateKeys(duplicate_keys))
+        }
+    }
+
+    /// Returns the keys that occurred more than once when this record was parsed with
+    /// [`from_bytes()`], in the order they were first duplicated. Records that were not parsed
+    /// have no duplicate keys.
+    ///
+    /// [`from_bytes()`]: #tymethod.from_bytes
+    fn duplicate_keys(&self) -> Vec<String>;
+
     /// Inserts the specified value at the specified key.
+    ///
+    /// Each entry is stored as a single `key=value` string, which DNS-SD limits to 255 bytes and
+    /// does not allow to be split across strings. Returns an error if the entry would exceed this
+    /// limit. A record may hold any number of entries.
     fn insert(&mut self, key: &str, value: &str) -> Result<()>;
 
+    /// Inserts the specified binary value at the specified key. The same length limit applies as
+    /// for [`insert()`].
+    ///
+    /// Values that are not valid UTF-8 are only returned intact by [`iter_bytes()`] and
+    /// [`values_bytes()`].
+    ///
+    /// [`insert()`]: #tymethod.insert
+    /// [`iter_bytes()`]: #tymethod.iter_bytes
+    /// [`values_bytes()`]: #method.values_bytes
+    fn insert_bytes(&mut self, key: &str, value: &[u8]) -> Result<()>;
+
     /// Returns the value at the specified key or `None` if no such key exists.
     ///
     /// This function returns an owned `String` because there are no guarantees that the
@@ -31,7 +150,8 @@ pub trait TTxtRecord: Clone + PartialEq + Eq + Debug {
     /// Returns the amount of entries in the TXT record.
     fn len(&self) -> usize;
 
-    /// Returns a new iterator for iterating over the record as you would a `HashMap`.
+    /// Returns a new iterator for iterating over the record as you would a `HashMap`, in insertion
+    /// order.
     fn iter<'a>(&'a self) -> Box<dyn Iterator<Item = (String, String)> + 'a>;
 
     /// Returns a new iterator over the records keys.
@@ -40,11 +160,105 @@ pub trait TTxtRecord: Clone + PartialEq + Eq + Debug {
     /// Returns a new iterator over the records values.
     fn values<'a>(&'a self) -> Box<dyn Iterator<Item = String> + 'a>;
 
+    /// Returns a new iterator over the record's keys and raw values. Unlike [`iter()`], this
+    /// preserves values that are not valid UTF-8.
+    ///
+    /// [`iter()`]: #tymethod.iter
+    fn iter_bytes<'a>(&'a self) -> Box<dyn Iterator<Item = (String, &'a [u8])> + 'a>;
+
+    /// Returns a new iterator over the record's raw values. Unlike [`values()`], this preserves
+    /// values that are not valid UTF-8.
+    ///
+    /// [`values()`]: #tymethod.values
+    fn values_bytes<'a>(&'a self) -> Box<dyn Iterator<Item = &'a [u8]> + 'a> {
+        Box::new(self.iter_bytes().map(|(_, value)| value))
+    }
+
     /// Returns true if there are no entries in the record.
     fn is_empty(&self) -> bool {
         self.len() == 0
     }
 
+    /// Returns the size in bytes this record occupies on the wire, i.e. the sum of the lengths of
+    /// all `key=value` entries plus one length byte per entry.
+    fn encoded_len(&self) -> usize {
+        self.iter_bytes()
+            .map(|(key, value)| 1 + key.len() + 1 + value.len())
+            .sum()
+    }
+
+    /// Returns the wire format of this record, the sequence of length-prefixed `key=value` strings
+    /// read by [`from_bytes()`], in insertion order. The result is [`encoded_len()`] bytes long;
+    /// a record without entries encodes to no bytes at all.
+    ///
+    /// To encode many records without allocating a buffer for each, see [`build_into()`].
+    ///
+    /// [`from_bytes()`]: #tymethod.from_bytes
+    /// [`encoded_len()`]: #method.encoded_len
+    /// [`build_into()`]: #method.build_into
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.encoded_len());
+
+        for (key, value) in self.iter_bytes() {
+            push_entry(&mut bytes, key.as_bytes(), value);
+        }
+
+        bytes
+    }
+
+    /// Appends the wire format of a record with the specified key-value pairs to `buf`, in order,
+    /// and returns the number of bytes appended. For pairs with unique keys, this is what
+    /// [`to_bytes()`] returns for the record built by [`from_pairs()`].
+    ///
+    /// No record is constructed and nothing is allocated besides growing `buf`, so a caller that
+    /// encodes records at a high rate can reuse one buffer, or an arena of several records, for
+    /// all of them:
+    ///
+    /// ```
+    /// use zeroconf::prelude::*;
+    /// use zeroconf::TxtRecord;
+    ///
+    /// let mut buf = Vec::new();
+    ///
+    /// for i in 0..3 {
+    ///     buf.clear();
+    ///     TxtRecord::build_into(&mut buf, &[("txtvers", "1"), ("load", &i.to_string())]).unwrap();
+    ///     assert_eq!(TxtRecord::from_bytes(&buf).unwrap().get("load"), Some(i.to_string()));
+    /// }
+    /// ```
+    ///
+    /// The pairs are written as given, so unlike with [`from_pairs()`], a key that occurs more
+    /// than once does not replace its earlier value; a record parsed from the result keeps the
+    /// first value and reports the key with [`duplicate_keys()`]. Returns an error if an entry
+    /// exceeds the length limit of [`insert()`], in which case `buf` is left as it was.
+    ///
+    /// [`to_bytes()`]: #method.to_bytes
+    /// [`from_pairs()`]: #method.from_pairs
+    /// [`duplicate_keys()`]: #tymethod.duplicate_keys
+    /// [`insert()`]: #tymethod.insert
+    fn build_into(buf: &mut Vec<u8>, pairs: &[(&str, &str)]) -> Result<usize> {
+        let start = buf.len();
+
+        for (key, value) in pairs {
+            if let Err(e) = check_entry_length(key, value.as_bytes()) {
+                buf.truncate(start);
+                return Err(e);
+            }
+
+            push_entry(buf, key.as_bytes(), value.as_bytes());
+        }
+
+        Ok(buf.len() - start)
+    }
+
+    /// Returns true if the encoded size of this record does not exceed
+    /// [`TXT_RECOMMENDED_MAX_LEN`].
+    ///
+    /// [`TXT_RECOMMENDED_MAX_LEN`]: constant.TXT_RECOMMENDED_MAX_LEN.html
+    fn is_within_recommended_size(&self) -> bool {
+        self.encoded_len() <= TXT_RECOMMENDED_MAX_LEN
+    }
+
     /// Returns a new `HashMap` with this record's keys and values.
     fn to_map(&self) -> HashMap<String, String> {
         let mut m = HashMap::new();
@@ -53,8 +267,278 @@ pub trait TTxtRecord: Clone + PartialEq + Eq + Debug {
         }
         m
     }
+
+    /// Parses this record into a `T`, such as a struct deriving `Deserialize`. See [`FromTxt`]
+    /// for how entries map onto its fields.
+    ///
+    /// [`FromTxt`]: trait.FromTxt.html
+    fn parse_as<T: FromTxt>(&self) -> Result<T> {
+        T::from_txt(self)
+    }
+
+    /// Reports the issues in this record that other DNS-SD implementations may reject or
+    /// mishandle, in the order of the entries they concern. The record itself is valid on both
+    /// platforms either way; see [`TxtLint`] for what is reported and why.
+    ///
+    /// [`TxtLint`]: enum.TxtLint.html
+    fn lint(&self) -> Vec<TxtLint> {
+        let mut lints = Vec::new();
+
+        if !self.is_empty() && !self.contains_key("txtvers") {
+            lints.push(TxtLint::MissingVersion);
+        }
+
+        for (key, value) in self.iter_bytes() {
+            if key.len() > TXT_KEY_RECOMMENDED_MAX_LEN {
+                lints.push(TxtLint::KeyTooLong(key.clone()));
+            }
+
+            if value.contains(&0) {
+                lints.push(TxtLint::ValueContainsNul(key));
+            }
+        }
+
+        lints.extend(self.duplicate_keys().into_iter().map(TxtLint::DuplicateKey));
+
+        let len = self.encoded_len();
+
+        if len > TXT_SINGLE_PACKET_MAX_LEN {
+            lints.push(TxtLint::TooLarge(len));
+        }
+
+        lints
+    }
+
+    /// Compares this record with the newer `other`, reporting the entries it adds, removes and
+    /// changes, such as to log what changed when a service updates its TXT record. Keys are
+    /// compared exactly and values byte for byte, while the order of the entries is ignored, so
+    /// records that only differ in order have an empty diff.
+    ///
+    /// ```
+    /// use zeroconf::prelude::*;
+    /// use zeroconf::TxtRecord;
+    ///
+    /// let old = TxtRecord::from_pairs(&[("status", "idle"), ("rev", "1")]).unwrap();
+    /// let new = TxtRecord::from_pairs(&[("status", "busy"), ("model", "X")]).unwrap();
+    ///
+    /// let diff = old.diff(&new);
+    ///
+    /// assert_eq!(diff.to_string(), "+model=X, -rev=1, ~status=idle->busy");
+    /// assert!(new.diff(&new.clone()).is_empty());
+    /// ```
+    fn diff(&self, other: &Self) -> TxtDiff {
+        let old: HashMap<String, &[u8]> = self.iter_bytes().collect();
+        let new: HashMap<String, &[u8]> = other.iter_bytes().collect();
+        let mut diff = TxtDiff::default();
+
+        for (key, value) in other.iter_bytes() {
+            if !old.contains_key(&key) {
+                diff.added.push((key, lossy(value)));
+            }
+        }
+
+        for (key, value) in self.iter_bytes() {
+            match new.get(&key) {
+                None => diff.removed.push((key, lossy(value))),
+                Some(new_value) if *new_value != value => {
+                    diff.changed.push((key, lossy(value), lossy(new_value)))
+                }
+                Some(_) => {}
+            }
+        }
+
+        diff
+    }
+}
+
+/// The entries that differ between two TXT records, as returned by [`TTxtRecord::diff()`].
+///
+/// Values are decoded as UTF-8, replacing invalid sequences. `Display` lists the changes on one
+/// line for logging, as in `+model=X, -rev=1, ~status=idle->busy`.
+///
+/// [`TTxtRecord::diff()`]: trait.TTxtRecord.html#method.diff
+#[derive(Debug, Default, Clone, PartialEq, Eq, Getters)]
+pub struct TxtDiff {
+    /// Entries only in the newer record as `(key, value)`, in its order
+    added: Vec<(String, String)>,
+    /// Entries only in the older record as `(key, value)`, in its order
+    removed: Vec<(String, String)>,
+    /// Keys in both records with different values as `(key, old value, new value)`, in the order
+    /// of the older record
+    changed: Vec<(String, String, String)>,
+}
+
+impl TxtDiff {
+    /// Returns `true` if both records have the same entries.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
 }
 
+impl fmt::Display for TxtDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return write!(f, "no changes");
+        }
+
+        let added = self.added.iter().map(|(k, v)| format!("+{}={}", k, v));
+        let removed = self.removed.iter().map(|(k, v)| format!("-{}={}", k, v));
+        let changed = self
+            .changed
+            .iter()
+            .map(|(k, old, new)| format!("~{}={}->{}", k, old, new));
+
+        let changes: Vec<String> = added.chain(removed).chain(changed).collect();
+        write!(f, "{}", changes.join(", "))
+    }
+}
+
+fn lossy(value: &[u8]) -> String {
+    String::from_utf8_lossy(value).into_owned()
+}
+
+/// An issue in a TXT record reported by [`TTxtRecord::lint()`], with the basis for it in
+/// [RFC 6763].
+///
+/// [`TTxtRecord::lint()`]: trait.TTxtRecord.html#method.lint
+/// [RFC 6763]: https://www.rfc-editor.org/rfc/rfc6763
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TxtLint {
+    /// The record has entries but no `txtvers` key. Section 6.7 recommends a `txtvers` key as
+    /// the first entry so that the format of the record can change later.
+    MissingVersion,
+    /// The key is longer than [`TXT_KEY_RECOMMENDED_MAX_LEN`] bytes. Section 6.4 advises keys of
+    /// no more than nine characters to keep records small.
+    ///
+    /// [`TXT_KEY_RECOMMENDED_MAX_LEN`]: constant.TXT_KEY_RECOMMENDED_MAX_LEN.html
+    KeyTooLong(String),
+    /// The value of the key contains a NUL byte. Section 6.5 allows binary values, but
+    /// implementations that treat values as C strings truncate the value at the NUL.
+    ValueContainsNul(String),
+    /// The key occurred more than once in the parsed record. Section 6.4 requires keys to be
+    /// unique, and only the first value is kept.
+    DuplicateKey(String),
+    /// The record is the contained number of bytes long, more than
+    /// [`TXT_SINGLE_PACKET_MAX_LEN`]. Section 6.2 recommends records that fit in a single
+    /// packet, as larger ones need multi-packet responses.
+    ///
+    /// [`TXT_SINGLE_PACKET_MAX_LEN`]: constant.TXT_SINGLE_PACKET_MAX_LEN.html
+    TooLarge(usize),
+}
+
+impl fmt::Display for TxtLint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TxtLint::MissingVersion => write!(f, "record has no `txtvers` key"),
+            TxtLint::KeyTooLong(key) => write!(
+                f,
+                "key `{}` is longer than {} bytes",
+                key, TXT_KEY_RECOMMENDED_MAX_LEN
+            ),
+            TxtLint::ValueContainsNul(key) => write!(f, "value of key `{}` contains NUL", key),
+            TxtLint::DuplicateKey(key) => write!(f, "key `{}` occurs more than once", key),
+            TxtLint::TooLarge(len) => write!(
+                f,
+                "record is {} bytes long, exceeding {} bytes",
+                len, TXT_SINGLE_PACKET_MAX_LEN
+            ),
+        }
+    }
+}
+
+/// Returns an error if the `key=value` entry formed by the specified key and value exceeds the
+/// 255 byte limit of a single TXT record string.
+pub(crate) fn check_entry_length(key: &str, value: &[u8]) -> Result<()> {
+    let len = key.len() + 1 + value.len();
+
+    if len > TXT_ENTRY_MAX_LEN {
+        Err(format!(
+            "TXT record entry `{}` is {} bytes long, exceeding the maximum of {} bytes",
+            key, len, TXT_ENTRY_MAX_LEN
+        )
+        .into())
+    } else {
+        Ok(())
+    }
+}
+
+/// Appends the `key=value` entry formed by the specified key and value to `buf`, prefixed with
+/// its length, which must have been checked with `check_entry_length()`.
+fn push_entry(buf: &mut Vec<u8>, key: &[u8], value: &[u8]) {
+    buf.push((key.len() + 1 + value.len()) as u8);
+    buf.extend_from_slice(key);
+    buf.push(b'=');
+    buf.extend_from_slice(value);
+}
+
+/// Splits the wire format of a TXT record into the raw `key=value` entries to keep, following the
+/// rules of [`TTxtRecord::from_bytes()`], and the keys that occurred more than once.
+///
+/// [`TTxtRecord::from_bytes()`]: trait.TTxtRecord.html#tymethod.from_bytes
+pub(crate) fn parse_entries(mut data: &[u8]) -> Result<(Vec<&[u8]>, Vec<String>)> {
+    let mut entries: Vec<&[u8]> = Vec::new();
+    let mut duplicate_keys = Vec::new();
+
+    while let Some((&len, rest)) = data.split_first() {
+        let len = len as usize;
+
+        if len > rest.len() {
+            return Err(format!(
+                "TXT record entry of {} bytes exceeds the remaining {} bytes",
+                len,
+                rest.len()
+            )
+            .into());
+        }
+
+        let (entry, rest) = rest.split_at(len);
+        data = rest;
+
+        let key = entry_key(entry);
+
+        if key.is_empty() {
+            continue;
+        }
+
+        if !entries
+            .iter()
+            .any(|e| entry_key(e).eq_ignore_ascii_case(key))
+        {
+            entries.push(entry);
+            continue;
+        }
+
+        let key = String::from_utf8_lossy(key).into_owned();
+
+        if !duplicate_keys
+            .iter()
+            .any(|k: &String| k.eq_ignore_ascii_case(&key))
+        {
+            duplicate_keys.push(key);
+        }
+    }
+
+    Ok((entries, duplicate_keys))
+}
+
+/// Returns the key of a raw `key=value` entry.
+pub(crate) fn entry_key(entry: &[u8]) -> &[u8] {
+    // a key without a value is stored without the `=`
+    match entry.iter().position(|b| *b == b'=') {
+        Some(i) => &entry[..i],
+        None => entry,
+    }
+}
+
+/// Builds a record from the entries of the map, in no particular order.
+///
+/// # Panics
+///
+/// Panics if an entry exceeds [`TXT_ENTRY_MAX_LEN`]. Use [`TTxtRecord::from_pairs()`] to build
+/// a record from entries that are not known to fit.
+///
+/// [`TXT_ENTRY_MAX_LEN`]: constant.TXT_ENTRY_MAX_LEN.html
+/// [`TTxtRecord::from_pairs()`]: trait.TTxtRecord.html#method.from_pairs
 impl From<HashMap<String, String>> for TxtRecord {
     fn from(map: HashMap<String, String>) -> TxtRecord {
         let mut record = TxtRecord::new();
@@ -65,6 +549,14 @@ impl From<HashMap<String, String>> for TxtRecord {
     }
 }
 
+/// Builds a record from the entries of the map, in no particular order.
+///
+/// # Panics
+///
+/// Panics if an entry exceeds [`TXT_ENTRY_MAX_LEN`], as the conversion from
+/// `HashMap<String, String>` does.
+///
+/// [`TXT_ENTRY_MAX_LEN`]: constant.TXT_ENTRY_MAX_LEN.html
 impl From<HashMap<&str, &str>> for TxtRecord {
     fn from(map: HashMap<&str, &str>) -> TxtRecord {
         map.iter()
@@ -113,8 +605,8 @@ impl<'de> Visitor<'de> for TxtRecordVisitor {
     {
         let mut map = TxtRecord::new();
 
-        while let Some((key, value)) = access.next_entry()? {
-            map.insert(key, value).unwrap();
+        while let Some((key, value)) = access.next_entry::<String, String>()? {
+            map.insert(&key, &value).map_err(de::Error::custom)?;
         }
 
         Ok(map)
@@ -130,6 +622,166 @@ impl<'de> Deserialize<'de> for TxtRecord {
     }
 }
 
+/// Types that can be parsed from a TXT record with [`TTxtRecord::parse_as()`].
+///
+/// This is implemented for every type that implements `Deserialize`, which reads the record as a
+/// map of string keys to string values. A client can thus describe the record it expects as a
+/// struct:
+///
+/// ```
+/// use serde::Deserialize;
+/// use zeroconf::prelude::*;
+/// use zeroconf::TxtRecord;
+///
+/// #[derive(Deserialize)]
+/// struct PrinterInfo {
+///     #[serde(rename = "ty")]
+///     model: String,
+///     rp: String,
+///     #[serde(rename = "Color", default)]
+///     color: bool,
+///     priority: Option<u16>,
+/// }
+///
+/// let txt = TxtRecord::from_pairs(&[("ty", "LaserJet"), ("rp", "ipp/print")]).unwrap();
+/// let info: PrinterInfo = txt.parse_as().unwrap();
+///
+/// assert_eq!(info.model, "LaserJet");
+/// assert_eq!(info.rp, "ipp/print");
+/// assert!(!info.color);
+/// assert_eq!(info.priority, None);
+/// ```
+///
+/// Keys are matched exactly as they appear in the record, so fields for keys that are not
+/// lowercase need a `#[serde(rename)]`. Missing keys follow serde's rules: an `Option` field is
+/// `None`, a field marked `#[serde(default)]` takes its default, and any other field is an error.
+/// Keys without a matching field are ignored unless the type denies unknown fields.
+///
+/// Values are parsed with `FromStr` for numeric and `bool` fields, where a key without a value
+/// reads as `true`, as [RFC 6763] specifies for boolean attributes. Unit enum variants are
+/// matched by name. Values that are not valid UTF-8 are read lossily.
+///
+/// [`TTxtRecord::parse_as()`]: trait.TTxtRecord.html#method.parse_as
+/// [RFC 6763]: https://www.rfc-editor.org/rfc/rfc6763#section-6.4
+pub trait FromTxt: Sized {
+    /// Parses the specified TXT record into a new `Self`.
+    fn from_txt<R: TTxtRecord>(txt: &R) -> Result<Self>;
+}
+
+impl<T: DeserializeOwned> FromTxt for T {
+    fn from_txt<R: TTxtRecord>(txt: &R) -> Result<Self> {
+        let entries = txt.iter_bytes().map(|(key, value)| {
+            let value = String::from_utf8_lossy(value).into_owned();
+            (key, TxtValueDeserializer(value))
+        });
+
+        T::deserialize(MapDeserializer::new(entries))
+            .map_err(|e| format!("could not parse TXT record: {}", e).into())
+    }
+}
+
+/// Deserializes a single TXT record value, parsing it for fields that are not strings.
+struct TxtValueDeserializer(String);
+
+macro_rules! deserialize_parsed {
+    ($($method:ident => $visit:ident,)*) => {
+        $(
+            fn $method<V>(self, visitor: V) -> std::result::Result<V::Value, Self::Error>
+            where
+                V: Visitor<'de>,
+            {
+                match self.0.parse() {
+                    Ok(value) => visitor.$visit(value),
+                    Err(_) => Err(de::Error::invalid_value(Unexpected::Str(&self.0), &visitor)),
+                }
+            }
+        )*
+    };
+}
+
+impl<'de> Deserializer<'de> for TxtValueDeserializer {
+    type Error = value::Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> std::result::Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_string(self.0)
+    }
+
+    fn deserialize_bool<V>(self, visitor: V) -> std::result::Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        if self.0.is_empty() {
+            return visitor.visit_bool(true);
+        }
+
+        match self.0.parse() {
+            Ok(value) => visitor.visit_bool(value),
+            Err(_) => Err(de::Error::invalid_value(Unexpected::Str(&self.0), &visitor)),
+        }
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> std::result::Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> std::result::Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> std::result::Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        let value: StringDeserializer<Self::Error> = self.0.into_deserializer();
+        value.deserialize_enum(name, variants, visitor)
+    }
+
+    deserialize_parsed! {
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_f32 => visit_f32,
+        deserialize_f64 => visit_f64,
+        deserialize_char => visit_char,
+    }
+
+    serde::forward_to_deserialize_any! {
+        i128 u128 str string bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
+
+impl<'de> IntoDeserializer<'de, value::Error> for TxtValueDeserializer {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
 impl Debug for TxtRecord {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("TxtRecord")
@@ -153,6 +805,260 @@ mod tests {
         assert_eq!(record.get("baz"), None);
     }
 
+    #[test]
+    fn insert_accepts_entry_below_limit() {
+        crate::tests::setup();
+        let mut record = TxtRecord::new();
+        let value = "a".repeat(254 - "foo=".len());
+        record.insert("foo", &value).unwrap();
+        assert_eq!(record.get("foo").unwrap(), value);
+    }
+
+    #[test]
+    fn insert_accepts_entry_at_limit() {
+        crate::tests::setup();
+        let mut record = TxtRecord::new();
+        let value = "a".repeat(255 - "foo=".len());
+        record.insert("foo", &value).unwrap();
+        assert_eq!(record.get("foo").unwrap(), value);
+    }
+
+    #[test]
+    fn insert_rejects_entry_above_limit() {
+        crate::tests::setup();
+        let mut record = TxtRecord::new();
+        let value = "a".repeat(256 - "foo=".len());
+        assert_eq!(
+            record.insert("foo", &value),
+            Err(
+                "TXT record entry `foo` is 256 bytes long, exceeding the maximum of 255 bytes"
+                    .into()
+            )
+        );
+        assert!(record.is_empty());
+    }
+
+    #[test]
+    fn insert_accepts_many_entries_exceeding_limit_in_total() {
+        crate::tests::setup();
+        let mut record = TxtRecord::new();
+        let value = "a".repeat(200);
+
+        for i in 0..10 {
+            record.insert(&format!("key{}", i), &value).unwrap();
+        }
+
+        assert_eq!(record.len(), 10);
+    }
+
+    #[test]
+    fn encoded_len_success() {
+        crate::tests::setup();
+        let mut record = TxtRecord::new();
+        assert_eq!(record.encoded_len(), 0);
+        record.insert("foo", "bar").unwrap();
+        record.insert("hello", "world").unwrap();
+        assert_eq!(record.encoded_len(), 8 + 12);
+    }
+
+    #[test]
+    fn encoded_len_counts_raw_bytes_of_binary_values() {
+        crate::tests::setup();
+        let mut record = TxtRecord::new();
+        record.insert_bytes("id", &[0xff, 0xfe, 0x00]).unwrap();
+        assert_eq!(record.encoded_len(), 1 + 2 + 1 + 3);
+        assert_eq!(record.encoded_len(), record.to_bytes().len());
+    }
+
+    #[test]
+    fn is_within_recommended_size_success() {
+        crate::tests::setup();
+        let mut record = TxtRecord::new();
+        let value = "a".repeat(TXT_ENTRY_MAX_LEN - "keyN=".len());
+
+        record.insert("key0", &value).unwrap();
+        assert!(record.is_within_recommended_size());
+
+        record.insert("key1", &value).unwrap();
+        assert!(!record.is_within_recommended_size());
+    }
+
+    #[test]
+    fn index_success() {
+        crate::tests::setup();
+        let mut record = TxtRecord::new();
+        record.insert("foo", "bar").unwrap();
+        record.insert("baz", "").unwrap();
+        assert_eq!(&record["foo"], "bar");
+        assert_eq!(&record["baz"], "");
+    }
+
+    #[test]
+    #[should_panic(expected = "TxtRecord has no key \"foo\"")]
+    fn index_panics_on_missing_key() {
+        crate::tests::setup();
+        let mut record = TxtRecord::new();
+        record.insert("bar", "baz").unwrap();
+        let _ = &record["foo"];
+    }
+
+    #[test]
+    fn iter_bytes_preserves_binary_values() {
+        crate::tests::setup();
+        let mut record = TxtRecord::new();
+        record.insert_bytes("foo", &[b'a', 0xff, 0x00]).unwrap();
+        record.insert("bar", "baz").unwrap();
+
+        let mut entries = record.iter_bytes().collect::<Vec<_>>();
+        entries.sort();
+
+        assert_eq!(
+            entries,
+            vec![
+                ("bar".to_string(), &b"baz"[..]),
+                ("foo".to_string(), &[b'a', 0xff, 0x00][..]),
+            ]
+        );
+
+        let mut values = record.values_bytes().collect::<Vec<_>>();
+        values.sort();
+
+        assert_eq!(values, vec![&[b'a', 0xff, 0x00][..], &b"baz"[..]]);
+    }
+
+    #[test]
+    fn insert_bytes_fails_if_entry_too_long() {
+        crate::tests::setup();
+        let mut record = TxtRecord::new();
+        let value = vec![0xff; 256 - "foo=".len()];
+        assert!(record.insert_bytes("foo", &value).is_err());
+    }
+
+    #[test]
+    fn with_capacity_builds_same_record_as_new() {
+        let mut reserved = TxtRecord::with_capacity(4);
+        let mut grown = TxtRecord::new();
+
+        for i in 0..64 {
+            reserved.insert(&format!("key{}", i), "value").unwrap();
+            grown.insert(&format!("key{}", i), "value").unwrap();
+        }
+
+        assert_eq!(reserved, grown);
+        assert_eq!(reserved.len(), 64);
+        assert_eq!(TxtRecord::with_capacity(usize::MAX), TxtRecord::new());
+    }
+
+    #[test]
+    fn lint_reports_nothing_for_conforming_record() {
+        let txt = TxtRecord::from_pairs(&[("txtvers", "1"), ("path", "/")]).unwrap();
+
+        assert_eq!(txt.lint(), vec![]);
+        assert_eq!(TxtRecord::new().lint(), vec![]);
+    }
+
+    #[test]
+    fn lint_reports_several_issues() {
+        let mut txt = TxtRecord::from_bytes(b"\x07model=a\x07model=b\x0fmanufacturer=Co").unwrap();
+        txt.insert_bytes("id", b"a\0b").unwrap();
+
+        assert_eq!(
+            txt.lint(),
+            vec![
+                TxtLint::MissingVersion,
+                TxtLint::KeyTooLong("manufacturer".to_string()),
+                TxtLint::ValueContainsNul("id".to_string()),
+                TxtLint::DuplicateKey("model".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn lint_reports_record_larger_than_packet() {
+        let mut txt = TxtRecord::new();
+        txt.insert("txtvers", "1").unwrap();
+
+        for i in 0..8 {
+            txt.insert(&format!("k{}", i), &"v".repeat(200)).unwrap();
+        }
+
+        assert_eq!(txt.lint(), vec![TxtLint::TooLarge(txt.encoded_len())]);
+        assert_eq!(
+            TxtLint::TooLarge(1640).to_string(),
+            "record is 1640 bytes long, exceeding 1300 bytes"
+        );
+    }
+
+    #[test]
+    fn diff_reports_additions() {
+        let old = TxtRecord::from_pairs(&[("txtvers", "1")]).unwrap();
+        let new = TxtRecord::from_pairs(&[("txtvers", "1"), ("b", "2"), ("a", "1")]).unwrap();
+
+        let diff = old.diff(&new);
+
+        assert_eq!(
+            diff.added(),
+            &vec![
+                ("b".to_string(), "2".to_string()),
+                ("a".to_string(), "1".to_string())
+            ]
+        );
+        assert!(diff.removed().is_empty());
+        assert!(diff.changed().is_empty());
+        assert_eq!(diff.to_string(), "+b=2, +a=1");
+    }
+
+    #[test]
+    fn diff_reports_removals() {
+        let old = TxtRecord::from_pairs(&[("txtvers", "1"), ("rev", "2")]).unwrap();
+        let new = TxtRecord::from_pairs(&[("txtvers", "1")]).unwrap();
+
+        let diff = old.diff(&new);
+
+        assert!(diff.added().is_empty());
+        assert_eq!(diff.removed(), &vec![("rev".to_string(), "2".to_string())]);
+        assert!(diff.changed().is_empty());
+        assert_eq!(new.diff(&old).added(), diff.removed());
+    }
+
+    #[test]
+    fn diff_reports_value_changes() {
+        let mut old = TxtRecord::new();
+        old.insert("status", "idle").unwrap();
+        old.insert_bytes("id", &[0, 1]).unwrap();
+
+        let mut new = TxtRecord::new();
+        new.insert("status", "busy").unwrap();
+        new.insert_bytes("id", &[0, 2]).unwrap();
+
+        let diff = old.diff(&new);
+
+        assert!(diff.added().is_empty());
+        assert!(diff.removed().is_empty());
+        assert_eq!(
+            diff.changed(),
+            &vec![
+                ("status".to_string(), "idle".to_string(), "busy".to_string()),
+                (
+                    "id".to_string(),
+                    "\u{0}\u{1}".to_string(),
+                    "\u{0}\u{2}".to_string()
+                )
+            ]
+        );
+    }
+
+    #[test]
+    fn diff_ignores_order() {
+        let old = TxtRecord::from_pairs(&[("a", "1"), ("b", "2")]).unwrap();
+        let new = TxtRecord::from_pairs(&[("b", "2"), ("a", "1")]).unwrap();
+
+        let diff = old.diff(&new);
+
+        assert!(diff.is_empty());
+        assert_eq!(diff.to_string(), "no changes");
+    }
+
     #[test]
     fn get_miss_returns_none() {
         crate::tests::setup();
@@ -224,6 +1130,60 @@ mod tests {
         }
     }
 
+    #[test]
+    fn iter_preserves_insertion_order() {
+        crate::tests::setup();
+
+        let mut record = TxtRecord::new();
+        record.insert("version", "1").unwrap();
+        record.insert("zeta", "z").unwrap();
+        record.insert("alpha", "a").unwrap();
+        record.insert_bytes("beta", b"\xff").unwrap();
+
+        assert_eq!(
+            record.iter_bytes().collect::<Vec<_>>(),
+            vec![
+                ("version".to_string(), &b"1"[..]),
+                ("zeta".to_string(), b"z"),
+                ("alpha".to_string(), b"a"),
+                ("beta".to_string(), b"\xff")
+            ]
+        );
+    }
+
+    #[test]
+    fn insert_existing_key_moves_it_to_end() {
+        crate::tests::setup();
+
+        let mut record = TxtRecord::new();
+        record.insert("foo", "bar").unwrap();
+        record.insert("baz", "qux").unwrap();
+        record.insert("foo", "quux").unwrap();
+
+        assert_eq!(record.len(), 2);
+        assert_eq!(
+            record.iter().collect::<Vec<_>>(),
+            vec![
+                ("baz".to_string(), "qux".to_string()),
+                ("foo".to_string(), "quux".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn remove_preserves_order() {
+        crate::tests::setup();
+
+        let mut record = TxtRecord::new();
+        record.insert("foo", "bar").unwrap();
+        record.insert("baz", "qux").unwrap();
+        record.insert("hello", "world").unwrap();
+
+        record.remove("baz");
+
+        assert_eq!(record.keys().collect::<Vec<_>>(), vec!["foo", "hello"]);
+    }
+
     #[test]
     fn iter_works_if_empty() {
         crate::tests::setup();
@@ -286,6 +1246,27 @@ mod tests {
         assert_eq!(record.get("foo").unwrap(), "bar");
     }
 
+    #[test]
+    #[should_panic(expected = "exceeding the maximum")]
+    fn from_hashmap_panics_on_entry_too_long() {
+        crate::tests::setup();
+
+        let value = "x".repeat(TXT_ENTRY_MAX_LEN);
+        let mut map = HashMap::new();
+        map.insert("foo", value.as_str());
+
+        let _: TxtRecord = map.into();
+    }
+
+    #[test]
+    fn deserialize_rejects_entry_too_long() {
+        let entries = vec![("foo".to_string(), "x".repeat(TXT_ENTRY_MAX_LEN))];
+        let result =
+            TxtRecord::deserialize(MapDeserializer::<_, value::Error>::new(entries.into_iter()));
+
+        assert!(result.is_err());
+    }
+
     #[test]
     fn clone_success() {
         crate::tests::setup();
@@ -296,6 +1277,252 @@ mod tests {
         assert_eq!(record.clone(), record);
     }
 
+    #[test]
+    fn from_pairs_success() {
+        crate::tests::setup();
+
+        let record =
+            TxtRecord::from_pairs(&[("version", "1"), ("foo", "bar"), ("empty", "")]).unwrap();
+
+        assert_eq!(
+            record.iter().collect::<Vec<_>>(),
+            vec![
+                ("version".to_string(), "1".to_string()),
+                ("foo".to_string(), "bar".to_string()),
+                ("empty".to_string(), "".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn from_pairs_equals_repeated_insert() {
+        crate::tests::setup();
+
+        let pairs = [("foo", "bar"), ("baz", "qux"), ("foo", "quux")];
+        let mut record = TxtRecord::new();
+
+        for (key, value) in &pairs {
+            record.insert(key, value).unwrap();
+        }
+
+        assert_eq!(TxtRecord::from_pairs(&pairs[..2]).unwrap().len(), 2);
+        assert_eq!(TxtRecord::from_pairs(&pairs).unwrap(), record);
+    }
+
+    #[test]
+    fn from_pairs_rejects_entry_above_limit() {
+        crate::tests::setup();
+
+        let value = "a".repeat(TXT_ENTRY_MAX_LEN);
+        assert!(TxtRecord::from_pairs(&[("foo", "bar"), ("key", &value)]).is_err());
+    }
+
+    #[test]
+    fn from_bytes_success() {
+        crate::tests::setup();
+
+        let record = TxtRecord::from_bytes(b"\x07foo=bar\x08hello=\xffx").unwrap();
+
+        assert_eq!(
+            record.iter_bytes().collect::<Vec<_>>(),
+            vec![
+                ("foo".to_string(), &b"bar"[..]),
+                ("hello".to_string(), b"\xffx")
+            ]
+        );
+        assert!(record.duplicate_keys().is_empty());
+    }
+
+    #[test]
+    fn from_bytes_first_duplicate_wins() {
+        crate::tests::setup();
+
+        let record = TxtRecord::from_bytes(b"\x03x=1\x03y=0\x03x=2").unwrap();
+
+        assert_eq!(record.get("x").unwrap(), "1");
+        assert_eq!(record.len(), 2);
+        assert_eq!(record.duplicate_keys(), vec!["x"]);
+    }
+
+    #[test]
+    fn from_bytes_compares_keys_case_insensitively() {
+        crate::tests::setup();
+
+        let record = TxtRecord::from_bytes(b"\x03x=1\x03X=2\x03x=3").unwrap();
+
+        assert_eq!(record.get("x").unwrap(), "1");
+        assert_eq!(record.duplicate_keys(), vec!["X"]);
+    }
+
+    #[test]
+    fn from_bytes_ignores_empty_entries() {
+        crate::tests::setup();
+
+        let record = TxtRecord::from_bytes(b"\x00\x02=x\x03x=1").unwrap();
+
+        assert_eq!(record.keys().collect::<Vec<_>>(), vec!["x"]);
+        assert!(TxtRecord::from_bytes(b"\x00").unwrap().is_empty());
+    }
+
+    #[test]
+    fn from_bytes_fails_if_truncated() {
+        crate::tests::setup();
+        assert!(TxtRecord::from_bytes(b"\x07foo=b").is_err());
+    }
+
+    #[test]
+    fn from_bytes_strict_rejects_duplicate_keys() {
+        crate::tests::setup();
+
+        assert_eq!(
+            TxtRecord::from_bytes_strict(b"\x03x=1\x03x=2"),
+            Err(Error::DuplicateKeys(vec!["x".to_string()]))
+        );
+        assert!(TxtRecord::from_bytes_strict(b"\x03x=1\x03y=2").is_ok());
+    }
+
+    #[test]
+    fn duplicate_keys_empty_if_not_parsed() {
+        crate::tests::setup();
+
+        let mut record = TxtRecord::new();
+        record.insert("x", "1").unwrap();
+        record.insert("x", "2").unwrap();
+
+        assert!(record.duplicate_keys().is_empty());
+    }
+
+    #[test]
+    fn to_bytes_returns_wire_format_in_order() {
+        crate::tests::setup();
+
+        let txt = TxtRecord::from_pairs(&[("txtvers", "1"), ("rp", "ipp")]).unwrap();
+        let bytes = txt.to_bytes();
+
+        assert_eq!(bytes, b"\x09txtvers=1\x06rp=ipp");
+        assert_eq!(bytes.len(), txt.encoded_len());
+        assert_eq!(TxtRecord::from_bytes(&bytes).unwrap(), txt);
+        assert!(TxtRecord::new().to_bytes().is_empty());
+    }
+
+    #[test]
+    fn build_into_appends_wire_format() {
+        crate::tests::setup();
+
+        let pairs = [("txtvers", "1"), ("rp", "ipp")];
+        let mut buf = b"prefix".to_vec();
+
+        assert_eq!(TxtRecord::build_into(&mut buf, &pairs), Ok(17));
+        assert_eq!(
+            &buf[6..],
+            &TxtRecord::from_pairs(&pairs).unwrap().to_bytes()[..]
+        );
+        assert_eq!(&buf[..6], b"prefix");
+    }
+
+    #[test]
+    fn build_into_leaves_buffer_on_error() {
+        crate::tests::setup();
+
+        let long = "x".repeat(TXT_ENTRY_MAX_LEN);
+        let mut buf = b"prefix".to_vec();
+
+        assert!(TxtRecord::build_into(&mut buf, &[("a", "1"), ("b", &long)]).is_err());
+        assert_eq!(buf, b"prefix");
+    }
+
+    #[test]
+    fn build_into_writes_duplicate_keys_as_given() {
+        crate::tests::setup();
+
+        let mut buf = Vec::new();
+        TxtRecord::build_into(&mut buf, &[("rp", "ipp"), ("RP", "lpd")]).unwrap();
+
+        let txt = TxtRecord::from_bytes(&buf).unwrap();
+
+        assert_eq!(txt.get("rp"), Some("ipp".to_string()));
+        assert_eq!(txt.duplicate_keys(), vec!["RP".to_string()]);
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct PrinterInfo {
+        model: String,
+        rp: String,
+        #[serde(rename = "Color", default)]
+        color: bool,
+        pages: Option<u32>,
+        #[serde(default)]
+        duplex: bool,
+    }
+
+    #[test]
+    fn parse_as_round_trips_struct() {
+        crate::tests::setup();
+
+        let txt = TxtRecord::from_pairs(&[
+            ("model", "LaserJet"),
+            ("rp", "ipp/print"),
+            ("Color", "true"),
+            ("pages", "30"),
+        ])
+        .unwrap();
+
+        let info = txt.parse_as::<PrinterInfo>().unwrap();
+
+        assert_eq!(
+            info,
+            PrinterInfo {
+                model: "LaserJet".to_string(),
+                rp: "ipp/print".to_string(),
+                color: true,
+                pages: Some(30),
+                duplex: false,
+            }
+        );
+
+        let pages = info.pages.unwrap().to_string();
+        let color = info.color.to_string();
+        let round_trip = TxtRecord::from_pairs(&[
+            ("model", &info.model),
+            ("rp", &info.rp),
+            ("Color", &color),
+            ("pages", &pages),
+        ])
+        .unwrap();
+
+        assert_eq!(round_trip, txt);
+    }
+
+    #[test]
+    fn parse_as_handles_missing_and_valueless_keys() {
+        crate::tests::setup();
+
+        let txt = TxtRecord::from_bytes(b"\x09model=Jet\x06rp=ipp\x05Color").unwrap();
+
+        assert_eq!(
+            txt.parse_as::<PrinterInfo>(),
+            Ok(PrinterInfo {
+                model: "Jet".to_string(),
+                rp: "ipp".to_string(),
+                color: true,
+                pages: None,
+                duplex: false,
+            })
+        );
+    }
+
+    #[test]
+    fn parse_as_rejects_missing_required_and_invalid_values() {
+        crate::tests::setup();
+
+        let missing = TxtRecord::from_pairs(&[("model", "Jet")]).unwrap();
+        let invalid =
+            TxtRecord::from_pairs(&[("model", "Jet"), ("rp", "ipp"), ("pages", "x")]).unwrap();
+
+        assert!(missing.parse_as::<PrinterInfo>().is_err());
+        assert!(invalid.parse_as::<PrinterInfo>().is_err());
+    }
+
     #[test]
     fn serialize_success() {
         crate::tests::setup();